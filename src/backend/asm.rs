@@ -0,0 +1,676 @@
+use crate::compiler::diagnostic::{Diagnostic, Diagnostics};
+use crate::compiler::node::{Location, Node};
+use crate::compiler::types::{DefaultValue, Type, TypeTable};
+use crate::runtime::MAX_CALL_DEPTH;
+use super::{mangle, quote};
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+// Linux syscalls only, so the output links with a bare `ld` and no C library
+const RUNTIME: &str = include_str!("mix_runtime.s");
+
+// arguments beyond these would need the stack, which calls do not support yet
+const ARG_REGISTERS: [&str; 6] = ["%rdi", "%rsi", "%rdx", "%rcx", "%r8", "%r9"];
+
+struct Loop {
+    start: String,
+    end: String,
+}
+
+// lowers the checked tree to x86-64 System V assembly for the GNU assembler;
+// values are 64-bit words: integers, booleans as 0 or 1, and pointers to length-prefixed strings
+pub struct AsmGenerator<'a> {
+    types: &'a TypeTable,
+    file: String,
+    // whether the program spans several files, so statements also record theirs
+    files: bool,
+    // parameter count of every top-level function
+    functions: HashMap<String, usize>,
+    strings: Vec<String>,
+    diagnostics: Diagnostics,
+    out: String,
+    labels: usize,
+    // state of the function being generated
+    scopes: Vec<HashMap<String, usize>>,
+    next: usize,
+    slots: usize,
+    loops: Vec<Loop>,
+    ret: String,
+}
+
+impl<'a> AsmGenerator<'a> {
+    pub fn new(types: &'a TypeTable, file: &str) -> Self {
+        Self {
+            types,
+            file: file.to_owned(),
+            files: false,
+            functions: HashMap::new(),
+            strings: Vec::new(),
+            diagnostics: Diagnostics::new(),
+            out: String::new(),
+            labels: 0,
+            scopes: Vec::new(),
+            next: 0,
+            slots: 0,
+            loops: Vec::new(),
+            ret: String::new(),
+        }
+    }
+
+    pub fn generate(mut self, nodes: &[Box<Node>]) -> Result<String, Diagnostics> {
+        self.files = nodes.iter().any(|node| node.loc().is_some_and(|loc| loc.file != self.file));
+        let mut main = None;
+        for node in nodes {
+            if let Node::FuncDef { name, args, loc, .. } = node.as_ref() {
+                self.functions.insert(name.clone(), args.len());
+                if name == "main" {
+                    main = Some(loc);
+                }
+            }
+        }
+
+        let Some(main) = main else {
+            self.diagnostics.push(Diagnostic::error("E0506", "`main` function not found", self.entry())
+                .with_help("add `func main() { ... }` to the entry file"));
+            return Err(self.diagnostics);
+        };
+
+        let _ = writeln!(self.out, "# generated by `mix build` from {}", self.file);
+        self.emit_raw("    .text");
+        self.emit_raw("    .globl _start");
+        self.emit_raw("_start:");
+        self.emit(&format!("call {}", Self::function_name("main")));
+        // an `int` result of `main` becomes the exit status, like under `mix run`
+        if Self::int_type(self.returns(main)) {
+            self.emit("movq %rax, %rdi");
+        } else {
+            self.emit("xorl %edi, %edi");
+        }
+        self.emit("jmp mx_exit");
+        self.emit_raw("");
+
+        // structs, enums and methods are only reachable through values rejected where they are used
+        for node in nodes {
+            if let Node::FuncDef { name, generics, args, rtype, body, loc, .. } = node.as_ref() {
+                if !generics.is_empty() {
+                    self.unsupported("generic functions", loc);
+                    continue;
+                }
+                self.function(name, args, rtype, body, loc);
+            }
+        }
+
+        if self.diagnostics.has_errors() {
+            return Err(self.diagnostics);
+        }
+
+        self.emit_raw("    .section .rodata");
+        let file = self.file.clone();
+        self.emit_string("mx_file", &file);
+        for (i, value) in self.strings.clone().iter().enumerate() {
+            self.emit_string(&format!("mx_k{}", i), value);
+        }
+        self.emit_raw("mx_msg_depth:");
+        self.emit(&format!(".ascii {}", quote(&format!("  = note: calls can nest at most {} deep\n", MAX_CALL_DEPTH))));
+        self.emit_raw("mx_msg_depth_end:");
+        self.emit_raw("");
+
+        let _ = writeln!(self.out, "    .set MX_MAX_DEPTH, {}\n", MAX_CALL_DEPTH);
+        self.out.push_str(RUNTIME);
+        self.emit_raw("    .section .note.GNU-stack,\"\",@progbits");
+
+        Ok(self.out)
+    }
+
+    fn function(&mut self, name: &str, args: &[Box<Node>], rtype: &Node, body: &[Box<Node>], loc: &Location) {
+        if args.len() > ARG_REGISTERS.len() {
+            self.unsupported("functions with more than 6 parameters", loc);
+            return;
+        }
+        if !matches!(rtype, Node::Void) && !Self::word_type(self.returns(loc)) {
+            self.unsupported(&format!("returning `{}`", Self::type_text(rtype)), rtype.loc().unwrap_or(loc));
+            return;
+        }
+
+        self.scopes = vec![HashMap::new()];
+        self.next = 0;
+        self.slots = 0;
+        self.ret = self.label();
+
+        let label = Self::function_name(name);
+        let name_label = self.string(name);
+
+        // the body goes first, so the frame size is known when the prologue is written
+        let outer = std::mem::take(&mut self.out);
+
+        for (i, arg) in args.iter().enumerate() {
+            if let Node::LetDef { name, dtype, loc, .. } = arg.as_ref() {
+                if !Self::word_type(self.types.declared(loc)) {
+                    self.unsupported(&format!("`{}` parameters", Self::type_text(dtype)), loc);
+                }
+                let slot = self.declare_local(name);
+                self.emit(&format!("movq {}, {}", ARG_REGISTERS[i], Self::slot(slot)));
+            }
+        }
+
+        self.block(body);
+        self.emit("xorl %eax, %eax");
+
+        let body = std::mem::replace(&mut self.out, outer);
+        let frame = (self.slots * 8).next_multiple_of(16);
+        let ok = self.label();
+
+        self.emit_raw(&format!("{}:", label));
+        self.emit("pushq %rbp");
+        self.emit("movq %rsp, %rbp");
+        if frame > 0 {
+            self.emit(&format!("subq ${}, %rsp", frame));
+        }
+        self.emit("incq mx_depth(%rip)");
+        self.emit("cmpq $MX_MAX_DEPTH, mx_depth(%rip)");
+        self.emit(&format!("jle {}", ok));
+        self.emit(&format!("leaq {}(%rip), %rdi", name_label));
+        self.emit("jmp mx_overflow");
+        self.emit_raw(&format!("{}:", ok));
+        self.out.push_str(&body);
+        self.emit_raw(&format!("{}:", self.ret));
+        self.emit("decq mx_depth(%rip)");
+        self.emit("leave");
+        self.emit("ret");
+        self.emit_raw("");
+    }
+
+    fn block(&mut self, body: &[Box<Node>]) {
+        self.scopes.push(HashMap::new());
+        let next = self.next;
+
+        for node in body {
+            self.stmt(node);
+        }
+
+        // slots of this block are free again once it ends
+        self.next = next;
+        self.scopes.pop();
+    }
+
+    fn stmt(&mut self, node: &Node) {
+        if let Some(loc) = node.loc() {
+            if self.files {
+                let file = self.string(&loc.file);
+                self.emit(&format!("leaq {}(%rip), %rax", file));
+                self.emit("movq %rax, mx_source(%rip)");
+            }
+            self.emit(&format!("movq ${}, mx_line(%rip)", loc.line));
+            self.emit(&format!("movq ${}, mx_column(%rip)", loc.column));
+        }
+
+        match node {
+            Node::LetDef { name, dtype, value, loc, .. } => {
+                match value.as_ref() {
+                    // `str` defaults to the empty string, `int`, `long` and `bool` to 0
+                    Node::Void => match self.types.default_value(loc) {
+                        DefaultValue::Str => {
+                            let empty = self.string("");
+                            self.emit(&format!("leaq {}(%rip), %rax", empty));
+                        }
+                        DefaultValue::Int | DefaultValue::Bool => self.emit("xorl %eax, %eax"),
+                        _ => self.unsupported(&format!("`{}` variables", Self::type_text(dtype)), loc),
+                    },
+                    value => self.expr(value),
+                }
+                let slot = self.declare_local(name);
+                self.emit(&format!("movq %rax, {}", Self::slot(slot)));
+            }
+            Node::AssignDef { targ, value, opr, loc } => {
+                let Node::Var { value: name, .. } = targ.as_ref() else {
+                    self.unsupported("assigning to fields", loc);
+                    return;
+                };
+                let Some(slot) = self.local(name) else {
+                    self.unsupported(&format!("assigning to `{}`", name), loc);
+                    return;
+                };
+
+                match opr.trim_end_matches('=') {
+                    "" => self.expr(value),
+                    opr => {
+                        self.emit(&format!("movq {}, %rax", Self::slot(slot)));
+                        self.emit("pushq %rax");
+                        self.expr(value);
+                        self.emit("movq %rax, %rcx");
+                        self.emit("popq %rax");
+                        self.binary(opr, self.types.type_of(targ), loc);
+                    }
+                }
+                self.emit(&format!("movq %rax, {}", Self::slot(slot)));
+            }
+            Node::Return { value, .. } => {
+                self.expr(value);
+                self.emit(&format!("jmp {}", self.ret));
+            }
+            Node::Conditional { cond, body_if, body_else, .. } => {
+                let (to_else, end) = (self.label(), self.label());
+                self.expr(cond);
+                self.emit("testq %rax, %rax");
+                self.emit(&format!("jz {}", to_else));
+                self.block(body_if);
+                self.emit(&format!("jmp {}", end));
+                self.emit_raw(&format!("{}:", to_else));
+                self.block(body_else);
+                self.emit_raw(&format!("{}:", end));
+            }
+            Node::WhileLoop { cond, body, .. } => {
+                let (start, end) = (self.label(), self.label());
+                self.emit_raw(&format!("{}:", start));
+                self.expr(cond);
+                self.emit("testq %rax, %rax");
+                self.emit(&format!("jz {}", end));
+
+                self.loops.push(Loop { start: start.clone(), end: end.clone() });
+                self.block(body);
+                self.loops.pop();
+
+                self.emit(&format!("jmp {}", start));
+                self.emit_raw(&format!("{}:", end));
+            }
+            Node::ForLoop { initializer, iterator, body, loc } => {
+                let (Node::LetDef { name, .. }, Node::Range { min, max, .. }) = (initializer.as_ref(), iterator.as_ref()) else {
+                    self.unsupported("`for` loops over anything but a range", loc);
+                    return;
+                };
+
+                self.scopes.push(HashMap::new());
+                let next = self.next;
+
+                // the position and the bound live in hidden slots, so the body cannot move them
+                let index = self.hidden_local();
+                let bound = self.hidden_local();
+                self.expr(min);
+                self.emit(&format!("movq %rax, {}", Self::slot(index)));
+                self.expr(max);
+                self.emit(&format!("movq %rax, {}", Self::slot(bound)));
+
+                let (start, step, end) = (self.label(), self.label(), self.label());
+                self.emit_raw(&format!("{}:", start));
+                self.emit(&format!("movq {}, %rax", Self::slot(index)));
+                self.emit(&format!("cmpq {}, %rax", Self::slot(bound)));
+                self.emit(&format!("jge {}", end));
+                let slot = self.declare_local(name);
+                self.emit(&format!("movq %rax, {}", Self::slot(slot)));
+
+                self.loops.push(Loop { start: step.clone(), end: end.clone() });
+                self.block(body);
+                self.loops.pop();
+
+                self.emit_raw(&format!("{}:", step));
+                self.emit(&format!("incq {}", Self::slot(index)));
+                self.emit(&format!("jmp {}", start));
+                self.emit_raw(&format!("{}:", end));
+
+                self.next = next;
+                self.scopes.pop();
+            }
+            Node::MatchCase { value, child, default, loc } => {
+                let equality = match self.types.type_of(value) {
+                    Some(Type::Str) => "str",
+                    Some(Type::Int | Type::Long | Type::Bool) => "word",
+                    _ => {
+                        self.unsupported("`match` on anything but integers, booleans and strings", loc);
+                        return;
+                    }
+                };
+
+                self.scopes.push(HashMap::new());
+                let next = self.next;
+
+                self.expr(value);
+                let subject = self.hidden_local();
+                self.emit(&format!("movq %rax, {}", Self::slot(subject)));
+
+                let end = self.label();
+                for arm in child {
+                    let next_arm = self.label();
+                    self.expr(&arm.0);
+                    if equality == "str" {
+                        self.emit("movq %rax, %rsi");
+                        self.emit(&format!("movq {}, %rdi", Self::slot(subject)));
+                        self.emit("call mx_str_eq");
+                        self.emit("testq %rax, %rax");
+                        self.emit(&format!("jz {}", next_arm));
+                    } else {
+                        self.emit(&format!("cmpq {}, %rax", Self::slot(subject)));
+                        self.emit(&format!("jne {}", next_arm));
+                    }
+                    self.block(&arm.1);
+                    self.emit(&format!("jmp {}", end));
+                    self.emit_raw(&format!("{}:", next_arm));
+                }
+                self.block(default);
+                self.emit_raw(&format!("{}:", end));
+
+                self.next = next;
+                self.scopes.pop();
+            }
+            Node::Break { .. } => {
+                if let Some(end) = self.loops.last().map(|current| current.end.clone()) {
+                    self.emit(&format!("jmp {}", end));
+                }
+            }
+            Node::Continue { .. } => {
+                if let Some(start) = self.loops.last().map(|current| current.start.clone()) {
+                    self.emit(&format!("jmp {}", start));
+                }
+            }
+            _ => self.expr(node),
+        }
+    }
+
+    // evaluates `node` into %rax
+    fn expr(&mut self, node: &Node) {
+        if let Some(loc) = node.loc()
+            && let Some(ty) = self.types.type_of(node)
+            && !matches!(ty, Type::Int | Type::Long | Type::Bool | Type::Str | Type::Void | Type::Unknown)
+        {
+            self.unsupported(&format!("`{}` values", ty), loc);
+            return;
+        }
+
+        match node {
+            Node::Int { value, loc, .. } => match value.parse::<i64>() {
+                Ok(value) if i32::try_from(value).is_ok() => self.emit(&format!("movq ${}, %rax", value)),
+                Ok(value) => self.emit(&format!("movabsq ${}, %rax", value)),
+                Err(_) => self.unsupported(&format!("the integer literal `{}`", value), loc),
+            },
+            Node::Bool { value, .. } => {
+                let bit = (value == "true") as u8;
+                self.emit(&format!("movq ${}, %rax", bit));
+            }
+            Node::Str { value, .. } => {
+                let label = self.string(value);
+                self.emit(&format!("leaq {}(%rip), %rax", label));
+            }
+            Node::Void => self.emit("xorl %eax, %eax"),
+            Node::Var { value, loc } => match self.local(value) {
+                Some(slot) => self.emit(&format!("movq {}, %rax", Self::slot(slot))),
+                None => self.unsupported(&format!("`{}` as a value", value), loc),
+            },
+            Node::BinaryOp { lhs, rhs, opr, .. } if opr == "&&" || opr == "||" => {
+                let end = self.label();
+                self.expr(lhs);
+                self.emit("testq %rax, %rax");
+                self.emit(&format!("{} {}", if opr == "&&" { "jz" } else { "jnz" }, end));
+                self.expr(rhs);
+                self.emit_raw(&format!("{}:", end));
+            }
+            Node::BinaryOp { lhs, rhs, opr, loc } => {
+                self.expr(lhs);
+                self.emit("pushq %rax");
+                self.expr(rhs);
+                self.emit("movq %rax, %rcx");
+                self.emit("popq %rax");
+                self.binary(opr, self.types.type_of(lhs), loc);
+            }
+            Node::UnaryOp { opr, value, loc } => {
+                self.expr(value);
+                match opr.as_str() {
+                    "-" => self.emit("negq %rax"),
+                    "!" => self.emit("xorq $1, %rax"),
+                    "~" => self.emit("notq %rax"),
+                    "+" => {}
+                    other => self.unsupported(&format!("unary `{}`", other), loc),
+                }
+            }
+            Node::FuncCall { func, args, loc } => self.call(func, args, loc),
+            other => {
+                let loc = other.loc().cloned().unwrap_or_else(|| self.entry());
+                self.unsupported(Self::describe(other), &loc);
+            }
+        }
+    }
+
+    // %rax = %rax `opr` %rcx
+    fn binary(&mut self, opr: &str, operand: Option<&Type>, loc: &Location) {
+        if matches!(operand, Some(Type::Str)) {
+            match opr {
+                "==" | "!=" => {
+                    self.emit("movq %rax, %rdi");
+                    self.emit("movq %rcx, %rsi");
+                    self.emit("call mx_str_eq");
+                    if opr == "!=" {
+                        self.emit("xorq $1, %rax");
+                    }
+                }
+                _ => self.unsupported(&format!("`{}` on strings", opr), loc),
+            }
+            return;
+        }
+
+        match opr {
+            "+" => self.emit("addq %rcx, %rax"),
+            "-" => self.emit("subq %rcx, %rax"),
+            "*" => self.emit("imulq %rcx, %rax"),
+            "/" | "%" => {
+                // a zero divisor is an error, and -1 would trap on the smallest integer
+                let (divide, end) = (self.label(), self.label());
+                self.emit("testq %rcx, %rcx");
+                self.emit("jz mx_divide_by_zero");
+                self.emit("cmpq $-1, %rcx");
+                self.emit(&format!("jne {}", divide));
+                self.emit(if opr == "/" { "negq %rax" } else { "xorl %eax, %eax" });
+                self.emit(&format!("jmp {}", end));
+                self.emit_raw(&format!("{}:", divide));
+                self.emit("cqto");
+                self.emit("idivq %rcx");
+                if opr == "%" {
+                    self.emit("movq %rdx, %rax");
+                }
+                self.emit_raw(&format!("{}:", end));
+            }
+            "^" => {
+                self.emit("movq %rax, %rdi");
+                self.emit("movq %rcx, %rsi");
+                self.emit("call mx_pow");
+            }
+            "&" => self.emit("andq %rcx, %rax"),
+            "|" => self.emit("orq %rcx, %rax"),
+            "<<" => self.emit("salq %cl, %rax"),
+            ">>" => self.emit("sarq %cl, %rax"),
+            "==" | "!=" | "<" | ">" | "<=" | ">=" => {
+                let set = match opr {
+                    "==" => "sete",
+                    "!=" => "setne",
+                    "<" => "setl",
+                    ">" => "setg",
+                    "<=" => "setle",
+                    _ => "setge",
+                };
+                self.emit("cmpq %rcx, %rax");
+                self.emit(&format!("{} %al", set));
+                self.emit("movzbq %al, %rax");
+            }
+            other => self.unsupported(&format!("`{}`", other), loc),
+        }
+    }
+
+    fn call(&mut self, func: &Node, args: &[Box<Node>], loc: &Location) {
+        if let Node::CodeLockup { targ, obj, .. } = func
+            && let (Node::Var { value: name, .. }, Node::Var { value: module, .. }) = (targ.as_ref(), obj.as_ref())
+            && module == "std"
+        {
+            if name != "print" && name != "println" {
+                self.unsupported(&format!("`std::{}`", name), loc);
+                return;
+            }
+
+            // every argument is evaluated before anything is printed, like under `mix run`
+            for arg in args {
+                self.expr(arg);
+                self.emit("pushq %rax");
+            }
+            for (i, arg) in args.iter().enumerate() {
+                let print = match self.types.type_of(arg) {
+                    Some(Type::Bool) => "mx_print_bool",
+                    Some(Type::Str) => "mx_print_str",
+                    _ => "mx_print_int",
+                };
+                if i > 0 {
+                    self.emit("call mx_print_space");
+                }
+                self.emit(&format!("movq {}(%rsp), %rdi", (args.len() - 1 - i) * 8));
+                self.emit(&format!("call {}", print));
+            }
+            if !args.is_empty() {
+                self.emit(&format!("addq ${}, %rsp", args.len() * 8));
+            }
+            if name == "println" {
+                self.emit("call mx_print_newline");
+            }
+            self.emit("xorl %eax, %eax");
+            return;
+        }
+
+        let Node::Var { value: name, .. } = func else {
+            self.unsupported("calls to anything but top-level functions", loc);
+            return;
+        };
+
+        match self.functions.get(name) {
+            Some(arity) if self.local(name).is_none() && *arity == args.len() => {}
+            _ => {
+                self.unsupported(&format!("calling `{}`", name), loc);
+                return;
+            }
+        }
+
+        for arg in args {
+            self.expr(arg);
+            self.emit("pushq %rax");
+        }
+        for register in ARG_REGISTERS[..args.len()].iter().rev() {
+            self.emit(&format!("popq {}", register));
+        }
+        self.emit(&format!("call {}", Self::function_name(name)));
+    }
+
+    fn unsupported(&mut self, what: &str, loc: &Location) {
+        self.diagnostics.push(
+            Diagnostic::error("E0601", format!("the x86-64 backend does not support {} yet", what), loc.clone())
+                .with_help("build with `--backend=c` or `--backend=bytecode` instead"),
+        );
+    }
+
+    fn describe(node: &Node) -> &'static str {
+        match node {
+            Node::Float { .. } => "floats",
+            Node::Null { .. } => "`null` values",
+            Node::List { .. } => "lists",
+            Node::Dict { .. } => "dicts",
+            Node::Range { .. } => "ranges outside of `for` loops",
+            Node::MemLockup { .. } => "fields and methods",
+            Node::CodeLockup { .. } => "paths",
+            _ => "these expressions",
+        }
+    }
+
+    // `int`, `long`, `bool` and `str` fit in one register
+    // whether a value of the checked type `ty` fits a register as is
+    fn word_type(ty: Option<&Type>) -> bool {
+        matches!(ty, Some(Type::Int | Type::Long | Type::Bool | Type::Str))
+    }
+
+    fn int_type(ty: Option<&Type>) -> bool {
+        matches!(ty, Some(Type::Int | Type::Long))
+    }
+
+    // the checked return type of the function declared at `loc`
+    fn returns(&self, loc: &Location) -> Option<&Type> {
+        match self.types.declared(loc) {
+            Some(Type::Function(_, ret)) => Some(ret),
+            _ => None,
+        }
+    }
+
+    fn type_text(dtype: &Node) -> String {
+        match dtype {
+            Node::Var { value, .. } => value.clone(),
+            Node::GenericType { name, .. } => Self::type_text(name),
+            Node::FuncType { .. } => "func".to_owned(),
+            _ => "void".to_owned(),
+        }
+    }
+
+    fn local(&self, name: &str) -> Option<usize> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name)).copied()
+    }
+
+    fn declare_local(&mut self, name: &str) -> usize {
+        let slot = self.hidden_local();
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_owned(), slot);
+        }
+        slot
+    }
+
+    // a slot no name refers to, for loop and match bookkeeping
+    fn hidden_local(&mut self) -> usize {
+        let slot = self.next;
+        self.next += 1;
+        self.slots = self.slots.max(self.next);
+        slot
+    }
+
+    fn slot(slot: usize) -> String {
+        format!("-{}(%rbp)", (slot + 1) * 8)
+    }
+
+    fn string(&mut self, value: &str) -> String {
+        let index = match self.strings.iter().position(|existing| existing == value) {
+            Some(index) => index,
+            None => {
+                self.strings.push(value.to_owned());
+                self.strings.len() - 1
+            }
+        };
+        format!("mx_k{}", index)
+    }
+
+    // a string as its length in bytes followed by the bytes
+    fn emit_string(&mut self, label: &str, value: &str) {
+        self.emit_raw(&format!("{}:", label));
+        self.emit(&format!(".quad {}", value.len()));
+        if !value.is_empty() {
+            self.emit(&format!(".ascii {}", quote(value)));
+        }
+    }
+
+    fn label(&mut self) -> String {
+        self.labels += 1;
+        format!(".L{}", self.labels)
+    }
+
+    fn emit(&mut self, instruction: &str) {
+        self.out.push_str("    ");
+        self.out.push_str(instruction);
+        self.out.push('\n');
+    }
+
+    fn emit_raw(&mut self, line: &str) {
+        self.out.push_str(line);
+        self.out.push('\n');
+    }
+
+    fn entry(&self) -> Location {
+        Location {
+            file: self.file.clone(),
+            line: 1,
+            column: 1,
+            start: 0,
+            end: 1,
+        }
+    }
+
+    fn function_name(name: &str) -> String {
+        format!("mx_f_{}", mangle(name))
+    }
+}
@@ -0,0 +1,732 @@
+use crate::compiler::diagnostic::Diagnostic;
+use crate::compiler::node::{Location, Node};
+use crate::compiler::types::{DefaultValue, Path, Type, TypeTable};
+use crate::runtime::MAX_CALL_DEPTH;
+use super::{mangle, quote};
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+// the C runtime every generated program includes
+pub const RUNTIME_HEADER: &str = include_str!("mix_runtime.h");
+
+struct StructInfo {
+    fields: Vec<String>,
+    // method name, C function and parameter count
+    methods: Vec<(String, String, usize)>,
+}
+
+// translates the checked tree into one C file built on `mix_runtime.h`
+pub struct CGenerator<'a> {
+    types: &'a TypeTable,
+    file: String,
+    // whether the program spans several files, so statements also record theirs
+    files: bool,
+    // C function and parameter count of every top-level function
+    functions: HashMap<String, (String, usize)>,
+    structs: HashMap<String, StructInfo>,
+    // declaration order, so the generated tables are stable
+    struct_order: Vec<String>,
+    enums: HashMap<String, Vec<String>>,
+    strings: Vec<String>,
+    out: String,
+    indent: usize,
+    // state of the function being generated
+    scopes: Vec<HashMap<String, String>>,
+    temps: usize,
+}
+
+impl<'a> CGenerator<'a> {
+    pub fn new(types: &'a TypeTable, file: &str) -> Self {
+        Self {
+            types,
+            file: file.to_owned(),
+            files: false,
+            functions: HashMap::new(),
+            structs: HashMap::new(),
+            struct_order: Vec::new(),
+            enums: HashMap::new(),
+            strings: Vec::new(),
+            out: String::new(),
+            indent: 0,
+            scopes: Vec::new(),
+            temps: 0,
+        }
+    }
+
+    pub fn generate(mut self, nodes: &[Box<Node>]) -> Result<String, Box<Diagnostic>> {
+        self.files = nodes.iter().any(|node| node.loc().is_some_and(|loc| loc.file != self.file));
+        for node in nodes {
+            self.declare(node);
+        }
+
+        if !self.functions.contains_key("main") {
+            let entry = Location {
+                file: self.file.clone(),
+                line: 1,
+                column: 1,
+                start: 0,
+                end: 1,
+            };
+            return Err(Diagnostic::error("E0506", "`main` function not found", entry)
+                .with_help("add `func main() { ... }` to the entry file").into());
+        }
+
+        // the C `main` calls it without arguments, which `cc` would reject
+        if let Some(Node::FuncDef { args, loc, .. }) = nodes.iter().map(|node| node.as_ref())
+            .find(|node| matches!(node, Node::FuncDef { name, .. } if name == "main"))
+            && !args.is_empty()
+        {
+            return Err(Diagnostic::error("E0412", "`main` cannot take parameters", loc.clone())
+                .with_help("declare it as `func main()` or `func main() -> int`").into());
+        }
+
+        for node in nodes {
+            match node.as_ref() {
+                Node::FuncDef { name, args, body, .. } => {
+                    let c_name = Self::function_name(name);
+                    self.function(&c_name, name, false, args, body);
+                }
+                Node::Struct { name: owner, body, .. } => {
+                    for method in body {
+                        if let Node::FuncDef { name, args, body, .. } = method.as_ref() {
+                            let c_name = Self::method_name(owner, name);
+                            self.function(&c_name, &format!("{}.{}", owner, name), true, args, body);
+                        }
+                    }
+                }
+                Node::Attach { name, attach_to, args, body, .. } => {
+                    if let Node::Var { value: owner, .. } = attach_to.as_ref() {
+                        let c_name = Self::method_name(owner, name);
+                        self.function(&c_name, &format!("{}.{}", owner, name), true, args, body);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let bodies = std::mem::take(&mut self.out);
+        let mut out = String::new();
+
+        let _ = writeln!(out, "/* generated by `mix build` from {} */", self.file);
+        let _ = writeln!(out, "#define MX_MAX_DEPTH {}", MAX_CALL_DEPTH);
+        let _ = writeln!(out, "#include \"mix_runtime.h\"\n");
+
+        self.write_declarations(&mut out);
+        out.push_str(&bodies);
+
+        let _ = writeln!(out, "int main(void) {{");
+        let _ = writeln!(out, "    mx_file = {};", quote(&self.file));
+        for (i, value) in self.strings.iter().enumerate() {
+            let _ = writeln!(out, "    mx_k{} = mx_str_lit({});", i, quote(value));
+        }
+        let _ = writeln!(out, "    mx_value result = mx_run({});", Self::function_name("main"));
+        let _ = writeln!(out, "    fflush(stdout);");
+        let _ = writeln!(out, "    return result.tag == MX_INT ? (int)result.as.i : 0;");
+        let _ = writeln!(out, "}}");
+
+        Ok(out)
+    }
+
+    fn declare(&mut self, node: &Node) {
+        match node {
+            Node::FuncDef { name, args, .. } => {
+                self.functions.insert(name.clone(), (Self::function_name(name), args.len()));
+            }
+            Node::Struct { name, child, body, .. } => {
+                let fields = child.iter().filter_map(|field| match field.as_ref() {
+                    Node::LetDef { name, .. } => Some(name.clone()),
+                    _ => None,
+                }).collect();
+                let methods = body.iter().filter_map(|method| match method.as_ref() {
+                    Node::FuncDef { name: method, args, .. } => Some((method.clone(), Self::method_name(name, method), args.len())),
+                    _ => None,
+                }).collect();
+
+                self.structs.insert(name.clone(), StructInfo { fields, methods });
+                self.struct_order.push(name.clone());
+            }
+            Node::Attach { name, attach_to, args, .. } => {
+                if let Node::Var { value: owner, .. } = attach_to.as_ref()
+                    && let Some(info) = self.structs.get_mut(owner)
+                {
+                    info.methods.push((name.clone(), Self::method_name(owner, name), args.len()));
+                }
+            }
+            Node::Enum { name, child, .. } => {
+                let variants = child.iter().filter_map(|variant| match variant.as_ref() {
+                    Node::Var { value, .. } => Some(value.clone()),
+                    _ => None,
+                }).collect();
+                self.enums.insert(name.clone(), variants);
+            }
+            _ => {}
+        }
+    }
+
+    // prototypes, callable wrappers and the struct and enum tables the runtime reads
+    fn write_declarations(&self, out: &mut String) {
+        let mut callables: Vec<(String, String, usize, bool)> = self.functions.iter()
+            .map(|(name, (c_name, arity))| (c_name.clone(), name.clone(), *arity, false))
+            .collect();
+        callables.sort();
+        for owner in &self.struct_order {
+            for (name, c_name, arity) in &self.structs[owner].methods {
+                callables.push((c_name.clone(), format!("{}.{}", owner, name), *arity, true));
+            }
+        }
+
+        for (c_name, _, arity, receiver) in &callables {
+            let count = arity + *receiver as usize;
+            let params = if count == 0 { "void".to_owned() } else { vec!["mx_value"; count].join(", ") };
+            let _ = writeln!(out, "static mx_value {}({});", c_name, params);
+        }
+        out.push('\n');
+
+        for (c_name, name, arity, receiver) in &callables {
+            let count = arity + *receiver as usize;
+            let args = (0..count).map(|i| format!("args[{}]", i)).collect::<Vec<_>>().join(", ");
+            let _ = writeln!(out, "static mx_value {}_call(mx_value *args) {{ (void)args; return {}({}); }}", c_name, c_name, args);
+            let _ = writeln!(out, "static const mx_func {}_info = {{{}, {}, {}, {}_call}};", c_name, quote(name), arity, *receiver as u8, c_name);
+        }
+        out.push('\n');
+
+        let mut enums: Vec<_> = self.enums.iter().collect();
+        enums.sort();
+        for (name, variants) in enums {
+            let c_name = Self::enum_name(name);
+            let list = variants.iter().map(|variant| quote(variant)).collect::<Vec<_>>();
+            let _ = writeln!(out, "static const char *const {}_variants[] = {{{}}};", c_name, Self::or_null(list));
+            let _ = writeln!(out, "static const mx_enum_info {} = {{{}, {}_variants}};", c_name, quote(name), c_name);
+        }
+
+        for name in &self.struct_order {
+            let info = &self.structs[name];
+            let c_name = Self::struct_name(name);
+
+            let fields = info.fields.iter().map(|field| quote(field)).collect::<Vec<_>>();
+            let _ = writeln!(out, "static const char *const {}_fields[] = {{{}}};", c_name, Self::or_null(fields));
+
+            let methods = info.methods.iter()
+                .map(|(method, function, _)| format!("{{{}, &{}_info}}", quote(method), function))
+                .collect::<Vec<_>>();
+            let methods = if methods.is_empty() { "{NULL, NULL}".to_owned() } else { methods.join(", ") };
+            let _ = writeln!(out, "static const mx_method {}_methods[] = {{{}}};", c_name, methods);
+
+            let _ = writeln!(
+                out,
+                "static const mx_struct_info {} = {{{}, {}, {}_fields, {}, {}_methods}};",
+                c_name, quote(name), info.fields.len(), c_name, info.methods.len(), c_name,
+            );
+        }
+
+        for i in 0..self.strings.len() {
+            let _ = writeln!(out, "static mx_value mx_k{};", i);
+        }
+        out.push('\n');
+    }
+
+    fn function(&mut self, c_name: &str, name: &str, receiver: bool, args: &[Box<Node>], body: &[Box<Node>]) {
+        self.scopes = vec![HashMap::new()];
+        self.temps = 0;
+
+        let mut params = Vec::new();
+        if receiver {
+            params.push(format!("mx_value {}", self.declare_local("self")));
+        }
+        for arg in args {
+            if let Node::LetDef { name, .. } = arg.as_ref() {
+                params.push(format!("mx_value {}", self.declare_local(name)));
+            }
+        }
+        let params = if params.is_empty() { "void".to_owned() } else { params.join(", ") };
+
+        self.line(&format!("static mx_value {}({}) {{", c_name, params));
+        self.indent += 1;
+        self.line(&format!("mx_enter({});", quote(name)));
+
+        self.block(body);
+
+        self.line("mx_depth--;");
+        self.line("return mx_void();");
+        self.indent -= 1;
+        self.line("}\n");
+    }
+
+    fn block(&mut self, body: &[Box<Node>]) {
+        self.scopes.push(HashMap::new());
+        for node in body {
+            self.stmt(node);
+        }
+        self.scopes.pop();
+    }
+
+    // a block in its own C braces, for bodies that follow `if`, `else` and loops
+    fn braced(&mut self, header: &str, body: &[Box<Node>]) {
+        self.line(&format!("{} {{", header));
+        self.indent += 1;
+        self.block(body);
+        self.indent -= 1;
+        self.line("}");
+    }
+
+    fn stmt(&mut self, node: &Node) {
+        if let Some(loc) = node.loc() {
+            if self.files {
+                self.line(&format!("mx_file = {};", quote(&loc.file)));
+            }
+            self.line(&format!("MX_AT({}, {});", loc.line, loc.column));
+        }
+
+        match node {
+            Node::LetDef { name, value, loc, .. } => {
+                let value = match value.as_ref() {
+                    Node::Void => Self::default_value(self.types.default_value(loc)).to_owned(),
+                    value => self.expr(value),
+                };
+                let local = self.declare_local(name);
+                self.line(&format!("mx_value {} = {};", local, value));
+            }
+            Node::AssignDef { targ, value, opr, .. } => self.assign(targ, value, opr),
+            Node::Return { value, .. } => {
+                let value = self.expr(value);
+                self.line("mx_depth--;");
+                self.line(&format!("return {};", value));
+            }
+            Node::Conditional { cond, body_if, body_else, .. } => {
+                let cond = self.condition(cond);
+                self.braced(&format!("if ({})", cond), body_if);
+                if !body_else.is_empty() {
+                    self.braced("else", body_else);
+                }
+            }
+            Node::WhileLoop { cond, body, .. } => {
+                // the condition may need statements of its own, so it is checked inside the loop
+                self.line("for (;;) {");
+                self.indent += 1;
+                let cond = self.condition(cond);
+                self.line(&format!("if (!({})) break;", cond));
+                self.block(body);
+                self.indent -= 1;
+                self.line("}");
+            }
+            Node::ForLoop { initializer, iterator, body, .. } => {
+                let Node::LetDef { name, .. } = initializer.as_ref() else {
+                    return;
+                };
+
+                // copied, so reassigning the variable inside the body does not move the bounds
+                let iterable = self.expr(iterator);
+                let iterable = self.temp(iterable);
+                let index = self.fresh("i");
+
+                self.scopes.push(HashMap::new());
+                if matches!(self.types.type_of(iterator), Some(Type::Range(_))) {
+                    self.line(&format!("for (int64_t {} = {}.as.r.min; {} < {}.as.r.max; {}++) {{", index, iterable, index, iterable, index));
+                    self.indent += 1;
+                    let local = self.declare_local(name);
+                    self.line(&format!("mx_value {} = mx_int({});", local, index));
+                } else {
+                    let items = self.fresh("items");
+                    self.line(&format!("mx_list *{} = mx_iterate({});", items, iterable));
+                    self.line(&format!("for (size_t {} = 0; {} < {}->len; {}++) {{", index, index, items, index));
+                    self.indent += 1;
+                    let local = self.declare_local(name);
+                    self.line(&format!("mx_value {} = {}->items[{}];", local, items, index));
+                }
+                self.block(body);
+                self.indent -= 1;
+                self.line("}");
+                self.scopes.pop();
+            }
+            Node::MatchCase { value, child, default, .. } => {
+                let subject = self.expr(value);
+
+                // patterns may need statements, so every arm nests in the previous `else`
+                for arm in child {
+                    let pattern = self.expr(&arm.0);
+                    self.braced(&format!("if (mx_eq({}, {}))", subject, pattern), &arm.1);
+                    self.line("else {");
+                    self.indent += 1;
+                }
+                self.block(default);
+                for _ in child {
+                    self.indent -= 1;
+                    self.line("}");
+                }
+            }
+            Node::Break { .. } => self.line("break;"),
+            Node::Continue { .. } => self.line("continue;"),
+            _ => {
+                let value = self.expr(node);
+                self.line(&format!("(void){};", value));
+            }
+        }
+    }
+
+    fn assign(&mut self, targ: &Node, value: &Node, opr: &str) {
+        let opr = opr.trim_end_matches('=');
+
+        match targ {
+            Node::Var { value: name, .. } => {
+                let Some(local) = self.local(name) else {
+                    self.fail(&format!("cannot assign to `{}`", name));
+                    return;
+                };
+
+                let value = if opr.is_empty() {
+                    self.expr(value)
+                } else {
+                    let right = self.expr(value);
+                    self.binary(opr, &local, &right, self.types.type_of(targ), self.types.type_of(value))
+                };
+                self.line(&format!("{} = {};", local, value));
+            }
+            Node::MemLockup { targ: field, obj, .. } => {
+                let Node::Var { value: field, .. } = field.as_ref() else {
+                    self.fail("invalid assignment target");
+                    return;
+                };
+
+                let object = self.expr(obj);
+                let slot = self.fresh("slot");
+                let place = self.field_place(&object, obj, field, "set");
+                self.line(&format!("mx_value *{} = {};", slot, place));
+
+                let value = if opr.is_empty() {
+                    self.expr(value)
+                } else {
+                    let current = self.temp(format!("*{}", slot));
+                    let right = self.expr(value);
+                    self.binary(opr, &current, &right, self.types.type_of(targ), self.types.type_of(value))
+                };
+                self.line(&format!("*{} = {};", slot, value));
+            }
+            _ => {
+                self.fail("invalid assignment target");
+            }
+        }
+    }
+
+    // the value of `let x: T;` before anything is assigned
+    fn default_value(default: DefaultValue) -> &'static str {
+        match default {
+            DefaultValue::Int => "mx_int(0)",
+            DefaultValue::Float => "mx_float(0.0)",
+            DefaultValue::Bool => "mx_bool(false)",
+            DefaultValue::Str => "mx_str_lit(\"\")",
+            DefaultValue::List => "mx_list_new(0, NULL)",
+            DefaultValue::Dict => "mx_dict_empty()",
+            DefaultValue::Null => "mx_null()",
+        }
+    }
+
+    fn condition(&mut self, cond: &Node) -> String {
+        let value = self.expr(cond);
+        match self.types.type_of(cond) {
+            Some(Type::Bool) => format!("{}.as.b", value),
+            _ => format!("mx_cond({})", value),
+        }
+    }
+
+    // C for the value of `node`, after emitting whatever statements it needs;
+    // the result is a local, a constant or a temporary, so it can be read in any order
+    fn expr(&mut self, node: &Node) -> String {
+        match node {
+            Node::Int { value, .. } => match value.parse::<i64>() {
+                Ok(value) => format!("mx_int(INT64_C({}))", value),
+                Err(_) => self.fail(&format!("invalid integer literal `{}`", value)),
+            },
+            Node::Float { value, .. } => match value.parse::<f64>() {
+                Ok(value) => format!("mx_float({:?})", value),
+                Err(_) => self.fail(&format!("invalid float literal `{}`", value)),
+            },
+            Node::Bool { value, .. } => format!("mx_bool({})", value == "true"),
+            Node::Str { value, .. } => self.string(value),
+            Node::Null { .. } => "mx_null()".to_owned(),
+            Node::Void => "mx_void()".to_owned(),
+            Node::Var { value, .. } => self.lookup(value),
+            Node::List { element, .. } => {
+                let items = self.args(element);
+                self.temp(format!("mx_list_new({}, {})", element.len(), items))
+            }
+            Node::Dict { key_value, .. } => {
+                let mut pairs = Vec::new();
+                for pair in key_value {
+                    pairs.push(self.expr(&pair.0));
+                    pairs.push(self.expr(&pair.1));
+                }
+                let pairs = Self::array(&pairs);
+                self.temp(format!("mx_dict_new({}, {})", key_value.len(), pairs))
+            }
+            Node::Range { min, max, .. } => {
+                let min = self.expr(min);
+                let max = self.expr(max);
+                self.temp(format!("mx_range({}, {})", min, max))
+            }
+            Node::MemLockup { targ, obj, .. } => {
+                let Node::Var { value: field, .. } = targ.as_ref() else {
+                    return "mx_void()".to_owned();
+                };
+
+                let object = self.expr(obj);
+                let place = self.field_place(&object, obj, field, "read");
+                self.temp(format!("*{}", place))
+            }
+            Node::CodeLockup { .. } => self.path(node),
+            Node::FuncCall { func, args, .. } => self.call(func, args),
+            Node::BinaryOp { lhs, rhs, opr, .. } if opr == "&&" || opr == "||" => {
+                let left = self.expr(lhs);
+                let result = self.temp(left);
+
+                // the right side only runs when the left one does not decide the result
+                let test = if opr == "&&" { "" } else { "!" };
+                self.line(&format!("if ({}mx_cond({})) {{", test, result));
+                self.indent += 1;
+                let right = self.expr(rhs);
+                self.line(&format!("{} = {};", result, right));
+                self.indent -= 1;
+                self.line("}");
+                result
+            }
+            Node::BinaryOp { lhs, rhs, opr, .. } => {
+                let left = self.expr(lhs);
+                let right = self.expr(rhs);
+                let value = self.binary(opr, &left, &right, self.types.type_of(lhs), self.types.type_of(rhs));
+                self.temp(value)
+            }
+            Node::UnaryOp { opr, value, .. } => {
+                let operand = self.expr(value);
+                let value = match (opr.as_str(), self.types.type_of(value)) {
+                    ("!", Some(Type::Bool)) => format!("mx_bool(!{}.as.b)", operand),
+                    ("-", Some(Type::Float | Type::Double)) => format!("mx_float(-{}.as.f)", operand),
+                    _ => format!("mx_unary({}, {})", quote(opr), operand),
+                };
+                self.temp(value)
+            }
+            _ => self.fail("expression cannot be evaluated"),
+        }
+    }
+
+    // uses the checked operand types to skip the runtime's dynamic dispatch when it can
+    fn binary(&self, opr: &str, left: &str, right: &str, ltype: Option<&Type>, rtype: Option<&Type>) -> String {
+        let int = |ty: Option<&Type>| matches!(ty, Some(Type::Int | Type::Long));
+        let float = |ty: Option<&Type>| matches!(ty, Some(Type::Float | Type::Double));
+
+        if int(ltype) && int(rtype) {
+            let (l, r) = (format!("{}.as.i", left), format!("{}.as.i", right));
+            match opr {
+                "+" => return format!("mx_int(mx_wrap_add({}, {}))", l, r),
+                "-" => return format!("mx_int(mx_wrap_sub({}, {}))", l, r),
+                "*" => return format!("mx_int(mx_wrap_mul({}, {}))", l, r),
+                "&" | "|" => return format!("mx_int({} {} {})", l, opr, r),
+                "<" | ">" | "<=" | ">=" | "==" | "!=" => return format!("mx_bool({} {} {})", l, opr, r),
+                _ => {}
+            }
+        }
+
+        if float(ltype) && float(rtype) {
+            let (l, r) = (format!("{}.as.f", left), format!("{}.as.f", right));
+            match opr {
+                "+" | "-" | "*" | "/" => return format!("mx_float({} {} {})", l, opr, r),
+                "<" | ">" | "<=" | ">=" | "==" | "!=" => return format!("mx_bool({} {} {})", l, opr, r),
+                _ => {}
+            }
+        }
+
+        if matches!((ltype, rtype), (Some(Type::Bool), Some(Type::Bool))) && matches!(opr, "==" | "!=") {
+            return format!("mx_bool({}.as.b {} {}.as.b)", left, opr, right);
+        }
+
+        format!("mx_binary({}, {}, {})", quote(opr), left, right)
+    }
+
+    // a pointer to a field, by position when the checker knows the struct
+    fn field_place(&self, object: &str, obj: &Node, field: &str, action: &str) -> String {
+        if let Some(Type::Struct(_, name, _)) = self.types.type_of(obj)
+            && let Some(index) = self.structs.get(name).and_then(|info| info.fields.iter().position(|f| f == field))
+        {
+            return format!("mx_field_at({}, {}, {}, {})", object, index, quote(field), quote(action));
+        }
+
+        format!("mx_field({}, {}, {})", object, quote(field), quote(action))
+    }
+
+    fn lookup(&mut self, name: &str) -> String {
+        if let Some(local) = self.local(name) {
+            return local;
+        }
+
+        if let Some((c_name, _)) = self.functions.get(name) {
+            return format!("mx_func_value(&{}_info)", c_name);
+        }
+
+        if self.structs.contains_key(name) {
+            return format!("mx_type_value(&{})", Self::struct_name(name));
+        }
+
+        self.fail(&format!("`{}` is not available at runtime", name))
+    }
+
+    // `Enum::Variant` or `Struct::method`
+    fn path(&mut self, node: &Node) -> String {
+        match self.types.path(node) {
+            Some(Path::Variant(owner, name)) => {
+                if let Some(variant) = self.enums.get(&owner).and_then(|variants| variants.iter().position(|v| *v == name)) {
+                    return format!("mx_enum_value(&{}, {})", Self::enum_name(&owner), variant);
+                }
+            }
+            Some(Path::Method(owner, name)) => {
+                if let Some(info) = self.structs.get(&owner)
+                    && let Some((_, c_name, _)) = info.methods.iter().find(|(method, _, _)| *method == name)
+                {
+                    return format!("mx_func_value(&{}_info)", c_name);
+                }
+            }
+            None => {}
+        }
+
+        self.fail("path cannot be evaluated at runtime")
+    }
+
+    fn call(&mut self, func: &Node, args: &[Box<Node>]) -> String {
+        // `std::name(...)` calls into the runtime
+        if let Node::CodeLockup { targ, obj, .. } = func
+            && let (Node::Var { value: name, .. }, Node::Var { value: module, .. }) = (targ.as_ref(), obj.as_ref())
+            && module == "std"
+        {
+            let values = self.args(args);
+            return self.temp(format!("mx_std({}, {}, {})", quote(name), args.len(), values));
+        }
+
+        if let Node::MemLockup { targ, obj, .. } = func
+            && let Node::Var { value: name, .. } = targ.as_ref()
+        {
+            let receiver = self.expr(obj);
+
+            // a method of a known struct is called directly, unless a field shadows it
+            if let Some(Type::Struct(_, owner, _)) = self.types.type_of(obj)
+                && let Some(info) = self.structs.get(owner)
+                && !info.fields.contains(name)
+                && let Some((_, c_name, arity)) = info.methods.iter().find(|(method, _, _)| method == name)
+                && *arity == args.len()
+            {
+                let c_name = c_name.clone();
+                let mut values = vec![format!("mx_receiver({}, {})", receiver, quote(name))];
+                for arg in args {
+                    values.push(self.expr(arg));
+                }
+                return self.temp(format!("{}({})", c_name, values.join(", ")));
+            }
+
+            let values = self.args(args);
+            return self.temp(format!("mx_call_method({}, {}, {}, {})", receiver, quote(name), args.len(), values));
+        }
+
+        // direct calls to functions and struct constructors the compiler can see
+        if let Node::Var { value: name, .. } = func
+            && self.local(name).is_none()
+        {
+            if let Some((c_name, arity)) = self.functions.get(name)
+                && *arity == args.len()
+            {
+                let c_name = c_name.clone();
+                let values = args.iter().map(|arg| self.expr(arg)).collect::<Vec<_>>();
+                return self.temp(format!("{}({})", c_name, values.join(", ")));
+            }
+
+            if self.structs.contains_key(name) {
+                let values = self.args(args);
+                return self.temp(format!("mx_new_struct(&{}, {}, {})", Self::struct_name(name), args.len(), values));
+            }
+        }
+
+        let callee = self.expr(func);
+        let values = self.args(args);
+        self.temp(format!("mx_call({}, {}, {})", callee, args.len(), values))
+    }
+
+    // arguments evaluated left to right, as a C array expression
+    fn args(&mut self, args: &[Box<Node>]) -> String {
+        let values = args.iter().map(|arg| self.expr(arg)).collect::<Vec<_>>();
+        Self::array(&values)
+    }
+
+    fn array(values: &[String]) -> String {
+        if values.is_empty() {
+            "NULL".to_owned()
+        } else {
+            format!("(mx_value[]){{{}}}", values.join(", "))
+        }
+    }
+
+    fn string(&mut self, value: &str) -> String {
+        let index = match self.strings.iter().position(|existing| existing == value) {
+            Some(index) => index,
+            None => {
+                self.strings.push(value.to_owned());
+                self.strings.len() - 1
+            }
+        };
+        format!("mx_k{}", index)
+    }
+
+    // a runtime error for code the checker let through but the backend cannot run
+    fn fail(&mut self, message: &str) -> String {
+        self.line(&format!("mx_fail(\"E0504\", \"%s\", {});", quote(message)));
+        "mx_void()".to_owned()
+    }
+
+    fn temp(&mut self, value: String) -> String {
+        let name = self.fresh("t");
+        self.line(&format!("mx_value {} = {};", name, value));
+        name
+    }
+
+    fn fresh(&mut self, prefix: &str) -> String {
+        self.temps += 1;
+        format!("{}{}", prefix, self.temps)
+    }
+
+    fn local(&self, name: &str) -> Option<String> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name)).cloned()
+    }
+
+    // every declaration gets its own C name, so shadowing never reads the new variable early
+    fn declare_local(&mut self, name: &str) -> String {
+        self.temps += 1;
+        let local = format!("v_{}_{}", mangle(name), self.temps);
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_owned(), local.clone());
+        }
+        local
+    }
+
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.indent {
+            self.out.push_str("    ");
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    fn or_null(items: Vec<String>) -> String {
+        if items.is_empty() { "NULL".to_owned() } else { items.join(", ") }
+    }
+
+    fn function_name(name: &str) -> String {
+        format!("mx_f_{}", mangle(name))
+    }
+
+    fn method_name(owner: &str, name: &str) -> String {
+        format!("mx_m_{}_{}", mangle(owner), mangle(name))
+    }
+
+    fn struct_name(name: &str) -> String {
+        format!("mx_s_{}", mangle(name))
+    }
+
+    fn enum_name(name: &str) -> String {
+        format!("mx_e_{}", mangle(name))
+    }
+}
@@ -0,0 +1,88 @@
+// host for modules built by `mix build --target wasm32`
+//
+//   node build/debug/wasm32/bin/mix_host.mjs build/debug/wasm32/bin/main.wasm
+//
+// or, from a page or another tool, `await run(bytes, { write })` with `write(fd, bytes)`
+// receiving everything the program prints, fd 1 for output and 2 for errors
+
+class Exit {
+    constructor(code) {
+        this.code = code;
+    }
+}
+
+// like Rust's `{:?}` for f64, which `mix run` uses
+export function formatFloat(value) {
+    if (Number.isNaN(value)) return "NaN";
+    if (value === Infinity) return "inf";
+    if (value === -Infinity) return "-inf";
+    if (value === 0) return Object.is(value, -0) ? "-0.0" : "0.0";
+
+    const [mantissa, power] = value.toExponential().split("e");
+    const exponent = Number(power);
+    const sign = value < 0 ? "-" : "";
+    const digits = mantissa.replace("-", "").replace(".", "");
+
+    if (exponent < -4 || exponent >= 16) {
+        return `${mantissa}e${exponent}`;
+    }
+    if (exponent < 0) {
+        return `${sign}0.${"0".repeat(-exponent - 1)}${digits}`;
+    }
+    const whole = digits.padEnd(exponent + 1, "0");
+    const fraction = digits.slice(exponent + 1);
+    return `${sign}${whole.slice(0, exponent + 1)}.${fraction || "0"}`;
+}
+
+// runs `main` and resolves to the exit code, the value `main` returns when it is an `int`
+export async function run(bytes, { write }) {
+    let memory;
+    const encoder = new TextEncoder();
+    const imports = {
+        mix: {
+            write: (fd, ptr, len) => write(fd, new Uint8Array(memory.buffer, ptr, len).slice()),
+            write_float: (fd, value) => write(fd, encoder.encode(formatFloat(value))),
+            exit: (code) => {
+                throw new Exit(code);
+            },
+            pow: Math.pow,
+            rem: (a, b) => a % b,
+        },
+    };
+
+    const { instance } = await WebAssembly.instantiate(bytes, imports);
+    memory = instance.exports.memory;
+
+    try {
+        const result = instance.exports.main();
+        return typeof result === "bigint" ? Number(BigInt.asIntN(32, result)) : 0;
+    } catch (error) {
+        if (error instanceof Exit) {
+            return error.code;
+        }
+        // the engine's stack ran out before the depth limit did
+        if (error instanceof RangeError) {
+            write(2, encoder.encode("error[E0505]: stack overflow\n  = note: the WebAssembly engine ran out of stack\n"));
+            return 1;
+        }
+        throw error;
+    }
+}
+
+// calls nest on the engine's stack, so from the command line the program runs on a thread with
+// room for as many as `mix run` allows
+const STACK_SIZE_MB = 512;
+
+const { isMainThread, workerData } = typeof process !== "undefined" ? await import("node:worker_threads") : {};
+
+if (isMainThread && process.argv[1] && import.meta.url.endsWith(process.argv[1].split("/").pop())) {
+    const { Worker } = await import("node:worker_threads");
+    const path = process.argv[2] ?? new URL("main.wasm", import.meta.url).pathname;
+    const worker = new Worker(new URL(import.meta.url), { workerData: path, resourceLimits: { stackSizeMb: STACK_SIZE_MB } });
+    worker.on("exit", (code) => {
+        process.exitCode = code;
+    });
+} else if (isMainThread === false && typeof workerData === "string") {
+    const { readFileSync, writeSync } = await import("node:fs");
+    process.exit(await run(readFileSync(workerData), { write: (fd, bytes) => writeSync(fd, bytes) }));
+}
@@ -0,0 +1,859 @@
+/* runtime for C generated by `mix build`, values behave like the interpreter's */
+#ifndef MIX_RUNTIME_H
+#define MIX_RUNTIME_H
+
+#include <math.h>
+#include <pthread.h>
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+/* the generated file defines MX_MAX_DEPTH, how deep calls nest under every backend, and `main`
+   runs on a stack with MX_FRAME_SIZE bytes for each of those calls */
+#define MX_FRAME_SIZE 4096
+
+typedef enum {
+    MX_INT, MX_FLOAT, MX_BOOL, MX_STR, MX_NULL, MX_VOID, MX_LIST,
+    MX_DICT, MX_RANGE, MX_STRUCT, MX_ENUM, MX_FUNC, MX_TYPE
+} mx_tag;
+
+typedef struct mx_value mx_value;
+
+typedef struct {
+    size_t len;
+    char data[];
+} mx_str;
+
+typedef struct {
+    size_t len, cap;
+    mx_value *items;
+} mx_list;
+
+/* kept as pairs so iteration follows insertion order */
+typedef struct {
+    size_t len, cap;
+    mx_value *keys, *values;
+} mx_dict;
+
+typedef struct {
+    const char *name;
+    int arity;
+    /* methods take `self` ahead of their parameters */
+    int receiver;
+    mx_value (*call)(mx_value *args);
+} mx_func;
+
+typedef struct {
+    const char *name;
+    const mx_func *func;
+} mx_method;
+
+typedef struct {
+    const char *name;
+    int nfields;
+    const char *const *fields;
+    int nmethods;
+    const mx_method *methods;
+} mx_struct_info;
+
+typedef struct {
+    const mx_struct_info *info;
+    mx_value *fields;
+} mx_object;
+
+typedef struct {
+    const char *name;
+    const char *const *variants;
+} mx_enum_info;
+
+struct mx_value {
+    mx_tag tag;
+    union {
+        int64_t i;
+        double f;
+        bool b;
+        mx_str *s;
+        mx_list *l;
+        mx_dict *d;
+        struct { int64_t min, max; } r;
+        mx_object *o;
+        struct { const mx_enum_info *info; int variant; } e;
+        const mx_func *fn;
+        const mx_struct_info *type;
+    } as;
+};
+
+/* position of the statement being executed, for runtime errors */
+static const char *mx_file = "";
+static int mx_line, mx_column, mx_depth;
+
+#define MX_AT(line, column) (mx_line = (line), mx_column = (column))
+
+static void mx_fail(const char *code, const char *format, ...) {
+    va_list args;
+    fflush(stdout);
+    fprintf(stderr, "error[%s]: ", code);
+    va_start(args, format);
+    vfprintf(stderr, format, args);
+    va_end(args);
+    fprintf(stderr, "\n  --> %s:%d:%d\n", mx_file, mx_line, mx_column);
+    exit(1);
+}
+
+static void *mx_alloc(size_t size) {
+    void *ptr = calloc(1, size ? size : 1);
+    if (!ptr) {
+        mx_fail("E0504", "out of memory");
+    }
+    return ptr;
+}
+
+static void mx_enter(const char *name) {
+    if (++mx_depth > MX_MAX_DEPTH) {
+        fflush(stdout);
+        fprintf(stderr, "error[E0505]: stack overflow while calling `%s`\n  --> %s:%d:%d\n", name, mx_file, mx_line, mx_column);
+        fprintf(stderr, "  = note: calls can nest at most %d deep\n", MX_MAX_DEPTH);
+        exit(1);
+    }
+}
+
+static mx_value (*mx_main)(void);
+static mx_value mx_result;
+
+static void *mx_start(void *unused) {
+    (void)unused;
+    mx_result = mx_main();
+    return NULL;
+}
+
+static mx_value mx_run(mx_value (*main)(void)) {
+    pthread_attr_t attr;
+    pthread_t thread;
+    mx_main = main;
+    if (pthread_attr_init(&attr) != 0 || pthread_attr_setstacksize(&attr, (size_t)MX_MAX_DEPTH * MX_FRAME_SIZE) != 0
+        || pthread_create(&thread, &attr, mx_start, NULL) != 0) {
+        mx_fail("E0504", "out of memory");
+    }
+    pthread_join(thread, NULL);
+    return mx_result;
+}
+
+static inline mx_value mx_int(int64_t value) { mx_value v; v.tag = MX_INT; v.as.i = value; return v; }
+static inline mx_value mx_float(double value) { mx_value v; v.tag = MX_FLOAT; v.as.f = value; return v; }
+static inline mx_value mx_bool(bool value) { mx_value v; v.tag = MX_BOOL; v.as.b = value; return v; }
+static inline mx_value mx_null(void) { mx_value v; v.tag = MX_NULL; v.as.i = 0; return v; }
+static inline mx_value mx_void(void) { mx_value v; v.tag = MX_VOID; v.as.i = 0; return v; }
+static inline mx_value mx_func_value(const mx_func *fn) { mx_value v; v.tag = MX_FUNC; v.as.fn = fn; return v; }
+static inline mx_value mx_type_value(const mx_struct_info *type) { mx_value v; v.tag = MX_TYPE; v.as.type = type; return v; }
+
+static inline mx_value mx_enum_value(const mx_enum_info *info, int variant) {
+    mx_value v;
+    v.tag = MX_ENUM;
+    v.as.e.info = info;
+    v.as.e.variant = variant;
+    return v;
+}
+
+static mx_value mx_str_n(const char *data, size_t len) {
+    mx_str *s = mx_alloc(sizeof(mx_str) + len + 1);
+    s->len = len;
+    memcpy(s->data, data, len);
+    s->data[len] = '\0';
+    mx_value v;
+    v.tag = MX_STR;
+    v.as.s = s;
+    return v;
+}
+
+static mx_value mx_str_lit(const char *data) {
+    return mx_str_n(data, strlen(data));
+}
+
+static mx_value mx_list_new(size_t count, const mx_value *items) {
+    mx_list *l = mx_alloc(sizeof(mx_list));
+    l->cap = count ? count : 4;
+    l->items = mx_alloc(l->cap * sizeof(mx_value));
+    if (count) {
+        memcpy(l->items, items, count * sizeof(mx_value));
+    }
+    l->len = count;
+    mx_value v;
+    v.tag = MX_LIST;
+    v.as.l = l;
+    return v;
+}
+
+static void mx_list_push(mx_list *l, mx_value item) {
+    if (l->len == l->cap) {
+        l->cap *= 2;
+        l->items = realloc(l->items, l->cap * sizeof(mx_value));
+        if (!l->items) {
+            mx_fail("E0504", "out of memory");
+        }
+    }
+    l->items[l->len++] = item;
+}
+
+static mx_value mx_dict_empty(void) {
+    mx_dict *d = mx_alloc(sizeof(mx_dict));
+    d->cap = 4;
+    d->keys = mx_alloc(d->cap * sizeof(mx_value));
+    d->values = mx_alloc(d->cap * sizeof(mx_value));
+    mx_value v;
+    v.tag = MX_DICT;
+    v.as.d = d;
+    return v;
+}
+
+static inline mx_value mx_range(mx_value min, mx_value max) {
+    if (min.tag != MX_INT || max.tag != MX_INT) {
+        mx_fail("E0504", "range bounds must be integers");
+    }
+    mx_value v;
+    v.tag = MX_RANGE;
+    v.as.r.min = min.as.i;
+    v.as.r.max = max.as.i;
+    return v;
+}
+
+static const char *mx_type_name(mx_value v) {
+    static const char *const names[] = {
+        "int", "float", "bool", "str", "null", "void", "list",
+        "dict", "range", "struct", "enum", "function", "type"
+    };
+    return names[v.tag];
+}
+
+static bool mx_eq(mx_value a, mx_value b) {
+    if ((a.tag == MX_INT || a.tag == MX_FLOAT) && (b.tag == MX_INT || b.tag == MX_FLOAT)) {
+        if (a.tag == MX_INT && b.tag == MX_INT) {
+            return a.as.i == b.as.i;
+        }
+        double x = a.tag == MX_INT ? (double)a.as.i : a.as.f;
+        double y = b.tag == MX_INT ? (double)b.as.i : b.as.f;
+        return x == y;
+    }
+    if (a.tag != b.tag) {
+        return false;
+    }
+
+    switch (a.tag) {
+    case MX_BOOL: return a.as.b == b.as.b;
+    case MX_STR: return a.as.s->len == b.as.s->len && memcmp(a.as.s->data, b.as.s->data, a.as.s->len) == 0;
+    case MX_NULL:
+    case MX_VOID: return true;
+    case MX_LIST:
+        if (a.as.l == b.as.l) return true;
+        if (a.as.l->len != b.as.l->len) return false;
+        for (size_t i = 0; i < a.as.l->len; i++) {
+            if (!mx_eq(a.as.l->items[i], b.as.l->items[i])) return false;
+        }
+        return true;
+    case MX_DICT:
+        if (a.as.d == b.as.d) return true;
+        if (a.as.d->len != b.as.d->len) return false;
+        for (size_t i = 0; i < a.as.d->len; i++) {
+            bool found = false;
+            for (size_t j = 0; j < b.as.d->len && !found; j++) {
+                found = mx_eq(a.as.d->keys[i], b.as.d->keys[j]) && mx_eq(a.as.d->values[i], b.as.d->values[j]);
+            }
+            if (!found) return false;
+        }
+        return true;
+    case MX_RANGE: return a.as.r.min == b.as.r.min && a.as.r.max == b.as.r.max;
+    case MX_STRUCT:
+        if (a.as.o == b.as.o) return true;
+        if (a.as.o->info != b.as.o->info) return false;
+        for (int i = 0; i < a.as.o->info->nfields; i++) {
+            if (!mx_eq(a.as.o->fields[i], b.as.o->fields[i])) return false;
+        }
+        return true;
+    case MX_ENUM: return a.as.e.info == b.as.e.info && a.as.e.variant == b.as.e.variant;
+    case MX_FUNC: return a.as.fn == b.as.fn;
+    case MX_TYPE: return a.as.type == b.as.type;
+    default: return false;
+    }
+}
+
+static void mx_dict_set(mx_dict *d, mx_value key, mx_value value) {
+    for (size_t i = 0; i < d->len; i++) {
+        if (mx_eq(d->keys[i], key)) {
+            d->values[i] = value;
+            return;
+        }
+    }
+    if (d->len == d->cap) {
+        d->cap *= 2;
+        d->keys = realloc(d->keys, d->cap * sizeof(mx_value));
+        d->values = realloc(d->values, d->cap * sizeof(mx_value));
+        if (!d->keys || !d->values) {
+            mx_fail("E0504", "out of memory");
+        }
+    }
+    d->keys[d->len] = key;
+    d->values[d->len] = value;
+    d->len++;
+}
+
+static mx_value mx_dict_new(size_t count, const mx_value *pairs) {
+    mx_value v = mx_dict_empty();
+    for (size_t i = 0; i < count; i++) {
+        mx_dict_set(v.as.d, pairs[2 * i], pairs[2 * i + 1]);
+    }
+    return v;
+}
+
+/* a growable byte buffer for formatting values */
+typedef struct {
+    char *data;
+    size_t len, cap;
+} mx_buf;
+
+static void mx_buf_put(mx_buf *b, const char *data, size_t len) {
+    if (b->len + len + 1 > b->cap) {
+        b->cap = (b->len + len + 1) * 2;
+        b->data = realloc(b->data, b->cap);
+        if (!b->data) {
+            mx_fail("E0504", "out of memory");
+        }
+    }
+    memcpy(b->data + b->len, data, len);
+    b->len += len;
+    b->data[b->len] = '\0';
+}
+
+static void mx_buf_str(mx_buf *b, const char *data) {
+    mx_buf_put(b, data, strlen(data));
+}
+
+/* shortest text that reads back as the same double, laid out like Rust's `{:?}` */
+static void mx_buf_float(mx_buf *b, double value) {
+    char text[64];
+    if (isnan(value)) {
+        mx_buf_str(b, "NaN");
+        return;
+    }
+    if (isinf(value)) {
+        mx_buf_str(b, value < 0 ? "-inf" : "inf");
+        return;
+    }
+    if (value == 0) {
+        mx_buf_str(b, signbit(value) ? "-0.0" : "0.0");
+        return;
+    }
+
+    int digits = 1;
+    for (; digits < 17; digits++) {
+        snprintf(text, sizeof text, "%.*e", digits - 1, value);
+        if (strtod(text, NULL) == value) break;
+    }
+    snprintf(text, sizeof text, "%.*e", digits - 1, value);
+
+    char *mark = strchr(text, 'e');
+    int exponent = atoi(mark + 1);
+    if (exponent < -4 || exponent >= 16) {
+        *mark = '\0';
+        mx_buf_str(b, text);
+        snprintf(text, sizeof text, "e%d", exponent);
+        mx_buf_str(b, text);
+        return;
+    }
+
+    int precision = digits - 1 - exponent;
+    snprintf(text, sizeof text, "%.*f", precision > 0 ? precision : 0, value);
+    mx_buf_str(b, text);
+    if (!strchr(text, '.')) {
+        mx_buf_str(b, ".0");
+    }
+}
+
+static void mx_buf_quoted(mx_buf *b, const mx_str *s) {
+    mx_buf_str(b, "\"");
+    for (size_t i = 0; i < s->len; i++) {
+        switch (s->data[i]) {
+        case '"': mx_buf_str(b, "\\\""); break;
+        case '\\': mx_buf_str(b, "\\\\"); break;
+        case '\n': mx_buf_str(b, "\\n"); break;
+        case '\r': mx_buf_str(b, "\\r"); break;
+        case '\t': mx_buf_str(b, "\\t"); break;
+        case '\0': mx_buf_str(b, "\\0"); break;
+        default: mx_buf_put(b, &s->data[i], 1);
+        }
+    }
+    mx_buf_str(b, "\"");
+}
+
+static void mx_buf_value(mx_buf *b, mx_value v, bool nested) {
+    char text[64];
+    switch (v.tag) {
+    case MX_INT:
+        snprintf(text, sizeof text, "%lld", (long long)v.as.i);
+        mx_buf_str(b, text);
+        break;
+    case MX_FLOAT: mx_buf_float(b, v.as.f); break;
+    case MX_BOOL: mx_buf_str(b, v.as.b ? "true" : "false"); break;
+    case MX_STR:
+        if (nested) mx_buf_quoted(b, v.as.s);
+        else mx_buf_put(b, v.as.s->data, v.as.s->len);
+        break;
+    case MX_NULL: mx_buf_str(b, "null"); break;
+    case MX_VOID: mx_buf_str(b, "void"); break;
+    case MX_LIST:
+        mx_buf_str(b, "[");
+        for (size_t i = 0; i < v.as.l->len; i++) {
+            if (i > 0) mx_buf_str(b, ", ");
+            mx_buf_value(b, v.as.l->items[i], true);
+        }
+        mx_buf_str(b, "]");
+        break;
+    case MX_DICT:
+        mx_buf_str(b, "{");
+        for (size_t i = 0; i < v.as.d->len; i++) {
+            if (i > 0) mx_buf_str(b, ", ");
+            mx_buf_value(b, v.as.d->keys[i], true);
+            mx_buf_str(b, ": ");
+            mx_buf_value(b, v.as.d->values[i], true);
+        }
+        mx_buf_str(b, "}");
+        break;
+    case MX_RANGE:
+        snprintf(text, sizeof text, "%lld->%lld", (long long)v.as.r.min, (long long)v.as.r.max);
+        mx_buf_str(b, text);
+        break;
+    case MX_STRUCT:
+        mx_buf_str(b, v.as.o->info->name);
+        mx_buf_str(b, " {");
+        for (int i = 0; i < v.as.o->info->nfields; i++) {
+            mx_buf_str(b, i > 0 ? ", " : " ");
+            mx_buf_str(b, v.as.o->info->fields[i]);
+            mx_buf_str(b, ": ");
+            mx_buf_value(b, v.as.o->fields[i], true);
+        }
+        mx_buf_str(b, " }");
+        break;
+    case MX_ENUM:
+        mx_buf_str(b, v.as.e.info->name);
+        mx_buf_str(b, "::");
+        mx_buf_str(b, v.as.e.info->variants[v.as.e.variant]);
+        break;
+    case MX_FUNC: mx_buf_str(b, "<function>"); break;
+    case MX_TYPE: mx_buf_str(b, v.as.type->name); break;
+    }
+}
+
+/* arguments joined by spaces, the way `std::print` shows them */
+static mx_value mx_join(int argc, const mx_value *argv) {
+    mx_buf b = {0};
+    mx_buf_str(&b, "");
+    for (int i = 0; i < argc; i++) {
+        if (i > 0) mx_buf_str(&b, " ");
+        mx_buf_value(&b, argv[i], false);
+    }
+    mx_value s = mx_str_n(b.data, b.len);
+    free(b.data);
+    return s;
+}
+
+static inline bool mx_cond(mx_value v) {
+    if (v.tag != MX_BOOL) {
+        mx_fail("E0504", "expected `bool` condition, found `%s`", mx_type_name(v));
+    }
+    return v.as.b;
+}
+
+static inline double mx_as_float(mx_value v) {
+    return v.tag == MX_INT ? (double)v.as.i : v.as.f;
+}
+
+static size_t mx_utf8_len(const mx_str *s) {
+    size_t count = 0;
+    for (size_t i = 0; i < s->len; i++) {
+        if (((unsigned char)s->data[i] & 0xC0) != 0x80) count++;
+    }
+    return count;
+}
+
+/* integer arithmetic wraps like the interpreter's */
+static inline int64_t mx_wrap_add(int64_t a, int64_t b) { return (int64_t)((uint64_t)a + (uint64_t)b); }
+static inline int64_t mx_wrap_sub(int64_t a, int64_t b) { return (int64_t)((uint64_t)a - (uint64_t)b); }
+static inline int64_t mx_wrap_mul(int64_t a, int64_t b) { return (int64_t)((uint64_t)a * (uint64_t)b); }
+
+static mx_value mx_operand_error(const char *op, mx_value a, mx_value b) {
+    mx_fail("E0504", "cannot apply `%s` to `%s` and `%s`", op, mx_type_name(a), mx_type_name(b));
+    return mx_void();
+}
+
+static int mx_str_cmp(const mx_str *a, const mx_str *b) {
+    size_t len = a->len < b->len ? a->len : b->len;
+    int order = memcmp(a->data, b->data, len);
+    if (order != 0) return order;
+    return a->len < b->len ? -1 : a->len > b->len;
+}
+
+static mx_value mx_int_op(const char *op, int64_t a, int64_t b) {
+    if (!strcmp(op, "+")) return mx_int(mx_wrap_add(a, b));
+    if (!strcmp(op, "-")) return mx_int(mx_wrap_sub(a, b));
+    if (!strcmp(op, "*")) return mx_int(mx_wrap_mul(a, b));
+    if (!strcmp(op, "/") || !strcmp(op, "%")) {
+        if (b == 0) mx_fail("E0501", "attempt to divide by zero");
+        if (a == INT64_MIN && b == -1) return mx_int(op[0] == '/' ? INT64_MIN : 0);
+        return mx_int(op[0] == '/' ? a / b : a % b);
+    }
+    if (!strcmp(op, "^")) {
+        if (b < 0) mx_fail("E0507", "attempt to raise an integer to a negative power");
+        uint64_t result = 1, base = (uint64_t)a;
+        for (uint64_t e = (uint64_t)b; e; e >>= 1) {
+            if (e & 1) result *= base;
+            base *= base;
+        }
+        return mx_int((int64_t)result);
+    }
+    if (!strcmp(op, "&")) return mx_int(a & b);
+    if (!strcmp(op, "|")) return mx_int(a | b);
+    if (!strcmp(op, "<<")) return mx_int((int64_t)((uint64_t)a << (b & 63)));
+    if (!strcmp(op, ">>")) return mx_int(a >> (b & 63));
+    if (!strcmp(op, "<")) return mx_bool(a < b);
+    if (!strcmp(op, ">")) return mx_bool(a > b);
+    if (!strcmp(op, "<=")) return mx_bool(a <= b);
+    if (!strcmp(op, ">=")) return mx_bool(a >= b);
+    return mx_operand_error(op, mx_int(a), mx_int(b));
+}
+
+static mx_value mx_binary(const char *op, mx_value a, mx_value b) {
+    if (!strcmp(op, "==")) return mx_bool(mx_eq(a, b));
+    if (!strcmp(op, "!=")) return mx_bool(!mx_eq(a, b));
+
+    if (a.tag == MX_STR && b.tag == MX_STR) {
+        if (!strcmp(op, "+")) {
+            mx_value s = mx_str_n(a.as.s->data, a.as.s->len + b.as.s->len);
+            memcpy(s.as.s->data + a.as.s->len, b.as.s->data, b.as.s->len);
+            return s;
+        }
+        int order = mx_str_cmp(a.as.s, b.as.s);
+        if (!strcmp(op, "<")) return mx_bool(order < 0);
+        if (!strcmp(op, ">")) return mx_bool(order > 0);
+        if (!strcmp(op, "<=")) return mx_bool(order <= 0);
+        if (!strcmp(op, ">=")) return mx_bool(order >= 0);
+        return mx_operand_error(op, a, b);
+    }
+
+    if (a.tag == MX_INT && b.tag == MX_INT) {
+        return mx_int_op(op, a.as.i, b.as.i);
+    }
+
+    if ((a.tag == MX_INT || a.tag == MX_FLOAT) && (b.tag == MX_INT || b.tag == MX_FLOAT)) {
+        double x = mx_as_float(a), y = mx_as_float(b);
+        if (!strcmp(op, "+")) return mx_float(x + y);
+        if (!strcmp(op, "-")) return mx_float(x - y);
+        if (!strcmp(op, "*")) return mx_float(x * y);
+        if (!strcmp(op, "/")) return mx_float(x / y);
+        if (!strcmp(op, "%")) return mx_float(fmod(x, y));
+        if (!strcmp(op, "^")) return mx_float(pow(x, y));
+        if (!strcmp(op, "<")) return mx_bool(x < y);
+        if (!strcmp(op, ">")) return mx_bool(x > y);
+        if (!strcmp(op, "<=")) return mx_bool(x <= y);
+        if (!strcmp(op, ">=")) return mx_bool(x >= y);
+    }
+
+    return mx_operand_error(op, a, b);
+}
+
+static mx_value mx_unary(const char *op, mx_value v) {
+    if (!strcmp(op, "-") && v.tag == MX_INT) return mx_int(mx_wrap_sub(0, v.as.i));
+    if (!strcmp(op, "-") && v.tag == MX_FLOAT) return mx_float(-v.as.f);
+    if (!strcmp(op, "+") && (v.tag == MX_INT || v.tag == MX_FLOAT)) return v;
+    if (!strcmp(op, "!") && v.tag == MX_BOOL) return mx_bool(!v.as.b);
+    if (!strcmp(op, "~") && v.tag == MX_INT) return mx_int(~v.as.i);
+    mx_fail("E0504", "cannot apply unary `%s` to `%s`", op, mx_type_name(v));
+    return mx_void();
+}
+
+/* `action` is "read" or "set", only for the error message */
+static mx_value *mx_field(mx_value v, const char *name, const char *action) {
+    if (v.tag != MX_STRUCT) {
+        mx_fail("E0504", "cannot %s field `%s` %s `%s`", action, name, strcmp(action, "set") ? "of" : "on", mx_type_name(v));
+    }
+    for (int i = 0; i < v.as.o->info->nfields; i++) {
+        if (!strcmp(v.as.o->info->fields[i], name)) {
+            return &v.as.o->fields[i];
+        }
+    }
+    mx_fail("E0504", "no field `%s` on `%s`", name, v.as.o->info->name);
+    return NULL;
+}
+
+/* a field whose position the compiler already knows, null still has to be caught */
+static inline mx_value *mx_field_at(mx_value v, int index, const char *name, const char *action) {
+    if (v.tag != MX_STRUCT) {
+        return mx_field(v, name, action);
+    }
+    return &v.as.o->fields[index];
+}
+
+/* the receiver of a method the compiler calls directly */
+static inline mx_value mx_receiver(mx_value v, const char *name) {
+    if (v.tag != MX_STRUCT) {
+        mx_fail("E0504", "cannot call `%s` on `%s`", name, mx_type_name(v));
+    }
+    return v;
+}
+
+static mx_value mx_new_struct(const mx_struct_info *info, int argc, const mx_value *argv) {
+    if (argc != info->nfields) {
+        mx_fail("E0504", "`%s` has %d field(s) but %d were supplied", info->name, info->nfields, argc);
+    }
+    mx_object *o = mx_alloc(sizeof(mx_object));
+    o->info = info;
+    o->fields = mx_alloc(sizeof(mx_value) * (size_t)argc);
+    if (argc) {
+        memcpy(o->fields, argv, sizeof(mx_value) * (size_t)argc);
+    }
+    mx_value v;
+    v.tag = MX_STRUCT;
+    v.as.o = o;
+    return v;
+}
+
+static mx_value mx_call(mx_value callee, int argc, const mx_value *argv) {
+    if (callee.tag == MX_TYPE) {
+        return mx_new_struct(callee.as.type, argc, argv);
+    }
+    if (callee.tag != MX_FUNC) {
+        mx_fail("E0504", "`%s` is not callable", mx_type_name(callee));
+    }
+
+    const mx_func *fn = callee.as.fn;
+    if (fn->arity != argc) {
+        mx_fail("E0504", "`%s` takes %d argument(s) but %d were supplied", fn->name, fn->arity, argc);
+    }
+
+    /* a method called through its path has no receiver */
+    mx_value args[argc + 1];
+    args[0] = mx_null();
+    if (argc) {
+        memcpy(args + fn->receiver, argv, sizeof(mx_value) * (size_t)argc);
+    }
+    return fn->call(args);
+}
+
+static int64_t mx_index(mx_value index, size_t len) {
+    if (index.tag != MX_INT) {
+        mx_fail("E0504", "list index must be `int`, found `%s`", mx_type_name(index));
+    }
+    if (index.as.i < 0 || (uint64_t)index.as.i >= len) {
+        mx_fail("E0502", "index %lld out of bounds for a list of length %zu", (long long)index.as.i, len);
+    }
+    return index.as.i;
+}
+
+/* methods every list, dict and string value has */
+static mx_value mx_builtin_method(mx_value receiver, const char *name, int argc, const mx_value *argv) {
+    mx_value arg0 = argc > 0 ? argv[0] : mx_void();
+    mx_value arg1 = argc > 1 ? argv[1] : mx_void();
+
+    if (!strcmp(name, "len")) {
+        if (receiver.tag == MX_LIST) return mx_int((int64_t)receiver.as.l->len);
+        if (receiver.tag == MX_DICT) return mx_int((int64_t)receiver.as.d->len);
+        if (receiver.tag == MX_STR) return mx_int((int64_t)mx_utf8_len(receiver.as.s));
+    }
+
+    if (receiver.tag == MX_LIST) {
+        mx_list *l = receiver.as.l;
+        if (!strcmp(name, "push")) {
+            mx_list_push(l, arg0);
+            return mx_void();
+        }
+        if (!strcmp(name, "pop")) {
+            if (l->len == 0) mx_fail("E0502", "pop from an empty list");
+            return l->items[--l->len];
+        }
+        if (!strcmp(name, "get")) return l->items[mx_index(arg0, l->len)];
+        if (!strcmp(name, "set")) {
+            l->items[mx_index(arg0, l->len)] = arg1;
+            return mx_void();
+        }
+        if (!strcmp(name, "contains")) {
+            for (size_t i = 0; i < l->len; i++) {
+                if (mx_eq(l->items[i], arg0)) return mx_bool(true);
+            }
+            return mx_bool(false);
+        }
+    }
+
+    if (receiver.tag == MX_DICT) {
+        mx_dict *d = receiver.as.d;
+        if (!strcmp(name, "get")) {
+            for (size_t i = 0; i < d->len; i++) {
+                if (mx_eq(d->keys[i], arg0)) return d->values[i];
+            }
+            mx_buf b = {0};
+            mx_buf_value(&b, arg0, false);
+            mx_fail("E0503", "key `%s` not found in dict", b.data);
+        }
+        if (!strcmp(name, "set")) {
+            mx_dict_set(d, arg0, arg1);
+            return mx_void();
+        }
+        if (!strcmp(name, "contains")) {
+            for (size_t i = 0; i < d->len; i++) {
+                if (mx_eq(d->keys[i], arg0)) return mx_bool(true);
+            }
+            return mx_bool(false);
+        }
+        if (!strcmp(name, "remove")) {
+            size_t kept = 0;
+            for (size_t i = 0; i < d->len; i++) {
+                if (!mx_eq(d->keys[i], arg0)) {
+                    d->keys[kept] = d->keys[i];
+                    d->values[kept] = d->values[i];
+                    kept++;
+                }
+            }
+            d->len = kept;
+            return mx_void();
+        }
+        if (!strcmp(name, "keys")) return mx_list_new(d->len, d->keys);
+        if (!strcmp(name, "values")) return mx_list_new(d->len, d->values);
+    }
+
+    if (receiver.tag == MX_STR && !strcmp(name, "contains")) {
+        if (arg0.tag != MX_STR) mx_fail("E0504", "expected `str`, found `%s`", mx_type_name(arg0));
+        if (arg0.as.s->len == 0) return mx_bool(true);
+        for (size_t i = 0; i + arg0.as.s->len <= receiver.as.s->len; i++) {
+            if (!memcmp(receiver.as.s->data + i, arg0.as.s->data, arg0.as.s->len)) return mx_bool(true);
+        }
+        return mx_bool(false);
+    }
+
+    if (receiver.tag == MX_NULL) {
+        mx_fail("E0504", "cannot call `%s` on `null`", name);
+    }
+    mx_fail("E0504", "no method `%s` on `%s`", name, mx_type_name(receiver));
+    return mx_void();
+}
+
+static mx_value mx_call_method(mx_value receiver, const char *name, int argc, const mx_value *argv) {
+    if (receiver.tag != MX_STRUCT) {
+        return mx_builtin_method(receiver, name, argc, argv);
+    }
+
+    const mx_struct_info *info = receiver.as.o->info;
+    /* a field holding a function shadows a method of the same name */
+    for (int i = 0; i < info->nfields; i++) {
+        if (!strcmp(info->fields[i], name)) {
+            return mx_call(receiver.as.o->fields[i], argc, argv);
+        }
+    }
+
+    for (int i = 0; i < info->nmethods; i++) {
+        const mx_func *fn = info->methods[i].func;
+        if (strcmp(info->methods[i].name, name)) continue;
+        if (fn->arity != argc) {
+            mx_fail("E0504", "`%s` takes %d argument(s) but %d were supplied", fn->name, fn->arity, argc);
+        }
+        mx_value args[argc + 1];
+        args[0] = receiver;
+        if (argc) {
+            memcpy(args + 1, argv, sizeof(mx_value) * (size_t)argc);
+        }
+        return fn->call(args);
+    }
+
+    mx_fail("E0504", "no method `%s` on `%s`", name, info->name);
+    return mx_void();
+}
+
+/* loops walk a snapshot, so pushing to the list inside the body does not loop forever */
+static mx_list *mx_iterate(mx_value v) {
+    mx_value items;
+    switch (v.tag) {
+    case MX_RANGE:
+        items = mx_list_new(0, NULL);
+        for (int64_t i = v.as.r.min; i < v.as.r.max; i++) mx_list_push(items.as.l, mx_int(i));
+        return items.as.l;
+    case MX_LIST: return mx_list_new(v.as.l->len, v.as.l->items).as.l;
+    case MX_DICT: return mx_list_new(v.as.d->len, v.as.d->keys).as.l;
+    case MX_STR:
+        items = mx_list_new(0, NULL);
+        for (size_t i = 0; i < v.as.s->len;) {
+            size_t width = 1;
+            while (i + width < v.as.s->len && ((unsigned char)v.as.s->data[i + width] & 0xC0) == 0x80) width++;
+            mx_list_push(items.as.l, mx_str_n(v.as.s->data + i, width));
+            i += width;
+        }
+        return items.as.l;
+    default:
+        mx_fail("E0504", "`%s` is not iterable", mx_type_name(v));
+        return NULL;
+    }
+}
+
+static mx_value mx_std(const char *name, int argc, const mx_value *argv) {
+    mx_value first = argc > 0 ? argv[0] : mx_void();
+
+    if (!strcmp(name, "print") || !strcmp(name, "println") || !strcmp(name, "input")) {
+        mx_value text = mx_join(argc, argv);
+        fwrite(text.as.s->data, 1, text.as.s->len, stdout);
+        if (!strcmp(name, "println")) {
+            fputc('\n', stdout);
+            return mx_void();
+        }
+        if (!strcmp(name, "print")) return mx_void();
+
+        fflush(stdout);
+        mx_buf line = {0};
+        mx_buf_str(&line, "");
+        int ch;
+        while ((ch = getchar()) != EOF && ch != '\n') {
+            char c = (char)ch;
+            mx_buf_put(&line, &c, 1);
+        }
+        while (line.len > 0 && line.data[line.len - 1] == '\r') line.len--;
+        mx_value result = mx_str_n(line.data, line.len);
+        free(line.data);
+        return result;
+    }
+    if (!strcmp(name, "str")) return mx_join(argc, argv);
+    if (!strcmp(name, "int") || !strcmp(name, "long")) {
+        switch (first.tag) {
+        case MX_INT: return first;
+        case MX_FLOAT: return mx_int((int64_t)first.as.f);
+        case MX_BOOL: return mx_int(first.as.b);
+        case MX_STR: {
+            char *end;
+            const char *text = first.as.s->data;
+            while (*text == ' ' || *text == '\t' || *text == '\n' || *text == '\r') text++;
+            long long value = strtoll(text, &end, 10);
+            while (*end == ' ' || *end == '\t' || *end == '\n' || *end == '\r') end++;
+            if (end == text || *end != '\0') mx_fail("E0504", "cannot convert `%s` to `%s`", first.as.s->data, name);
+            return mx_int(value);
+        }
+        default: mx_fail("E0504", "cannot convert `%s` to `%s`", mx_type_name(first), name);
+        }
+    }
+    if (!strcmp(name, "float") || !strcmp(name, "double")) {
+        switch (first.tag) {
+        case MX_INT: return mx_float((double)first.as.i);
+        case MX_FLOAT: return first;
+        case MX_STR: {
+            char *end;
+            const char *text = first.as.s->data;
+            while (*text == ' ' || *text == '\t' || *text == '\n' || *text == '\r') text++;
+            double value = strtod(text, &end);
+            while (*end == ' ' || *end == '\t' || *end == '\n' || *end == '\r') end++;
+            if (end == text || *end != '\0') mx_fail("E0504", "cannot convert `%s` to `%s`", first.as.s->data, name);
+            return mx_float(value);
+        }
+        default: mx_fail("E0504", "cannot convert `%s` to `%s`", mx_type_name(first), name);
+        }
+    }
+    if (!strcmp(name, "len")) return mx_builtin_method(first, "len", 0, NULL);
+
+    mx_fail("E0504", "no function `%s` in module `std`", name);
+    return mx_void();
+}
+
+#endif
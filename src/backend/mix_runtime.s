@@ -0,0 +1,280 @@
+# runtime for programs built by the x86-64 backend, appended to every generated file;
+# strings are a quad length followed by the bytes, and output to stdout is buffered
+
+    .set MX_BUFFER_SIZE, 4096
+
+    .bss
+    .align 8
+mx_depth:
+    .zero 8
+mx_line:
+    .zero 8
+mx_column:
+    .zero 8
+mx_out_len:
+    .zero 8
+mx_out_buf:
+    .zero MX_BUFFER_SIZE
+mx_num_buf:
+    .zero 32
+
+    .data
+    .align 8
+# the file of the statement running last, as a string
+mx_source:
+    .quad mx_file
+
+    .section .rodata
+mx_true:
+    .ascii "true"
+mx_false:
+    .ascii "false"
+mx_space:
+    .ascii " "
+mx_newline:
+    .ascii "\n"
+mx_arrow:
+    .ascii "\n  --> "
+mx_arrow_end:
+mx_colon:
+    .ascii ":"
+mx_msg_divide:
+    .ascii "error[E0501]: attempt to divide by zero"
+mx_msg_divide_end:
+mx_msg_power:
+    .ascii "error[E0507]: attempt to raise an integer to a negative power"
+mx_msg_power_end:
+mx_msg_overflow:
+    .ascii "error[E0505]: stack overflow while calling `"
+mx_msg_overflow_end:
+mx_msg_tick:
+    .ascii "`"
+
+    .text
+
+# write(fd, %rsi, %rdx) until every byte is out
+mx_syscall_write:
+    testq %rdx, %rdx
+    jz 2f
+1:
+    movl $1, %eax
+    syscall
+    testq %rax, %rax
+    jle 2f
+    addq %rax, %rsi
+    subq %rax, %rdx
+    jnz 1b
+2:
+    ret
+
+mx_flush:
+    movl $1, %edi
+    leaq mx_out_buf(%rip), %rsi
+    movq mx_out_len(%rip), %rdx
+    movq $0, mx_out_len(%rip)
+    jmp mx_syscall_write
+
+# buffers %rsi bytes at %rdi for stdout
+mx_write:
+    movq mx_out_len(%rip), %rax
+    addq %rsi, %rax
+    cmpq $MX_BUFFER_SIZE, %rax
+    jbe 1f
+    pushq %rdi
+    pushq %rsi
+    call mx_flush
+    popq %rsi
+    popq %rdi
+    cmpq $MX_BUFFER_SIZE, %rsi
+    jbe 1f
+    movq %rsi, %rdx
+    movq %rdi, %rsi
+    movl $1, %edi
+    jmp mx_syscall_write
+1:
+    leaq mx_out_buf(%rip), %rax
+    addq mx_out_len(%rip), %rax
+    addq %rsi, mx_out_len(%rip)
+    movq %rsi, %rcx
+    movq %rdi, %rsi
+    movq %rax, %rdi
+    rep movsb
+    ret
+
+# writes %rsi bytes at %rdi to stderr unbuffered
+mx_ewrite:
+    movq %rsi, %rdx
+    movq %rdi, %rsi
+    movl $2, %edi
+    jmp mx_syscall_write
+
+# decimal digits of %rdi, returned as %rax = start and %rdx = length
+mx_itoa:
+    leaq mx_num_buf+32(%rip), %r8
+    movq %r8, %r9
+    movq %rdi, %rax
+    testq %rax, %rax
+    jns 1f
+    negq %rax
+1:
+    movl $10, %ecx
+2:
+    xorl %edx, %edx
+    divq %rcx
+    addb $'0', %dl
+    decq %r9
+    movb %dl, (%r9)
+    testq %rax, %rax
+    jnz 2b
+    testq %rdi, %rdi
+    jns 3f
+    decq %r9
+    movb $'-', (%r9)
+3:
+    movq %r9, %rax
+    movq %r8, %rdx
+    subq %r9, %rdx
+    ret
+
+mx_print_int:
+    call mx_itoa
+    movq %rax, %rdi
+    movq %rdx, %rsi
+    jmp mx_write
+
+mx_print_str:
+    movq (%rdi), %rsi
+    addq $8, %rdi
+    jmp mx_write
+
+mx_print_bool:
+    testq %rdi, %rdi
+    jz 1f
+    leaq mx_true(%rip), %rdi
+    movl $4, %esi
+    jmp mx_write
+1:
+    leaq mx_false(%rip), %rdi
+    movl $5, %esi
+    jmp mx_write
+
+mx_print_space:
+    leaq mx_space(%rip), %rdi
+    movl $1, %esi
+    jmp mx_write
+
+mx_print_newline:
+    leaq mx_newline(%rip), %rdi
+    movl $1, %esi
+    jmp mx_write
+
+# 1 if the strings at %rdi and %rsi hold the same bytes, else 0
+mx_str_eq:
+    movq (%rdi), %rcx
+    xorl %eax, %eax
+    cmpq (%rsi), %rcx
+    jne 1f
+    addq $8, %rdi
+    addq $8, %rsi
+    repe cmpsb
+    sete %al
+1:
+    ret
+
+# %rdi to the power of %rsi with wrapping multiplication
+mx_pow:
+    testq %rsi, %rsi
+    js 3f
+    movl $1, %eax
+1:
+    testq %rsi, %rsi
+    jz 2f
+    testq $1, %rsi
+    jz 4f
+    imulq %rdi, %rax
+4:
+    imulq %rdi, %rdi
+    shrq $1, %rsi
+    jmp 1b
+2:
+    ret
+3:
+    leaq mx_msg_power(%rip), %rdi
+    movq $(mx_msg_power_end - mx_msg_power), %rsi
+    jmp mx_error
+
+mx_divide_by_zero:
+    leaq mx_msg_divide(%rip), %rdi
+    movq $(mx_msg_divide_end - mx_msg_divide), %rsi
+    jmp mx_error
+
+# the `  --> file:line:column` line of an error, for the statement running last
+mx_location:
+    leaq mx_arrow(%rip), %rdi
+    movq $(mx_arrow_end - mx_arrow), %rsi
+    call mx_ewrite
+    movq mx_source(%rip), %rax
+    leaq 8(%rax), %rdi
+    movq (%rax), %rsi
+    call mx_ewrite
+    leaq mx_colon(%rip), %rdi
+    movl $1, %esi
+    call mx_ewrite
+    movq mx_line(%rip), %rdi
+    call mx_itoa
+    movq %rax, %rdi
+    movq %rdx, %rsi
+    call mx_ewrite
+    leaq mx_colon(%rip), %rdi
+    movl $1, %esi
+    call mx_ewrite
+    movq mx_column(%rip), %rdi
+    call mx_itoa
+    movq %rax, %rdi
+    movq %rdx, %rsi
+    call mx_ewrite
+    leaq mx_newline(%rip), %rdi
+    movl $1, %esi
+    jmp mx_ewrite
+
+# reports the message of %rsi bytes at %rdi and exits with status 1
+mx_error:
+    pushq %rdi
+    pushq %rsi
+    call mx_flush
+    popq %rsi
+    popq %rdi
+    call mx_ewrite
+    call mx_location
+    movl $1, %edi
+    jmp mx_exit_now
+
+# the call to the function named by the string at %rdi nests too deep
+mx_overflow:
+    pushq %rdi
+    call mx_flush
+    leaq mx_msg_overflow(%rip), %rdi
+    movq $(mx_msg_overflow_end - mx_msg_overflow), %rsi
+    call mx_ewrite
+    popq %rdi
+    movq (%rdi), %rsi
+    addq $8, %rdi
+    call mx_ewrite
+    leaq mx_msg_tick(%rip), %rdi
+    movl $1, %esi
+    call mx_ewrite
+    call mx_location
+    leaq mx_msg_depth(%rip), %rdi
+    movq $(mx_msg_depth_end - mx_msg_depth), %rsi
+    call mx_ewrite
+    movl $1, %edi
+    jmp mx_exit_now
+
+# flushes stdout and exits with status %rdi
+mx_exit:
+    pushq %rdi
+    call mx_flush
+    popq %rdi
+mx_exit_now:
+    movl $60, %eax
+    syscall
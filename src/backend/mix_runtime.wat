@@ -0,0 +1,725 @@
+  ;; runtime for modules built by the wasm32 backend, spliced into every generated module;
+  ;; `@name` stands for the address of a runtime string, strings are an i32 length followed
+  ;; by the bytes, lists are an i32 length, capacity and item pointer with 8 bytes per item
+
+  ;; bump allocation, growing the memory a page at a time; nothing is ever freed
+  (func $mx_alloc (param $size i32) (result i32)
+    (local $ptr i32)
+    global.get $mx_heap
+    local.set $ptr
+    global.get $mx_heap
+    local.get $size
+    i32.const 7
+    i32.add
+    i32.const -8
+    i32.and
+    i32.add
+    global.set $mx_heap
+    block $done
+      loop $grow
+        global.get $mx_heap
+        memory.size
+        i32.const 16
+        i32.shl
+        i32.le_u
+        br_if $done
+        i32.const 1
+        memory.grow
+        i32.const -1
+        i32.eq
+        if
+          i32.const @out_of_memory
+          call $mx_error
+        end
+        br $grow
+      end
+    end
+    local.get $ptr)
+
+  (func $mx_print_str (param $s i32)
+    local.get $s
+    i32.eqz
+    if
+      i32.const @null
+      local.set $s
+    end
+    global.get $mx_fd
+    local.get $s
+    i32.const 4
+    i32.add
+    local.get $s
+    i32.load
+    call $mx_write)
+
+  ;; digits go right to left into the scratch area below the data
+  (func $mx_print_int (param $value i64)
+    (local $pos i32)
+    (local $negative i32)
+    (local $rest i64)
+    i32.const 64
+    local.set $pos
+    local.get $value
+    i64.const 0
+    i64.lt_s
+    local.set $negative
+    local.get $value
+    local.set $rest
+    local.get $negative
+    if
+      i64.const 0
+      local.get $value
+      i64.sub
+      local.set $rest
+    end
+    loop $digit
+      local.get $pos
+      i32.const 1
+      i32.sub
+      local.tee $pos
+      local.get $rest
+      i64.const 10
+      i64.rem_u
+      i32.wrap_i64
+      i32.const 48
+      i32.add
+      i32.store8
+      local.get $rest
+      i64.const 10
+      i64.div_u
+      local.tee $rest
+      i64.const 0
+      i64.ne
+      br_if $digit
+    end
+    local.get $negative
+    if
+      local.get $pos
+      i32.const 1
+      i32.sub
+      local.tee $pos
+      i32.const 45
+      i32.store8
+    end
+    global.get $mx_fd
+    local.get $pos
+    i32.const 64
+    local.get $pos
+    i32.sub
+    call $mx_write)
+
+  ;; the host formats floats, there is no shortest round-trip printing in here
+  (func $mx_print_float (param $value f64)
+    global.get $mx_fd
+    local.get $value
+    call $mx_write_float)
+
+  (func $mx_print_bool (param $value i32)
+    i32.const @true
+    i32.const @false
+    local.get $value
+    select
+    call $mx_print_str)
+
+  ;; a string inside a list, quoted and escaped like `{:?}` does
+  (func $mx_print_quoted (param $s i32)
+    (local $i i32)
+    (local $byte i32)
+    local.get $s
+    i32.eqz
+    if
+      i32.const @null
+      call $mx_print_str
+      return
+    end
+    i32.const @quote
+    call $mx_print_str
+    block $done
+      loop $next
+        local.get $i
+        local.get $s
+        i32.load
+        i32.ge_u
+        br_if $done
+        local.get $s
+        local.get $i
+        i32.add
+        i32.load8_u offset=4
+        local.set $byte
+        block $plain
+          block $escaped
+            local.get $byte
+            i32.const 34
+            i32.eq
+            if
+              i32.const @escape_quote
+              call $mx_print_str
+              br $escaped
+            end
+            local.get $byte
+            i32.const 92
+            i32.eq
+            if
+              i32.const @escape_backslash
+              call $mx_print_str
+              br $escaped
+            end
+            local.get $byte
+            i32.const 10
+            i32.eq
+            if
+              i32.const @escape_newline
+              call $mx_print_str
+              br $escaped
+            end
+            local.get $byte
+            i32.const 13
+            i32.eq
+            if
+              i32.const @escape_return
+              call $mx_print_str
+              br $escaped
+            end
+            local.get $byte
+            i32.const 9
+            i32.eq
+            if
+              i32.const @escape_tab
+              call $mx_print_str
+              br $escaped
+            end
+            local.get $byte
+            i32.eqz
+            if
+              i32.const @escape_zero
+              call $mx_print_str
+              br $escaped
+            end
+            br $plain
+          end
+          local.get $i
+          i32.const 1
+          i32.add
+          local.set $i
+          br $next
+        end
+        global.get $mx_fd
+        local.get $s
+        local.get $i
+        i32.add
+        i32.const 4
+        i32.add
+        i32.const 1
+        call $mx_write
+        local.get $i
+        i32.const 1
+        i32.add
+        local.set $i
+        br $next
+      end
+    end
+    i32.const @quote
+    call $mx_print_str)
+
+  ;; `  --> file:line:column` for the statement running last
+  (func $mx_location
+    i32.const @arrow
+    call $mx_print_str
+    global.get $mx_file
+    call $mx_print_str
+    i32.const @colon
+    call $mx_print_str
+    global.get $mx_line
+    i64.extend_i32_u
+    call $mx_print_int
+    i32.const @colon
+    call $mx_print_str
+    global.get $mx_column
+    i64.extend_i32_u
+    call $mx_print_int
+    i32.const @newline
+    call $mx_print_str)
+
+  ;; an error message is written in pieces between these two, the second one never returns
+  (func $mx_error_begin (param $message i32)
+    i32.const 2
+    global.set $mx_fd
+    local.get $message
+    call $mx_print_str)
+
+  (func $mx_error_end
+    call $mx_location
+    i32.const 1
+    call $mx_exit
+    unreachable)
+
+  (func $mx_error (param $message i32)
+    local.get $message
+    call $mx_error_begin
+    call $mx_error_end)
+
+  (func $mx_enter (param $name i32)
+    global.get $mx_depth
+    i32.const 1
+    i32.add
+    global.set $mx_depth
+    global.get $mx_depth
+    i32.const @max_depth
+    i32.gt_u
+    if
+      i32.const @stack_overflow
+      call $mx_error_begin
+      local.get $name
+      call $mx_print_str
+      i32.const @tick
+      call $mx_print_str
+      call $mx_location
+      i32.const @depth_note
+      call $mx_print_str
+      i32.const 1
+      call $mx_exit
+      unreachable
+    end)
+
+  (func $mx_leave
+    global.get $mx_depth
+    i32.const 1
+    i32.sub
+    global.set $mx_depth)
+
+  ;; `value` unless it is null, which has no methods
+  (func $mx_check (param $value i32) (param $method i32) (result i32)
+    local.get $value
+    i32.eqz
+    if
+      i32.const @call_on_null
+      call $mx_error_begin
+      local.get $method
+      call $mx_print_str
+      i32.const @on_null
+      call $mx_print_str
+      call $mx_error_end
+    end
+    local.get $value)
+
+  (func $mx_div (param $a i64) (param $b i64) (result i64)
+    local.get $b
+    i64.eqz
+    if
+      i32.const @divide_by_zero
+      call $mx_error
+    end
+    local.get $b
+    i64.const -1
+    i64.eq
+    if
+      i64.const 0
+      local.get $a
+      i64.sub
+      return
+    end
+    local.get $a
+    local.get $b
+    i64.div_s)
+
+  (func $mx_rem (param $a i64) (param $b i64) (result i64)
+    local.get $b
+    i64.eqz
+    if
+      i32.const @divide_by_zero
+      call $mx_error
+    end
+    local.get $a
+    local.get $b
+    i64.rem_s)
+
+  (func $mx_pow (param $base i64) (param $exponent i64) (result i64)
+    (local $result i64)
+    local.get $exponent
+    i64.const 0
+    i64.lt_s
+    if
+      i32.const @negative_exponent
+      call $mx_error
+    end
+    i64.const 1
+    local.set $result
+    block $done
+      loop $next
+        local.get $exponent
+        i64.eqz
+        br_if $done
+        local.get $exponent
+        i64.const 1
+        i64.and
+        i64.eqz
+        i32.eqz
+        if
+          local.get $result
+          local.get $base
+          i64.mul
+          local.set $result
+        end
+        local.get $base
+        local.get $base
+        i64.mul
+        local.set $base
+        local.get $exponent
+        i64.const 1
+        i64.shr_u
+        local.set $exponent
+        br $next
+      end
+    end
+    local.get $result)
+
+  (func $mx_str_eq (param $a i32) (param $b i32) (result i32)
+    (local $i i32)
+    (local $len i32)
+    local.get $a
+    local.get $b
+    i32.eq
+    if
+      i32.const 1
+      return
+    end
+    local.get $a
+    i32.eqz
+    local.get $b
+    i32.eqz
+    i32.or
+    if
+      i32.const 0
+      return
+    end
+    local.get $a
+    i32.load
+    local.tee $len
+    local.get $b
+    i32.load
+    i32.ne
+    if
+      i32.const 0
+      return
+    end
+    block $done
+      loop $next
+        local.get $i
+        local.get $len
+        i32.ge_u
+        br_if $done
+        local.get $a
+        local.get $i
+        i32.add
+        i32.load8_u offset=4
+        local.get $b
+        local.get $i
+        i32.add
+        i32.load8_u offset=4
+        i32.ne
+        if
+          i32.const 0
+          return
+        end
+        local.get $i
+        i32.const 1
+        i32.add
+        local.set $i
+        br $next
+      end
+    end
+    i32.const 1)
+
+  ;; -1, 0 or 1 comparing bytes, like `str` ordering does
+  (func $mx_str_cmp (param $a i32) (param $b i32) (result i32)
+    (local $i i32)
+    (local $x i32)
+    (local $y i32)
+    local.get $a
+    i32.eqz
+    local.get $b
+    i32.eqz
+    i32.or
+    if
+      i32.const @null_operand
+      call $mx_error
+    end
+    block $done
+      loop $next
+        local.get $i
+        local.get $a
+        i32.load
+        i32.ge_u
+        local.get $i
+        local.get $b
+        i32.load
+        i32.ge_u
+        i32.or
+        br_if $done
+        local.get $a
+        local.get $i
+        i32.add
+        i32.load8_u offset=4
+        local.tee $x
+        local.get $b
+        local.get $i
+        i32.add
+        i32.load8_u offset=4
+        local.tee $y
+        i32.ne
+        if
+          i32.const -1
+          i32.const 1
+          local.get $x
+          local.get $y
+          i32.lt_u
+          select
+          return
+        end
+        local.get $i
+        i32.const 1
+        i32.add
+        local.set $i
+        br $next
+      end
+    end
+    local.get $a
+    i32.load
+    local.get $b
+    i32.load
+    i32.gt_u
+    local.get $a
+    i32.load
+    local.get $b
+    i32.load
+    i32.lt_u
+    i32.sub)
+
+  (func $mx_str_concat (param $a i32) (param $b i32) (result i32)
+    (local $s i32)
+    local.get $a
+    i32.eqz
+    local.get $b
+    i32.eqz
+    i32.or
+    if
+      i32.const @null_operand
+      call $mx_error
+    end
+    local.get $a
+    i32.load
+    local.get $b
+    i32.load
+    i32.add
+    i32.const 4
+    i32.add
+    call $mx_alloc
+    local.tee $s
+    local.get $a
+    i32.load
+    local.get $b
+    i32.load
+    i32.add
+    i32.store
+    local.get $s
+    i32.const 4
+    i32.add
+    local.get $a
+    i32.const 4
+    i32.add
+    local.get $a
+    i32.load
+    memory.copy
+    local.get $s
+    i32.const 4
+    i32.add
+    local.get $a
+    i32.load
+    i32.add
+    local.get $b
+    i32.const 4
+    i32.add
+    local.get $b
+    i32.load
+    memory.copy
+    local.get $s)
+
+  ;; length in characters, counting every byte that does not continue a UTF-8 sequence
+  (func $mx_str_len (param $s i32) (result i64)
+    (local $i i32)
+    (local $count i64)
+    block $done
+      loop $next
+        local.get $i
+        local.get $s
+        i32.load
+        i32.ge_u
+        br_if $done
+        local.get $s
+        local.get $i
+        i32.add
+        i32.load8_u offset=4
+        i32.const 192
+        i32.and
+        i32.const 128
+        i32.ne
+        i64.extend_i32_u
+        local.get $count
+        i64.add
+        local.set $count
+        local.get $i
+        i32.const 1
+        i32.add
+        local.set $i
+        br $next
+      end
+    end
+    local.get $count)
+
+  (func $mx_list_new (param $capacity i32) (result i32)
+    (local $list i32)
+    local.get $capacity
+    i32.const 4
+    local.get $capacity
+    i32.const 4
+    i32.gt_u
+    select
+    local.set $capacity
+    i32.const 12
+    call $mx_alloc
+    local.tee $list
+    local.get $capacity
+    i32.store offset=4
+    local.get $list
+    local.get $capacity
+    i32.const 3
+    i32.shl
+    call $mx_alloc
+    i32.store offset=8
+    local.get $list)
+
+  (func $mx_list_len (param $list i32) (result i64)
+    local.get $list
+    i32.load
+    i64.extend_i32_u)
+
+  ;; the address of a new item at the end, growing the items twice as large when full
+  (func $mx_list_push_slot (param $list i32) (result i32)
+    (local $items i32)
+    local.get $list
+    i32.load
+    local.get $list
+    i32.load offset=4
+    i32.eq
+    if
+      local.get $list
+      i32.load offset=4
+      i32.const 4
+      i32.shl
+      call $mx_alloc
+      local.tee $items
+      local.get $list
+      i32.load offset=8
+      local.get $list
+      i32.load
+      i32.const 3
+      i32.shl
+      memory.copy
+      local.get $list
+      local.get $items
+      i32.store offset=8
+      local.get $list
+      local.get $list
+      i32.load offset=4
+      i32.const 1
+      i32.shl
+      i32.store offset=4
+    end
+    local.get $list
+    local.get $list
+    i32.load
+    i32.const 1
+    i32.add
+    i32.store
+    local.get $list
+    i32.load offset=8
+    local.get $list
+    i32.load
+    i32.const 1
+    i32.sub
+    i32.const 3
+    i32.shl
+    i32.add)
+
+  (func $mx_list_pop_slot (param $list i32) (result i32)
+    local.get $list
+    i32.load
+    i32.eqz
+    if
+      i32.const @pop_empty
+      call $mx_error
+    end
+    local.get $list
+    local.get $list
+    i32.load
+    i32.const 1
+    i32.sub
+    i32.store
+    local.get $list
+    i32.load offset=8
+    local.get $list
+    i32.load
+    i32.const 3
+    i32.shl
+    i32.add)
+
+  (func $mx_list_slot (param $list i32) (param $index i64) (result i32)
+    local.get $index
+    i64.const 0
+    i64.lt_s
+    local.get $index
+    local.get $list
+    i32.load
+    i64.extend_i32_u
+    i64.ge_s
+    i32.or
+    if
+      i32.const @index_begin
+      call $mx_error_begin
+      local.get $index
+      call $mx_print_int
+      i32.const @index_end
+      call $mx_print_str
+      local.get $list
+      i32.load
+      i64.extend_i32_u
+      call $mx_print_int
+      call $mx_error_end
+    end
+    local.get $list
+    i32.load offset=8
+    local.get $index
+    i32.wrap_i64
+    i32.const 3
+    i32.shl
+    i32.add)
+
+  ;; `for` walks a copy, so the body may change the list freely
+  (func $mx_list_copy (param $list i32) (result i32)
+    (local $copy i32)
+    local.get $list
+    i32.load
+    call $mx_list_new
+    local.tee $copy
+    local.get $list
+    i32.load
+    i32.store
+    local.get $copy
+    i32.load offset=8
+    local.get $list
+    i32.load offset=8
+    local.get $list
+    i32.load
+    i32.const 3
+    i32.shl
+    memory.copy
+    local.get $copy)
@@ -0,0 +1,42 @@
+pub mod c;
+pub mod asm;
+pub mod wasm;
+pub mod wat;
+
+use std::fmt::Write;
+
+// Mix names may use characters C and assembler symbols cannot, and `_` doubles as the escape
+pub fn mangle(name: &str) -> String {
+    let mut out = String::new();
+    for ch in name.chars() {
+        match ch {
+            'a'..='z' | 'A'..='Z' | '0'..='9' => out.push(ch),
+            '_' => out.push_str("__"),
+            other => {
+                let _ = write!(out, "_u{:x}_", other as u32);
+            }
+        }
+    }
+    out
+}
+
+// a double-quoted literal both C and the GNU assembler read back as the same bytes
+pub fn quote(value: &str) -> String {
+    let mut out = String::from("\"");
+    for byte in value.bytes() {
+        match byte {
+            b'"' => out.push_str("\\\""),
+            b'\\' => out.push_str("\\\\"),
+            b'\n' => out.push_str("\\n"),
+            b'\r' => out.push_str("\\r"),
+            b'\t' => out.push_str("\\t"),
+            // `?` too, so no trigraph can form
+            0x20..=0x7e if byte != b'?' => out.push(byte as char),
+            _ => {
+                let _ = write!(out, "\\{:03o}", byte);
+            }
+        }
+    }
+    out.push('"');
+    out
+}
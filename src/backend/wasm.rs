@@ -0,0 +1,1209 @@
+use crate::compiler::diagnostic::{Diagnostic, Diagnostics};
+use crate::compiler::node::{Location, Node};
+use crate::compiler::types::{Type, TypeTable};
+use crate::runtime::MAX_CALL_DEPTH;
+use super::mangle;
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+const RUNTIME: &str = include_str!("mix_runtime.wat");
+
+// the JavaScript that runs a built module, under node or embedded in a page
+pub const HOST: &str = include_str!("mix_host.mjs");
+
+// strings the runtime refers to as `@name`
+const RUNTIME_STRINGS: [(&str, &str); 24] = [
+    ("null", "null"),
+    ("true", "true"),
+    ("false", "false"),
+    ("quote", "\""),
+    ("escape_quote", "\\\""),
+    ("escape_backslash", "\\\\"),
+    ("escape_newline", "\\n"),
+    ("escape_return", "\\r"),
+    ("escape_tab", "\\t"),
+    ("escape_zero", "\\0"),
+    ("arrow", "\n  --> "),
+    ("colon", ":"),
+    ("newline", "\n"),
+    ("tick", "`"),
+    ("out_of_memory", "error[E0504]: out of memory"),
+    ("divide_by_zero", "error[E0501]: attempt to divide by zero"),
+    ("negative_exponent", "error[E0507]: attempt to raise an integer to a negative power"),
+    ("null_operand", "error[E0504]: cannot apply an operator to `null`"),
+    ("call_on_null", "error[E0504]: cannot call `"),
+    ("on_null", "` on `null`"),
+    ("pop_empty", "error[E0502]: pop from an empty list"),
+    ("index_begin", "error[E0502]: index "),
+    ("index_end", " out of bounds for a list of length "),
+    ("stack_overflow", "error[E0505]: stack overflow while calling `"),
+];
+
+// the first 64 bytes are scratch space for printing numbers, and 0 is `null`
+const DATA_START: u32 = 64;
+
+// lowers the checked tree to a WebAssembly text module; `int` is i64, `float` is f64, and `bool`,
+// `str` and lists are i32, the last two pointers into linear memory
+pub struct WasmGenerator<'a> {
+    types: &'a TypeTable,
+    file: String,
+    // whether the program spans several files, so statements also record theirs
+    files: bool,
+    // parameter and return types of every top-level function
+    functions: HashMap<String, (Vec<Type>, Type)>,
+    strings: HashMap<String, u32>,
+    // (address, bytes) of every string, as a length followed by the bytes
+    segments: Vec<(u32, Vec<u8>)>,
+    data_end: u32,
+    // list types that need a printer, by the index in their function name
+    printers: Vec<Type>,
+    diagnostics: Diagnostics,
+    out: String,
+    indent: usize,
+    labels: usize,
+    // state of the function being generated
+    scopes: Vec<HashMap<String, (String, Type)>>,
+    locals: Vec<(String, &'static str)>,
+    // (continue, break) labels of the enclosing loops
+    loops: Vec<(String, String)>,
+    result: Type,
+}
+
+impl<'a> WasmGenerator<'a> {
+    pub fn new(types: &'a TypeTable, file: &str) -> Self {
+        Self {
+            types,
+            file: file.to_owned(),
+            files: false,
+            functions: HashMap::new(),
+            strings: HashMap::new(),
+            segments: Vec::new(),
+            data_end: DATA_START,
+            printers: Vec::new(),
+            diagnostics: Diagnostics::new(),
+            out: String::new(),
+            indent: 0,
+            labels: 0,
+            scopes: Vec::new(),
+            locals: Vec::new(),
+            loops: Vec::new(),
+            result: Type::Void,
+        }
+    }
+
+    pub fn generate(mut self, nodes: &[Box<Node>]) -> Result<String, Diagnostics> {
+        self.files = nodes.iter().any(|node| node.loc().is_some_and(|loc| loc.file != self.file));
+        for node in nodes {
+            if let Node::FuncDef { name, generics, args, rtype, loc, .. } = node.as_ref() {
+                if !generics.is_empty() {
+                    self.unsupported("generic functions", loc);
+                    continue;
+                }
+                let params = args.iter().map(|arg| match arg.as_ref() {
+                    Node::LetDef { dtype, loc, .. } => self.declared_type(self.types.declared(loc).cloned(), dtype.loc().unwrap_or(loc)),
+                    _ => Type::Unknown,
+                }).collect();
+                let result = match rtype.as_ref() {
+                    Node::Void => Type::Void,
+                    rtype => {
+                        let ret = match self.types.declared(loc) {
+                            Some(Type::Function(_, ret)) => Some(ret.as_ref().clone()),
+                            _ => None,
+                        };
+                        self.declared_type(ret, rtype.loc().unwrap_or(loc))
+                    }
+                };
+                self.functions.insert(name.clone(), (params, result));
+            }
+        }
+
+        if !self.functions.contains_key("main") {
+            self.diagnostics.push(Diagnostic::error("E0506", "`main` function not found", self.entry())
+                .with_help("add `func main() { ... }` to the entry file"));
+            return Err(self.diagnostics);
+        }
+
+        let mut runtime = String::new();
+        for line in RUNTIME.lines() {
+            match line.split_once('@').filter(|_| !line.trim_start().starts_with(";;")) {
+                Some((before, name)) => {
+                    let address = match name {
+                        "max_depth" => MAX_CALL_DEPTH as u32,
+                        "depth_note" => self.string(&format!("  = note: calls can nest at most {} deep\n", MAX_CALL_DEPTH)),
+                        _ => match RUNTIME_STRINGS.iter().find(|(key, _)| *key == name) {
+                            Some((_, value)) => self.string(value),
+                            None => 0,
+                        },
+                    };
+                    let _ = writeln!(runtime, "{}{}", before, address);
+                }
+                None => {
+                    runtime.push_str(line);
+                    runtime.push('\n');
+                }
+            }
+        }
+
+        let mut functions = String::new();
+        for node in nodes {
+            if let Node::FuncDef { name, generics, args, body, .. } = node.as_ref()
+                && generics.is_empty()
+            {
+                self.function(name, args, body);
+                functions.push_str(&std::mem::take(&mut self.out));
+            }
+        }
+
+        // printers may ask for the printers of their item types
+        let mut printed = 0;
+        while printed < self.printers.len() {
+            self.printer(printed);
+            functions.push_str(&std::mem::take(&mut self.out));
+            printed += 1;
+        }
+
+        if self.diagnostics.has_errors() {
+            return Err(self.diagnostics);
+        }
+
+        let file = self.string(&self.file.clone());
+        let heap = self.data_end.next_multiple_of(8);
+        let pages = heap.div_ceil(65536).max(1);
+
+        let mut out = String::new();
+        let _ = writeln!(out, "(module");
+        let _ = writeln!(out, "  ;; generated by `mix build` from {}", self.file);
+        let _ = writeln!(out, "  (import \"mix\" \"write\" (func $mx_write (param i32 i32 i32)))");
+        let _ = writeln!(out, "  (import \"mix\" \"write_float\" (func $mx_write_float (param i32 f64)))");
+        let _ = writeln!(out, "  (import \"mix\" \"exit\" (func $mx_exit (param i32)))");
+        let _ = writeln!(out, "  (import \"mix\" \"pow\" (func $mx_powf (param f64 f64) (result f64)))");
+        let _ = writeln!(out, "  (import \"mix\" \"rem\" (func $mx_remf (param f64 f64) (result f64)))");
+        let _ = writeln!(out, "  (memory (export \"memory\") {})", pages);
+        let _ = writeln!(out, "  (global $mx_heap (mut i32) (i32.const {}))", heap);
+        let _ = writeln!(out, "  (global $mx_fd (mut i32) (i32.const 1))");
+        let _ = writeln!(out, "  (global $mx_file (mut i32) (i32.const {}))", file);
+        for global in ["mx_line", "mx_column", "mx_depth"] {
+            let _ = writeln!(out, "  (global ${} (mut i32) (i32.const 0))", global);
+        }
+        for (address, bytes) in &self.segments {
+            let _ = writeln!(out, "  (data (i32.const {}) \"{}\")", address, Self::data_text(bytes));
+        }
+        out.push('\n');
+        out.push_str(&runtime);
+        out.push_str(&functions);
+        out.push_str(")\n");
+
+        Ok(out)
+    }
+
+    fn function(&mut self, name: &str, args: &[Box<Node>], body: &[Box<Node>]) {
+        let (params, result) = self.functions[name].clone();
+        self.scopes = vec![HashMap::new()];
+        self.locals.clear();
+        self.result = result.clone();
+
+        let mut header = format!("  (func {}", Self::function_name(name));
+        if name == "main" {
+            header.push_str(" (export \"main\")");
+        }
+        for (arg, ty) in args.iter().zip(&params) {
+            if let Node::LetDef { name, .. } = arg.as_ref() {
+                let local = self.declare_local(name, ty.clone());
+                let _ = write!(header, " (param {} {})", local, Self::value_type(ty).unwrap_or("i32"));
+            }
+        }
+        let params = self.locals.len();
+        if let Some(result) = Self::value_type(&result) {
+            let _ = write!(header, " (result {})", result);
+        }
+
+        self.indent = 2;
+        let name_address = self.string(name);
+        self.emit(&format!("i32.const {}", name_address));
+        self.emit("call $mx_enter");
+        // every `return` leaves through this block, so the depth always goes back down
+        match Self::value_type(&result) {
+            Some(result) => self.emit(&format!("block $return (result {})", result)),
+            None => self.emit("block $return"),
+        }
+        self.block(body);
+        self.zero(&result);
+        self.emit("end");
+        self.emit("call $mx_leave");
+
+        let code = std::mem::take(&mut self.out);
+        self.out.push_str(&header);
+        self.out.push('\n');
+        for (local, ty) in &self.locals[params..] {
+            let _ = writeln!(self.out, "    (local {} {})", local, ty);
+        }
+        self.out.push_str(code.trim_end());
+        self.out.push_str(")\n\n");
+    }
+
+    // `$mx_print_list_N`, writing a list of `printers[index]` like `{}` does
+    fn printer(&mut self, index: usize) {
+        let Type::List(item) = self.printers[index].clone() else {
+            return;
+        };
+
+        let _ = writeln!(self.out, "  (func $mx_print_list_{} (param $list i32)", index);
+        let _ = writeln!(self.out, "    (local $i i32)");
+        self.indent = 2;
+        let (null, open, comma, close) = (self.string("null"), self.string("["), self.string(", "), self.string("]"));
+
+        self.emit("local.get $list");
+        self.emit("i32.eqz");
+        self.emit("if");
+        self.print_string(null);
+        self.emit("return");
+        self.emit("end");
+        self.print_string(open);
+        self.emit("block $done");
+        self.emit("loop $next");
+        self.emit("local.get $i");
+        self.emit("local.get $list");
+        self.emit("i32.load");
+        self.emit("i32.ge_u");
+        self.emit("br_if $done");
+        self.emit("local.get $i");
+        self.emit("if");
+        self.print_string(comma);
+        self.emit("end");
+        self.emit("local.get $list");
+        self.emit("i32.load offset=8");
+        self.emit("local.get $i");
+        self.emit("i32.const 3");
+        self.emit("i32.shl");
+        self.emit("i32.add");
+        self.load(&item);
+        self.print_value(&item, true);
+        self.emit("local.get $i");
+        self.emit("i32.const 1");
+        self.emit("i32.add");
+        self.emit("local.set $i");
+        self.emit("br $next");
+        self.emit("end");
+        self.emit("end");
+        self.print_string(close);
+
+        let code = std::mem::take(&mut self.out);
+        self.out.push_str(code.trim_end());
+        self.out.push_str(")\n\n");
+    }
+
+    fn block(&mut self, body: &[Box<Node>]) {
+        self.scopes.push(HashMap::new());
+        for node in body {
+            self.stmt(node);
+        }
+        self.scopes.pop();
+    }
+
+    fn stmt(&mut self, node: &Node) {
+        if let Some(loc) = node.loc() {
+            if self.files {
+                let file = self.string(&loc.file);
+                self.emit(&format!("i32.const {}", file));
+                self.emit("global.set $mx_file");
+            }
+            self.emit(&format!("i32.const {}", loc.line));
+            self.emit("global.set $mx_line");
+            self.emit(&format!("i32.const {}", loc.column));
+            self.emit("global.set $mx_column");
+        }
+
+        match node {
+            Node::LetDef { name, dtype, value, loc, .. } => {
+                // a missing annotation parses as `null`
+                let ty = match (dtype.as_ref(), value.as_ref()) {
+                    (Node::Null { .. }, Node::Void) => {
+                        self.unsupported("`let` without a type or a value", loc);
+                        return;
+                    }
+                    (Node::Null { .. }, value) => self.infer(value),
+                    _ => self.types.declared(loc).cloned().unwrap_or(Type::Unknown),
+                };
+                if !self.supported(&ty, dtype.loc().unwrap_or(loc)) {
+                    return;
+                }
+
+                match value.as_ref() {
+                    Node::Void => self.default_value(&ty),
+                    value => {
+                        let value_type = self.expr(value);
+                        self.coerce(&value_type, &ty);
+                    }
+                }
+
+                // declared after the value, which may still see an outer variable of the same name
+                let local = self.declare_local(name, ty);
+                self.emit(&format!("local.set {}", local));
+            }
+            Node::AssignDef { targ, value, opr, loc } => {
+                let Node::Var { value: name, .. } = targ.as_ref() else {
+                    self.unsupported("assigning to fields", loc);
+                    return;
+                };
+                let Some((local, ty)) = self.local(name) else {
+                    self.unsupported(&format!("assigning to `{}`", name), loc);
+                    return;
+                };
+
+                let value_type = match opr.trim_end_matches('=') {
+                    "" => self.expr(value),
+                    opr => self.expr(&Node::BinaryOp {
+                        lhs: targ.clone(),
+                        rhs: value.clone(),
+                        opr: opr.to_owned(),
+                        loc: loc.clone(),
+                    }),
+                };
+                self.coerce(&value_type, &ty);
+                self.emit(&format!("local.set {}", local));
+            }
+            Node::Return { value, .. } => {
+                if !matches!(value.as_ref(), Node::Void) {
+                    let value_type = self.expr(value);
+                    let result = self.result.clone();
+                    self.coerce(&value_type, &result);
+                }
+                self.emit("br $return");
+            }
+            Node::Conditional { cond, body_if, body_else, .. } => {
+                self.expr(cond);
+                self.emit("if");
+                self.block(body_if);
+                if !body_else.is_empty() {
+                    self.emit("else");
+                    self.block(body_else);
+                }
+                self.emit("end");
+            }
+            Node::WhileLoop { cond, body, .. } => {
+                let (next, done) = (self.label(), self.label());
+                self.emit(&format!("block {}", done));
+                self.emit(&format!("loop {}", next));
+                self.expr(cond);
+                self.emit("i32.eqz");
+                self.emit(&format!("br_if {}", done));
+
+                self.loops.push((next.clone(), done.clone()));
+                self.block(body);
+                self.loops.pop();
+
+                self.emit(&format!("br {}", next));
+                self.emit("end");
+                self.emit("end");
+            }
+            Node::ForLoop { initializer, iterator, body, loc } => {
+                let Node::LetDef { name, .. } = initializer.as_ref() else {
+                    return;
+                };
+                self.scopes.push(HashMap::new());
+
+                let (next, step, done) = (self.label(), self.label(), self.label());
+                let index;
+                match iterator.as_ref() {
+                    Node::Range { min, max, .. } => {
+                        // the position and the bound live in hidden locals, so the body cannot move them
+                        index = self.temp("i64");
+                        let bound = self.temp("i64");
+                        let min_type = self.expr(min);
+                        self.coerce(&min_type, &Type::Int);
+                        self.emit(&format!("local.set {}", index));
+                        let max_type = self.expr(max);
+                        self.coerce(&max_type, &Type::Int);
+                        self.emit(&format!("local.set {}", bound));
+
+                        self.emit(&format!("block {}", done));
+                        self.emit(&format!("loop {}", next));
+                        self.emit(&format!("local.get {}", index));
+                        self.emit(&format!("local.get {}", bound));
+                        self.emit("i64.ge_s");
+                        self.emit(&format!("br_if {}", done));
+                        self.emit(&format!("local.get {}", index));
+                        let local = self.declare_local(name, Type::Int);
+                        self.emit(&format!("local.set {}", local));
+                    }
+                    iterator => {
+                        let Type::List(item) = self.infer(iterator) else {
+                            self.unsupported("`for` loops over anything but ranges and lists", loc);
+                            self.scopes.pop();
+                            return;
+                        };
+                        if !self.supported(&item, loc) {
+                            self.scopes.pop();
+                            return;
+                        }
+
+                        // a copy, so the body may change the list freely
+                        let list = self.temp("i32");
+                        index = self.temp("i32");
+                        self.expr(iterator);
+                        self.emit("call $mx_list_copy");
+                        self.emit(&format!("local.set {}", list));
+
+                        self.emit(&format!("block {}", done));
+                        self.emit(&format!("loop {}", next));
+                        self.emit(&format!("local.get {}", index));
+                        self.emit(&format!("local.get {}", list));
+                        self.emit("i32.load");
+                        self.emit("i32.ge_u");
+                        self.emit(&format!("br_if {}", done));
+                        self.emit(&format!("local.get {}", list));
+                        self.emit("i32.load offset=8");
+                        self.emit(&format!("local.get {}", index));
+                        self.emit("i32.const 3");
+                        self.emit("i32.shl");
+                        self.emit("i32.add");
+                        self.load(&item);
+                        let local = self.declare_local(name, *item);
+                        self.emit(&format!("local.set {}", local));
+                    }
+                }
+
+                self.emit(&format!("block {}", step));
+                self.loops.push((step.clone(), done.clone()));
+                self.block(body);
+                self.loops.pop();
+                self.emit("end");
+
+                let kind = if matches!(iterator.as_ref(), Node::Range { .. }) { "i64" } else { "i32" };
+                self.emit(&format!("local.get {}", index));
+                self.emit(&format!("{}.const 1", kind));
+                self.emit(&format!("{}.add", kind));
+                self.emit(&format!("local.set {}", index));
+                self.emit(&format!("br {}", next));
+                self.emit("end");
+                self.emit("end");
+
+                self.scopes.pop();
+            }
+            Node::MatchCase { value, child, default, loc } => {
+                let ty = self.infer(value);
+                if !matches!(ty, Type::Int | Type::Long | Type::Float | Type::Double | Type::Bool | Type::Str) {
+                    self.unsupported("`match` on anything but numbers, booleans and strings", loc);
+                    return;
+                }
+
+                let subject = self.temp(Self::value_type(&ty).unwrap_or("i32"));
+                self.expr(value);
+                self.emit(&format!("local.set {}", subject));
+
+                let done = self.label();
+                self.emit(&format!("block {}", done));
+                for arm in child {
+                    self.emit(&format!("local.get {}", subject));
+                    let arm_type = self.expr(&arm.0);
+                    self.coerce(&arm_type, &ty);
+                    self.emit(match ty {
+                        Type::Str => "call $mx_str_eq",
+                        Type::Bool => "i32.eq",
+                        Type::Float | Type::Double => "f64.eq",
+                        _ => "i64.eq",
+                    });
+                    self.emit("if");
+                    self.block(&arm.1);
+                    self.emit(&format!("br {}", done));
+                    self.emit("end");
+                }
+                self.block(default);
+                self.emit("end");
+            }
+            Node::Break { .. } => {
+                if let Some((_, done)) = self.loops.last().cloned() {
+                    self.emit(&format!("br {}", done));
+                }
+            }
+            Node::Continue { .. } => {
+                if let Some((next, _)) = self.loops.last().cloned() {
+                    self.emit(&format!("br {}", next));
+                }
+            }
+            _ => {
+                let ty = self.expr(node);
+                if Self::value_type(&ty).is_some() {
+                    self.emit("drop");
+                }
+            }
+        }
+    }
+
+    // pushes the value of `node` and returns its type, `Void` when nothing was pushed
+    fn expr(&mut self, node: &Node) -> Type {
+        match node {
+            Node::Int { value, loc, .. } => match value.parse::<i64>() {
+                Ok(value) => self.emit(&format!("i64.const {}", value)),
+                Err(_) => self.unsupported(&format!("the integer literal `{}`", value), loc),
+            },
+            Node::Float { value, loc, .. } => match value.parse::<f64>() {
+                Ok(value) => self.emit(&format!("f64.const {}", Self::float_text(value))),
+                Err(_) => self.unsupported(&format!("the float literal `{}`", value), loc),
+            },
+            Node::Bool { value, .. } => self.emit(&format!("i32.const {}", (value == "true") as u8)),
+            Node::Null { .. } => self.emit("i32.const 0"),
+            Node::Str { value, .. } => {
+                let address = self.string(value);
+                self.emit(&format!("i32.const {}", address));
+            }
+            Node::Var { value, loc } => match self.local(value) {
+                Some((local, _)) => self.emit(&format!("local.get {}", local)),
+                None => self.unsupported(&format!("`{}` as a value", value), loc),
+            },
+            Node::List { element, loc } => {
+                let ty = self.infer(node);
+                let Type::List(item) = &ty else {
+                    self.unsupported("lists of unknown type", loc);
+                    return Type::Unknown;
+                };
+                if !self.supported(item, loc) {
+                    return Type::Unknown;
+                }
+
+                let list = self.temp("i32");
+                self.emit(&format!("i32.const {}", element.len()));
+                self.emit("call $mx_list_new");
+                self.emit(&format!("local.set {}", list));
+                for value in element {
+                    let value_type = self.expr(value);
+                    self.coerce(&value_type, item);
+                    let temp = self.temp(Self::value_type(item).unwrap_or("i32"));
+                    self.emit(&format!("local.set {}", temp));
+                    self.emit(&format!("local.get {}", list));
+                    self.emit("call $mx_list_push_slot");
+                    self.emit(&format!("local.get {}", temp));
+                    self.store(item);
+                }
+                self.emit(&format!("local.get {}", list));
+            }
+            Node::BinaryOp { lhs, rhs, opr, loc } => self.binary(lhs, rhs, opr, loc),
+            Node::UnaryOp { opr, value, loc } => {
+                let ty = self.infer(value);
+                match (opr.as_str(), &ty) {
+                    ("-", Type::Int | Type::Long) => {
+                        self.emit("i64.const 0");
+                        self.expr(value);
+                        self.emit("i64.sub");
+                    }
+                    ("-", Type::Float | Type::Double) => {
+                        self.expr(value);
+                        self.emit("f64.neg");
+                    }
+                    ("+", _) => {
+                        self.expr(value);
+                    }
+                    ("!", _) => {
+                        self.expr(value);
+                        self.emit("i32.eqz");
+                    }
+                    ("~", _) => {
+                        self.expr(value);
+                        self.emit("i64.const -1");
+                        self.emit("i64.xor");
+                    }
+                    (opr, _) => self.unsupported(&format!("unary `{}`", opr), loc),
+                }
+            }
+            Node::FuncCall { func, args, loc } => return self.call(func, args, loc),
+            Node::Void => return Type::Void,
+            other => {
+                let loc = other.loc().cloned().unwrap_or_else(|| self.entry());
+                self.unsupported(Self::describe(other), &loc);
+                return Type::Unknown;
+            }
+        }
+
+        self.infer(node)
+    }
+
+    fn binary(&mut self, lhs: &Node, rhs: &Node, opr: &str, loc: &Location) {
+        let (left, right) = (self.infer(lhs), self.infer(rhs));
+
+        if opr == "&&" || opr == "||" {
+            self.expr(lhs);
+            self.emit("if (result i32)");
+            if opr == "&&" {
+                self.expr(rhs);
+                self.emit("else");
+                self.emit("i32.const 0");
+            } else {
+                self.emit("i32.const 1");
+                self.emit("else");
+                self.expr(rhs);
+            }
+            self.emit("end");
+            return;
+        }
+
+        if left == Type::Str || right == Type::Str {
+            self.expr(lhs);
+            self.expr(rhs);
+            match opr {
+                "+" => self.emit("call $mx_str_concat"),
+                "==" => self.emit("call $mx_str_eq"),
+                "!=" => {
+                    self.emit("call $mx_str_eq");
+                    self.emit("i32.eqz");
+                }
+                "<" | ">" | "<=" | ">=" => {
+                    self.emit("call $mx_str_cmp");
+                    self.emit("i32.const 0");
+                    self.emit(&format!("i32.{}_s", Self::comparison(opr)));
+                }
+                _ => self.unsupported(&format!("`{}` on strings", opr), loc),
+            }
+            return;
+        }
+
+        // only whether the pointers are the same, which is all `null` needs
+        if left == Type::Null || right == Type::Null || left == Type::Bool {
+            self.expr(lhs);
+            self.expr(rhs);
+            match opr {
+                "==" => self.emit("i32.eq"),
+                "!=" => self.emit("i32.ne"),
+                _ => self.unsupported(&format!("`{}` on `{}`", opr, left), loc),
+            }
+            return;
+        }
+
+        if !left.is_numeric() || !right.is_numeric() {
+            self.unsupported(&format!("`{}` on `{}` and `{}`", opr, left, right), loc);
+            return;
+        }
+
+        let float = matches!(left, Type::Float | Type::Double) || matches!(right, Type::Float | Type::Double);
+        let operand = if float { Type::Float } else { Type::Int };
+        let left_type = self.expr(lhs);
+        self.coerce(&left_type, &operand);
+        let right_type = self.expr(rhs);
+        self.coerce(&right_type, &operand);
+
+        let instruction = match (opr, float) {
+            ("+", false) => "i64.add",
+            ("-", false) => "i64.sub",
+            ("*", false) => "i64.mul",
+            ("/", false) => "call $mx_div",
+            ("%", false) => "call $mx_rem",
+            ("^", false) => "call $mx_pow",
+            ("&", false) => "i64.and",
+            ("|", false) => "i64.or",
+            ("<<", false) => "i64.shl",
+            (">>", false) => "i64.shr_s",
+            ("==", false) => "i64.eq",
+            ("!=", false) => "i64.ne",
+            ("<" | ">" | "<=" | ">=", false) => {
+                self.emit(&format!("i64.{}_s", Self::comparison(opr)));
+                return;
+            }
+            ("+", true) => "f64.add",
+            ("-", true) => "f64.sub",
+            ("*", true) => "f64.mul",
+            ("/", true) => "f64.div",
+            ("%", true) => "call $mx_remf",
+            ("^", true) => "call $mx_powf",
+            ("==", true) => "f64.eq",
+            ("!=", true) => "f64.ne",
+            ("<" | ">" | "<=" | ">=", true) => {
+                self.emit(&format!("f64.{}", Self::comparison(opr)));
+                return;
+            }
+            _ => {
+                self.unsupported(&format!("`{}`", opr), loc);
+                return;
+            }
+        };
+        self.emit(instruction);
+    }
+
+    fn call(&mut self, func: &Node, args: &[Box<Node>], loc: &Location) -> Type {
+        if let Node::CodeLockup { targ, obj, .. } = func
+            && let (Node::Var { value: name, .. }, Node::Var { value: module, .. }) = (targ.as_ref(), obj.as_ref())
+            && module == "std"
+        {
+            return self.call_std(name, args, loc);
+        }
+
+        if let Node::MemLockup { targ, obj, .. } = func
+            && let Node::Var { value: name, .. } = targ.as_ref()
+        {
+            return self.call_method(obj, name, args, loc);
+        }
+
+        let Node::Var { value: name, .. } = func else {
+            self.unsupported("calls to anything but functions and `std`", loc);
+            return Type::Unknown;
+        };
+
+        let signature = self.functions.get(name).cloned().filter(|(params, _)| params.len() == args.len());
+        let Some((params, result)) = signature.filter(|_| self.local(name).is_none()) else {
+            self.unsupported(&format!("calling `{}`", name), loc);
+            return Type::Unknown;
+        };
+
+        for (arg, param) in args.iter().zip(&params) {
+            let ty = self.expr(arg);
+            self.coerce(&ty, param);
+        }
+        self.emit(&format!("call {}", Self::function_name(name)));
+        result
+    }
+
+    fn call_std(&mut self, name: &str, args: &[Box<Node>], loc: &Location) -> Type {
+        match name {
+            "print" | "println" => {
+                // every argument is evaluated before anything is printed, like under `mix run`
+                let mut values = Vec::new();
+                for arg in args {
+                    let ty = self.expr(arg);
+                    let Some(kind) = Self::value_type(&ty) else {
+                        continue;
+                    };
+                    let temp = self.temp(kind);
+                    self.emit(&format!("local.set {}", temp));
+                    values.push((temp, ty));
+                }
+
+                let space = self.string(" ");
+                for (i, (temp, ty)) in values.iter().enumerate() {
+                    if i > 0 {
+                        self.print_string(space);
+                    }
+                    self.emit(&format!("local.get {}", temp));
+                    self.print_value(ty, false);
+                }
+                if name == "println" {
+                    let newline = self.string("\n");
+                    self.print_string(newline);
+                }
+                Type::Void
+            }
+            "len" if args.len() == 1 => {
+                let ty = self.expr(&args[0]);
+                self.len(&ty, loc);
+                Type::Int
+            }
+            _ => {
+                self.unsupported(&format!("`std::{}`", name), loc);
+                Type::Unknown
+            }
+        }
+    }
+
+    fn call_method(&mut self, receiver: &Node, name: &str, args: &[Box<Node>], loc: &Location) -> Type {
+        let ty = self.infer(receiver);
+        let item = match &ty {
+            Type::List(item) => Some(item.as_ref().clone()),
+            _ => None,
+        };
+
+        let method = self.string(name);
+        match (&item, name, args.len()) {
+            (_, "len", 0) if matches!(ty, Type::Str | Type::List(_)) => {
+                self.expr(receiver);
+                self.len(&ty, loc);
+                Type::Int
+            }
+            (Some(item), "get", 1) => {
+                self.expr(receiver);
+                self.emit(&format!("i32.const {}", method));
+                self.emit("call $mx_check");
+                let index = self.expr(&args[0]);
+                self.coerce(&index, &Type::Int);
+                self.emit("call $mx_list_slot");
+                self.load(item);
+                item.clone()
+            }
+            (Some(item), "pop", 0) => {
+                self.expr(receiver);
+                self.emit(&format!("i32.const {}", method));
+                self.emit("call $mx_check");
+                self.emit("call $mx_list_pop_slot");
+                self.load(item);
+                item.clone()
+            }
+            (Some(item), "push" | "set", _) if args.len() == if name == "push" { 1 } else { 2 } => {
+                // the list changes only once every argument is evaluated
+                let list = self.temp("i32");
+                self.expr(receiver);
+                self.emit(&format!("local.set {}", list));
+
+                let index = self.temp("i64");
+                if name == "set" {
+                    let index_type = self.expr(&args[0]);
+                    self.coerce(&index_type, &Type::Int);
+                    self.emit(&format!("local.set {}", index));
+                }
+                let value = self.temp(Self::value_type(item).unwrap_or("i32"));
+                let value_type = self.expr(&args[args.len() - 1]);
+                self.coerce(&value_type, item);
+                self.emit(&format!("local.set {}", value));
+
+                self.emit(&format!("local.get {}", list));
+                self.emit(&format!("i32.const {}", method));
+                self.emit("call $mx_check");
+                if name == "set" {
+                    self.emit(&format!("local.get {}", index));
+                    self.emit("call $mx_list_slot");
+                } else {
+                    self.emit("call $mx_list_push_slot");
+                }
+                self.emit(&format!("local.get {}", value));
+                self.store(item);
+                Type::Void
+            }
+            _ => {
+                self.unsupported(&format!("the method `{}` on `{}`", name, ty), loc);
+                Type::Unknown
+            }
+        }
+    }
+
+    // length of the string or list on the stack
+    fn len(&mut self, ty: &Type, loc: &Location) {
+        let method = self.string("len");
+        self.emit(&format!("i32.const {}", method));
+        self.emit("call $mx_check");
+        match ty {
+            Type::Str => self.emit("call $mx_str_len"),
+            Type::List(_) => self.emit("call $mx_list_len"),
+            other => self.unsupported(&format!("the length of `{}`", other), loc),
+        }
+    }
+
+    // prints the value of type `ty` on the stack, quoting strings inside lists
+    fn print_value(&mut self, ty: &Type, nested: bool) {
+        match ty {
+            Type::Int | Type::Long => self.emit("call $mx_print_int"),
+            Type::Float | Type::Double => self.emit("call $mx_print_float"),
+            Type::Bool => self.emit("call $mx_print_bool"),
+            Type::Str if nested => self.emit("call $mx_print_quoted"),
+            Type::List(_) => {
+                let index = match self.printers.iter().position(|existing| existing == ty) {
+                    Some(index) => index,
+                    None => {
+                        self.printers.push(ty.clone());
+                        self.printers.len() - 1
+                    }
+                };
+                self.emit(&format!("call $mx_print_list_{}", index));
+            }
+            _ => self.emit("call $mx_print_str"),
+        }
+    }
+
+    fn print_string(&mut self, address: u32) {
+        self.emit(&format!("i32.const {}", address));
+        self.emit("call $mx_print_str");
+    }
+
+    fn load(&mut self, ty: &Type) {
+        let kind = Self::value_type(ty).unwrap_or("i32");
+        self.emit(&format!("{}.load", kind));
+    }
+
+    fn store(&mut self, ty: &Type) {
+        let kind = Self::value_type(ty).unwrap_or("i32");
+        self.emit(&format!("{}.store", kind));
+    }
+
+    // an `int` where a `float` is expected
+    fn coerce(&mut self, from: &Type, to: &Type) {
+        if matches!(from, Type::Int | Type::Long) && matches!(to, Type::Float | Type::Double) {
+            self.emit("f64.convert_i64_s");
+        }
+    }
+
+    fn default_value(&mut self, ty: &Type) {
+        match ty {
+            Type::Str => {
+                let empty = self.string("");
+                self.emit(&format!("i32.const {}", empty));
+            }
+            Type::List(_) => {
+                self.emit("i32.const 0");
+                self.emit("call $mx_list_new");
+            }
+            ty => self.zero(ty),
+        }
+    }
+
+    fn zero(&mut self, ty: &Type) {
+        if let Some(kind) = Self::value_type(ty) {
+            self.emit(&format!("{}.const 0", kind));
+        }
+    }
+
+    // the type of `node` without generating anything
+    fn infer(&self, node: &Node) -> Type {
+        match node {
+            Node::Int { .. } => Type::Int,
+            Node::Float { .. } => Type::Float,
+            Node::Bool { .. } => Type::Bool,
+            Node::Str { .. } => Type::Str,
+            Node::Null { .. } => Type::Null,
+            Node::Var { value, .. } if self.local(value).is_some() => self.local(value).map(|(_, ty)| ty).unwrap_or(Type::Unknown),
+            Node::BinaryOp { lhs, rhs, opr, .. } => match opr.as_str() {
+                "&&" | "||" | "==" | "!=" | "<" | ">" | "<=" | ">=" => Type::Bool,
+                _ => {
+                    let (left, right) = (self.infer(lhs), self.infer(rhs));
+                    if left == Type::Str {
+                        Type::Str
+                    } else if matches!(left, Type::Float | Type::Double) || matches!(right, Type::Float | Type::Double) {
+                        Type::Float
+                    } else {
+                        Type::Int
+                    }
+                }
+            },
+            Node::UnaryOp { opr, value, .. } => match opr.as_str() {
+                "!" => Type::Bool,
+                _ => self.infer(value),
+            },
+            Node::FuncCall { func, args, .. } => match func.as_ref() {
+                Node::CodeLockup { targ, .. } => match targ.as_ref() {
+                    Node::Var { value, .. } if value == "len" => Type::Int,
+                    _ => Type::Void,
+                },
+                Node::MemLockup { targ, obj, .. } => match (targ.as_ref(), self.infer(obj)) {
+                    (Node::Var { value, .. }, _) if value == "len" => Type::Int,
+                    (Node::Var { value, .. }, Type::List(item)) if value == "get" || value == "pop" => *item,
+                    _ => Type::Void,
+                },
+                Node::Var { value, .. } => match self.functions.get(value) {
+                    Some((params, result)) if params.len() == args.len() => result.clone(),
+                    _ => self.types.type_of(node).cloned().unwrap_or(Type::Unknown),
+                },
+                _ => Type::Unknown,
+            },
+            // the checker knows the item type of an empty list from where it ends up
+            Node::List { element, .. } => match self.types.type_of(node) {
+                Some(Type::List(item)) if Self::value_type(item).is_some() => Type::List(item.clone()),
+                _ if !element.is_empty() => Type::List(Box::new(self.infer(&element[0]))),
+                _ => Type::Unknown,
+            },
+            Node::Void => Type::Void,
+            node => self.types.type_of(node).cloned().unwrap_or(Type::Unknown),
+        }
+    }
+
+    // the checked type of a declaration, reported when this backend cannot represent it
+    fn declared_type(&mut self, ty: Option<Type>, loc: &Location) -> Type {
+        let ty = ty.unwrap_or(Type::Unknown);
+        self.supported(&ty, loc);
+        ty
+    }
+
+    fn supported(&mut self, ty: &Type, loc: &Location) -> bool {
+        let fits = match ty {
+            Type::List(item) => Self::value_type(item).is_some() && !matches!(item.as_ref(), Type::Null),
+            ty => Self::value_type(ty).is_some(),
+        };
+        if !fits {
+            let what = match ty {
+                Type::Unknown => "values of this type".to_owned(),
+                ty => format!("`{}` values", ty),
+            };
+            self.unsupported(&what, loc);
+        }
+        fits
+    }
+
+    fn value_type(ty: &Type) -> Option<&'static str> {
+        match ty {
+            Type::Int | Type::Long => Some("i64"),
+            Type::Float | Type::Double => Some("f64"),
+            Type::Bool | Type::Str | Type::Null => Some("i32"),
+            Type::List(item) if Self::value_type(item).is_some() => Some("i32"),
+            _ => None,
+        }
+    }
+
+    fn comparison(opr: &str) -> &'static str {
+        match opr {
+            "<" => "lt",
+            ">" => "gt",
+            "<=" => "le",
+            _ => "ge",
+        }
+    }
+
+    fn unsupported(&mut self, what: &str, loc: &Location) {
+        self.diagnostics.push(
+            Diagnostic::error("E0601", format!("the wasm32 backend does not support {} yet", what), loc.clone())
+                .with_help("build with `--backend=c` or `--backend=bytecode` instead"),
+        );
+    }
+
+    fn describe(node: &Node) -> &'static str {
+        match node {
+            Node::Dict { .. } => "dicts",
+            Node::Range { .. } => "ranges outside of `for` loops",
+            Node::MemLockup { .. } => "fields",
+            Node::CodeLockup { .. } => "paths",
+            _ => "these expressions",
+        }
+    }
+
+    fn local(&self, name: &str) -> Option<(String, Type)> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name)).cloned()
+    }
+
+    fn declare_local(&mut self, name: &str, ty: Type) -> String {
+        let local = format!("$v_{}_{}", mangle(name), self.locals.len());
+        self.locals.push((local.clone(), Self::value_type(&ty).unwrap_or("i32")));
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_owned(), (local.clone(), ty));
+        }
+        local
+    }
+
+    // a local no name refers to, for loop bookkeeping and evaluation order
+    fn temp(&mut self, kind: &'static str) -> String {
+        let local = format!("$t{}", self.locals.len());
+        self.locals.push((local.clone(), kind));
+        local
+    }
+
+    // the address of `value` in linear memory
+    fn string(&mut self, value: &str) -> u32 {
+        if let Some(address) = self.strings.get(value) {
+            return *address;
+        }
+
+        let address = self.data_end;
+        let mut bytes = (value.len() as u32).to_le_bytes().to_vec();
+        bytes.extend_from_slice(value.as_bytes());
+        self.data_end = (address + bytes.len() as u32).next_multiple_of(4);
+        self.segments.push((address, bytes));
+        self.strings.insert(value.to_owned(), address);
+        address
+    }
+
+    fn data_text(data: &[u8]) -> String {
+        let mut out = String::new();
+        for byte in data {
+            match byte {
+                b'"' | b'\\' => {
+                    let _ = write!(out, "\\{:02x}", byte);
+                }
+                0x20..=0x7e => out.push(*byte as char),
+                _ => {
+                    let _ = write!(out, "\\{:02x}", byte);
+                }
+            }
+        }
+        out
+    }
+
+    // text the assembler and other WebAssembly tools read back as the same double
+    fn float_text(value: f64) -> String {
+        if value.is_nan() {
+            "nan".to_owned()
+        } else if value.is_infinite() {
+            if value > 0.0 { "inf" } else { "-inf" }.to_owned()
+        } else {
+            format!("{:?}", value)
+        }
+    }
+
+    fn label(&mut self) -> String {
+        self.labels += 1;
+        format!("$l{}", self.labels)
+    }
+
+    // one instruction, indented by how deeply it is nested
+    fn emit(&mut self, instruction: &str) {
+        let opens = ["block", "loop", "if"].iter().any(|op| instruction == *op || instruction.starts_with(&format!("{} ", op)));
+        if instruction == "end" || instruction == "else" {
+            self.indent = self.indent.saturating_sub(1);
+        }
+        for _ in 0..self.indent {
+            self.out.push_str("  ");
+        }
+        self.out.push_str(instruction);
+        self.out.push('\n');
+        if opens || instruction == "else" {
+            self.indent += 1;
+        }
+    }
+
+    fn entry(&self) -> Location {
+        Location {
+            file: self.file.clone(),
+            line: 1,
+            column: 1,
+            start: 0,
+            end: 1,
+        }
+    }
+
+    fn function_name(name: &str) -> String {
+        format!("$mx_f_{}", mangle(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::wat;
+    use crate::compiler;
+
+    use std::process::Command;
+
+    fn wasm(source: &str) -> Vec<u8> {
+        let (nodes, types) = compiler::analyze(source);
+        let text = WasmGenerator::new(&types, "main.mx").generate(&nodes).unwrap();
+        wat::assemble(&text).unwrap()
+    }
+
+    // output and exit code of the module under `mix_host.mjs`
+    fn run(source: &str) -> (String, String, i32) {
+        let dir = std::env::temp_dir().join(format!("mix-wasm-test-{}-{:x}", std::process::id(), source_hash(source)));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("main.wasm"), wasm(source)).unwrap();
+        std::fs::write(dir.join("mix_host.mjs"), HOST).unwrap();
+
+        let output = Command::new("node").arg(dir.join("mix_host.mjs")).arg(dir.join("main.wasm")).output();
+        std::fs::remove_dir_all(&dir).ok();
+        let output = output.expect("`node` runs the module");
+        let text = |bytes: Vec<u8>| String::from_utf8(bytes).unwrap();
+        (text(output.stdout), text(output.stderr), output.status.code().unwrap_or(-1))
+    }
+
+    fn source_hash(source: &str) -> u64 {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        source.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn assembles_a_module_exporting_main() {
+        let binary = wasm("func main() -> int {\n    return 0;\n}\n");
+        assert_eq!(&binary[..8], b"\0asm\x01\0\0\0");
+        assert!(binary.windows(4).any(|bytes| bytes == b"main"));
+    }
+
+    #[test]
+    #[ignore = "needs `node`"]
+    fn runs_like_mix_run() {
+        let source = "import std;\n\nfunc fib(n: int) -> int {\n    if n < 2 {\n        return n;\n    }\n    return fib(n - 1) + fib(n - 2);\n}\n\n\
+            func main() -> int {\n    let xs = [1, 2, 3];\n    xs.push(4);\n    let total = 0;\n    for x in xs {\n        total = total + x;\n    }\n    \
+            std::println(total, fib(10), 1.0 / 4.0, \"mix\");\n    return 3;\n}\n";
+        let (stdout, _, code) = run(source);
+        assert_eq!(stdout, "10 55 0.25 mix\n");
+        assert_eq!(code, 3);
+    }
+
+    #[test]
+    #[ignore = "needs `node`"]
+    fn reports_runtime_errors() {
+        let source = "func main() -> int {\n    let a = 0;\n    return 10 / a;\n}\n";
+        let (_, stderr, code) = run(source);
+        assert!(stderr.starts_with("error[E0501]: attempt to divide by zero"), "{}", stderr);
+        assert_eq!(code, 1);
+    }
+}
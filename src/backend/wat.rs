@@ -0,0 +1,662 @@
+// assembles the WebAssembly text the wasm32 backend writes into the binary format;
+// it understands the flat instruction style and the module fields that backend uses,
+// not the folded expressions or abbreviations of the full text format
+
+use std::collections::HashMap;
+
+enum Sexp {
+    List(Vec<Sexp>),
+    Atom(String),
+    Str(Vec<u8>),
+}
+
+impl Sexp {
+    fn atom(&self) -> Option<&str> {
+        match self {
+            Sexp::Atom(atom) => Some(atom),
+            _ => None,
+        }
+    }
+
+    fn list(&self) -> Option<&[Sexp]> {
+        match self {
+            Sexp::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    // `(keyword ...)`
+    fn head(&self) -> Option<&str> {
+        self.list().and_then(|items| items.first()).and_then(Sexp::atom)
+    }
+}
+
+#[derive(Clone, PartialEq)]
+struct FuncType {
+    params: Vec<u8>,
+    results: Vec<u8>,
+}
+
+// a parameter or local, with its name if it has one
+type Local = (Option<String>, u8);
+
+struct Func<'s> {
+    name: Option<String>,
+    ty: u32,
+    export: Option<String>,
+    // parameters first
+    locals: Vec<Local>,
+    params: usize,
+    body: &'s [Sexp],
+}
+
+pub fn assemble(source: &str) -> Result<Vec<u8>, String> {
+    let module = parse(source)?;
+    let fields = match module.list() {
+        Some([Sexp::Atom(keyword), fields @ ..]) if keyword == "module" => fields,
+        _ => return Err("expected `(module ...)`".to_owned()),
+    };
+
+    let mut types: Vec<FuncType> = Vec::new();
+    let mut type_index = |ty: FuncType| match types.iter().position(|existing| *existing == ty) {
+        Some(index) => index as u32,
+        None => {
+            types.push(ty);
+            types.len() as u32 - 1
+        }
+    };
+
+    let mut imports = Vec::new();
+    let mut funcs = Vec::new();
+    let mut globals = Vec::new();
+    let mut memory = None;
+    let mut data = Vec::new();
+
+    for field in fields {
+        let items = field.list().ok_or("expected a module field")?;
+        match field.head() {
+            Some("import") => {
+                let (Some(Sexp::Str(module)), Some(Sexp::Str(name)), Some(desc)) = (items.get(1), items.get(2), items.get(3)) else {
+                    return Err("malformed `import`".to_owned());
+                };
+                let desc = desc.list().filter(|desc| desc.first().and_then(Sexp::atom) == Some("func"))
+                    .ok_or("only functions can be imported")?;
+                let (ty, _, _) = signature(&desc[1..])?;
+                let name_id = desc.get(1).and_then(Sexp::atom).filter(|id| id.starts_with('$')).map(str::to_owned);
+                imports.push((module.clone(), name.clone(), name_id, type_index(ty)));
+            }
+            Some("func") => {
+                let name = items.get(1).and_then(Sexp::atom).filter(|id| id.starts_with('$')).map(str::to_owned);
+                let export = items.iter().find(|item| item.head() == Some("export"))
+                    .and_then(|item| match item.list() {
+                        Some([_, Sexp::Str(name)]) => Some(String::from_utf8_lossy(name).into_owned()),
+                        _ => None,
+                    });
+                let (ty, locals, header) = signature(&items[1..])?;
+                let params = ty.params.len();
+                funcs.push(Func { name, ty: type_index(ty), export, locals, params, body: &items[1 + header..] });
+            }
+            Some("global") => {
+                let name = items.get(1).and_then(Sexp::atom).filter(|id| id.starts_with('$')).map(str::to_owned);
+                let rest = &items[1 + name.is_some() as usize..];
+                let (ty, mutable) = match rest.first() {
+                    Some(Sexp::Atom(ty)) => (value_type(ty)?, false),
+                    Some(Sexp::List(inner)) if inner.first().and_then(Sexp::atom) == Some("mut") => {
+                        (value_type(inner.get(1).and_then(Sexp::atom).unwrap_or(""))?, true)
+                    }
+                    _ => return Err("malformed `global`".to_owned()),
+                };
+                let init = rest.get(1).and_then(Sexp::list).ok_or("a global needs an initializer")?;
+                globals.push((name, ty, mutable, constant(init)?));
+            }
+            Some("memory") => {
+                let export = items.iter().find(|item| item.head() == Some("export"))
+                    .and_then(|item| match item.list() {
+                        Some([_, Sexp::Str(name)]) => Some(String::from_utf8_lossy(name).into_owned()),
+                        _ => None,
+                    });
+                let pages = items.iter().filter_map(Sexp::atom).find_map(|atom| atom.parse::<u32>().ok())
+                    .ok_or("a memory needs a page count")?;
+                memory = Some((pages, export));
+            }
+            Some("data") => {
+                let offset = items.get(1).and_then(Sexp::list).ok_or("a data segment needs an offset")?;
+                let mut bytes = Vec::new();
+                for item in &items[2..] {
+                    if let Sexp::Str(part) = item {
+                        bytes.extend_from_slice(part);
+                    }
+                }
+                data.push((constant(offset)?, bytes));
+            }
+            Some(other) => return Err(format!("unsupported module field `{}`", other)),
+            None => return Err("expected a module field".to_owned()),
+        }
+    }
+
+    let mut func_ids = HashMap::new();
+    for (i, name) in imports.iter().map(|import| &import.2).chain(funcs.iter().map(|func| &func.name)).enumerate() {
+        if let Some(name) = name {
+            func_ids.insert(name.clone(), i as u32);
+        }
+    }
+    let global_ids: HashMap<String, u32> = globals.iter().enumerate()
+        .filter_map(|(i, global)| global.0.clone().map(|name| (name, i as u32)))
+        .collect();
+
+    let mut out = b"\0asm\x01\0\0\0".to_vec();
+
+    section(&mut out, 1, types.len(), |body| {
+        for ty in &types {
+            body.push(0x60);
+            vector(body, &ty.params);
+            vector(body, &ty.results);
+        }
+    });
+
+    section(&mut out, 2, imports.len(), |body| {
+        for (module, name, _, ty) in &imports {
+            vector(body, module);
+            vector(body, name);
+            body.push(0x00);
+            unsigned(body, *ty as u64);
+        }
+    });
+
+    section(&mut out, 3, funcs.len(), |body| {
+        for func in &funcs {
+            unsigned(body, func.ty as u64);
+        }
+    });
+
+    if let Some((pages, _)) = &memory {
+        section(&mut out, 5, 1, |body| {
+            body.push(0x00);
+            unsigned(body, *pages as u64);
+        });
+    }
+
+    section(&mut out, 6, globals.len(), |body| {
+        for (_, ty, mutable, init) in &globals {
+            body.push(*ty);
+            body.push(*mutable as u8);
+            body.extend_from_slice(init);
+        }
+    });
+
+    let mut exports = Vec::new();
+    if let Some((_, Some(name))) = &memory {
+        exports.push((name.clone(), 0x02, 0));
+    }
+    for (i, func) in funcs.iter().enumerate() {
+        if let Some(name) = &func.export {
+            exports.push((name.clone(), 0x00, (imports.len() + i) as u32));
+        }
+    }
+    section(&mut out, 7, exports.len(), |body| {
+        for (name, kind, index) in &exports {
+            vector(body, name.as_bytes());
+            body.push(*kind);
+            unsigned(body, *index as u64);
+        }
+    });
+
+    let mut code = Vec::new();
+    for func in &funcs {
+        let context = func.name.as_deref().unwrap_or("an unnamed function");
+        let body = function_body(func, &func_ids, &global_ids).map_err(|e| format!("{} in `{}`", e, context))?;
+        unsigned(&mut code, body.len() as u64);
+        code.extend_from_slice(&body);
+    }
+    section(&mut out, 10, funcs.len(), |body| body.extend_from_slice(&code));
+
+    section(&mut out, 11, data.len(), |body| {
+        for (offset, bytes) in &data {
+            body.push(0x00);
+            body.extend_from_slice(offset);
+            vector(body, bytes);
+        }
+    });
+
+    Ok(out)
+}
+
+// the type of a `func` or import header, with the named locals; also returns how many items the header took
+fn signature(items: &[Sexp]) -> Result<(FuncType, Vec<Local>, usize), String> {
+    let mut ty = FuncType { params: Vec::new(), results: Vec::new() };
+    let mut locals = Vec::new();
+    let mut extra = Vec::new();
+    let mut taken = 0;
+
+    for item in items {
+        match item {
+            Sexp::Atom(id) if id.starts_with('$') && taken == 0 => {}
+            Sexp::List(parts) => {
+                let keyword = parts.first().and_then(Sexp::atom).unwrap_or("");
+                if !matches!(keyword, "export" | "param" | "result" | "local") {
+                    break;
+                }
+                if keyword == "export" {
+                    taken += 1;
+                    continue;
+                }
+                // `(param $name type)` or `(param type type ...)`
+                let named = parts.get(1).and_then(Sexp::atom).filter(|id| id.starts_with('$')).map(str::to_owned);
+                let kinds = parts[1 + named.is_some() as usize..].iter()
+                    .map(|part| value_type(part.atom().unwrap_or("")))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match keyword {
+                    "param" => {
+                        ty.params.extend(&kinds);
+                        locals.extend(kinds.iter().map(|kind| (named.clone(), *kind)));
+                    }
+                    "result" => ty.results.extend(&kinds),
+                    "local" => extra.extend(kinds.iter().map(|kind| (named.clone(), *kind))),
+                    _ => {}
+                }
+            }
+            _ => break,
+        }
+        taken += 1;
+    }
+
+    locals.extend(extra);
+    Ok((ty, locals, taken))
+}
+
+fn function_body(func: &Func, funcs: &HashMap<String, u32>, globals: &HashMap<String, u32>) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+
+    // locals beyond the parameters, as runs of the same type
+    let mut runs: Vec<(u32, u8)> = Vec::new();
+    for (_, kind) in &func.locals[func.params..] {
+        match runs.last_mut() {
+            Some((count, last)) if last == kind => *count += 1,
+            _ => runs.push((1, *kind)),
+        }
+    }
+    unsigned(&mut out, runs.len() as u64);
+    for (count, kind) in runs {
+        unsigned(&mut out, count as u64);
+        out.push(kind);
+    }
+
+    let local = |id: &str| -> Result<u32, String> {
+        if let Ok(index) = id.parse() {
+            return Ok(index);
+        }
+        func.locals.iter().position(|(name, _)| name.as_deref() == Some(id))
+            .map(|index| index as u32)
+            .ok_or_else(|| format!("unknown local `{}`", id))
+    };
+
+    // block labels from the innermost outwards, `None` for unlabelled ones
+    let mut labels: Vec<Option<String>> = Vec::new();
+    let mut items = func.body.iter().peekable();
+
+    while let Some(item) = items.next() {
+        let op = item.atom().ok_or("expected an instruction")?;
+        let mut operand = || items.next().and_then(Sexp::atom).ok_or_else(|| format!("`{}` needs an operand", op));
+
+        match op {
+            "block" | "loop" | "if" => {
+                out.push(match op {
+                    "block" => 0x02,
+                    "loop" => 0x03,
+                    _ => 0x04,
+                });
+                let label = items.next_if(|next| next.atom().is_some_and(|atom| atom.starts_with('$')))
+                    .and_then(Sexp::atom)
+                    .map(str::to_owned);
+                labels.push(label);
+                match items.next_if(|next| next.head() == Some("result")) {
+                    Some(result) => {
+                        let kind = result.list().and_then(|parts| parts.get(1)).and_then(Sexp::atom).unwrap_or("");
+                        out.push(value_type(kind)?);
+                    }
+                    None => out.push(0x40),
+                }
+            }
+            "else" => out.push(0x05),
+            "end" => {
+                labels.pop();
+                out.push(0x0b);
+            }
+            "br" | "br_if" => {
+                out.push(if op == "br" { 0x0c } else { 0x0d });
+                let target = operand()?;
+                let depth = match target.parse() {
+                    Ok(depth) => depth,
+                    Err(_) => labels.iter().rev().position(|label| label.as_deref() == Some(target))
+                        .ok_or_else(|| format!("unknown label `{}`", target))? as u32,
+                };
+                unsigned(&mut out, depth as u64);
+            }
+            "call" => {
+                out.push(0x10);
+                let target = operand()?;
+                let index = funcs.get(target).copied().ok_or_else(|| format!("unknown function `{}`", target))?;
+                unsigned(&mut out, index as u64);
+            }
+            "local.get" | "local.set" | "local.tee" => {
+                out.push(match op {
+                    "local.get" => 0x20,
+                    "local.set" => 0x21,
+                    _ => 0x22,
+                });
+                let index = local(operand()?)?;
+                unsigned(&mut out, index as u64);
+            }
+            "global.get" | "global.set" => {
+                out.push(if op == "global.get" { 0x23 } else { 0x24 });
+                let target = operand()?;
+                let index = globals.get(target).copied().ok_or_else(|| format!("unknown global `{}`", target))?;
+                unsigned(&mut out, index as u64);
+            }
+            "i32.const" => {
+                out.push(0x41);
+                let value = integer(operand()?)?;
+                signed(&mut out, value as i32 as i64);
+            }
+            "i64.const" => {
+                out.push(0x42);
+                let value = integer(operand()?)?;
+                signed(&mut out, value);
+            }
+            "f64.const" => {
+                out.push(0x44);
+                let value = float(operand()?)?;
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+            "memory.size" | "memory.grow" => {
+                out.push(if op == "memory.size" { 0x3f } else { 0x40 });
+                out.push(0x00);
+            }
+            "memory.copy" => out.extend_from_slice(&[0xfc, 10, 0x00, 0x00]),
+            _ => {
+                if let Some((opcode, align)) = memory_op(op) {
+                    let mut offset = 0;
+                    let mut align = align;
+                    while let Some(next) = items.next_if(|next| next.atom().is_some_and(|atom| atom.starts_with("offset=") || atom.starts_with("align="))) {
+                        let atom = next.atom().unwrap_or("");
+                        if let Some(value) = atom.strip_prefix("offset=") {
+                            offset = integer(value)? as u64;
+                        } else if let Some(value) = atom.strip_prefix("align=") {
+                            align = (integer(value)? as u64).trailing_zeros() as u8;
+                        }
+                    }
+                    out.push(opcode);
+                    unsigned(&mut out, align as u64);
+                    unsigned(&mut out, offset);
+                } else {
+                    out.push(plain_op(op).ok_or_else(|| format!("unknown instruction `{}`", op))?);
+                }
+            }
+        }
+    }
+
+    // the function's own `end`
+    out.push(0x0b);
+    Ok(out)
+}
+
+// the initializer of a global or the offset of a data segment
+fn constant(expr: &[Sexp]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    match (expr.first().and_then(Sexp::atom), expr.get(1).and_then(Sexp::atom)) {
+        (Some("i32.const"), Some(value)) => {
+            out.push(0x41);
+            signed(&mut out, integer(value)? as i32 as i64);
+        }
+        (Some("i64.const"), Some(value)) => {
+            out.push(0x42);
+            signed(&mut out, integer(value)?);
+        }
+        (Some("f64.const"), Some(value)) => {
+            out.push(0x44);
+            out.extend_from_slice(&float(value)?.to_le_bytes());
+        }
+        _ => return Err("expected a constant expression".to_owned()),
+    }
+    out.push(0x0b);
+    Ok(out)
+}
+
+fn value_type(name: &str) -> Result<u8, String> {
+    match name {
+        "i32" => Ok(0x7f),
+        "i64" => Ok(0x7e),
+        "f32" => Ok(0x7d),
+        "f64" => Ok(0x7c),
+        _ => Err(format!("unknown value type `{}`", name)),
+    }
+}
+
+// opcode and natural alignment as a power of two
+fn memory_op(name: &str) -> Option<(u8, u8)> {
+    Some(match name {
+        "i32.load" => (0x28, 2),
+        "i64.load" => (0x29, 3),
+        "f64.load" => (0x2b, 3),
+        "i32.load8_u" => (0x2d, 0),
+        "i32.store" => (0x36, 2),
+        "i64.store" => (0x37, 3),
+        "f64.store" => (0x39, 3),
+        "i32.store8" => (0x3a, 0),
+        _ => return None,
+    })
+}
+
+fn plain_op(name: &str) -> Option<u8> {
+    Some(match name {
+        "unreachable" => 0x00,
+        "nop" => 0x01,
+        "return" => 0x0f,
+        "drop" => 0x1a,
+        "select" => 0x1b,
+        "i32.eqz" => 0x45,
+        "i32.eq" => 0x46,
+        "i32.ne" => 0x47,
+        "i32.lt_s" => 0x48,
+        "i32.lt_u" => 0x49,
+        "i32.gt_s" => 0x4a,
+        "i32.gt_u" => 0x4b,
+        "i32.le_s" => 0x4c,
+        "i32.le_u" => 0x4d,
+        "i32.ge_s" => 0x4e,
+        "i32.ge_u" => 0x4f,
+        "i64.eqz" => 0x50,
+        "i64.eq" => 0x51,
+        "i64.ne" => 0x52,
+        "i64.lt_s" => 0x53,
+        "i64.lt_u" => 0x54,
+        "i64.gt_s" => 0x55,
+        "i64.gt_u" => 0x56,
+        "i64.le_s" => 0x57,
+        "i64.le_u" => 0x58,
+        "i64.ge_s" => 0x59,
+        "i64.ge_u" => 0x5a,
+        "f64.eq" => 0x61,
+        "f64.ne" => 0x62,
+        "f64.lt" => 0x63,
+        "f64.gt" => 0x64,
+        "f64.le" => 0x65,
+        "f64.ge" => 0x66,
+        "i32.add" => 0x6a,
+        "i32.sub" => 0x6b,
+        "i32.mul" => 0x6c,
+        "i32.div_s" => 0x6d,
+        "i32.div_u" => 0x6e,
+        "i32.rem_s" => 0x6f,
+        "i32.rem_u" => 0x70,
+        "i32.and" => 0x71,
+        "i32.or" => 0x72,
+        "i32.xor" => 0x73,
+        "i32.shl" => 0x74,
+        "i32.shr_s" => 0x75,
+        "i32.shr_u" => 0x76,
+        "i64.add" => 0x7c,
+        "i64.sub" => 0x7d,
+        "i64.mul" => 0x7e,
+        "i64.div_s" => 0x7f,
+        "i64.div_u" => 0x80,
+        "i64.rem_s" => 0x81,
+        "i64.rem_u" => 0x82,
+        "i64.and" => 0x83,
+        "i64.or" => 0x84,
+        "i64.xor" => 0x85,
+        "i64.shl" => 0x86,
+        "i64.shr_s" => 0x87,
+        "i64.shr_u" => 0x88,
+        "f64.abs" => 0x99,
+        "f64.neg" => 0x9a,
+        "f64.add" => 0xa0,
+        "f64.sub" => 0xa1,
+        "f64.mul" => 0xa2,
+        "f64.div" => 0xa3,
+        "i32.wrap_i64" => 0xa7,
+        "i64.extend_i32_s" => 0xac,
+        "i64.extend_i32_u" => 0xad,
+        "f64.convert_i64_s" => 0xb9,
+        _ => return None,
+    })
+}
+
+fn integer(text: &str) -> Result<i64, String> {
+    let text = text.replace('_', "");
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text.strip_prefix('+').unwrap_or(&text)),
+    };
+    let value = match digits.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => digits.parse::<u64>(),
+    }.map_err(|_| format!("invalid integer `{}`", text))?;
+    Ok(if negative { (value as i64).wrapping_neg() } else { value as i64 })
+}
+
+fn float(text: &str) -> Result<f64, String> {
+    match text {
+        "inf" | "+inf" => Ok(f64::INFINITY),
+        "-inf" => Ok(f64::NEG_INFINITY),
+        "nan" | "+nan" => Ok(f64::NAN),
+        "-nan" => Ok(-f64::NAN),
+        _ => text.replace('_', "").parse().map_err(|_| format!("invalid float `{}`", text)),
+    }
+}
+
+fn parse(source: &str) -> Result<Sexp, String> {
+    let bytes = source.as_bytes();
+    let mut pos = 0;
+    let mut stack: Vec<Vec<Sexp>> = vec![Vec::new()];
+
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b';' if bytes.get(pos + 1) == Some(&b';') => {
+                while pos < bytes.len() && bytes[pos] != b'\n' {
+                    pos += 1;
+                }
+            }
+            b'(' => {
+                stack.push(Vec::new());
+                pos += 1;
+            }
+            b')' => {
+                let list = stack.pop().filter(|_| !stack.is_empty()).ok_or("unbalanced `)`")?;
+                if let Some(parent) = stack.last_mut() {
+                    parent.push(Sexp::List(list));
+                }
+                pos += 1;
+            }
+            b'"' => {
+                pos += 1;
+                let mut text = Vec::new();
+                loop {
+                    match bytes.get(pos) {
+                        None => return Err("unterminated string".to_owned()),
+                        Some(b'"') => break,
+                        Some(b'\\') => {
+                            let escape = bytes.get(pos + 1).copied().ok_or("unterminated string")?;
+                            pos += 2;
+                            match escape {
+                                b'n' => text.push(b'\n'),
+                                b't' => text.push(b'\t'),
+                                b'r' => text.push(b'\r'),
+                                b'"' | b'\\' | b'\'' => text.push(escape),
+                                _ => {
+                                    let hex = std::str::from_utf8(&bytes[pos - 1..(pos + 1).min(bytes.len())]).unwrap_or("");
+                                    text.push(u8::from_str_radix(hex, 16).map_err(|_| format!("invalid escape `\\{}`", hex))?);
+                                    pos += 1;
+                                }
+                            }
+                            continue;
+                        }
+                        Some(&byte) => text.push(byte),
+                    }
+                    pos += 1;
+                }
+                pos += 1;
+                if let Some(list) = stack.last_mut() {
+                    list.push(Sexp::Str(text));
+                }
+            }
+            byte if byte.is_ascii_whitespace() => pos += 1,
+            _ => {
+                let start = pos;
+                while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() && !matches!(bytes[pos], b'(' | b')' | b'"' | b';') {
+                    pos += 1;
+                }
+                if let Some(list) = stack.last_mut() {
+                    list.push(Sexp::Atom(source[start..pos].to_owned()));
+                }
+            }
+        }
+    }
+
+    match stack.pop() {
+        Some(mut top) if stack.is_empty() && top.len() == 1 => Ok(top.remove(0)),
+        _ => Err("expected a single `(module ...)`".to_owned()),
+    }
+}
+
+fn section(out: &mut Vec<u8>, id: u8, count: usize, write: impl FnOnce(&mut Vec<u8>)) {
+    if count == 0 {
+        return;
+    }
+    let mut body = Vec::new();
+    // the code section writes its own bodies after the count
+    unsigned(&mut body, count as u64);
+    write(&mut body);
+    out.push(id);
+    unsigned(out, body.len() as u64);
+    out.extend_from_slice(&body);
+}
+
+fn vector(out: &mut Vec<u8>, bytes: &[u8]) {
+    unsigned(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+fn unsigned(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn signed(out: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
@@ -0,0 +1,1122 @@
+use super::node::{Node, Location};
+use super::diagnostic::{Diagnostic, Diagnostics};
+use super::resolver::{SymbolId, SymbolKind, SymbolTable};
+use super::types::{Type, TypeTable};
+
+use std::collections::{HashMap, HashSet};
+
+pub struct Checker<'a> {
+    table: &'a SymbolTable,
+    types: TypeTable,
+    diagnostics: Diagnostics,
+    aliases: HashMap<SymbolId, &'a Node>,
+    // return type of the function being checked
+    rtype: Type,
+    // struct whose methods are being checked, for private field access
+    owner: Option<SymbolId>,
+    loops: usize,
+    // bindings of `Type::Infer` placeholders, filled in as usage is checked
+    vars: Vec<Option<Type>>,
+    // `let`s of the current function whose type still depends on a placeholder
+    pending: Vec<(String, Location, SymbolId)>,
+    // `let x;` without a type that may not have been assigned yet at the current statement
+    unassigned: HashSet<SymbolId>,
+    // files an earlier build checked whose inputs did not change, only their signatures are used
+    trusted: HashSet<String>,
+    debug: bool,
+}
+
+impl<'a> Checker<'a> {
+    pub fn new(table: &'a SymbolTable, debug: bool) -> Self {
+        Self {
+            table,
+            types: TypeTable::default(),
+            diagnostics: Diagnostics::new(),
+            aliases: HashMap::new(),
+            rtype: Type::Void,
+            owner: None,
+            loops: 0,
+            vars: Vec::new(),
+            pending: Vec::new(),
+            unassigned: HashSet::new(),
+            trusted: HashSet::new(),
+            debug,
+        }
+    }
+
+    // skips the bodies of functions declared in `files`, leaving their expressions untyped
+    pub fn trust(&mut self, files: HashSet<String>) {
+        self.trusted = files;
+    }
+
+    pub fn check(&mut self, nodes: &'a [Box<Node>]) -> (TypeTable, Diagnostics) {
+        if self.debug {
+            println!("[CHECKER] Starting Type Checking");
+        }
+
+        for node in nodes {
+            if let Node::Alias { loc, real, .. } = node.as_ref()
+                && let Some(id) = self.table.declaration(loc)
+            {
+                self.aliases.insert(id, real);
+            }
+        }
+
+        // signatures first, so bodies can call functions and methods declared later
+        for node in nodes {
+            self.declare_signatures(node);
+        }
+
+        for node in nodes {
+            if let Node::FuncDef { name, args, rtype, loc, .. } = node.as_ref()
+                && name == "main"
+            {
+                self.check_main(args, rtype, loc);
+            }
+            self.check_item(node);
+        }
+
+        // anything still unbound was reported already, later stages see it as unknown
+        let vars = &self.vars;
+        self.types.map_types(|ty| Self::substitute(vars, ty, true));
+
+        for ((file, start), id) in self.table.declarations() {
+            self.types.set_declaration(file.clone(), *start, *id);
+        }
+
+        let mut diagnostics = std::mem::take(&mut self.diagnostics);
+        diagnostics.sort();
+
+        (std::mem::take(&mut self.types), diagnostics)
+    }
+
+    fn declare_signatures(&mut self, node: &Node) {
+        match node {
+            Node::FuncDef { args, rtype, loc, .. } | Node::Attach { args, rtype, loc, .. } => {
+                let signature = self.signature(args, rtype);
+                if let Some(id) = self.table.declaration(loc) {
+                    self.types.set_symbol(id, signature);
+                }
+            }
+            Node::Struct { child, body, loc, .. } => {
+                let Some(owner) = self.table.declaration(loc) else {
+                    return;
+                };
+
+                let mut fields = Vec::new();
+                for field in child {
+                    if let Node::LetDef { dtype, loc, .. } = field.as_ref() {
+                        let ty = self.annotation(dtype).unwrap_or(Type::Unknown);
+                        fields.push(ty.clone());
+
+                        if let Some(id) = self.table.declaration(loc) {
+                            self.types.set_symbol(id, ty);
+                        }
+                    }
+                }
+
+                // calling the struct name builds an instance from its fields in declaration order
+                let instance = self.own_type(owner);
+                self.types.set_symbol(owner, Type::Function(fields, Box::new(instance)));
+
+                for method in body {
+                    self.declare_signatures(method);
+                }
+            }
+            Node::Enum { child, loc, .. } => {
+                let Some(owner) = self.table.declaration(loc) else {
+                    return;
+                };
+
+                let name = self.table.symbol(owner).name.clone();
+                for variant in child {
+                    if let Some(id) = variant.loc().and_then(|loc| self.table.declaration(loc)) {
+                        self.types.set_symbol(id, Type::Enum(owner, name.clone()));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn signature(&mut self, args: &[Box<Node>], rtype: &Node) -> Type {
+        let mut params = Vec::new();
+
+        for arg in args {
+            if let Node::LetDef { dtype, loc, .. } = arg.as_ref() {
+                let ty = match self.annotation(dtype) {
+                    Some(ty) => ty,
+                    None => {
+                        self.diagnostics.push(
+                            Diagnostic::error("E0409", "missing type annotation for parameter", loc.clone())
+                                .with_help("parameters need an explicit type, e.g. `name: int`"),
+                        );
+                        Type::Unknown
+                    }
+                };
+
+                if let Some(id) = self.table.declaration(loc) {
+                    self.types.set_symbol(id, ty.clone());
+                }
+                params.push(ty);
+            }
+        }
+
+        let ret = self.annotation(rtype).unwrap_or(Type::Void);
+        Type::Function(params, Box::new(ret))
+    }
+
+    // `None` when the node is the placeholder for a missing annotation
+    fn annotation(&mut self, node: &Node) -> Option<Type> {
+        match node {
+            Node::Null { .. } => None,
+            Node::Void => Some(Type::Void),
+            _ => Some(self.resolve_type(node, 0)),
+        }
+    }
+
+    fn resolve_type(&mut self, node: &Node, depth: usize) -> Type {
+        match node {
+            Node::Var { value, loc } => {
+                match value.as_str() {
+                    "int" => return Type::Int,
+                    "long" => return Type::Long,
+                    "float" => return Type::Float,
+                    "double" => return Type::Double,
+                    "bool" => return Type::Bool,
+                    "str" => return Type::Str,
+                    "void" => return Type::Void,
+                    "List" | "Dict" => {
+                        self.diagnostics.push(Diagnostic::error("E0410", format!("missing type parameters for `{}`", value), loc.clone()));
+                        return Type::Unknown;
+                    }
+                    _ => {}
+                }
+
+                let Some(id) = self.table.reference(loc) else {
+                    return Type::Unknown;
+                };
+
+                let symbol = self.table.symbol(id);
+                match symbol.kind {
+                    SymbolKind::Struct => self.generic_type(id, Vec::new(), loc),
+                    SymbolKind::TypeParam => Type::Param(id, symbol.name.clone()),
+                    SymbolKind::Enum => Type::Enum(id, symbol.name.clone()),
+                    SymbolKind::Alias if depth < 32 => match self.aliases.get(&id).copied() {
+                        Some(real) => self.resolve_type(real, depth + 1),
+                        None => Type::Unknown,
+                    },
+                    SymbolKind::Alias => {
+                        self.diagnostics.push(Diagnostic::error("E0410", format!("alias `{}` refers to itself", value), loc.clone()));
+                        Type::Unknown
+                    }
+                    _ => Type::Unknown,
+                }
+            }
+            Node::GenericType { name, params, loc } => {
+                let mut args: Vec<Type> = params.iter().map(|param| self.resolve_type(param, depth)).collect();
+
+                let Node::Var { value, loc: name_loc } = name.as_ref() else {
+                    return Type::Unknown;
+                };
+
+                match (value.as_str(), args.len()) {
+                    ("List", 1) => return Type::List(Box::new(args.remove(0))),
+                    ("Dict", 2) => {
+                        let value = args.pop().unwrap_or(Type::Unknown);
+                        return Type::Dict(Box::new(args.remove(0)), Box::new(value));
+                    }
+                    ("List" | "Dict", found) => {
+                        let expected = if value == "List" { 1 } else { 2 };
+                        self.arity_error(value, expected, found, loc);
+                        return Type::Unknown;
+                    }
+                    _ => {}
+                }
+
+                match self.table.reference(name_loc) {
+                    Some(id) if self.table.symbol(id).kind == SymbolKind::Struct => self.generic_type(id, args, loc),
+                    Some(_) => {
+                        self.diagnostics.push(Diagnostic::error("E0410", format!("`{}` does not take type parameters", value), loc.clone()));
+                        Type::Unknown
+                    }
+                    None => Type::Unknown,
+                }
+            }
+            Node::FuncType { params, rtype, .. } => {
+                let params = params.iter().map(|param| self.resolve_type(param, depth)).collect();
+                let ret = match rtype.as_ref() {
+                    Node::Void => Type::Void,
+                    rtype => self.resolve_type(rtype, depth),
+                };
+                Type::Function(params, Box::new(ret))
+            }
+            _ => Type::Unknown,
+        }
+    }
+
+    // struct `id` applied to `args`, which must match its type parameters
+    fn generic_type(&mut self, id: SymbolId, args: Vec<Type>, loc: &Location) -> Type {
+        let name = self.table.symbol(id).name.clone();
+        let expected = self.table.generics(id).len();
+
+        if args.len() != expected {
+            self.arity_error(&name, expected, args.len(), loc);
+            return Type::Unknown;
+        }
+
+        Type::Struct(id, name, args)
+    }
+
+    fn arity_error(&mut self, name: &str, expected: usize, found: usize, loc: &Location) {
+        let message = if found == 0 {
+            format!("missing type parameters for `{}`", name)
+        } else {
+            format!("`{}` takes {} type parameter(s) but {} were supplied", name, expected, found)
+        };
+        self.diagnostics.push(Diagnostic::error("E0410", message, loc.clone()));
+    }
+
+    // the type of a struct seen from inside its own declaration, `Pair<A, B>` for `struct Pair<A, B>`
+    fn own_type(&self, owner: SymbolId) -> Type {
+        let params = self.table.generics(owner).iter()
+            .map(|id| Type::Param(*id, self.table.symbol(*id).name.clone()))
+            .collect();
+        Type::Struct(owner, self.table.symbol(owner).name.clone(), params)
+    }
+
+    // a use of generic function or struct `id`, with fresh placeholders for its type parameters
+    fn instantiate_fresh(&mut self, id: SymbolId, ty: &Type) -> Type {
+        let table = self.table;
+        let generics = table.generics(id);
+        if generics.is_empty() {
+            return ty.clone();
+        }
+
+        let with = generics.iter().map(|param| (*param, self.fresh())).collect();
+        ty.instantiate(&with)
+    }
+
+    fn check_item(&mut self, node: &Node) {
+        if node.loc().is_some_and(|loc| self.trusted.contains(&loc.file)) {
+            return;
+        }
+
+        match node {
+            Node::FuncDef { name, rtype, body, loc, .. } => {
+                self.check_function(name, rtype, body, None, loc);
+            }
+            Node::Attach { name, rtype, body, loc, .. } => {
+                let owner = self.table.declaration(loc).and_then(|id| self.table.symbol(id).owner);
+                self.check_function(name, rtype, body, owner, loc);
+            }
+            Node::Struct { body, loc, .. } => {
+                let owner = self.table.declaration(loc);
+                for method in body {
+                    if let Node::FuncDef { name, rtype, body, loc, .. } = method.as_ref() {
+                        self.check_function(name, rtype, body, owner, loc);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // every backend calls `main` without arguments and turns its result into the exit code
+    fn check_main(&mut self, args: &[Box<Node>], rtype: &Node, loc: &Location) {
+        if !args.is_empty() {
+            self.diagnostics.push(
+                Diagnostic::error("E0412", "`main` cannot take parameters", loc.clone())
+                    .with_help("declare it as `func main()` or `func main() -> int`"),
+            );
+        }
+
+        let rtype = self.annotation(rtype).unwrap_or(Type::Void);
+        if !matches!(rtype, Type::Int | Type::Void | Type::Unknown) {
+            self.diagnostics.push(
+                Diagnostic::error("E0412", format!("`main` must return `int` or nothing, found `{}`", rtype), loc.clone())
+                    .with_help("the `int` `main` returns becomes the exit code of the program"),
+            );
+        }
+    }
+
+    fn check_function(&mut self, name: &str, rtype: &Node, body: &[Box<Node>], owner: Option<SymbolId>, loc: &Location) {
+        self.rtype = self.annotation(rtype).unwrap_or(Type::Void);
+        self.owner = owner;
+        self.unassigned.clear();
+
+        self.check_block(body);
+        self.settle_pending();
+
+        if self.rtype != Type::Void && !self.rtype.is_unknown() && !Self::always_returns(body) {
+            self.diagnostics.push(
+                Diagnostic::error("E0407", format!("function `{}` may finish without returning a value of type `{}`", name, self.rtype), loc.clone())
+                    .with_help("add a `return` at the end of the function body"),
+            );
+        }
+
+        self.owner = None;
+    }
+
+    fn settle_pending(&mut self) {
+        for (name, loc, id) in std::mem::take(&mut self.pending) {
+            let ty = self.types.symbol(id).map(|ty| self.apply(ty)).unwrap_or(Type::Unknown);
+
+            if ty.has_infer() {
+                let shown = Self::substitute(&self.vars, &ty, false);
+                self.diagnostics.push(
+                    Diagnostic::error("E0409", format!("type annotations needed for `{}`", name), loc.clone())
+                        .with_note(format!("the type of `{}` is `{}`, which is not fully known from its uses", name, shown))
+                        .with_help(format!("add a type annotation, e.g. `let {}: Type`", name)),
+                );
+            }
+
+            self.types.set_symbol(id, Self::substitute(&self.vars, &ty, true));
+        }
+    }
+
+    fn always_returns(body: &[Box<Node>]) -> bool {
+        body.iter().any(|node| match node.as_ref() {
+            Node::Return { .. } => true,
+            Node::Conditional { body_if, body_else, .. } => {
+                Self::always_returns(body_if) && Self::always_returns(body_else)
+            }
+            Node::MatchCase { child, default, .. } => {
+                !default.is_empty() && Self::always_returns(default) &&
+                child.iter().all(|arm| Self::always_returns(&arm.1))
+            }
+            _ => false,
+        })
+    }
+
+    // whether running `body` never reaches the statement after it
+    fn diverges(body: &[Box<Node>]) -> bool {
+        body.iter().any(|node| match node.as_ref() {
+            Node::Return { .. } | Node::Break { .. } | Node::Continue { .. } => true,
+            Node::Conditional { body_if, body_else, .. } => Self::diverges(body_if) && Self::diverges(body_else),
+            Node::MatchCase { child, default, .. } => {
+                !default.is_empty() && Self::diverges(default) && child.iter().all(|arm| Self::diverges(&arm.1))
+            }
+            _ => false,
+        })
+    }
+
+    // checks each of `bodies` from the same starting point; afterwards a variable counts as
+    // assigned only when every body that falls through assigned it
+    fn check_branches(&mut self, bodies: &[&[Box<Node>]]) {
+        let before = self.unassigned.clone();
+        let mut after = HashSet::new();
+        let mut reached = false;
+        for body in bodies {
+            self.unassigned = before.clone();
+            self.check_block(body);
+            if !Self::diverges(body) {
+                after.extend(self.unassigned.drain());
+                reached = true;
+            }
+        }
+        self.unassigned = if reached { after } else { before };
+    }
+
+    fn check_block(&mut self, body: &[Box<Node>]) {
+        for node in body {
+            self.check_stmt(node);
+        }
+    }
+
+    fn check_stmt(&mut self, node: &Node) {
+        match node {
+            Node::LetDef { name, dtype, value, loc, .. } => {
+                let declared = self.annotation(dtype);
+                // only a type annotation gives `let x;` a value to start from
+                let empty = declared.is_none() && matches!(value.as_ref(), Node::Void);
+                let ty = match (declared, value.as_ref()) {
+                    (Some(declared), Node::Void) => declared,
+                    (Some(declared), value) => {
+                        let found = self.check_expr(value);
+                        self.expect(&declared, &found, value);
+                        declared
+                    }
+                    (None, Node::Void) => self.fresh(),
+                    (None, value) => {
+                        let found = self.check_expr(value);
+                        if found == Type::Null {
+                            self.diagnostics.push(
+                                Diagnostic::error("E0409", format!("cannot infer the type of `{}` from `null`", name), loc.clone())
+                                    .with_help(format!("add a type annotation, e.g. `let {}: Type = null;`", name)),
+                            );
+                            Type::Unknown
+                        } else {
+                            found
+                        }
+                    }
+                };
+
+                if let Some(id) = self.table.declaration(loc) {
+                    if empty {
+                        self.unassigned.insert(id);
+                    }
+                    if ty.has_infer() {
+                        self.pending.push((name.clone(), loc.clone(), id));
+                    }
+                    self.types.set_symbol(id, ty);
+                }
+            }
+            Node::AssignDef { targ, value, opr, loc } => {
+                if !matches!(targ.as_ref(), Node::Var { .. } | Node::MemLockup { .. }) {
+                    self.diagnostics.push(Diagnostic::error("E0408", "invalid left-hand side of assignment", loc.clone()));
+                }
+
+                // the value is read before a plain `x = ...` assigns `x`
+                let (target, found) = match targ.as_ref() {
+                    Node::Var { loc, .. } if opr == "=" => {
+                        let found = self.check_expr(value);
+                        if let Some(id) = self.table.reference(loc) {
+                            self.unassigned.remove(&id);
+                        }
+                        (self.check_expr(targ), found)
+                    }
+                    _ => (self.check_expr(targ), self.check_expr(value)),
+                };
+
+                match opr.as_str() {
+                    "=" => self.expect(&target, &found, value),
+                    "+=" if target == Type::Str => self.expect(&Type::Str, &found, value),
+                    "+=" | "-=" | "*=" | "/=" | "%=" | "^=" => {
+                        self.unify(&target, &found);
+                        let (target, found) = (self.apply(&target), self.apply(&found));
+
+                        if !target.is_numeric() || !found.is_numeric() {
+                            self.operand_error(opr, &target, &found, loc);
+                        } else if !target.promote(&found).is_assignable_to(&target) {
+                            self.expect(&target, &found, value);
+                        }
+                    }
+                    _ => {
+                        self.diagnostics.push(Diagnostic::error("E0408", format!("`{}` is not an assignment operator", opr), loc.clone()));
+                    }
+                }
+            }
+            Node::Return { value, loc } => {
+                let expected = self.rtype.clone();
+
+                match value.as_ref() {
+                    Node::Void if expected != Type::Void && !expected.is_unknown() => {
+                        self.diagnostics.push(Diagnostic::error("E0401", format!("expected a `{}` return value", expected), loc.clone()));
+                    }
+                    Node::Void => {}
+                    value => {
+                        let found = self.check_expr(value);
+                        if expected == Type::Void {
+                            self.diagnostics.push(
+                                Diagnostic::error("E0401", format!("this function returns nothing, but a `{}` is returned", found), loc.clone())
+                                    .with_help("add a return type to the function signature"),
+                            );
+                        } else {
+                            self.expect(&expected, &found, value);
+                        }
+                    }
+                }
+            }
+            Node::Conditional { cond, body_if, body_else, .. } => {
+                let found = self.check_expr(cond);
+                self.expect(&Type::Bool, &found, cond);
+                self.check_branches(&[body_if, body_else]);
+            }
+            Node::WhileLoop { cond, body, .. } => {
+                let found = self.check_expr(cond);
+                self.expect(&Type::Bool, &found, cond);
+
+                self.loops += 1;
+                self.check_branches(&[body, &[]]);
+                self.loops -= 1;
+            }
+            Node::ForLoop { initializer, iterator, body, .. } => {
+                let iterable = self.check_expr(iterator);
+                let item = match &iterable {
+                    Type::Range(item) | Type::List(item) | Type::Dict(item, _) => item.as_ref().clone(),
+                    Type::Str => Type::Str,
+                    Type::Unknown => Type::Unknown,
+                    other => {
+                        self.diagnostics.push(Diagnostic::error("E0402", format!("`{}` is not iterable", other), Self::loc_of(iterator)));
+                        Type::Unknown
+                    }
+                };
+
+                if let Node::LetDef { dtype, loc, .. } = initializer.as_ref() {
+                    let ty = match self.annotation(dtype) {
+                        Some(declared) => {
+                            if !item.is_assignable_to(&declared) {
+                                self.mismatch(&declared, &item, loc.clone());
+                            }
+                            declared
+                        }
+                        None => item,
+                    };
+
+                    if let Some(id) = self.table.declaration(loc) {
+                        self.types.set_symbol(id, ty);
+                    }
+                }
+
+                self.loops += 1;
+                self.check_branches(&[body, &[]]);
+                self.loops -= 1;
+            }
+            Node::MatchCase { value, child, default, .. } => {
+                let subject = self.check_expr(value);
+
+                for arm in child {
+                    let pattern = self.check_expr(&arm.0);
+                    if !pattern.is_assignable_to(&subject) && !subject.is_assignable_to(&pattern) {
+                        self.mismatch(&subject, &pattern, Self::loc_of(&arm.0));
+                    }
+                }
+
+                // without a `default`, no arm may run at all
+                let mut bodies: Vec<&[Box<Node>]> = child.iter().map(|arm| arm.1.as_slice()).collect();
+                bodies.push(default);
+                self.check_branches(&bodies);
+            }
+            Node::Break { loc } | Node::Continue { loc } => {
+                if self.loops == 0 {
+                    let keyword = if matches!(node, Node::Break { .. }) { "break" } else { "continue" };
+                    self.diagnostics.push(Diagnostic::error("E0411", format!("`{}` outside of a loop", keyword), loc.clone()));
+                }
+            }
+            _ => {
+                self.check_expr(node);
+            }
+        }
+    }
+
+    fn check_expr(&mut self, node: &Node) -> Type {
+        let ty = self.infer_expr(node);
+        let ty = self.apply(&ty);
+        self.types.set_expr(node, ty.clone());
+        ty
+    }
+
+    fn infer_expr(&mut self, node: &Node) -> Type {
+        match node {
+            Node::Int { lbit, .. } => if *lbit { Type::Long } else { Type::Int },
+            Node::Float { lbit, .. } => if *lbit { Type::Double } else { Type::Float },
+            Node::Bool { .. } => Type::Bool,
+            Node::Str { .. } => Type::Str,
+            Node::Null { .. } => Type::Null,
+            Node::Void => Type::Void,
+            Node::Var { value, loc } => {
+                if let Some(id) = self.table.reference(loc)
+                    && self.unassigned.remove(&id)
+                {
+                    self.diagnostics.push(
+                        Diagnostic::error("E0413", format!("`{}` is read before it is assigned", value), loc.clone())
+                            .with_help(format!("assign `{}` first, or give it a type to start from its default, e.g. `let {}: int;`", value, value)),
+                    );
+                }
+                self.symbol_type(loc)
+            }
+            Node::List { element, .. } if element.is_empty() => Type::List(Box::new(self.fresh())),
+            Node::List { element, .. } => {
+                let item = self.unify_items(element.iter().map(|e| e.as_ref()));
+                Type::List(Box::new(item))
+            }
+            Node::Dict { key_value, .. } if key_value.is_empty() => {
+                Type::Dict(Box::new(self.fresh()), Box::new(self.fresh()))
+            }
+            Node::Dict { key_value, .. } => {
+                let key = self.unify_items(key_value.iter().map(|pair| &pair.0));
+                let value = self.unify_items(key_value.iter().map(|pair| &pair.1));
+                Type::Dict(Box::new(key), Box::new(value))
+            }
+            Node::Range { min, max, .. } => {
+                let min = self.check_expr(min);
+                let max = self.check_expr(max);
+                let item = min.promote(&max);
+
+                if !item.is_integer() {
+                    self.diagnostics.push(Diagnostic::error("E0402", format!("range bounds must be integers, found `{}`", item), Self::loc_of(node)));
+                }
+                Type::Range(Box::new(item))
+            }
+            Node::MemLockup { targ, obj, .. } => {
+                let object = self.check_expr(obj);
+                let Node::Var { value: name, loc } = targ.as_ref() else {
+                    return Type::Unknown;
+                };
+                self.member_type(&object, name, loc)
+            }
+            Node::CodeLockup { targ, obj, .. } => {
+                let Node::Var { loc, .. } = targ.as_ref() else {
+                    return Type::Unknown;
+                };
+
+                match obj.as_ref() {
+                    Node::Var { .. } => self.symbol_type(loc),
+                    _ => Type::Unknown,
+                }
+            }
+            Node::FuncCall { func, args, loc } => self.check_call(func, args, loc),
+            Node::BinaryOp { lhs, rhs, opr, loc } => {
+                let left = self.check_expr(lhs);
+                let right = self.check_expr(rhs);
+                self.binary_type(opr, &left, &right, loc)
+            }
+            Node::UnaryOp { opr, value, loc } => {
+                let ty = self.check_expr(value);
+                let valid = match opr.as_str() {
+                    "-" | "+" => ty.is_numeric(),
+                    "!" => ty.is_bool(),
+                    "~" => ty.is_integer(),
+                    _ => false,
+                };
+
+                if !valid {
+                    self.diagnostics.push(Diagnostic::error("E0402", format!("cannot apply unary `{}` to `{}`", opr, ty), loc.clone()));
+                    return Type::Unknown;
+                }
+                ty
+            }
+            Node::Tuple { element, .. } => {
+                for item in element {
+                    self.check_expr(item);
+                }
+                Type::Unknown
+            }
+            _ => Type::Unknown,
+        }
+    }
+
+    fn symbol_type(&mut self, loc: &Location) -> Type {
+        let Some(id) = self.table.reference(loc) else {
+            return Type::Unknown;
+        };
+
+        let symbol = self.table.symbol(id);
+
+        if symbol.name == "self" && symbol.kind == SymbolKind::Variable && let Some(owner) = symbol.owner {
+            return self.own_type(owner);
+        }
+
+        match self.types.symbol(id).cloned() {
+            Some(ty) => {
+                let ty = self.instantiate_fresh(id, &ty);
+                self.apply(&ty)
+            }
+            None => Type::Unknown,
+        }
+    }
+
+    fn member_type(&mut self, object: &Type, name: &str, loc: &Location) -> Type {
+        match object {
+            Type::Struct(owner, struct_name, args) => {
+                let Some(id) = self.table.member(*owner, name) else {
+                    self.diagnostics.push(Diagnostic::error("E0405", format!("no field or method `{}` on type `{}`", name, struct_name), loc.clone()));
+                    return Type::Unknown;
+                };
+
+                let member = self.table.symbol(id);
+                if !member.public && self.owner != Some(*owner) {
+                    let mut diagnostic = Diagnostic::error("E0406", format!("{} `{}` of `{}` is private", if member.kind == SymbolKind::Field { "field" } else { "method" }, name, struct_name), loc.clone());
+                    if let Some(decl) = &member.loc {
+                        diagnostic = diagnostic.with_label(decl.clone(), "declared here without `public`");
+                    }
+                    self.diagnostics.push(diagnostic);
+                }
+
+                let with = self.table.generics(*owner).iter().copied().zip(args.iter().cloned()).collect();
+                let ty = self.types.symbol(id).cloned().unwrap_or(Type::Unknown).instantiate(&with);
+                self.instantiate_fresh(id, &ty)
+            }
+            Type::Unknown => Type::Unknown,
+            other => match Self::builtin_method(other, name) {
+                Some(ty) => ty,
+                None => {
+                    self.diagnostics.push(Diagnostic::error("E0405", format!("no method `{}` on type `{}`", name, other), loc.clone()));
+                    Type::Unknown
+                }
+            },
+        }
+    }
+
+    // methods every list, dict and string value has
+    pub fn builtin_method(object: &Type, name: &str) -> Option<Type> {
+        let func = |params: Vec<Type>, ret: Type| Some(Type::Function(params, Box::new(ret)));
+
+        match (object, name) {
+            (Type::List(_) | Type::Dict(_, _) | Type::Str, "len") => func(vec![], Type::Int),
+            (Type::List(item), "push") => func(vec![item.as_ref().clone()], Type::Void),
+            (Type::List(item), "pop") => func(vec![], item.as_ref().clone()),
+            (Type::List(item), "get") => func(vec![Type::Int], item.as_ref().clone()),
+            (Type::List(item), "set") => func(vec![Type::Int, item.as_ref().clone()], Type::Void),
+            (Type::List(item), "contains") => func(vec![item.as_ref().clone()], Type::Bool),
+            (Type::Dict(key, value), "get") => func(vec![key.as_ref().clone()], value.as_ref().clone()),
+            (Type::Dict(key, value), "set") => func(vec![key.as_ref().clone(), value.as_ref().clone()], Type::Void),
+            (Type::Dict(key, _), "contains") => func(vec![key.as_ref().clone()], Type::Bool),
+            (Type::Dict(key, _), "remove") => func(vec![key.as_ref().clone()], Type::Void),
+            (Type::Dict(key, _), "keys") => func(vec![], Type::List(key.clone())),
+            (Type::Dict(_, value), "values") => func(vec![], Type::List(value.clone())),
+            (Type::Str, "contains") => func(vec![Type::Str], Type::Bool),
+            _ => None,
+        }
+    }
+
+    // return type of the functions in the builtin `std` module
+    pub fn std_function(name: &str) -> Option<Type> {
+        match name {
+            "print" | "println" => Some(Type::Void),
+            "input" | "str" => Some(Type::Str),
+            "int" | "len" => Some(Type::Int),
+            "long" => Some(Type::Long),
+            "float" => Some(Type::Float),
+            "double" => Some(Type::Double),
+            _ => None,
+        }
+    }
+
+    fn check_call(&mut self, func: &Node, args: &[Box<Node>], loc: &Location) -> Type {
+        if let Node::CodeLockup { targ, obj, .. } = func
+            && let (Node::Var { value: name, loc: name_loc }, Node::Var { loc: module_loc, .. }) = (targ.as_ref(), obj.as_ref())
+            && let Some(module) = self.table.reference(module_loc)
+            && self.table.symbol(module).kind == SymbolKind::Module
+            && self.table.symbol(module).loc.is_none()
+        {
+            for arg in args {
+                self.check_expr(arg);
+            }
+
+            return match Self::std_function(name) {
+                Some(ret) => ret,
+                None => {
+                    self.diagnostics.push(Diagnostic::error("E0405", format!("no function `{}` in module `std`", name), name_loc.clone()));
+                    Type::Unknown
+                }
+            };
+        }
+
+        let callee = self.check_expr(func);
+
+        match callee {
+            Type::Function(params, ret) => {
+                if params.len() != args.len() {
+                    self.diagnostics.push(Diagnostic::error(
+                        "E0403",
+                        format!("this function takes {} argument(s) but {} were supplied", params.len(), args.len()),
+                        loc.clone(),
+                    ));
+                }
+
+                for (i, arg) in args.iter().enumerate() {
+                    let found = self.check_expr(arg);
+                    if let Some(param) = params.get(i) {
+                        self.expect(param, &found, arg);
+                    }
+                }
+
+                *ret
+            }
+            Type::Unknown => {
+                for arg in args {
+                    self.check_expr(arg);
+                }
+                Type::Unknown
+            }
+            other => {
+                self.diagnostics.push(Diagnostic::error("E0404", format!("`{}` is not callable", other), loc.clone()));
+                Type::Unknown
+            }
+        }
+    }
+
+    fn binary_type(&mut self, opr: &str, left: &Type, right: &Type, loc: &Location) -> Type {
+        // an operand of still unknown type takes the type of the other one, `&&` and `||` need bools
+        if matches!(opr, "&&" | "||") {
+            self.unify(left, &Type::Bool);
+            self.unify(right, &Type::Bool);
+        } else {
+            self.unify(left, right);
+        }
+
+        let (left, right) = (&self.apply(left), &self.apply(right));
+
+        match opr {
+            "+" if *left == Type::Str && *right == Type::Str => Type::Str,
+            "+" | "-" | "*" | "/" | "%" | "^" => {
+                if left.is_numeric() && right.is_numeric() {
+                    return left.promote(right);
+                }
+                self.operand_error(opr, left, right, loc);
+                Type::Unknown
+            }
+            "&" | "|" | "<<" | ">>" => {
+                if left.is_integer() && right.is_integer() {
+                    return left.promote(right);
+                }
+                self.operand_error(opr, left, right, loc);
+                Type::Unknown
+            }
+            "&&" | "||" => {
+                if !left.is_bool() || !right.is_bool() {
+                    self.operand_error(opr, left, right, loc);
+                }
+                Type::Bool
+            }
+            "==" | "!=" => {
+                if !left.is_assignable_to(right) && !right.is_assignable_to(left) {
+                    self.operand_error(opr, left, right, loc);
+                }
+                Type::Bool
+            }
+            "<" | ">" | "<=" | ">=" => {
+                let comparable = (left.is_numeric() && right.is_numeric()) ||
+                    (*left == Type::Str && *right == Type::Str);
+                if !comparable {
+                    self.operand_error(opr, left, right, loc);
+                }
+                Type::Bool
+            }
+            _ => Type::Unknown,
+        }
+    }
+
+    // the common element type of a list or dict literal
+    fn unify_items<'n>(&mut self, items: impl Iterator<Item = &'n Node>) -> Type {
+        let mut unified = Type::Unknown;
+
+        for item in items {
+            let ty = self.check_expr(item);
+
+            if unified.is_unknown() || unified == Type::Null {
+                unified = ty;
+            } else if ty.is_assignable_to(&unified) {
+                continue;
+            } else if unified.is_assignable_to(&ty) {
+                unified = ty;
+            } else if unified.is_numeric() && ty.is_numeric() {
+                unified = unified.promote(&ty);
+            } else {
+                self.mismatch(&unified, &ty, Self::loc_of(item));
+            }
+        }
+
+        unified
+    }
+
+    fn expect(&mut self, expected: &Type, found: &Type, node: &Node) {
+        self.unify(expected, found);
+
+        let (expected, found) = (self.apply(expected), self.apply(found));
+        if !found.is_assignable_to(&expected) {
+            self.mismatch(&expected, &found, Self::loc_of(node));
+        }
+    }
+
+    fn fresh(&mut self) -> Type {
+        self.vars.push(None);
+        Type::Infer(self.vars.len() - 1)
+    }
+
+    fn apply(&self, ty: &Type) -> Type {
+        Self::substitute(&self.vars, ty, false)
+    }
+
+    // replace bound placeholders with their types, and unbound ones with `Unknown` when `settle` is set
+    fn substitute(vars: &[Option<Type>], ty: &Type, settle: bool) -> Type {
+        match ty {
+            Type::Infer(var) => match &vars[*var] {
+                Some(bound) => Self::substitute(vars, bound, settle),
+                None if settle => Type::Unknown,
+                None => ty.clone(),
+            },
+            Type::List(item) => Type::List(Box::new(Self::substitute(vars, item, settle))),
+            Type::Range(item) => Type::Range(Box::new(Self::substitute(vars, item, settle))),
+            Type::Dict(key, value) => Type::Dict(
+                Box::new(Self::substitute(vars, key, settle)),
+                Box::new(Self::substitute(vars, value, settle)),
+            ),
+            Type::Function(params, ret) => Type::Function(
+                params.iter().map(|param| Self::substitute(vars, param, settle)).collect(),
+                Box::new(Self::substitute(vars, ret, settle)),
+            ),
+            Type::Struct(id, name, args) => Type::Struct(
+                *id,
+                name.clone(),
+                args.iter().map(|arg| Self::substitute(vars, arg, settle)).collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+
+    // bind placeholders on either side to the matching part of the other side
+    fn unify(&mut self, a: &Type, b: &Type) {
+        let (a, b) = (self.apply(a), self.apply(b));
+
+        match (&a, &b) {
+            (Type::Infer(x), Type::Infer(y)) if x == y => {}
+            (Type::Infer(var), other) | (other, Type::Infer(var))
+                if !matches!(other, Type::Unknown | Type::Null) && !Self::occurs(*var, other) =>
+            {
+                self.vars[*var] = Some(other.clone());
+            }
+            (Type::List(x), Type::List(y)) | (Type::Range(x), Type::Range(y)) => self.unify(x, y),
+            (Type::Dict(ak, av), Type::Dict(bk, bv)) => {
+                self.unify(ak, bk);
+                self.unify(av, bv);
+            }
+            (Type::Struct(a, _, xs), Type::Struct(b, _, ys)) if a == b => {
+                for (x, y) in xs.iter().zip(ys) {
+                    self.unify(x, y);
+                }
+            }
+            (Type::Function(ap, ar), Type::Function(bp, br)) if ap.len() == bp.len() => {
+                for (a, b) in ap.iter().zip(bp) {
+                    self.unify(a, b);
+                }
+                self.unify(ar, br);
+            }
+            _ => {}
+        }
+    }
+
+    fn occurs(var: usize, ty: &Type) -> bool {
+        match ty {
+            Type::Infer(other) => *other == var,
+            Type::List(item) | Type::Range(item) => Self::occurs(var, item),
+            Type::Dict(key, value) => Self::occurs(var, key) || Self::occurs(var, value),
+            Type::Function(params, ret) => params.iter().any(|p| Self::occurs(var, p)) || Self::occurs(var, ret),
+            Type::Struct(_, _, args) => args.iter().any(|a| Self::occurs(var, a)),
+            _ => false,
+        }
+    }
+
+    fn mismatch(&mut self, expected: &Type, found: &Type, loc: Location) {
+        self.diagnostics.push(Diagnostic::error("E0401", format!("mismatched types: expected `{}`, found `{}`", expected, found), loc));
+    }
+
+    fn operand_error(&mut self, opr: &str, left: &Type, right: &Type, loc: &Location) {
+        self.diagnostics.push(Diagnostic::error("E0402", format!("cannot apply `{}` to `{}` and `{}`", opr, left, right), loc.clone()));
+    }
+
+    // the most useful location to point at for an expression
+    fn loc_of(node: &Node) -> Location {
+        match node {
+            Node::BinaryOp { lhs, .. } => Self::loc_of(lhs),
+            Node::FuncCall { func, .. } => Self::loc_of(func),
+            Node::MemLockup { obj, .. } | Node::CodeLockup { obj, .. } => Self::loc_of(obj),
+            Node::Range { min, .. } => Self::loc_of(min),
+            other => other.loc().cloned().unwrap_or_else(|| Location {
+                file: String::new(),
+                line: 0,
+                column: 0,
+                start: 0,
+                end: 0,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::{analyze, diagnose};
+
+    // the codes of everything `source` is rejected with, in source order
+    fn codes(source: &str) -> Vec<&'static str> {
+        let (_, _, mut diagnostics) = diagnose(source);
+        diagnostics.sort();
+        diagnostics.iter().map(|d| d.code).collect()
+    }
+
+    // the checked types of the `let`s in the body of `main`, which comes last
+    fn lets(source: &str) -> Vec<Type> {
+        let (nodes, types) = analyze(source);
+        let Node::FuncDef { body, .. } = nodes.last().unwrap().as_ref() else {
+            panic!("expected `main` last");
+        };
+        body.iter().filter_map(|node| match node.as_ref() {
+            Node::LetDef { loc, .. } => types.declared(loc).cloned(),
+            _ => None,
+        }).collect()
+    }
+
+    #[test]
+    fn infers_lets_from_their_initializers_and_later_uses() {
+        let types = lets(
+            "func main() { let a = 1; let c = 1.5; let d = \"s\"; \
+             let xs = []; xs.push(a); let ys = {}; ys.set(d, c); let e; e = true; }",
+        );
+        assert_eq!(types, [
+            Type::Int,
+            Type::Float,
+            Type::Str,
+            Type::List(Box::new(Type::Int)),
+            Type::Dict(Box::new(Type::Str), Box::new(Type::Float)),
+            Type::Bool,
+        ]);
+    }
+
+    #[test]
+    fn rejects_lets_whose_type_cannot_be_inferred() {
+        assert_eq!(codes("func main() { let xs = []; }"), ["E0409"]);
+        assert_eq!(codes("func main() { let x; }"), ["E0409"]);
+        assert_eq!(codes("func main() { let x = null; }"), ["E0409"]);
+        assert_eq!(codes("func main() { let xs = []; let n = xs.len(); }"), ["E0409"]);
+    }
+
+    #[test]
+    fn rejects_uses_that_disagree_with_the_inferred_type() {
+        assert_eq!(codes("func main() { let xs = []; xs.push(1); xs.push(\"s\"); }"), ["E0401"]);
+        assert_eq!(codes("func main() { let x = 1; x = \"s\"; }"), ["E0401"]);
+    }
+
+    #[test]
+    fn rejects_functions_that_may_finish_without_returning() {
+        assert_eq!(codes("func f(x: int) -> int { if x > 0 { return 1; } } func main() {}"), ["E0407"]);
+        assert!(codes("func f(x: int) -> int { if x > 0 { return 1; } else { return 2; } } func main() {}").is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_assignments() {
+        assert_eq!(codes("func f() -> int { return 1; } func main() { f() = 1; }"), ["E0408"]);
+    }
+
+    #[test]
+    fn rejects_break_and_continue_outside_a_loop() {
+        assert_eq!(codes("func main() { break; continue; }"), ["E0411", "E0411"]);
+        assert!(codes("func main() { while true { if true { break; } continue; } }").is_empty());
+    }
+
+    #[test]
+    fn rejects_reads_before_assignment() {
+        assert_eq!(codes("func main() { let x; let y = x + 1; x = 2; }"), ["E0413"]);
+        assert_eq!(codes("func main() { let x; if true { x = 1; } let y = x; }"), ["E0413"]);
+        assert!(codes("func main() { let x; if true { x = 1; } else { x = 2; } let y = x; }").is_empty());
+    }
+
+    const PAIR: &str = "struct Pair<A, B> { public first: A; public second: B; } \
+        func map<T, U>(xs: List<T>, f: func(T) -> U) -> List<U> { let ys: List<U> = []; for x in xs { ys.push(f(x)); } return ys; } \
+        func show(x: int) -> str { return std::str(x); } ";
+
+    #[test]
+    fn instantiates_generic_structs_and_functions() {
+        let types = lets(&format!(
+            "import std; {}func main() {{ let p = Pair(1, \"one\"); let a = p.second; let names = map([1, 2], show); \
+             let q: Pair<List<int>, Dict<str, Pair<int, str>>> = Pair([], {{}}); }}",
+            PAIR,
+        ));
+        let shown: Vec<String> = types.iter().map(Type::to_string).collect();
+        assert_eq!(shown, ["Pair<int, str>", "str", "List<str>", "Pair<List<int>, Dict<str, Pair<int, str>>>"]);
+    }
+
+    #[test]
+    fn rejects_mismatched_type_arguments() {
+        let program = |main: &str| format!("import std; {}func main() {{ {} }}", PAIR, main);
+        assert_eq!(codes(&program("let p: Pair<int, str> = Pair(\"one\", 1);")), ["E0401"]);
+        assert_eq!(codes(&program("let p: Pair<int, str> = Pair(1, \"one\"); let n: int = p.second;")), ["E0401"]);
+        assert_eq!(codes(&program("let names: List<int> = map([1, 2], show);")), ["E0401"]);
+        assert_eq!(codes(&program("let p: Pair<int> = Pair(1, 2);")), ["E0410"]);
+        assert_eq!(codes(&program("let xs: List<int, int> = [];")), ["E0410"]);
+        assert_eq!(codes(&program("let n: int<str> = 1;")), ["E0410"]);
+        assert_eq!(codes("func add<T>(a: T, b: T) -> T { return a + b; } func main() {}"), ["E0402"]);
+    }
+}
@@ -0,0 +1,345 @@
+use super::node::Location;
+
+use colored::*;
+use std::collections::HashMap;
+
+// Diagnostic codes:
+//   E01xx  lexer   (E0101 unknown token, E0102 malformed number,
+//                   E0103 unknown escape, E0104 unterminated string)
+//   E02xx  parser  (E0201 unexpected token, E0202 expected token,
+//                   E0203 expected identifier, E0204 expected expression,
+//                   E0205 integer literal out of range)
+//   E03xx  resolver (E0301 undefined name, E0302 duplicate definition,
+//                   E0303 private member, E0304 expected type,
+//                   E0305 invalid attach target, E0306 unknown member,
+//                   E0307 unresolved import, E0308 import cycle)
+//   E04xx  checker (E0401 mismatched types, E0402 invalid operands,
+//                   E0403 wrong argument count, E0404 not callable,
+//                   E0405 unknown field or method, E0406 private field,
+//                   E0407 missing return, E0408 invalid assignment,
+//                   E0409 cannot infer type, E0410 invalid type,
+//                   E0411 break/continue outside loop)
+//   E05xx  runtime (E0501 division by zero, E0502 index out of bounds,
+//                   E0503 missing dict key, E0504 invalid operation,
+//                   E0505 stack overflow, E0506 missing `main`)
+//   E06xx  backend (E0601 not supported by the backend)
+//   E07xx  manifest (E0701 malformed `mix.conf`, E0702 invalid value,
+//                   E0703 invalid version or version requirement)
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub loc: Location,
+    pub message: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+    pub primary: Location,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, code: &'static str, message: impl Into<String>, primary: Location) -> Self {
+        Self {
+            severity,
+            code,
+            message: message.into(),
+            primary,
+            labels: Vec::new(),
+            notes: Vec::new(),
+            help: Vec::new(),
+        }
+    }
+
+    pub fn error(code: &'static str, message: impl Into<String>, primary: Location) -> Self {
+        Self::new(Severity::Error, code, message, primary)
+    }
+
+    pub fn with_label(mut self, loc: Location, message: impl Into<String>) -> Self {
+        self.labels.push(Label { loc, message: message.into() });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help.push(help.into());
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics {
+    items: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self { items: Vec::new() }
+    }
+
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.items.push(diagnostic);
+    }
+
+    pub fn extend(&mut self, other: Diagnostics) {
+        self.items.extend(other.items);
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.items.iter().any(|d| d.is_error())
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.items.iter().filter(|d| d.is_error()).count()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.items.iter()
+    }
+
+    // order by position so lexer and parser diagnostics interleave the way they appear in the source
+    pub fn sort(&mut self) {
+        self.items.sort_by(|a, b| {
+            (&a.primary.file, a.primary.start).cmp(&(&b.primary.file, b.primary.start))
+        });
+    }
+
+    // keeps only the first of the diagnostics at the same position, later ones are knock-on
+    // errors of the same mistake; run after `sort`
+    pub fn dedup(&mut self) {
+        self.items.dedup_by(|b, a| (&a.primary.file, a.primary.start) == (&b.primary.file, b.primary.start));
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+        };
+
+        write!(f, "{}[{}]: {}:{}:{}: {}", severity, self.code, self.primary.file, self.primary.line, self.primary.column, self.message)?;
+
+        for label in &self.labels {
+            write!(f, "\n  {}:{}:{}: {}", label.loc.file, label.loc.line, label.loc.column, label.message)?;
+        }
+
+        for note in &self.notes {
+            write!(f, "\n  note: {}", note)?;
+        }
+
+        for help in &self.help {
+            write!(f, "\n  help: {}", help)?;
+        }
+
+        Ok(())
+    }
+}
+
+// what a tab in a source line is shown as
+const TAB: &str = "    ";
+
+// columns `ch` takes up in a terminal: two for East Asian wide characters, none for combining
+// marks, and a tab as wide as `TAB`
+fn display_width(ch: char) -> usize {
+    match ch as u32 {
+        0x09 => TAB.len(),
+        0x0300..=0x036F | 0x200B..=0x200F | 0xFE00..=0xFE0F => 0,
+        0x1100..=0x115F | 0x2E80..=0x303E | 0x3041..=0x33FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF | 0xAC00..=0xD7A3 | 0xF900..=0xFAFF | 0xFE30..=0xFE4F | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6 | 0x1F300..=0x1F64F | 0x1F900..=0x1F9FF | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+pub struct Emitter {
+    sources: HashMap<String, String>,
+}
+
+impl Emitter {
+    pub fn new() -> Self {
+        Self { sources: HashMap::new() }
+    }
+
+    pub fn add_source(&mut self, file: String, source: String) {
+        self.sources.insert(file, source);
+    }
+
+    pub fn source(&self, file: &str) -> Option<&str> {
+        self.sources.get(file).map(String::as_str)
+    }
+
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        let mut out = String::new();
+
+        let severity = match diagnostic.severity {
+            Severity::Error => "error".red().bold(),
+        };
+
+        out.push_str(&format!("{}{}: {}\n", severity, format!("[{}]", diagnostic.code).bold(), diagnostic.message.bold()));
+
+        let primary = &diagnostic.primary;
+
+        // every annotated line of the primary file, with (line, indent, width, message, is_primary),
+        // the indent and width in terminal columns
+        let mut annotations: Vec<(usize, usize, usize, String, bool)> = Vec::new();
+        let mut foreign = Vec::new();
+
+        // line 0 is no position at all, so there is nothing to underline
+        if let Some(source) = self.sources.get(&primary.file)
+            && primary.line > 0
+            && let Some((column, width)) = Self::span_on_line(source, primary)
+        {
+            annotations.push((primary.line, column, width, String::new(), true));
+
+            for label in &diagnostic.labels {
+                if label.loc.line == 0 {
+                    continue;
+                }
+                match Self::span_on_line(source, &label.loc).filter(|_| label.loc.file == primary.file) {
+                    Some((column, width)) => annotations.push((label.loc.line, column, width, label.message.clone(), false)),
+                    None => foreign.push(label),
+                }
+            }
+        }
+
+        let max_line = annotations.iter().map(|a| a.0).max().unwrap_or(primary.line);
+        let gutter = max_line.to_string().len();
+        let pad = " ".repeat(gutter);
+
+        if primary.line > 0 {
+            out.push_str(&format!("{}{} {}:{}:{}\n", pad, "-->".blue().bold(), primary.file, primary.line, primary.column));
+        } else {
+            out.push_str(&format!("{}{} {}\n", pad, "-->".blue().bold(), primary.file));
+        }
+
+        if let Some(source) = self.sources.get(&primary.file)
+            && !annotations.is_empty()
+        {
+            let lines: Vec<&str> = source.lines().collect();
+
+            annotations.sort_by_key(|a| (a.0, !a.4, a.1));
+
+            out.push_str(&format!("{} {}\n", pad, "|".blue().bold()));
+
+            let mut last_line = None;
+            for (line, column, width, message, is_primary) in &annotations {
+                if last_line != Some(*line) {
+                    if let Some(last) = last_line && *line > last + 1 {
+                        out.push_str(&format!("{}\n", "...".blue().bold()));
+                    }
+
+                    let text = lines.get(line.saturating_sub(1)).copied().unwrap_or("");
+                    out.push_str(&format!("{} {} {}\n", format!("{:>gutter$}", line).blue().bold(), "|".blue().bold(), text.replace('\t', TAB)));
+                    last_line = Some(*line);
+                }
+
+                let marker = if *is_primary { "^" } else { "-" }.repeat(*width);
+                let underline = format!("{}{} {}", " ".repeat(*column), marker, message);
+                let underline = underline.trim_end().to_string();
+
+                let underline = if *is_primary {
+                    match diagnostic.severity {
+                        Severity::Error => underline.red().bold(),
+                    }
+                } else {
+                    underline.blue().bold()
+                };
+
+                out.push_str(&format!("{} {} {}\n", pad, "|".blue().bold(), underline));
+            }
+        }
+
+        for label in foreign {
+            out.push_str(&format!("{} {} {}:{}:{}: {}\n", pad, "=".blue().bold(), label.loc.file, label.loc.line, label.loc.column, label.message));
+        }
+
+        for note in &diagnostic.notes {
+            out.push_str(&format!("{} {} {}: {}\n", pad, "=".blue().bold(), "note".bold(), note));
+        }
+
+        for help in &diagnostic.help {
+            out.push_str(&format!("{} {} {}: {}\n", pad, "=".blue().bold(), "help".bold(), help));
+        }
+
+        out
+    }
+
+    // how far into its line `loc` starts and how wide its underline is, in terminal columns and
+    // clipped to the first line it covers, or nothing when `loc` does not point at a character
+    // of `source`
+    fn span_on_line(source: &str, loc: &Location) -> Option<(usize, usize)> {
+        let before = source.get(..loc.start)?;
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let indent = before[line_start..].chars().map(display_width).sum();
+
+        let span = source.get(loc.start..loc.end.min(source.len())).unwrap_or("");
+        let width = span.split('\n').next().unwrap_or("").chars().map(display_width).sum::<usize>();
+
+        Some((indent, width.max(1)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(source: &str, start: usize, end: usize) -> String {
+        let mut emitter = Emitter::new();
+        emitter.add_source("main.mx".to_owned(), source.to_owned());
+        let loc = Location { file: "main.mx".to_owned(), line: 1, column: start + 1, start, end };
+        plain(&emitter.render(&Diagnostic::error("E0501", "attempt to divide by zero", loc)))
+    }
+
+    // `text` without its colour codes, which depend on the terminal the tests run in
+    fn plain(text: &str) -> String {
+        let mut out = String::new();
+        let mut chars = text.chars();
+        while let Some(ch) = chars.next() {
+            if ch == '\x1b' {
+                chars.by_ref().find(|&ch| ch == 'm');
+            } else {
+                out.push(ch);
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn underlines_the_span() {
+        assert!(render("let b = 10 / a;", 11, 12).contains("\n  |            ^\n"));
+    }
+
+    #[test]
+    fn aligns_under_tabs_and_wide_characters() {
+        let source = "\tlet s = \"日本語\"; let y: int = \"x\";";
+        let start = source.find("\"x\"").unwrap();
+        let out = render(source, start, start + 3);
+        assert!(out.contains("\n1 |     let s = \"日本語\"; let y: int = \"x\";\n"));
+        assert!(out.contains(&format!("\n  | {}^^^\n", " ".repeat(35))));
+    }
+
+    #[test]
+    fn skips_the_snippet_inside_a_character() {
+        let out = render("éééé", 3, 4);
+        assert!(out.contains("--> main.mx:1:4\n"));
+        assert!(!out.contains('^'));
+    }
+}
@@ -0,0 +1,678 @@
+use super::module::SourceModule;
+use super::node::{Location, Node};
+use crate::project::hash::Sha256;
+use crate::project::layout::{Artifact, BuildDir};
+use crate::runtime::bytecode::{Module, Reader, Writer};
+use crate::runtime::compile::Cached;
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+
+// file header, followed by the format version and the version of `mix` that wrote it
+const MAGIC: &[u8; 4] = b"MXC\0";
+const VERSION: u32 = 1;
+
+// what `mix build` keeps about one module for the next build: the hashes that decide whether
+// it can be reused, its declarations without function bodies to resolve and check the others
+// against, and its compiled code
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    pub file: String,
+    pub source: String,
+    pub interface: String,
+    // the interfaces of every module it imports, directly or not
+    pub dependencies: String,
+    pub opt_level: u32,
+    pub nodes: Vec<Box<Node>>,
+    pub bytecode: Module,
+}
+
+impl CacheEntry {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Writer::default();
+        out.bytes.extend_from_slice(MAGIC);
+        out.u32(VERSION);
+        out.str(env!("CARGO_PKG_VERSION"));
+        out.str(&self.file);
+        out.str(&self.source);
+        out.str(&self.interface);
+        out.str(&self.dependencies);
+        out.u32(self.opt_level);
+
+        let mut encoder = Encoder { out, locations: true };
+        encoder.nodes(&self.nodes);
+        let mut out = encoder.out;
+
+        let bytecode = self.bytecode.to_bytes();
+        out.u32(bytecode.len() as u32);
+        out.bytes.extend_from_slice(&bytecode);
+        out.bytes
+    }
+
+    // anything written by another format or another version of `mix` is an error, so the
+    // module is compiled again
+    pub fn from_bytes(bytes: &[u8]) -> Result<CacheEntry, String> {
+        if bytes.len() < MAGIC.len() || &bytes[..MAGIC.len()] != MAGIC {
+            return Err("not a Mix cache file".to_owned());
+        }
+        let mut reader = Reader::new(&bytes[MAGIC.len()..]);
+        if reader.u32()? != VERSION || reader.str()? != env!("CARGO_PKG_VERSION") {
+            return Err("written by another version of mix".to_owned());
+        }
+
+        let file = reader.str()?;
+        let source = reader.str()?;
+        let interface = reader.str()?;
+        let dependencies = reader.str()?;
+        let opt_level = reader.u32()?;
+        let nodes = Decoder { reader: &mut reader, file: &file }.nodes()?;
+        let len = reader.u32()? as usize;
+        let bytecode = Module::from_portable_bytes(reader.take(len)?)?;
+        if !reader.done() {
+            return Err("unexpected content after the end of the cache file".to_owned());
+        }
+
+        Ok(CacheEntry { file, source, interface, dependencies, opt_level, nodes, bytecode })
+    }
+}
+
+pub fn source_hash(content: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(content.as_bytes());
+    hasher.finish()
+}
+
+// the top-level items of a module with every function body left out, which is all other
+// modules can see of it
+pub fn interface(nodes: &[Box<Node>]) -> Vec<Box<Node>> {
+    nodes.iter().map(|node| {
+        let mut node = node.clone();
+        match node.as_mut() {
+            Node::FuncDef { body, .. } | Node::Attach { body, .. } => body.clear(),
+            Node::Struct { body, .. } => {
+                for method in body.iter_mut() {
+                    if let Node::FuncDef { body, .. } = method.as_mut() {
+                        body.clear();
+                    }
+                }
+            }
+            _ => {}
+        }
+        node
+    }).collect()
+}
+
+// positions are left out, so moving code around without changing a declaration keeps the hash
+pub fn interface_hash(interface: &[Box<Node>]) -> String {
+    let mut encoder = Encoder { out: Writer::default(), locations: false };
+    encoder.nodes(interface);
+
+    let mut hasher = Sha256::new();
+    hasher.update(&encoder.out.bytes);
+    hasher.finish()
+}
+
+// the paths of the modules `nodes` import, whether they exist or not
+pub fn imports(nodes: &[Box<Node>]) -> Vec<String> {
+    let path = |segments: &[Box<Node>]| -> Vec<String> {
+        segments.iter().filter_map(|segment| match segment.as_ref() {
+            Node::Var { value, .. } => Some(value.clone()),
+            _ => None,
+        }).collect()
+    };
+
+    let mut found = Vec::new();
+    for node in nodes {
+        match node.as_ref() {
+            Node::Import { package, module, .. } => {
+                let package = path(package);
+                for name in path(module) {
+                    found.push([package.as_slice(), std::slice::from_ref(&name)].concat().join("::"));
+                }
+            }
+            Node::Use { package, .. } => found.push(path(package).join("::")),
+            _ => {}
+        }
+    }
+    found
+}
+
+// for every module, a hash of the interfaces of the modules it reaches through imports, given
+// the imports and interface hash of each module by name
+pub fn dependency_hashes(modules: &HashMap<String, (Vec<String>, String)>) -> HashMap<String, String> {
+    modules.keys().map(|name| {
+        let mut reached = BTreeMap::new();
+        let mut work = vec![name];
+        while let Some(current) = work.pop() {
+            for import in &modules[current].0 {
+                if import != name && modules.contains_key(import) && !reached.contains_key(import) {
+                    reached.insert(import.clone(), &modules[import].1);
+                    work.push(import);
+                }
+            }
+        }
+
+        let mut hasher = Sha256::new();
+        for (import, interface) in reached {
+            hasher.update(import.as_bytes());
+            hasher.update(&[0]);
+            hasher.update(interface.as_bytes());
+        }
+        (name.clone(), hasher.finish())
+    }).collect()
+}
+
+// what a bytecode `mix build` keeps in `cache` between builds
+pub struct BuildCache<'b> {
+    build: &'b BuildDir,
+    opt_level: u32,
+    verbose: bool,
+    // the code of every module taken from the cache, by file
+    pub reused: Cached,
+    // modules compiled this time by cache file name, their code filled in once the build succeeds
+    compiled: Vec<(String, CacheEntry)>,
+}
+
+impl<'b> BuildCache<'b> {
+    pub fn new(build: &'b BuildDir, opt_level: u32, verbose: bool) -> BuildCache<'b> {
+        BuildCache { build, opt_level, verbose, reused: Cached::new(), compiled: Vec::new() }
+    }
+
+    // `name` is the module path, empty for the entry file
+    fn file_name(name: &str) -> String {
+        if name.is_empty() {
+            "entry.mxc".to_owned()
+        } else {
+            format!("mod.{}.mxc", name.replace("::", "."))
+        }
+    }
+
+    // a cache file that cannot be read is as good as none
+    fn load(&self, name: &str) -> Option<CacheEntry> {
+        let bytes = fs::read(self.build.path(Artifact::Cache, &Self::file_name(name))).ok()?;
+        CacheEntry::from_bytes(&bytes).ok()
+    }
+
+    // the modules of `inputs`, each `(name, file, content)`: a module is reused when its source,
+    // the interfaces of what it imports and the optimization level are those it was cached
+    // with, and then holds only its declarations; every other module goes through `parse`
+    pub fn select(&mut self, inputs: Vec<(String, String, String)>, mut parse: impl FnMut(&str, String) -> Vec<Box<Node>>) -> Vec<SourceModule> {
+        let mut entries = Vec::new();
+        let mut parsed = Vec::new();
+        let mut interfaces = HashMap::new();
+        for (name, file, content) in &inputs {
+            let source = source_hash(content);
+            let (entry, reason) = match self.load(name) {
+                Some(entry) if entry.file == *file && entry.source == source => (Some(entry), ""),
+                Some(_) => (None, "source changed"),
+                None => (None, "not cached"),
+            };
+
+            let (nodes, imports, interface) = match &entry {
+                Some(entry) => (None, imports(&entry.nodes), entry.interface.clone()),
+                None => {
+                    let nodes = parse(file, content.clone());
+                    let interface = interface_hash(&interface(&nodes));
+                    let imports = imports(&nodes);
+                    (Some(nodes), imports, interface)
+                }
+            };
+            interfaces.insert(name.clone(), (imports, interface.clone()));
+            entries.push((entry, source, interface, reason));
+            parsed.push(nodes);
+        }
+
+        let dependencies = dependency_hashes(&interfaces);
+        let mut modules = Vec::new();
+        for (((name, file, content), (entry, source, interface, reason)), nodes) in inputs.into_iter().zip(entries).zip(parsed) {
+            let dependencies = dependencies[&name].clone();
+            let reason = match entry {
+                Some(entry) if entry.dependencies == dependencies && entry.opt_level == self.opt_level => {
+                    if self.verbose {
+                        println!("[CACHE] reused {}", Self::shown(&name, &file));
+                    }
+                    self.reused.insert(file.clone(), entry.bytecode);
+                    modules.push(SourceModule { name, file, nodes: entry.nodes });
+                    continue;
+                }
+                Some(entry) if entry.dependencies != dependencies => "dependency changed",
+                Some(_) => "optimization level changed",
+                None => reason,
+            };
+
+            if self.verbose {
+                println!("[CACHE] compiled {} ({})", Self::shown(&name, &file), reason);
+            }
+            let nodes = nodes.unwrap_or_else(|| parse(&file, content));
+            let entry = CacheEntry {
+                file: file.clone(),
+                source,
+                interface,
+                dependencies,
+                opt_level: self.opt_level,
+                nodes: self::interface(&nodes),
+                bytecode: Module::default(),
+            };
+            self.compiled.push((Self::file_name(&name), entry));
+            modules.push(SourceModule { name, file, nodes });
+        }
+        modules
+    }
+
+    // the entry file has no module path, so it goes by its file
+    fn shown<'a>(name: &'a str, file: &'a str) -> &'a str {
+        if name.is_empty() { file } else { name }
+    }
+
+    // writes what was compiled this time, taking its code out of the finished `module`
+    pub fn save(self, module: &Module) -> Result<(), String> {
+        for (name, mut entry) in self.compiled {
+            entry.bytecode = module.portable(&entry.file);
+            self.build.write(Artifact::Cache, &name, entry.to_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+struct Encoder {
+    out: Writer,
+    locations: bool,
+}
+
+impl Encoder {
+    fn nodes(&mut self, nodes: &[Box<Node>]) {
+        self.out.u32(nodes.len() as u32);
+        for node in nodes {
+            self.node(node);
+        }
+    }
+
+    fn tag(&mut self, tag: u8) {
+        self.out.bytes.push(tag);
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.out.bytes.push(value as u8);
+    }
+
+    // every location of a module is in its file, so only the position is written
+    fn loc(&mut self, loc: &Location) {
+        if self.locations {
+            for value in [loc.line, loc.column, loc.start, loc.end] {
+                self.out.u32(value as u32);
+            }
+        }
+    }
+
+    fn node(&mut self, node: &Node) {
+        match node {
+            Node::Int { value, lbit, loc } | Node::Float { value, lbit, loc } => {
+                self.tag(if matches!(node, Node::Int { .. }) { 0 } else { 1 });
+                self.out.str(value);
+                self.bool(*lbit);
+                self.loc(loc);
+            }
+            Node::Bool { value, loc } | Node::Null { value, loc } | Node::Str { value, loc } | Node::Var { value, loc } => {
+                self.tag(match node {
+                    Node::Bool { .. } => 2,
+                    Node::Null { .. } => 3,
+                    Node::Str { .. } => 4,
+                    _ => 5,
+                });
+                self.out.str(value);
+                self.loc(loc);
+            }
+            Node::List { element, loc } | Node::Tuple { element, loc } => {
+                self.tag(if matches!(node, Node::List { .. }) { 6 } else { 7 });
+                self.nodes(element);
+                self.loc(loc);
+            }
+            Node::Dict { key_value, loc } => {
+                self.tag(8);
+                self.out.u32(key_value.len() as u32);
+                for pair in key_value {
+                    self.node(&pair.0);
+                    self.node(&pair.1);
+                }
+                self.loc(loc);
+            }
+            Node::GenericType { name, params, loc } => {
+                self.tag(9);
+                self.node(name);
+                self.nodes(params);
+                self.loc(loc);
+            }
+            Node::FuncType { params, rtype, loc } => {
+                self.tag(10);
+                self.nodes(params);
+                self.node(rtype);
+                self.loc(loc);
+            }
+            Node::MemLockup { targ, obj, loc } | Node::CodeLockup { targ, obj, loc } => {
+                self.tag(if matches!(node, Node::MemLockup { .. }) { 11 } else { 12 });
+                self.node(targ);
+                self.node(obj);
+                self.loc(loc);
+            }
+            Node::FuncDef { name, public, generics, args, rtype, body, loc } => {
+                self.tag(13);
+                self.out.str(name);
+                self.bool(*public);
+                self.nodes(generics);
+                self.nodes(args);
+                self.node(rtype);
+                self.nodes(body);
+                self.loc(loc);
+            }
+            Node::Struct { name, public, generics, child, body, loc } => {
+                self.tag(14);
+                self.out.str(name);
+                self.bool(*public);
+                self.nodes(generics);
+                self.nodes(child);
+                self.nodes(body);
+                self.loc(loc);
+            }
+            Node::Attach { name, attach_to, public, generics, args, rtype, body, loc } => {
+                self.tag(15);
+                self.out.str(name);
+                self.node(attach_to);
+                self.bool(*public);
+                self.nodes(generics);
+                self.nodes(args);
+                self.node(rtype);
+                self.nodes(body);
+                self.loc(loc);
+            }
+            Node::Enum { name, public, child, loc } => {
+                self.tag(16);
+                self.out.str(name);
+                self.bool(*public);
+                self.nodes(child);
+                self.loc(loc);
+            }
+            Node::Return { value, loc } => {
+                self.tag(17);
+                self.node(value);
+                self.loc(loc);
+            }
+            Node::LetDef { name, dtype, public, value, loc } => {
+                self.tag(18);
+                self.out.str(name);
+                self.node(dtype);
+                self.bool(*public);
+                self.node(value);
+                self.loc(loc);
+            }
+            Node::AssignDef { targ: lhs, value: rhs, opr, loc } | Node::BinaryOp { lhs, rhs, opr, loc } => {
+                self.tag(if matches!(node, Node::AssignDef { .. }) { 19 } else { 20 });
+                self.node(lhs);
+                self.node(rhs);
+                self.out.str(opr);
+                self.loc(loc);
+            }
+            Node::UnaryOp { opr, value, loc } => {
+                self.tag(21);
+                self.out.str(opr);
+                self.node(value);
+                self.loc(loc);
+            }
+            Node::Conditional { cond, body_if, body_else, loc } => {
+                self.tag(22);
+                self.node(cond);
+                self.nodes(body_if);
+                self.nodes(body_else);
+                self.loc(loc);
+            }
+            Node::ForLoop { initializer, iterator, body, loc } => {
+                self.tag(23);
+                self.node(initializer);
+                self.node(iterator);
+                self.nodes(body);
+                self.loc(loc);
+            }
+            Node::WhileLoop { cond, body, loc } => {
+                self.tag(24);
+                self.node(cond);
+                self.nodes(body);
+                self.loc(loc);
+            }
+            Node::MatchCase { value, child, default, loc } => {
+                self.tag(25);
+                self.node(value);
+                self.out.u32(child.len() as u32);
+                for arm in child {
+                    self.node(&arm.0);
+                    self.nodes(&arm.1);
+                }
+                self.nodes(default);
+                self.loc(loc);
+            }
+            Node::Break { loc } => {
+                self.tag(26);
+                self.loc(loc);
+            }
+            Node::Continue { loc } => {
+                self.tag(27);
+                self.loc(loc);
+            }
+            Node::Range { min, max, loc } => {
+                self.tag(28);
+                self.node(min);
+                self.node(max);
+                self.loc(loc);
+            }
+            Node::Import { package, module, loc } | Node::Use { package, module, loc } => {
+                self.tag(if matches!(node, Node::Import { .. }) { 29 } else { 30 });
+                self.nodes(package);
+                self.nodes(module);
+                self.loc(loc);
+            }
+            Node::Alias { name, real, loc } => {
+                self.tag(31);
+                self.out.str(name);
+                self.node(real);
+                self.loc(loc);
+            }
+            Node::FuncCall { func, args, loc } => {
+                self.tag(32);
+                self.node(func);
+                self.nodes(args);
+                self.loc(loc);
+            }
+            Node::Void => self.tag(33),
+        }
+    }
+}
+
+struct Decoder<'r, 'b> {
+    reader: &'r mut Reader<'b>,
+    file: &'r str,
+}
+
+impl Decoder<'_, '_> {
+    fn nodes(&mut self) -> Result<Vec<Box<Node>>, String> {
+        (0..self.reader.u32()?).map(|_| self.node().map(Box::new)).collect()
+    }
+
+    fn bool(&mut self) -> Result<bool, String> {
+        Ok(self.reader.byte()? != 0)
+    }
+
+    fn str(&mut self) -> Result<String, String> {
+        self.reader.str()
+    }
+
+    fn boxed(&mut self) -> Result<Box<Node>, String> {
+        self.node().map(Box::new)
+    }
+
+    fn loc(&mut self) -> Result<Location, String> {
+        Ok(Location {
+            file: self.file.to_owned(),
+            line: self.reader.u32()? as usize,
+            column: self.reader.u32()? as usize,
+            start: self.reader.u32()? as usize,
+            end: self.reader.u32()? as usize,
+        })
+    }
+
+    fn node(&mut self) -> Result<Node, String> {
+        let node = match self.reader.byte()? {
+            0 => Node::Int { value: self.str()?, lbit: self.bool()?, loc: self.loc()? },
+            1 => Node::Float { value: self.str()?, lbit: self.bool()?, loc: self.loc()? },
+            2 => Node::Bool { value: self.str()?, loc: self.loc()? },
+            3 => Node::Null { value: self.str()?, loc: self.loc()? },
+            4 => Node::Str { value: self.str()?, loc: self.loc()? },
+            5 => Node::Var { value: self.str()?, loc: self.loc()? },
+            6 => Node::List { element: self.nodes()?, loc: self.loc()? },
+            7 => Node::Tuple { element: self.nodes()?, loc: self.loc()? },
+            8 => {
+                let mut key_value = Vec::new();
+                for _ in 0..self.reader.u32()? {
+                    key_value.push(Box::new((self.node()?, self.node()?)));
+                }
+                Node::Dict { key_value, loc: self.loc()? }
+            }
+            9 => Node::GenericType { name: self.boxed()?, params: self.nodes()?, loc: self.loc()? },
+            10 => Node::FuncType { params: self.nodes()?, rtype: self.boxed()?, loc: self.loc()? },
+            11 => Node::MemLockup { targ: self.boxed()?, obj: self.boxed()?, loc: self.loc()? },
+            12 => Node::CodeLockup { targ: self.boxed()?, obj: self.boxed()?, loc: self.loc()? },
+            13 => Node::FuncDef {
+                name: self.str()?,
+                public: self.bool()?,
+                generics: self.nodes()?,
+                args: self.nodes()?,
+                rtype: self.boxed()?,
+                body: self.nodes()?,
+                loc: self.loc()?,
+            },
+            14 => Node::Struct {
+                name: self.str()?,
+                public: self.bool()?,
+                generics: self.nodes()?,
+                child: self.nodes()?,
+                body: self.nodes()?,
+                loc: self.loc()?,
+            },
+            15 => Node::Attach {
+                name: self.str()?,
+                attach_to: self.boxed()?,
+                public: self.bool()?,
+                generics: self.nodes()?,
+                args: self.nodes()?,
+                rtype: self.boxed()?,
+                body: self.nodes()?,
+                loc: self.loc()?,
+            },
+            16 => Node::Enum { name: self.str()?, public: self.bool()?, child: self.nodes()?, loc: self.loc()? },
+            17 => Node::Return { value: self.boxed()?, loc: self.loc()? },
+            18 => Node::LetDef {
+                name: self.str()?,
+                dtype: self.boxed()?,
+                public: self.bool()?,
+                value: self.boxed()?,
+                loc: self.loc()?,
+            },
+            19 => Node::AssignDef { targ: self.boxed()?, value: self.boxed()?, opr: self.str()?, loc: self.loc()? },
+            20 => Node::BinaryOp { lhs: self.boxed()?, rhs: self.boxed()?, opr: self.str()?, loc: self.loc()? },
+            21 => Node::UnaryOp { opr: self.str()?, value: self.boxed()?, loc: self.loc()? },
+            22 => Node::Conditional { cond: self.boxed()?, body_if: self.nodes()?, body_else: self.nodes()?, loc: self.loc()? },
+            23 => Node::ForLoop { initializer: self.boxed()?, iterator: self.boxed()?, body: self.nodes()?, loc: self.loc()? },
+            24 => Node::WhileLoop { cond: self.boxed()?, body: self.nodes()?, loc: self.loc()? },
+            25 => {
+                let value = self.boxed()?;
+                let mut child = Vec::new();
+                for _ in 0..self.reader.u32()? {
+                    child.push(Box::new((self.node()?, self.nodes()?)));
+                }
+                Node::MatchCase { value, child, default: self.nodes()?, loc: self.loc()? }
+            }
+            26 => Node::Break { loc: self.loc()? },
+            27 => Node::Continue { loc: self.loc()? },
+            28 => Node::Range { min: self.boxed()?, max: self.boxed()?, loc: self.loc()? },
+            29 => Node::Import { package: self.nodes()?, module: self.nodes()?, loc: self.loc()? },
+            30 => Node::Use { package: self.nodes()?, module: self.nodes()?, loc: self.loc()? },
+            31 => Node::Alias { name: self.str()?, real: self.boxed()?, loc: self.loc()? },
+            32 => Node::FuncCall { func: self.boxed()?, args: self.nodes()?, loc: self.loc()? },
+            33 => Node::Void,
+            other => return Err(format!("unknown node tag {}", other)),
+        };
+        Ok(node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::lexer::Lexer;
+    use crate::compiler::scanner::Scanner;
+
+    use std::path::{Path, PathBuf};
+
+    const MAIN: &str = "import shapes;\n\nfunc main() -> int {\n    return shapes::area(2);\n}\n";
+    const SHAPES: &str = "public func area(side: int) -> int {\n    return side * side;\n}\n";
+
+    fn project(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mix-cache-test-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // selects and saves the entry `main` and the module `shapes` as `mix build` would, and
+    // returns the files taken from the cache
+    fn build(project: &Path, opt_level: u32, main: &str, shapes: &str) -> Vec<String> {
+        let dir = BuildDir::new(project, "debug", "bytecode");
+        let mut cache = BuildCache::new(&dir, opt_level, false);
+        let inputs = vec![
+            (String::new(), "main.mx".to_owned(), main.to_owned()),
+            ("shapes".to_owned(), "shapes.mx".to_owned(), shapes.to_owned()),
+        ];
+        cache.select(inputs, |file, content| Scanner::new(Lexer::new(content, file.to_owned()), false).scan().0);
+
+        let mut reused: Vec<String> = cache.reused.keys().cloned().collect();
+        reused.sort();
+        cache.save(&Module::default()).unwrap();
+        reused
+    }
+
+    #[test]
+    fn reuses_an_unchanged_build() {
+        let dir = project("unchanged");
+        let first = build(&dir, 0, MAIN, SHAPES);
+        let second = build(&dir, 0, MAIN, SHAPES);
+        fs::remove_dir_all(&dir).ok();
+        assert!(first.is_empty());
+        assert_eq!(second, ["main.mx", "shapes.mx"]);
+    }
+
+    #[test]
+    fn recompiles_an_edited_module() {
+        let dir = project("edited");
+        build(&dir, 0, MAIN, SHAPES);
+        let reused = build(&dir, 0, &MAIN.replace("area(2)", "area(3)"), SHAPES);
+        fs::remove_dir_all(&dir).ok();
+        assert_eq!(reused, ["shapes.mx"]);
+    }
+
+    #[test]
+    fn recompiles_the_importers_of_a_changed_interface() {
+        let dir = project("interface");
+        build(&dir, 0, MAIN, SHAPES);
+        // a new body keeps the interface `main` was checked against, a new signature does not
+        let body = build(&dir, 0, MAIN, &SHAPES.replace("side * side", "side * side * 1"));
+        let signature = build(&dir, 0, MAIN, &SHAPES.replace("side: int", "side: int, unused: int"));
+        fs::remove_dir_all(&dir).ok();
+        assert_eq!(body, ["main.mx"]);
+        assert!(signature.is_empty());
+    }
+
+    #[test]
+    fn recompiles_at_another_optimization_level() {
+        let dir = project("level");
+        build(&dir, 0, MAIN, SHAPES);
+        let reused = build(&dir, 1, MAIN, SHAPES);
+        fs::remove_dir_all(&dir).ok();
+        assert!(reused.is_empty());
+    }
+}
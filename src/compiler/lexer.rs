@@ -1,5 +1,6 @@
 use super::token::{Token, TokenType};
-use colored::*;
+use super::node::Location;
+use super::diagnostic::{Diagnostic, Diagnostics};
 
 pub struct Lexer {
     source: String,
@@ -7,56 +8,87 @@ pub struct Lexer {
     index: usize,
     line: usize,
     column: usize,
-    err: bool,
+    diagnostics: Diagnostics,
+    tokens: Vec<Token>,
+    cursor: usize,
 }
 
 impl Lexer {
     pub fn new(source: String, file: String) -> Self {
-        Self {
+        let mut lexer = Self {
             source,
             file,
             index: 0,
             line: 1,
             column: 1,
-            err: true,
-        }
+            diagnostics: Diagnostics::new(),
+            tokens: Vec::new(),
+            cursor: 0,
+        };
+
+        lexer.tokenize();
+        lexer
     }
 
-    pub fn peek(&mut self) -> Token {
+    pub fn peek(&self) -> Token {
+        self.peek_nth(0).clone()
+    }
 
-        // temporarily save the current position value
-        let index = self.index;
-        let line = self.line;
-        let column = self.column;
+    pub fn peek_nth(&self, k: usize) -> &Token {
+        // the stream always ends with `Eof`, so looking past it keeps yielding `Eof`
+        let last = self.tokens.len() - 1;
+        &self.tokens[(self.cursor + k).min(last)]
+    }
 
-        // get current token
-        let token = self.peek_next();
+    pub fn previous(&self) -> Option<&Token> {
+        self.cursor.checked_sub(1).map(|i| &self.tokens[i])
+    }
 
-        // return the previous position value
-        self.index = index;
-        self.line = line;
-        self.column = column;
+    pub fn peek_next(&mut self) -> Token {
+        let token = self.peek();
+
+        if self.cursor < self.tokens.len() - 1 {
+            self.cursor += 1;
+        }
 
         token
     }
 
-    pub fn peek_next(&mut self) -> Token {
-        self.skip_whitespace();
-       
-        if self.peek_char() == '/' {
-            self.advance();
-            if self.peek_char() == '/' {
-                self.skip_inline_comment();
-            } else if self.peek_char() == '*' {
-                self.skip_multiline_comment();
-            } else {
-                return Token::new(TokenType::Slash, "/".to_owned(), self.file.clone(), self.line, self.column, self.index - 1, self.index);
+    // split a `>>` token in two, so it can close two nested type parameter lists
+    pub fn split_token(&mut self) {
+        let token = &self.tokens[self.cursor];
+        if token.kind != TokenType::ShiftRight {
+            return;
+        }
+
+        let split = token.start + 1;
+        let second = Token::new(TokenType::Greater, ">".to_owned(), token.file.clone(), token.line, token.column + 1, split, token.end);
+
+        let token = &mut self.tokens[self.cursor];
+        token.kind = TokenType::Greater;
+        token.value = ">".to_owned();
+        token.end = split;
+
+        self.tokens.insert(self.cursor + 1, second);
+    }
+
+    fn tokenize(&mut self) {
+        loop {
+            let token = self.next_token();
+            let done = token.kind == TokenType::Eof;
+
+            self.tokens.push(token);
+
+            if done {
+                break;
             }
         }
+    }
 
-        self.skip_whitespace();
+    fn next_token(&mut self) -> Token {
+        self.skip_trivia();
 
-        let mut token = Token::new(TokenType::Eof, "\0".to_owned(), self.file.clone(), self.line, self.column, self.index, self.index + 1);
+        let mut token = Token::new(TokenType::Eof, "end of file".to_owned(), self.file.clone(), self.line, self.column, self.index, self.index + 1);
 
         if !self.is_at_end() {
             if self.peek_char().is_ascii_digit() {
@@ -67,19 +99,31 @@ impl Lexer {
                 token = self.collect_identifier();
             } else if self.peek_char().is_ascii_punctuation() {
                 token = self.collect_punctuation();
-            } else { 
-                println!("{} {}:{}", "error:".red(), self.file, self.line);
-                println!("└───unknown token.");
-                self.err = true;
-                std::process::exit(1);
+            } else {
+                token = self.collect_unknown();
             }
         }
 
         token
     }
 
+    fn collect_unknown(&mut self) -> Token {
+        let start = self.index;
+        let line = self.line;
+        let column = self.column;
+
+        let value = self.peek_char().to_string();
+        self.advance();
+
+        self.error("E0101", format!("unknown character `{}`", value.escape_debug()), start);
+
+        Token::new(TokenType::Error, value, self.file.clone(), line, column, start, self.index)
+    }
+
     fn collect_number(&mut self) -> Token {
         let start = self.index;
+        let line = self.line;
+        let column = self.column;
         let mut value = String::new();
 
         while self.peek_char().is_ascii_digit() {
@@ -92,9 +136,7 @@ impl Lexer {
             self.advance();
 
             if !self.peek_char().is_ascii_digit() {
-                println!("{} {}:{}", "error:".red(), self.file, self.line);
-                println!("└───expected ascii digit.");
-                self.err = true;
+                self.error("E0102", "expected digit in number literal", start);
             }
 
             while self.peek_char().is_ascii_digit() {
@@ -113,9 +155,7 @@ impl Lexer {
             }
 
             if !self.peek_char().is_ascii_digit() {
-                println!("{} {}:{}", "error:".red(), self.file, self.line);
-                println!("└───expected ascii digit.");
-                self.err = true;
+                self.error("E0102", "expected digit in number literal", start);
             }
 
             while self.peek_char().is_ascii_digit() {
@@ -124,7 +164,7 @@ impl Lexer {
             }
         }
 
-        Token::new(TokenType::Number, value, self.file.clone(), self.line, self.column, start, self.index)
+        Token::new(TokenType::Number, value, self.file.clone(), line, column, start, self.index)
     }
 
     fn collect_string(&mut self) -> Token {
@@ -159,16 +199,12 @@ impl Lexer {
                     value.push(self.peek_char());
                     self.advance();
                 } else {
-                    println!("{} {}:{}", "error:".red(), self.file, self.line);
-                    println!("└───unknown escape detection.");
-                    self.err = true;
+                    self.error("E0103", "unknown escape sequence", self.index - 1);
                 }
 
                 continue;
             } else if self.peek_char() == '\n' || self.peek_char() == '\r' || self.peek_char() == '\t' || self.peek_char() == '\0' {
-                println!("{} {}:{}", "error:".red(), self.file, self.line);
-                println!("└───unknown escape detection.");
-                self.err = true;
+                self.error("E0103", "unescaped control character in string literal", self.index);
             }
 
             value.push(self.peek_char());
@@ -178,9 +214,7 @@ impl Lexer {
         if self.peek_char() == markup {
             self.advance();
         } else {
-            println!("{} {}:{}", "error:".red(), self.file, self.line);
-            println!("└───expected close quotes.");
-            self.err = true;
+            self.error("E0104", "unterminated string literal, expected close quote", start);
         }
 
         Token::new(TokenType::StringLiteral, value, self.file.clone(), line, column, start, self.index)
@@ -253,6 +287,10 @@ impl Lexer {
             "<=" => (TokenType::LessEqual, two_chars, true),
             ">=" => (TokenType::GreaterEqual, two_chars, true),
             "->" => (TokenType::Arrow, two_chars, true),
+            "&&" => (TokenType::And, two_chars, true),
+            "||" => (TokenType::Or, two_chars, true),
+            "<<" => (TokenType::ShiftLeft, two_chars, true),
+            ">>" => (TokenType::ShiftRight, two_chars, true),
             _ => {
                 let kind = match ch1 {
                     '+' => TokenType::Plus,
@@ -276,6 +314,8 @@ impl Lexer {
                     ',' => TokenType::Comma,
                     '.' => TokenType::Dot,
                     '&' => TokenType::Ampersand,
+                    '|' => TokenType::Pipe,
+                    '~' => TokenType::Tilde,
                     '$' => TokenType::Dollar,
                     '@' => TokenType::At,
                     '#' => TokenType::Hash,
@@ -287,9 +327,7 @@ impl Lexer {
         };
 
         if kind == TokenType::Error {
-            println!("{} {}:{}", "error:".red(), self.file, self.line);
-            println!("└───unknown token.");
-            self.err = true;
+            self.error("E0101", format!("unknown character `{}`", value), start);
         }
 
         if advance_extra {
@@ -299,10 +337,52 @@ impl Lexer {
         Token::new(kind, value, self.file.clone(), self.line, column, start, self.index)
     }
 
+    pub fn take_diagnostics(&mut self) -> Diagnostics {
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    fn error(&mut self, code: &'static str, message: impl Into<String>, start: usize) {
+        let end = self.index.max(start + 1);
+        let loc = self.location_at(start, end);
+        self.diagnostics.push(Diagnostic::error(code, message, loc));
+    }
+
+    fn location_at(&self, start: usize, end: usize) -> Location {
+        let before = &self.source[..start.min(self.source.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+
+        Location {
+            file: self.file.clone(),
+            line,
+            column,
+            start,
+            end,
+        }
+    }
+
     fn peek_char(&self) -> char {
         self.source[self.index..].chars().next().unwrap_or('\0')
     }
 
+    fn peek_char_nth(&self, k: usize) -> char {
+        self.source[self.index..].chars().nth(k).unwrap_or('\0')
+    }
+
+    fn skip_trivia(&mut self) {
+        loop {
+            self.skip_whitespace();
+
+            if self.peek_char() == '/' && self.peek_char_nth(1) == '/' {
+                self.skip_inline_comment();
+            } else if self.peek_char() == '/' && self.peek_char_nth(1) == '*' {
+                self.skip_multiline_comment();
+            } else {
+                break;
+            }
+        }
+    }
+
     fn skip_whitespace(&mut self) {
         while self.peek_char().is_ascii_whitespace() {
             self.advance();
@@ -310,37 +390,113 @@ impl Lexer {
     }
 
     fn skip_inline_comment(&mut self) {
-        while self.peek_char() != '\n' {
+        while !self.is_at_end() && self.peek_char() != '\n' {
             self.advance();
         }
     }
 
     fn skip_multiline_comment(&mut self) {
-        loop {
-            if self.peek_char() == '/' {
+        // skip the opening `/*`
+        self.advance();
+        self.advance();
+
+        while !self.is_at_end() {
+            if self.peek_char() == '*' && self.peek_char_nth(1) == '/' {
                 self.advance();
-                if self.peek_char() == '*' {
-                    self.advance();
-                    break;
-                }
+                self.advance();
+                break;
             }
             self.advance();
         }
     }
 
     fn is_at_end(&self) -> bool {
-        if self.peek_char() == '\0' { true }
-        else { false }
+        self.peek_char() == '\0'
     }
 
     fn advance(&mut self) {
+        if self.index >= self.source.len() {
+            return;
+        }
+
+        let ch = self.peek_char();
 
-        if self.peek_char() == '\n' {
+        if ch == '\n' {
             self.line += 1;
             self.column = 0;
         }
 
         self.column += 1;
-        self.index += 1;
+        self.index += ch.len_utf8();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lexer(source: &str) -> Lexer {
+        Lexer::new(source.to_owned(), "main.mx".to_owned())
+    }
+
+    // the kinds of the tokens left in `lexer`, up to the end of file
+    fn kinds(lexer: &mut Lexer) -> Vec<TokenType> {
+        let mut kinds = Vec::new();
+        while lexer.peek().kind != TokenType::Eof {
+            kinds.push(lexer.peek_next().kind);
+        }
+        kinds
+    }
+
+    #[test]
+    fn lexes_logical_and_bitwise_operators() {
+        use TokenType::*;
+        assert_eq!(kinds(&mut lexer("a && b || c")), [Identifier, And, Identifier, Or, Identifier]);
+        assert_eq!(kinds(&mut lexer("a & b | c ^ ~d")), [Identifier, Ampersand, Identifier, Pipe, Identifier, Power, Tilde, Identifier]);
+        assert_eq!(kinds(&mut lexer("a << 1 >> 2")), [Identifier, ShiftLeft, Number, ShiftRight, Number]);
+        assert_eq!(kinds(&mut lexer("&&&|||")), [And, Ampersand, Or, Pipe]);
+    }
+
+    #[test]
+    fn looks_ahead_without_consuming() {
+        let mut lexer = lexer("let x = 1;");
+        assert_eq!(lexer.peek_nth(2).kind, TokenType::Equal);
+        assert_eq!(lexer.peek_nth(4).kind, TokenType::SemiColon);
+        assert_eq!(lexer.peek().kind, TokenType::Let);
+        assert!(lexer.previous().is_none());
+
+        assert_eq!(lexer.peek_next().kind, TokenType::Let);
+        assert_eq!(lexer.peek_nth(0).value, "x");
+        assert_eq!(lexer.previous().map(|token| token.kind.clone()), Some(TokenType::Let));
+    }
+
+    #[test]
+    fn keeps_yielding_the_end_of_file() {
+        let mut lexer = lexer("x");
+        assert_eq!(lexer.peek_nth(1).kind, TokenType::Eof);
+        assert_eq!(lexer.peek_nth(100).kind, TokenType::Eof);
+        for _ in 0..3 {
+            lexer.peek_next();
+        }
+        assert_eq!(lexer.peek().kind, TokenType::Eof);
+        assert_eq!(lexer.previous().map(|token| token.value.as_str()), Some("x"));
+    }
+
+    #[test]
+    fn splits_a_shift_closing_two_type_lists() {
+        let mut lexer = lexer(">> x");
+        lexer.split_token();
+        assert_eq!(kinds(&mut lexer), [TokenType::Greater, TokenType::Greater, TokenType::Identifier]);
+    }
+
+    #[test]
+    fn continues_past_an_unknown_character() {
+        let mut lexer = lexer("a \u{20ac} b ` c");
+        let found: Vec<(usize, &str)> = lexer.take_diagnostics().iter().map(|d| (d.primary.column, d.code)).collect();
+        assert_eq!(found, [(3, "E0101"), (7, "E0101")]);
+        assert_eq!(
+            kinds(&mut lexer),
+            [TokenType::Identifier, TokenType::Error, TokenType::Identifier, TokenType::Error, TokenType::Identifier],
+        );
     }
 }
@@ -1,4 +1,40 @@
 pub mod lexer;
 pub mod token;
 pub mod node;
+pub mod module;
 pub mod scanner;
+pub mod diagnostic;
+pub mod resolver;
+pub mod types;
+pub mod checker;
+pub mod optimizer;
+pub mod incremental;
+
+// lexes, parses, resolves and checks `source` as the entry module of a program, for tests of
+// the stages after the checker; any diagnostic fails the test
+#[cfg(test)]
+pub fn analyze(source: &str) -> (Vec<Box<node::Node>>, types::TypeTable) {
+    let (nodes, types, diagnostics) = diagnose(source);
+    assert!(!diagnostics.has_errors(), "{:?}", diagnostics);
+    (nodes, types)
+}
+
+// `analyze` that hands back the diagnostics instead, for tests of what is rejected; the checker
+// only runs when the earlier stages found nothing
+#[cfg(test)]
+pub fn diagnose(source: &str) -> (Vec<Box<node::Node>>, types::TypeTable, diagnostic::Diagnostics) {
+    let file = "main.mx".to_owned();
+    let (nodes, mut diagnostics) = scanner::Scanner::new(lexer::Lexer::new(source.to_owned(), file.clone()), false).scan();
+    let modules = vec![module::SourceModule { name: String::new(), file, nodes }];
+
+    let (symbols, resolved) = resolver::Resolver::new(false).resolve(&modules);
+    diagnostics.extend(resolved);
+    let nodes = module::link(modules, &symbols);
+    if diagnostics.has_errors() {
+        return (nodes, types::TypeTable::default(), diagnostics);
+    }
+
+    let (types, checked) = checker::Checker::new(&symbols, false).check(&nodes);
+    diagnostics.extend(checked);
+    (nodes, types, diagnostics)
+}
@@ -0,0 +1,78 @@
+use super::node::Node;
+use super::resolver::{SymbolKind, SymbolTable};
+
+// one source file of a project, `name` is the path other modules import it by, like
+// `net::http` for `src/net/http.mx`, and empty for the entry file
+#[derive(Debug, Clone)]
+pub struct SourceModule {
+    pub name: String,
+    pub file: String,
+    pub nodes: Vec<Box<Node>>,
+}
+
+// joins resolved modules into the one tree later stages work on: items of other modules are
+// renamed to their qualified name, like `net::http::get`, paths through an imported module
+// become plain names, and `import` and `use` are dropped
+pub fn link(modules: Vec<SourceModule>, table: &SymbolTable) -> Vec<Box<Node>> {
+    let mut nodes = Vec::new();
+
+    for module in modules {
+        for mut node in module.nodes {
+            if matches!(node.as_ref(), Node::Import { .. } | Node::Use { .. }) {
+                continue;
+            }
+            rename(&mut node, table);
+            nodes.push(node);
+        }
+    }
+
+    nodes
+}
+
+// the qualified name of the top-level item `id`, the only symbols that can clash across modules
+fn item_name(table: &SymbolTable, id: usize) -> Option<&str> {
+    let symbol = table.symbol(id);
+    match symbol.kind {
+        SymbolKind::Function | SymbolKind::Struct | SymbolKind::Enum | SymbolKind::Alias => Some(&symbol.name),
+        _ => None,
+    }
+}
+
+// whether `node` names a module of the project, as opposed to `std`
+fn is_module(node: &Node, table: &SymbolTable) -> bool {
+    match node {
+        Node::Var { loc, .. } => table.reference(loc).is_some_and(|id| {
+            let symbol = table.symbol(id);
+            symbol.kind == SymbolKind::Module && symbol.loc.is_some()
+        }),
+        _ => false,
+    }
+}
+
+fn rename(node: &mut Node, table: &SymbolTable) {
+    match node {
+        Node::Var { value, loc } => {
+            if let Some(name) = table.reference(loc).and_then(|id| item_name(table, id)) {
+                *value = name.to_owned();
+            }
+            return;
+        }
+        // `http::get` in an expression and `geo::Point` in a type
+        Node::CodeLockup { targ, obj, .. } | Node::MemLockup { targ, obj, .. } if is_module(obj, table) => {
+            *node = std::mem::replace(targ.as_mut(), Node::Void);
+            rename(node, table);
+            return;
+        }
+        Node::FuncDef { name, loc, .. } | Node::Struct { name, loc, .. } | Node::Enum { name, loc, .. } |
+        Node::Alias { name, loc, .. } => {
+            if let Some(qualified) = table.declaration(loc).and_then(|id| item_name(table, id)) {
+                *name = qualified.to_owned();
+            }
+        }
+        _ => {}
+    }
+
+    for child in node.children_mut() {
+        rename(child, table);
+    }
+}
@@ -45,14 +45,16 @@ pub enum Node {
         key_value: Vec<Box<(Node, Node)>>,
         loc: Location,
     },
-    DictType {
-        dictname: Box<Node>,
-        dictype: Box<(Node, Node)>,
+    // `Name<A, B>` in a type position, e.g. `List<int>` or `Pair<str, List<int>>`
+    GenericType {
+        name: Box<Node>,
+        params: Vec<Box<Node>>,
         loc: Location,
     },
-    ListType {
-        listname: Box<Node>,
-        listtype: Box<Node>,
+    // `func(A, B) -> R` in a type position
+    FuncType {
+        params: Vec<Box<Node>>,
+        rtype: Box<Node>,
         loc: Location,
     },
     MemLockup {
@@ -68,6 +70,7 @@ pub enum Node {
     FuncDef {
         name: String,
         public: bool,
+        generics: Vec<Box<Node>>,
         args: Vec<Box<Node>>,
         rtype: Box<Node>,
         body: Vec<Box<Node>>,
@@ -76,6 +79,7 @@ pub enum Node {
     Struct {
         name: String,
         public: bool,
+        generics: Vec<Box<Node>>,
         child: Vec<Box<Node>>,
         body: Vec<Box<Node>>,
         loc: Location,
@@ -84,6 +88,7 @@ pub enum Node {
         name: String,
         attach_to: Box<Node>,
         public: bool,
+        generics: Vec<Box<Node>>,
         args: Vec<Box<Node>>,
         rtype: Box<Node>,
         body: Vec<Box<Node>>,
@@ -150,9 +155,13 @@ pub enum Node {
         default: Vec<Box<Node>>,
         loc: Location,
     },
-    Break,
+    Break {
+        loc: Location,
+    },
     // Default,
-    Continue,
+    Continue {
+        loc: Location,
+    },
     Range {
         min: Box<Node>,
         max: Box<Node>,
@@ -163,6 +172,11 @@ pub enum Node {
         module: Vec<Box<Node>>,
         loc: Location,
     },
+    Use {
+        package: Vec<Box<Node>>,
+        module: Vec<Box<Node>>,
+        loc: Location,
+    },
     Alias {
         name: String,
         real: Box<Node>,
@@ -175,3 +189,112 @@ pub enum Node {
     },
     Void,
 }
+
+impl Node {
+    pub fn loc(&self) -> Option<&Location> {
+        match self {
+            Node::Int { loc, .. } | Node::Float { loc, .. } | Node::Bool { loc, .. } |
+            Node::Null { loc, .. } | Node::Str { loc, .. } | Node::Var { loc, .. } |
+            Node::List { loc, .. } | Node::Dict { loc, .. } | Node::GenericType { loc, .. } |
+            Node::FuncType { loc, .. } | Node::MemLockup { loc, .. } | Node::CodeLockup { loc, .. } |
+            Node::FuncDef { loc, .. } | Node::Struct { loc, .. } | Node::Attach { loc, .. } |
+            Node::Enum { loc, .. } | Node::Return { loc, .. } | Node::Tuple { loc, .. } |
+            Node::LetDef { loc, .. } | Node::AssignDef { loc, .. } | Node::BinaryOp { loc, .. } |
+            Node::UnaryOp { loc, .. } | Node::Conditional { loc, .. } | Node::ForLoop { loc, .. } |
+            Node::WhileLoop { loc, .. } | Node::MatchCase { loc, .. } | Node::Range { loc, .. } |
+            Node::Import { loc, .. } | Node::Use { loc, .. } | Node::Alias { loc, .. } |
+            Node::FuncCall { loc, .. } | Node::Break { loc } | Node::Continue { loc } => Some(loc),
+            Node::Void => None,
+        }
+    }
+
+    // every node directly inside this one, type annotations included, for passes that rewrite
+    // the tree; walking it through here means a new node kind cannot be missed by one of them
+    pub fn children_mut(&mut self) -> Vec<&mut Node> {
+        let mut out: Vec<&mut Node> = Vec::new();
+        match self {
+            Node::FuncDef { generics, args, rtype, body, .. } => {
+                out.extend(generics.iter_mut().chain(args.iter_mut()).map(|node| node.as_mut()));
+                out.push(rtype.as_mut());
+                out.extend(body.iter_mut().map(|node| node.as_mut()));
+            }
+            Node::Attach { attach_to, generics, args, rtype, body, .. } => {
+                out.push(attach_to.as_mut());
+                out.extend(generics.iter_mut().chain(args.iter_mut()).map(|node| node.as_mut()));
+                out.push(rtype.as_mut());
+                out.extend(body.iter_mut().map(|node| node.as_mut()));
+            }
+            Node::Struct { generics, child, body, .. } => {
+                out.extend(generics.iter_mut().chain(child.iter_mut()).chain(body.iter_mut()).map(|node| node.as_mut()));
+            }
+            Node::Enum { child: items, .. } | Node::List { element: items, .. } | Node::Tuple { element: items, .. } => {
+                out.extend(items.iter_mut().map(|node| node.as_mut()));
+            }
+            Node::Dict { key_value, .. } => {
+                for pair in key_value.iter_mut() {
+                    let (key, value) = pair.as_mut();
+                    out.push(key);
+                    out.push(value);
+                }
+            }
+            Node::GenericType { name, params, .. } => {
+                out.push(name.as_mut());
+                out.extend(params.iter_mut().map(|node| node.as_mut()));
+            }
+            Node::FuncType { params, rtype, .. } => {
+                out.extend(params.iter_mut().map(|node| node.as_mut()));
+                out.push(rtype.as_mut());
+            }
+            Node::MemLockup { targ, obj, .. } | Node::CodeLockup { targ, obj, .. } => {
+                out.push(obj.as_mut());
+                out.push(targ.as_mut());
+            }
+            Node::LetDef { dtype, value, .. } => {
+                out.push(dtype.as_mut());
+                out.push(value.as_mut());
+            }
+            Node::Return { value, .. } | Node::UnaryOp { value, .. } => out.push(value.as_mut()),
+            Node::Alias { real, .. } => out.push(real.as_mut()),
+            Node::AssignDef { targ, value, .. } => {
+                out.push(targ.as_mut());
+                out.push(value.as_mut());
+            }
+            Node::BinaryOp { lhs, rhs, .. } => {
+                out.push(lhs.as_mut());
+                out.push(rhs.as_mut());
+            }
+            Node::Range { min, max, .. } => {
+                out.push(min.as_mut());
+                out.push(max.as_mut());
+            }
+            Node::Conditional { cond, body_if, body_else, .. } => {
+                out.push(cond.as_mut());
+                out.extend(body_if.iter_mut().chain(body_else.iter_mut()).map(|node| node.as_mut()));
+            }
+            Node::WhileLoop { cond, body, .. } => {
+                out.push(cond.as_mut());
+                out.extend(body.iter_mut().map(|node| node.as_mut()));
+            }
+            Node::ForLoop { initializer, iterator, body, .. } => {
+                out.push(initializer.as_mut());
+                out.push(iterator.as_mut());
+                out.extend(body.iter_mut().map(|node| node.as_mut()));
+            }
+            Node::MatchCase { value, child, default, .. } => {
+                out.push(value.as_mut());
+                for arm in child.iter_mut() {
+                    let (pattern, body) = arm.as_mut();
+                    out.push(pattern);
+                    out.extend(body.iter_mut().map(|node| node.as_mut()));
+                }
+                out.extend(default.iter_mut().map(|node| node.as_mut()));
+            }
+            Node::FuncCall { func, args, .. } => {
+                out.push(func.as_mut());
+                out.extend(args.iter_mut().map(|node| node.as_mut()));
+            }
+            _ => {}
+        }
+        out
+    }
+}
@@ -0,0 +1,360 @@
+use super::node::{Location, Node};
+use crate::runtime::builtins;
+use crate::runtime::value::Value;
+
+use std::collections::{HashMap, HashSet};
+
+// something a pass rewrote, for `mix build --verbose`
+#[derive(Debug, Clone)]
+pub struct Change {
+    pub loc: Location,
+    pub message: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub pass: &'static str,
+    pub changes: Vec<Change>,
+}
+
+// runs the passes of optimization `level` over the checked tree, in place; every backend sees
+// the result. 1 folds constants and removes dead code, 2 also drops unused private functions
+pub fn optimize(nodes: &mut Vec<Box<Node>>, level: u32) -> Vec<Report> {
+    let mut reports = Vec::new();
+
+    if level >= 1 {
+        let mut changes = Vec::new();
+        for node in nodes.iter_mut() {
+            fold(node, &mut changes);
+        }
+        reports.push(Report { pass: "fold", changes });
+
+        let mut changes = Vec::new();
+        each_body(nodes, &mut |body| branches(body, &mut changes));
+        reports.push(Report { pass: "branches", changes });
+
+        let mut changes = Vec::new();
+        each_body(nodes, &mut |body| unreachable(body, &mut changes));
+        reports.push(Report { pass: "unreachable", changes });
+    }
+
+    if level >= 2 {
+        let mut changes = Vec::new();
+        unused_functions(nodes, &mut changes);
+        reports.push(Report { pass: "functions", changes });
+    }
+
+    reports
+}
+
+// the statement lists directly inside `node`
+fn bodies_mut(node: &mut Node) -> Vec<&mut Vec<Box<Node>>> {
+    match node {
+        Node::FuncDef { body, .. } | Node::Attach { body, .. } | Node::Struct { body, .. } |
+        Node::WhileLoop { body, .. } | Node::ForLoop { body, .. } => vec![body],
+        Node::Conditional { body_if, body_else, .. } => vec![body_if, body_else],
+        Node::MatchCase { child, default, .. } => {
+            let mut bodies: Vec<&mut Vec<Box<Node>>> = child.iter_mut().map(|arm| &mut arm.1).collect();
+            bodies.push(default);
+            bodies
+        }
+        _ => Vec::new(),
+    }
+}
+
+// calls `f` on every statement list, innermost first
+fn each_body(body: &mut Vec<Box<Node>>, f: &mut impl FnMut(&mut Vec<Box<Node>>)) {
+    for node in body.iter_mut() {
+        for inner in bodies_mut(node) {
+            each_body(inner, f);
+        }
+    }
+    f(body);
+}
+
+fn literal(node: &Node) -> Option<Value> {
+    match node {
+        Node::Int { value, .. } => value.parse().ok().map(Value::Int),
+        Node::Float { value, .. } => value.parse().ok().map(Value::Float),
+        Node::Bool { value, .. } => Some(Value::Bool(value == "true")),
+        Node::Str { value, .. } => Some(Value::Str(value.clone())),
+        _ => None,
+    }
+}
+
+fn long(node: &Node) -> bool {
+    matches!(node, Node::Int { lbit: true, .. } | Node::Float { lbit: true, .. })
+}
+
+// the literal for a folded value, unless a backend could not write it back out
+fn to_node(value: Value, lbit: bool, loc: &Location) -> Option<Node> {
+    let loc = loc.clone();
+    match value {
+        // `-9223372036854775808` is not a valid C literal
+        Value::Int(value) if value != i64::MIN => Some(Node::Int { value: value.to_string(), lbit, loc }),
+        Value::Float(value) if value.is_finite() => Some(Node::Float { value: format!("{:?}", value), lbit, loc }),
+        Value::Bool(value) => Some(Node::Bool { value: value.to_string(), loc }),
+        Value::Str(value) => Some(Node::Str { value, loc }),
+        _ => None,
+    }
+}
+
+fn show(value: &Value) -> String {
+    match value {
+        Value::Str(value) => format!("{:?}", value),
+        Value::Float(value) => format!("{:?}", value),
+        value => value.to_string(),
+    }
+}
+
+// folds operators on literals bottom up, leaving anything that fails at runtime to fail there
+fn fold(node: &mut Node, changes: &mut Vec<Change>) {
+    for child in node.children_mut() {
+        fold(child, changes);
+    }
+
+    let folded = match node {
+        Node::UnaryOp { opr, value, loc } => literal(value).and_then(|operand| {
+            let result = builtins::unary(opr, operand.clone()).ok()?;
+            let (before, after) = (format!("{}{}", opr, show(&operand)), show(&result));
+            // a negative literal is written as a negation
+            let message = if before == after {
+                format!("folded `{}` into a literal", before)
+            } else {
+                format!("folded `{}` to `{}`", before, after)
+            };
+            Some((to_node(result, long(value), loc)?, message))
+        }),
+        // only the left side decides whether the right one runs at all
+        Node::BinaryOp { lhs, rhs, opr, loc } if opr == "&&" || opr == "||" => match literal(lhs) {
+            Some(Value::Bool(left)) => {
+                let message = format!("folded `{} {} ...`", left, opr);
+                if left == (opr == "&&") {
+                    Some((std::mem::replace(rhs.as_mut(), Node::Void), format!("{} to its right side", message)))
+                } else {
+                    Some((Node::Bool { value: left.to_string(), loc: loc.clone() }, format!("{} to `{}`", message, left)))
+                }
+            }
+            _ => None,
+        },
+        Node::BinaryOp { lhs, rhs, opr, loc } => literal(lhs).zip(literal(rhs)).and_then(|(left, right)| {
+            // what fails at runtime, like dividing by zero or `int ^ negative int`, is left for
+            // the runtime to report
+            let result = builtins::binary(opr, left.clone(), right.clone()).ok()?;
+            let message = format!("folded `{} {} {}` to `{}`", show(&left), opr, show(&right), show(&result));
+            Some((to_node(result, long(lhs) || long(rhs), loc)?, message))
+        }),
+        _ => None,
+    };
+
+    if let Some((replacement, message)) = folded {
+        if let Some(loc) = node.loc() {
+            changes.push(Change { loc: loc.clone(), message });
+        }
+        *node = replacement;
+    }
+}
+
+fn declares(body: &[Box<Node>]) -> bool {
+    body.iter().any(|node| matches!(node.as_ref(), Node::LetDef { .. }))
+}
+
+// `if` and `while` on a literal condition keep only the code that can run
+fn branches(body: &mut Vec<Box<Node>>, changes: &mut Vec<Change>) {
+    let mut index = 0;
+    while index < body.len() {
+        let node = body[index].as_mut();
+        match node {
+            Node::Conditional { cond, body_if, body_else, loc } => {
+                let Node::Bool { value, .. } = cond.as_ref() else {
+                    index += 1;
+                    continue;
+                };
+                let taken = value == "true";
+                let kept = std::mem::take(if taken { body_if } else { body_else });
+                let loc = loc.clone();
+
+                // a branch declaring variables keeps its own scope behind an `if true`
+                if declares(&kept) {
+                    if taken && body_else.is_empty() {
+                        *body_if = kept;
+                        index += 1;
+                        continue;
+                    }
+                    changes.push(Change { loc: loc.clone(), message: format!("removed the `{}` branch of an `if` on `{}`", if taken { "else" } else { "if" }, value) });
+                    *node = Node::Conditional {
+                        cond: Box::new(Node::Bool { value: "true".to_owned(), loc: loc.clone() }),
+                        body_if: kept,
+                        body_else: Vec::new(),
+                        loc,
+                    };
+                    index += 1;
+                } else {
+                    changes.push(Change { loc, message: format!("replaced an `if` on `{}` with its `{}` branch", value, if taken { "if" } else { "else" }) });
+                    let count = kept.len();
+                    body.splice(index..=index, kept);
+                    index += count;
+                }
+            }
+            Node::WhileLoop { cond, loc, .. } if matches!(cond.as_ref(), Node::Bool { value, .. } if value == "false") => {
+                changes.push(Change { loc: loc.clone(), message: "removed a `while` loop on `false`".to_owned() });
+                body.remove(index);
+            }
+            _ => index += 1,
+        }
+    }
+}
+
+// statements after `return`, `break` and `continue` never run
+fn unreachable(body: &mut Vec<Box<Node>>, changes: &mut Vec<Change>) {
+    let Some(end) = body.iter().position(|node| matches!(node.as_ref(), Node::Return { .. } | Node::Break { .. } | Node::Continue { .. })) else {
+        return;
+    };
+    if end + 1 == body.len() {
+        return;
+    }
+
+    let keyword = match body[end].as_ref() {
+        Node::Return { .. } => "return",
+        Node::Break { .. } => "break",
+        _ => "continue",
+    };
+    let removed = body.len() - end - 1;
+    if let Some(loc) = body[end + 1].loc() {
+        changes.push(Change { loc: loc.clone(), message: format!("removed {} unreachable statement(s) after `{}`", removed, keyword) });
+    }
+    body.truncate(end + 1);
+}
+
+fn references(node: &mut Node, names: &mut Vec<String>) {
+    if let Node::Var { value, .. } = node {
+        names.push(value.clone());
+    }
+    for child in node.children_mut() {
+        references(child, names);
+    }
+}
+
+// private functions nothing reachable from `main` or a public item calls or refers to
+fn unused_functions(nodes: &mut Vec<Box<Node>>, changes: &mut Vec<Change>) {
+    let private: HashMap<String, usize> = nodes.iter().enumerate().filter_map(|(i, node)| match node.as_ref() {
+        Node::FuncDef { name, public: false, .. } if name != "main" => Some((name.clone(), i)),
+        _ => None,
+    }).collect();
+    let candidates: HashSet<usize> = private.values().copied().collect();
+
+    let mut work = Vec::new();
+    for (i, node) in nodes.iter_mut().enumerate() {
+        if !candidates.contains(&i) {
+            references(node, &mut work);
+        }
+    }
+
+    let mut used = HashSet::new();
+    while let Some(name) = work.pop() {
+        if let Some(index) = private.get(&name).copied()
+            && used.insert(index)
+        {
+            references(&mut nodes[index], &mut work);
+        }
+    }
+
+    let mut index = 0;
+    nodes.retain(|node| {
+        let keep = !candidates.contains(&index) || used.contains(&index);
+        index += 1;
+        if !keep && let Node::FuncDef { name, loc, .. } = node.as_ref() {
+            changes.push(Change { loc: loc.clone(), message: format!("removed the unused function `{}`", name) });
+        }
+        keep
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn optimized(source: &str, level: u32) -> Vec<Box<Node>> {
+        let (mut nodes, _) = crate::compiler::analyze(source);
+        optimize(&mut nodes, level);
+        nodes
+    }
+
+    // the body of `main`, which comes last
+    fn main_body(source: &str) -> Vec<Box<Node>> {
+        match *optimized(source, 1).pop().unwrap() {
+            Node::FuncDef { body, .. } => body,
+            _ => panic!("expected `main` last"),
+        }
+    }
+
+    fn value(node: &Node) -> &Node {
+        match node {
+            Node::LetDef { value, .. } => value,
+            _ => panic!("expected a `let`, found {:?}", node),
+        }
+    }
+
+    fn functions(nodes: &[Box<Node>]) -> Vec<&str> {
+        nodes.iter().filter_map(|node| match node.as_ref() {
+            Node::FuncDef { name, .. } => Some(name.as_str()),
+            _ => None,
+        }).collect()
+    }
+
+    #[test]
+    fn short_circuits_on_a_literal_left_side() {
+        let body = main_body(
+            "func f() -> bool { return true; } \
+             func main() { let a = true && f(); let b = false && f(); let c = true || f(); let d = false || f(); }",
+        );
+        assert!(matches!(value(&body[0]), Node::FuncCall { .. }));
+        assert!(matches!(value(&body[1]), Node::Bool { value, .. } if value == "false"));
+        assert!(matches!(value(&body[2]), Node::Bool { value, .. } if value == "true"));
+        assert!(matches!(value(&body[3]), Node::FuncCall { .. }));
+    }
+
+    #[test]
+    fn keeps_the_scope_of_a_branch_that_declares_variables() {
+        let body = main_body("func main() { let x = 1; if true { let x = 2; } else { x = 3; } if true { x = 4; } }");
+        assert_eq!(body.len(), 3);
+        let Node::Conditional { cond, body_if, body_else, .. } = body[1].as_ref() else {
+            panic!("expected the `if` to stay, found {:?}", body[1]);
+        };
+        assert!(matches!(cond.as_ref(), Node::Bool { value, .. } if value == "true"));
+        assert!(matches!(body_if[0].as_ref(), Node::LetDef { .. }));
+        assert!(body_else.is_empty());
+        // nothing is declared in the second `if`, so its body takes its place
+        assert!(matches!(body[2].as_ref(), Node::AssignDef { .. }));
+    }
+
+    #[test]
+    fn removes_statements_after_return_and_break() {
+        let body = main_body("func main() { let x = 1; while true { break; x = 2; } return; x = 3; }");
+        assert_eq!(body.len(), 3);
+        assert!(matches!(body[2].as_ref(), Node::Return { .. }));
+        let Node::WhileLoop { body: inner, .. } = body[1].as_ref() else {
+            panic!("expected the loop to stay, found {:?}", body[1]);
+        };
+        assert_eq!(inner.len(), 1);
+        assert!(matches!(inner[0].as_ref(), Node::Break { .. }));
+    }
+
+    #[test]
+    fn keeps_private_functions_reachable_through_others() {
+        let nodes = optimized(
+            "func leaf() -> int { return 1; } func middle() -> int { return leaf(); } func unused() -> int { return leaf(); } \
+             func main() { let x = middle(); }",
+            2,
+        );
+        assert_eq!(functions(&nodes), ["leaf", "middle", "main"]);
+    }
+
+    #[test]
+    fn leaves_what_fails_or_cannot_be_written_to_runtime() {
+        let body = main_body("func main() { let a = 1 / 0; let b = -9223372036854775807 - 1; let c = 6 / 3; }");
+        assert!(matches!(value(&body[0]), Node::BinaryOp { .. }));
+        assert!(matches!(value(&body[1]), Node::BinaryOp { .. }));
+        assert!(matches!(value(&body[2]), Node::Int { value, .. } if value == "2"));
+    }
+}
@@ -0,0 +1,885 @@
+use super::node::{Node, Location};
+use super::diagnostic::{Diagnostic, Diagnostics};
+use super::module::SourceModule;
+
+use std::collections::HashMap;
+
+pub type SymbolId = usize;
+pub type ScopeId = usize;
+
+// names every program can use without declaring or importing them
+pub const BUILTIN_TYPES: &[&str] = &["int", "long", "float", "double", "bool", "str", "void", "List", "Dict"];
+pub const BUILTIN_MODULES: &[&str] = &["std"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function,
+    Struct,
+    Enum,
+    Variant,
+    Field,
+    Method,
+    Variable,
+    Parameter,
+    Module,
+    Alias,
+    BuiltinType,
+    TypeParam,
+}
+
+impl SymbolKind {
+    pub fn is_type(&self) -> bool {
+        matches!(self, SymbolKind::Struct | SymbolKind::Enum | SymbolKind::Alias | SymbolKind::BuiltinType | SymbolKind::TypeParam)
+    }
+
+    fn describe(&self) -> &'static str {
+        match self {
+            SymbolKind::Function => "function",
+            SymbolKind::Struct => "struct",
+            SymbolKind::Enum => "enum",
+            SymbolKind::Variant => "variant",
+            SymbolKind::Field => "field",
+            SymbolKind::Method => "method",
+            SymbolKind::Variable => "variable",
+            SymbolKind::Parameter => "parameter",
+            SymbolKind::Module => "module",
+            SymbolKind::Alias => "alias",
+            SymbolKind::BuiltinType => "builtin type",
+            SymbolKind::TypeParam => "type parameter",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub public: bool,
+    pub loc: Option<Location>,
+    pub owner: Option<SymbolId>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Scope {
+    pub parent: Option<ScopeId>,
+    pub names: HashMap<String, SymbolId>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    pub symbols: Vec<Symbol>,
+    pub scopes: Vec<Scope>,
+    members: HashMap<SymbolId, HashMap<String, SymbolId>>,
+    references: HashMap<(String, usize), SymbolId>,
+    declarations: HashMap<(String, usize), SymbolId>,
+    // type parameters of generic structs and functions, in declaration order
+    generics: HashMap<SymbolId, Vec<SymbolId>>,
+}
+
+impl SymbolTable {
+    pub fn symbol(&self, id: SymbolId) -> &Symbol {
+        &self.symbols[id]
+    }
+
+    pub fn global(&self, name: &str) -> Option<SymbolId> {
+        self.scopes.first().and_then(|scope| scope.names.get(name).copied())
+    }
+
+    pub fn member(&self, owner: SymbolId, name: &str) -> Option<SymbolId> {
+        self.members.get(&owner).and_then(|members| members.get(name).copied())
+    }
+
+    pub fn generics(&self, owner: SymbolId) -> &[SymbolId] {
+        self.generics.get(&owner).map(|params| params.as_slice()).unwrap_or(&[])
+    }
+
+    // the declaration a `Node::Var` at `loc` refers to
+    pub fn reference(&self, loc: &Location) -> Option<SymbolId> {
+        self.references.get(&(loc.file.clone(), loc.start)).copied()
+    }
+
+    // the symbol declared by the node at `loc`
+    pub fn declaration(&self, loc: &Location) -> Option<SymbolId> {
+        self.declarations.get(&(loc.file.clone(), loc.start)).copied()
+    }
+
+    pub fn declarations(&self) -> impl Iterator<Item = (&(String, usize), &SymbolId)> {
+        self.declarations.iter()
+    }
+
+    pub fn lookup(&self, scope: ScopeId, name: &str) -> Option<SymbolId> {
+        let mut current = Some(scope);
+
+        while let Some(id) = current {
+            if let Some(symbol) = self.scopes[id].names.get(name) {
+                return Some(*symbol);
+            }
+            current = self.scopes[id].parent;
+        }
+
+        None
+    }
+}
+
+pub struct Resolver {
+    table: SymbolTable,
+    diagnostics: Diagnostics,
+    scope: ScopeId,
+    // struct whose methods are being resolved, for private member access
+    owner: Option<SymbolId>,
+    // every module by its path, and the one being resolved with the scope of its items
+    modules: HashMap<String, SymbolId>,
+    module: Option<(SymbolId, ScopeId)>,
+    // `from` imports `to` at `loc`, for finding cycles
+    imports: Vec<(SymbolId, SymbolId, Location)>,
+    debug: bool,
+}
+
+impl Resolver {
+    pub fn new(debug: bool) -> Self {
+        let mut table = SymbolTable::default();
+        table.scopes.push(Scope::default());
+
+        Self {
+            table,
+            diagnostics: Diagnostics::new(),
+            scope: 0,
+            owner: None,
+            modules: HashMap::new(),
+            module: None,
+            imports: Vec::new(),
+            debug,
+        }
+    }
+
+    pub fn resolve(&mut self, modules: &[SourceModule]) -> (SymbolTable, Diagnostics) {
+        if self.debug {
+            println!("[RESOLVER] Starting Resolution");
+        }
+
+        for name in BUILTIN_TYPES {
+            self.declare_builtin(name, SymbolKind::BuiltinType);
+        }
+
+        for name in BUILTIN_MODULES {
+            self.declare_builtin(name, SymbolKind::Module);
+        }
+
+        // each module has its own scope below the builtins, and a symbol whose members are its items
+        let mut scopes = Vec::new();
+        for module in modules {
+            self.scope = 0;
+            self.enter_scope();
+            let id = self.table.symbols.len();
+            self.table.symbols.push(Symbol {
+                name: module.name.clone(),
+                kind: SymbolKind::Module,
+                public: true,
+                loc: Some(Location { file: module.file.clone(), line: 1, column: 1, start: 0, end: 0 }),
+                owner: None,
+            });
+            self.modules.insert(module.name.clone(), id);
+            scopes.push((id, self.scope));
+        }
+
+        // declare every top-level item first so items can refer to ones defined later, or in
+        // modules resolved later
+        for (module, current) in modules.iter().zip(&scopes) {
+            self.enter_module(*current);
+            for node in &module.nodes {
+                self.declare_item(node);
+            }
+        }
+
+        for (module, current) in modules.iter().zip(&scopes) {
+            self.enter_module(*current);
+            for node in &module.nodes {
+                self.declare_import(node);
+            }
+        }
+        self.check_cycles();
+
+        for (module, current) in modules.iter().zip(&scopes) {
+            self.enter_module(*current);
+            for node in &module.nodes {
+                self.declare_attached(node);
+            }
+        }
+
+        for (module, current) in modules.iter().zip(&scopes) {
+            self.enter_module(*current);
+            for node in &module.nodes {
+                self.resolve_item(node);
+            }
+        }
+
+        let mut diagnostics = std::mem::take(&mut self.diagnostics);
+        diagnostics.sort();
+
+        (std::mem::take(&mut self.table), diagnostics)
+    }
+
+    fn declare_builtin(&mut self, name: &str, kind: SymbolKind) {
+        let id = self.table.symbols.len();
+        self.table.symbols.push(Symbol {
+            name: name.to_owned(),
+            kind,
+            public: true,
+            loc: None,
+            owner: None,
+        });
+        self.table.scopes[0].names.insert(name.to_owned(), id);
+    }
+
+    fn enter_module(&mut self, (id, scope): (SymbolId, ScopeId)) {
+        self.module = Some((id, scope));
+        self.scope = scope;
+    }
+
+    fn declare_item(&mut self, node: &Node) {
+        match node {
+            Node::FuncDef { name, public, loc, .. } => {
+                self.declare_global(name, SymbolKind::Function, *public, loc);
+            }
+            Node::Struct { name, public, generics, child, body, loc } => {
+                if let Some(id) = self.declare_global(name, SymbolKind::Struct, *public, loc) {
+                    // declared now so attached methods can see them, brought into scope by `enter_generics`
+                    self.enter_scope();
+                    self.declare_generics(id, generics);
+                    self.exit_scope();
+
+                    for field in child {
+                        if let Node::LetDef { name, public, loc, .. } = field.as_ref() {
+                            self.declare_member(id, name, SymbolKind::Field, *public, loc);
+                        }
+                    }
+
+                    for method in body {
+                        if let Node::FuncDef { name, public, loc, .. } = method.as_ref() {
+                            self.declare_member(id, name, SymbolKind::Method, *public, loc);
+                        }
+                    }
+                }
+            }
+            Node::Enum { name, public, child, loc } => {
+                if let Some(id) = self.declare_global(name, SymbolKind::Enum, *public, loc) {
+                    for variant in child {
+                        if let Node::Var { value, loc } = variant.as_ref() {
+                            self.declare_member(id, value, SymbolKind::Variant, true, loc);
+                        }
+                    }
+                }
+            }
+            Node::Alias { name, loc, .. } => {
+                self.declare_global(name, SymbolKind::Alias, false, loc);
+            }
+            _ => {}
+        }
+    }
+
+    // a top-level item, named `module::name` outside the entry file so it cannot clash with
+    // the items of other modules
+    fn declare_global(&mut self, name: &str, kind: SymbolKind, public: bool, loc: &Location) -> Option<SymbolId> {
+        let id = self.declare(name, kind, public, loc)?;
+
+        if let Some((module, _)) = self.module {
+            let path = &self.table.symbols[module].name;
+            if !path.is_empty() {
+                self.table.symbols[id].name = format!("{}::{}", path, name);
+            }
+            self.table.members.entry(module).or_default().insert(name.to_owned(), id);
+        }
+        Some(id)
+    }
+
+    // `import a::b;` binds module `a::b` as `b`, `use a::b::c;` binds item `c` of module `a::b`
+    fn declare_import(&mut self, node: &Node) {
+        let (package, items, is_use) = match node {
+            Node::Import { package, module, .. } => (package, module, false),
+            Node::Use { package, module, .. } => (package, module, true),
+            _ => return,
+        };
+
+        let mut path = Vec::new();
+        let mut last = None;
+        for segment in package {
+            if let Node::Var { value, loc } = segment.as_ref() {
+                path.push(value.clone());
+                last = Some(loc);
+            }
+        }
+
+        for item in items {
+            let Node::Var { value: name, loc } = item.as_ref() else {
+                continue;
+            };
+
+            if !is_use {
+                let module = [path.as_slice(), std::slice::from_ref(name)].concat().join("::");
+                if let Some(id) = self.find_module(&module, loc) {
+                    self.bind(name, id, loc);
+                }
+                continue;
+            }
+
+            let module = path.join("::");
+            let Some(module_loc) = last else {
+                self.diagnostics.push(Diagnostic::error("E0307", format!("cannot `use` `{}` without a module path", name), loc.clone())
+                    .with_help(format!("name the module it comes from, like `use module::{};`", name)));
+                continue;
+            };
+            if BUILTIN_MODULES.contains(&module.as_str()) {
+                self.diagnostics.push(Diagnostic::error("E0307", format!("cannot `use` items of the builtin module `{}`", module), loc.clone())
+                    .with_help(format!("call it as `{}::{}` instead", module, name)));
+                continue;
+            }
+            let Some(owner) = self.find_module(&module, module_loc) else {
+                continue;
+            };
+            if let Some(id) = self.module_member(owner, name, loc) {
+                self.bind(name, id, loc);
+            }
+        }
+    }
+
+    fn find_module(&mut self, path: &str, loc: &Location) -> Option<SymbolId> {
+        if BUILTIN_MODULES.contains(&path) {
+            return self.table.global(path);
+        }
+        if let Some((builtin, _)) = path.split_once("::").filter(|(first, _)| BUILTIN_MODULES.contains(first)) {
+            self.diagnostics.push(Diagnostic::error("E0307", format!("cannot find module `{}`, `{}` has no submodules", path, builtin), loc.clone())
+                .with_help(format!("import `{}` itself and call its functions as `{}::name`", builtin, builtin)));
+            return None;
+        }
+
+        let Some(id) = self.modules.get(path).copied() else {
+            self.diagnostics.push(Diagnostic::error("E0307", format!("cannot find module `{}`", path), loc.clone())
+                .with_note(format!("modules are the `.mx` files under `src`, `{}` would be `src/{}.mx`, and the packages in `mix.conf`", path, path.replace("::", "/"))));
+            return None;
+        };
+
+        if let Some((current, _)) = self.module {
+            self.imports.push((current, id, loc.clone()));
+        }
+        Some(id)
+    }
+
+    // item `name` of another module, which must be public
+    fn module_member(&mut self, module: SymbolId, name: &str, loc: &Location) -> Option<SymbolId> {
+        let module_name = self.table.symbol(module).name.clone();
+        let Some(id) = self.table.member(module, name) else {
+            self.diagnostics.push(Diagnostic::error("E0306", format!("no item `{}` in module `{}`", name, module_name), loc.clone()));
+            return None;
+        };
+
+        let member = self.table.symbol(id);
+        if !member.public {
+            let mut diagnostic = Diagnostic::error("E0303", format!("{} `{}` of module `{}` is private", member.kind.describe(), name, module_name), loc.clone());
+            if let Some(decl) = &member.loc {
+                diagnostic = diagnostic.with_label(decl.clone(), "declared here without `public`");
+            }
+            self.diagnostics.push(diagnostic);
+        }
+        Some(id)
+    }
+
+    // makes the existing symbol `id` visible as `name` in the current scope
+    fn bind(&mut self, name: &str, id: SymbolId, loc: &Location) {
+        match self.table.scopes[self.scope].names.get(name).copied() {
+            Some(previous) if previous == id => {}
+            Some(previous) => self.duplicate(name, previous, loc),
+            None => {
+                self.table.scopes[self.scope].names.insert(name.to_owned(), id);
+            }
+        }
+        self.table.references.insert((loc.file.clone(), loc.start), id);
+    }
+
+    // modules that end up importing themselves, reported once per import closing a cycle
+    fn check_cycles(&mut self) {
+        let mut edges: HashMap<SymbolId, Vec<(SymbolId, &Location)>> = HashMap::new();
+        for (from, to, loc) in &self.imports {
+            edges.entry(*from).or_default().push((*to, loc));
+        }
+
+        // 1 while a module is on the current path, 2 once all its imports are done
+        let mut state: HashMap<SymbolId, u8> = HashMap::new();
+        let mut path = Vec::new();
+        let mut diagnostics = Vec::new();
+
+        let mut roots: Vec<SymbolId> = self.modules.values().copied().collect();
+        roots.sort();
+        for root in roots {
+            self.visit(root, &edges, &mut state, &mut path, &mut diagnostics);
+        }
+
+        for diagnostic in diagnostics {
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    fn visit(&self, module: SymbolId, edges: &HashMap<SymbolId, Vec<(SymbolId, &Location)>>, state: &mut HashMap<SymbolId, u8>,
+             path: &mut Vec<SymbolId>, diagnostics: &mut Vec<Diagnostic>) {
+        if state.contains_key(&module) {
+            return;
+        }
+        state.insert(module, 1);
+        path.push(module);
+
+        for (next, loc) in edges.get(&module).into_iter().flatten() {
+            match state.get(next) {
+                Some(1) => {
+                    let start = path.iter().position(|id| id == next).unwrap_or(0);
+                    let cycle: Vec<String> = path[start..].iter().chain(std::iter::once(next))
+                        .map(|id| format!("`{}`", self.table.symbol(*id).name))
+                        .collect();
+                    diagnostics.push(Diagnostic::error("E0308", format!("import cycle between modules: {}", cycle.join(" -> ")), (*loc).clone())
+                        .with_help("move what the modules share into a module neither of them imports from the other"));
+                }
+                Some(_) => {}
+                None => self.visit(*next, edges, state, path, diagnostics),
+            }
+        }
+
+        path.pop();
+        state.insert(module, 2);
+    }
+
+    fn declare_attached(&mut self, node: &Node) {
+        if let Node::Attach { name, attach_to, public, loc, .. } = node
+            && let Some(owner) = self.attach_target(attach_to)
+        {
+            self.declare_member(owner, name, SymbolKind::Method, *public, loc);
+        }
+    }
+
+    fn attach_target(&mut self, attach_to: &Node) -> Option<SymbolId> {
+        let Node::Var { value, loc } = attach_to else {
+            return None;
+        };
+
+        match self.table.lookup(self.scope, value) {
+            Some(id) if self.table.symbol(id).kind == SymbolKind::Struct => {
+                self.table.references.insert((loc.file.clone(), loc.start), id);
+                Some(id)
+            }
+            Some(id) => {
+                let kind = self.table.symbol(id).kind.describe();
+                self.diagnostics.push(
+                    Diagnostic::error("E0305", format!("cannot attach a method to {} `{}`", kind, value), loc.clone())
+                        .with_note("methods can only be attached to structs"),
+                );
+                None
+            }
+            None => {
+                self.diagnostics.push(Diagnostic::error("E0301", format!("cannot find struct `{}` in this scope", value), loc.clone()));
+                None
+            }
+        }
+    }
+
+    fn resolve_item(&mut self, node: &Node) {
+        match node {
+            Node::FuncDef { generics, args, rtype, body, loc, .. } => {
+                self.resolve_function(generics, args, rtype, body, None, loc);
+            }
+            Node::Struct { child, body, loc, .. } => {
+                let owner = self.table.declaration(loc);
+
+                self.enter_scope();
+                self.enter_generics(owner);
+
+                for field in child {
+                    if let Node::LetDef { dtype, .. } = field.as_ref() {
+                        self.resolve_type(dtype);
+                    }
+                }
+
+                for method in body {
+                    if let Node::FuncDef { generics, args, rtype, body, loc, .. } = method.as_ref() {
+                        self.resolve_function(generics, args, rtype, body, owner, loc);
+                    }
+                }
+
+                self.exit_scope();
+            }
+            Node::Attach { attach_to, generics, args, rtype, body, loc, .. } => {
+                let owner = attach_to.loc().and_then(|loc| self.table.reference(loc))
+                    .filter(|id| self.table.symbol(*id).kind == SymbolKind::Struct);
+
+                self.enter_scope();
+                self.enter_generics(owner);
+                self.resolve_function(generics, args, rtype, body, owner, loc);
+                self.exit_scope();
+            }
+            Node::Alias { real, .. } => {
+                self.resolve_type(real);
+            }
+            _ => {}
+        }
+    }
+
+    fn resolve_function(&mut self, generics: &[Box<Node>], args: &[Box<Node>], rtype: &Node, body: &[Box<Node>], owner: Option<SymbolId>, loc: &Location) {
+        let previous_owner = self.owner;
+        self.owner = owner;
+
+        self.enter_scope();
+
+        if let Some(id) = self.table.declaration(loc) {
+            self.declare_generics(id, generics);
+        }
+
+        if let Some(owner) = owner {
+            let id = self.table.symbols.len();
+            self.table.symbols.push(Symbol {
+                name: "self".to_owned(),
+                kind: SymbolKind::Variable,
+                public: false,
+                loc: Some(loc.clone()),
+                owner: Some(owner),
+            });
+            self.table.scopes[self.scope].names.insert("self".to_owned(), id);
+        }
+
+        for arg in args {
+            if let Node::LetDef { name, dtype, loc, .. } = arg.as_ref() {
+                self.resolve_type(dtype);
+                self.declare(name, SymbolKind::Parameter, false, loc);
+            }
+        }
+
+        self.resolve_type(rtype);
+        self.resolve_block(body);
+
+        self.exit_scope();
+        self.owner = previous_owner;
+    }
+
+    fn resolve_block(&mut self, body: &[Box<Node>]) {
+        self.enter_scope();
+
+        for node in body {
+            self.resolve_stmt(node);
+        }
+
+        self.exit_scope();
+    }
+
+    fn resolve_stmt(&mut self, node: &Node) {
+        match node {
+            Node::LetDef { name, dtype, value, loc, .. } => {
+                self.resolve_type(dtype);
+                self.resolve_expr(value);
+                self.declare(name, SymbolKind::Variable, false, loc);
+            }
+            Node::AssignDef { targ, value, .. } => {
+                self.resolve_expr(targ);
+                self.resolve_expr(value);
+            }
+            Node::Return { value, .. } => {
+                self.resolve_expr(value);
+            }
+            Node::Conditional { cond, body_if, body_else, .. } => {
+                self.resolve_expr(cond);
+                self.resolve_block(body_if);
+                self.resolve_block(body_else);
+            }
+            Node::WhileLoop { cond, body, .. } => {
+                self.resolve_expr(cond);
+                self.resolve_block(body);
+            }
+            Node::ForLoop { initializer, iterator, body, .. } => {
+                self.resolve_expr(iterator);
+
+                self.enter_scope();
+                if let Node::LetDef { name, dtype, loc, .. } = initializer.as_ref() {
+                    self.resolve_type(dtype);
+                    self.declare(name, SymbolKind::Variable, false, loc);
+                }
+                self.resolve_block(body);
+                self.exit_scope();
+            }
+            Node::MatchCase { value, child, default, .. } => {
+                self.resolve_expr(value);
+
+                for arm in child {
+                    self.resolve_expr(&arm.0);
+                    self.resolve_block(&arm.1);
+                }
+
+                self.resolve_block(default);
+            }
+            _ => self.resolve_expr(node),
+        }
+    }
+
+    fn resolve_expr(&mut self, node: &Node) {
+        match node {
+            Node::Var { value, loc } => {
+                match self.table.lookup(self.scope, value) {
+                    Some(id) => {
+                        self.table.references.insert((loc.file.clone(), loc.start), id);
+                    }
+                    None => {
+                        self.diagnostics.push(Diagnostic::error("E0301", format!("cannot find `{}` in this scope", value), loc.clone()));
+                    }
+                }
+            }
+            Node::MemLockup { obj, .. } => {
+                // the member name depends on the type of `obj`, which the type checker resolves
+                self.resolve_expr(obj);
+            }
+            Node::CodeLockup { targ, obj, .. } => {
+                self.resolve_expr(obj);
+                self.resolve_path_member(obj, targ);
+            }
+            Node::FuncCall { func, args, .. } => {
+                self.resolve_expr(func);
+                for arg in args {
+                    self.resolve_expr(arg);
+                }
+            }
+            Node::BinaryOp { lhs, rhs, .. } => {
+                self.resolve_expr(lhs);
+                self.resolve_expr(rhs);
+            }
+            Node::UnaryOp { value, .. } => {
+                self.resolve_expr(value);
+            }
+            Node::List { element, .. } | Node::Tuple { element, .. } => {
+                for item in element {
+                    self.resolve_expr(item);
+                }
+            }
+            Node::Dict { key_value, .. } => {
+                for pair in key_value {
+                    self.resolve_expr(&pair.0);
+                    self.resolve_expr(&pair.1);
+                }
+            }
+            Node::Range { min, max, .. } => {
+                self.resolve_expr(min);
+                self.resolve_expr(max);
+            }
+            _ => {}
+        }
+    }
+
+    // `obj::targ` where `obj` has already been resolved
+    fn resolve_path_member(&mut self, obj: &Node, targ: &Node) {
+        let Node::Var { value: name, loc } = targ else {
+            return;
+        };
+
+        // `a::B::c` looks `c` up in whatever `a::B` named
+        let owner = match obj {
+            Node::Var { loc, .. } => self.table.reference(loc),
+            Node::CodeLockup { targ, .. } => targ.loc().and_then(|loc| self.table.reference(loc)),
+            _ => None,
+        };
+
+        let Some(owner) = owner else {
+            return;
+        };
+
+        let owner_symbol = self.table.symbol(owner).clone();
+
+        if owner_symbol.kind == SymbolKind::Module {
+            // members of `std` are checked by the type checker
+            if owner_symbol.loc.is_some()
+                && let Some(id) = self.module_member(owner, name, loc)
+            {
+                self.table.references.insert((loc.file.clone(), loc.start), id);
+            }
+            return;
+        }
+
+        if !matches!(owner_symbol.kind, SymbolKind::Struct | SymbolKind::Enum) {
+            return;
+        }
+
+        match self.table.member(owner, name) {
+            Some(id) => {
+                self.table.references.insert((loc.file.clone(), loc.start), id);
+
+                let member = self.table.symbol(id);
+                if !member.public && self.owner != Some(owner) {
+                    let mut diagnostic = Diagnostic::error("E0303", format!("{} `{}` of `{}` is private", member.kind.describe(), name, owner_symbol.name), loc.clone());
+                    if let Some(decl) = &member.loc {
+                        diagnostic = diagnostic.with_label(decl.clone(), "declared here without `public`");
+                    }
+                    self.diagnostics.push(diagnostic);
+                }
+            }
+            None => {
+                self.diagnostics.push(Diagnostic::error("E0306", format!("no member `{}` in {} `{}`", name, owner_symbol.kind.describe(), owner_symbol.name), loc.clone()));
+            }
+        }
+    }
+
+    fn resolve_type(&mut self, node: &Node) {
+        match node {
+            Node::Var { value, loc } => {
+                match self.table.lookup(self.scope, value) {
+                    Some(id) if self.table.symbol(id).kind.is_type() => {
+                        self.table.references.insert((loc.file.clone(), loc.start), id);
+                    }
+                    Some(id) => {
+                        let kind = self.table.symbol(id).kind.describe();
+                        self.diagnostics.push(Diagnostic::error("E0304", format!("expected type, found {} `{}`", kind, value), loc.clone()));
+                    }
+                    None => {
+                        self.diagnostics.push(Diagnostic::error("E0304", format!("cannot find type `{}` in this scope", value), loc.clone()));
+                    }
+                }
+            }
+            Node::MemLockup { targ, obj, .. } => {
+                // `module::Type`, a public type of an imported module
+                self.resolve_expr(obj);
+
+                let name = match targ.as_ref() {
+                    Node::GenericType { name, params, .. } => {
+                        for param in params {
+                            self.resolve_type(param);
+                        }
+                        name.as_ref()
+                    }
+                    targ => targ,
+                };
+                self.resolve_path_member(obj, name);
+
+                if let Node::Var { value, loc } = name
+                    && let Some(id) = self.table.reference(loc)
+                    && !self.table.symbol(id).kind.is_type()
+                {
+                    let kind = self.table.symbol(id).kind.describe();
+                    self.diagnostics.push(Diagnostic::error("E0304", format!("expected type, found {} `{}`", kind, value), loc.clone()));
+                }
+            }
+            Node::GenericType { name, params, .. } => {
+                self.resolve_type(name);
+                for param in params {
+                    self.resolve_type(param);
+                }
+            }
+            Node::FuncType { params, rtype, .. } => {
+                for param in params {
+                    self.resolve_type(param);
+                }
+                self.resolve_type(rtype);
+            }
+            _ => {}
+        }
+    }
+
+    fn declare_generics(&mut self, owner: SymbolId, generics: &[Box<Node>]) {
+        let mut params = Vec::new();
+
+        for param in generics {
+            if let Node::Var { value, loc } = param.as_ref()
+                && let Some(id) = self.declare(value, SymbolKind::TypeParam, false, loc)
+            {
+                params.push(id);
+            }
+        }
+
+        if !params.is_empty() {
+            self.table.generics.insert(owner, params);
+        }
+    }
+
+    // make the type parameters of struct `owner` visible in the current scope
+    fn enter_generics(&mut self, owner: Option<SymbolId>) {
+        let Some(owner) = owner else {
+            return;
+        };
+
+        for id in self.table.generics(owner).to_vec() {
+            let name = self.table.symbol(id).name.clone();
+            self.table.scopes[self.scope].names.insert(name, id);
+        }
+    }
+
+    fn declare(&mut self, name: &str, kind: SymbolKind, public: bool, loc: &Location) -> Option<SymbolId> {
+        if let Some(previous) = self.table.scopes[self.scope].names.get(name).copied() {
+            self.duplicate(name, previous, loc);
+            return None;
+        }
+
+        let id = self.push_symbol(name, kind, public, loc, None);
+        self.table.scopes[self.scope].names.insert(name.to_owned(), id);
+        Some(id)
+    }
+
+    fn declare_member(&mut self, owner: SymbolId, name: &str, kind: SymbolKind, public: bool, loc: &Location) {
+        if let Some(previous) = self.table.member(owner, name) {
+            self.duplicate(name, previous, loc);
+            return;
+        }
+
+        let id = self.push_symbol(name, kind, public, loc, Some(owner));
+        self.table.members.entry(owner).or_default().insert(name.to_owned(), id);
+    }
+
+    fn push_symbol(&mut self, name: &str, kind: SymbolKind, public: bool, loc: &Location, owner: Option<SymbolId>) -> SymbolId {
+        let id = self.table.symbols.len();
+        self.table.symbols.push(Symbol {
+            name: name.to_owned(),
+            kind,
+            public,
+            loc: Some(loc.clone()),
+            owner,
+        });
+        self.table.declarations.insert((loc.file.clone(), loc.start), id);
+        id
+    }
+
+    fn duplicate(&mut self, name: &str, previous: SymbolId, loc: &Location) {
+        let symbol = self.table.symbol(previous);
+        let mut diagnostic = Diagnostic::error("E0302", format!("the name `{}` is defined multiple times", name), loc.clone());
+
+        match &symbol.loc {
+            Some(decl) => diagnostic = diagnostic.with_label(decl.clone(), format!("previous definition of `{}` here", name)),
+            None => diagnostic = diagnostic.with_note(format!("`{}` is a builtin {}", name, symbol.kind.describe())),
+        }
+
+        self.diagnostics.push(diagnostic);
+    }
+
+    fn enter_scope(&mut self) {
+        self.table.scopes.push(Scope {
+            parent: Some(self.scope),
+            names: HashMap::new(),
+        });
+        self.scope = self.table.scopes.len() - 1;
+    }
+
+    fn exit_scope(&mut self) {
+        self.scope = self.table.scopes[self.scope].parent.unwrap_or(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::lexer::Lexer;
+    use crate::compiler::scanner::Scanner;
+
+    fn resolve(source: &str) -> Vec<String> {
+        let file = "main.mx".to_owned();
+        let (nodes, _) = Scanner::new(Lexer::new(source.to_owned(), file.clone()), false).scan();
+        let (_, diagnostics) = Resolver::new(false).resolve(&[SourceModule { name: String::new(), file, nodes }]);
+        diagnostics.iter().map(|d| format!("{} {}", d.code, d.message)).collect()
+    }
+
+    #[test]
+    fn imports_std() {
+        assert!(resolve("import std;\n\nfunc main() {\n    std::println(1);\n}\n").is_empty());
+    }
+
+    #[test]
+    fn reports_std_submodules_as_missing() {
+        assert_eq!(resolve("import std::io;\n"), ["E0307 cannot find module `std::io`, `std` has no submodules"]);
+        assert_eq!(resolve("use std::io::{println};\n"), ["E0307 cannot find module `std::io`, `std` has no submodules"]);
+    }
+}
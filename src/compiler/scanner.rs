@@ -1,21 +1,45 @@
 use super::lexer::Lexer;
 use super::token::{TokenType, Token};
 use super::node::{Node, Location};
-
-use colored::*;
+use super::diagnostic::{Diagnostic, Diagnostics};
+
+
+// Binary operator precedence, from loosest to tightest binding, as
+// (operator, binding power, right associative). Unary `+`, `-`, `!` and
+// `~` bind tighter than every binary operator.
+const BINARY_OPERATORS: &[(TokenType, u8, bool)] = &[
+    (TokenType::Or, 1, false),
+    (TokenType::And, 2, false),
+    (TokenType::DoubleEqual, 3, false),
+    (TokenType::NotEqual, 3, false),
+    (TokenType::Less, 4, false),
+    (TokenType::Greater, 4, false),
+    (TokenType::LessEqual, 4, false),
+    (TokenType::GreaterEqual, 4, false),
+    (TokenType::Pipe, 5, false),
+    (TokenType::Ampersand, 6, false),
+    (TokenType::ShiftLeft, 7, false),
+    (TokenType::ShiftRight, 7, false),
+    (TokenType::Plus, 8, false),
+    (TokenType::Minus, 8, false),
+    (TokenType::Star, 9, false),
+    (TokenType::Slash, 9, false),
+    (TokenType::Percent, 9, false),
+    (TokenType::Power, 10, true),
+];
 
 pub struct Scanner {
     lexer: Lexer,
     debug: bool,
-    err: bool,
+    diagnostics: Diagnostics,
 }
 
 impl Scanner {
     pub fn new(lexer: Lexer, debug: bool) -> Self {
-        Self { lexer, debug, err: false }
+        Self { lexer, debug, diagnostics: Diagnostics::new() }
     }
 
-    pub fn scan(&mut self) -> Vec<Box<Node>> {
+    pub fn scan(&mut self) -> (Vec<Box<Node>>, Diagnostics) {
         let mut node = Vec::new();
 
         if self.debug {
@@ -23,12 +47,18 @@ impl Scanner {
         }
 
         while !self.is(TokenType::Eof) {
-            if self.is_next(TokenType::Func) {
+            if self.is_next(TokenType::Error) {
+                continue;
+            } else if self.is_next(TokenType::Func) {
                 node.push(Box::new(self.parse_funcdef(false)));
             } else if self.is_next(TokenType::Struct) {
                 node.push(Box::new(self.parse_struct(false)));
             } else if self.is_next(TokenType::Enum) {
                 node.push(Box::new(self.parse_enum(false)));
+            } else if self.is(TokenType::Import) || self.is(TokenType::Use) {
+                node.push(Box::new(self.parse_import()));
+            } else if self.is(TokenType::Alias) {
+                node.push(Box::new(self.parse_alias()));
             } else if self.is_next(TokenType::Public) {
                 if self.is_next(TokenType::Func) {
                     node.push(Box::new(self.parse_funcdef(true)));
@@ -38,28 +68,148 @@ impl Scanner {
                     node.push(Box::new(self.parse_enum(true)));
                 } else {
                     let info = self.lexer.peek_next();
-                    println!("{} {}:{}", "error:".red(), info.file, info.line);
-                    println!("└───unexpected `{}`", info.value);
-                    self.err = true;
+                    self.error("E0201", format!("unexpected `{}`", info.value), info);
+                    self.synchronize_item();
                 }
             } else {
                 let info = self.lexer.peek_next();
-                println!("{} {}:{}", "error:".red(), info.file, info.line);
-                println!("└───unexpected `{}`", info.value);
-                self.err = true;
+                self.error("E0201", format!("unexpected `{}`", info.value), info);
+                self.synchronize_item();
             }
         }
 
-        node
+        let mut diagnostics = self.lexer.take_diagnostics();
+        diagnostics.extend(std::mem::take(&mut self.diagnostics));
+        diagnostics.sort();
+        diagnostics.dedup();
+
+        (node, diagnostics)
+    }
+
+    fn parse_import(&mut self) -> Node {
+        let token = self.lexer.peek_next();
+        let location = self.create_loc(token.clone());
+
+        let mut package = Vec::new();
+        let mut module = Vec::new();
+
+        if !self.is(TokenType::Identifier) {
+            let info = self.lexer.peek();
+            self.error("E0203", format!("expected module path, but got `{}`", info.value), info);
+        }
+
+        while self.is(TokenType::Identifier) {
+            let info = self.lexer.peek_next();
+            let segment = Node::Var {
+                value: info.value.clone(),
+                loc: self.create_loc(info),
+            };
+
+            if !self.is_next(TokenType::DoubleColon) {
+                module.push(Box::new(segment));
+                break;
+            }
+
+            package.push(Box::new(segment));
+
+            if self.is_next(TokenType::OpenBrace) {
+                while !self.is(TokenType::CloseBrace) && !self.is(TokenType::Eof) {
+                    if self.is(TokenType::Identifier) {
+                        let info = self.lexer.peek_next();
+                        module.push(Box::new(Node::Var {
+                            value: info.value.clone(),
+                            loc: self.create_loc(info),
+                        }));
+
+                        if !self.is_next(TokenType::Comma) && !self.is(TokenType::CloseBrace) {
+                            let info = self.lexer.peek();
+                            self.error("E0202", format!("expected comma `,`, but got `{}`", info.value), info);
+                            break;
+                        }
+                    } else {
+                        let info = self.lexer.peek();
+                        self.error("E0203", format!("expected identifier, but got `{}`", info.value), info);
+                        break;
+                    }
+                }
+
+                if !self.is_next(TokenType::CloseBrace) {
+                    let info = self.lexer.peek();
+                    self.error("E0202", format!("expected close brace `}}`, but got `{}`", info.value), info);
+                }
+
+                break;
+            }
+
+            if !self.is(TokenType::Identifier) {
+                let info = self.lexer.peek();
+                self.error("E0203", format!("expected identifier, but got `{}`", info.value), info);
+            }
+        }
+
+        if !self.is_next(TokenType::SemiColon) {
+            let info = self.lexer.peek();
+            self.error("E0202", format!("expected semicolon `;`, but got `{}`", info.value), info);
+
+            if let Some(TokenType::SemiColon) = self.synchronize_until(&[
+                TokenType::SemiColon,
+                TokenType::Func,
+                TokenType::Public,
+                TokenType::Import,
+                TokenType::Use,
+            ]) {
+                self.is_next(TokenType::SemiColon);
+            }
+        }
+
+        if token.kind == TokenType::Use {
+            Node::Use {
+                package,
+                module,
+                loc: location,
+            }
+        } else {
+            Node::Import {
+                package,
+                module,
+                loc: location,
+            }
+        }
+    }
+
+    fn parse_alias(&mut self) -> Node {
+        self.lexer.peek_next();
+
+        if !self.is(TokenType::Identifier) {
+            let info = self.lexer.peek();
+            self.error("E0203", format!("expected alias identifier, but got `{}`", info.value), info);
+        }
+
+        let token = self.lexer.peek_next();
+        let name = token.value.clone();
+        let location = self.create_loc(token);
+
+        if !self.is_next(TokenType::Equal) {
+            let info = self.lexer.peek();
+            self.error("E0202", format!("expected equal `=`, but got `{}`", info.value), info);
+        }
+
+        let real = self.parse_type();
+
+        self.expect_semicolon();
+
+        Node::Alias {
+            name,
+            real: Box::new(real),
+            loc: location,
+        }
     }
 
     fn parse_struct(&mut self, public: bool) -> Node {
 
         if !self.is(TokenType::Identifier) {
             let info = self.lexer.peek_next();
-            println!("{} {}:{}", "error:".red(), info.file, info.line);
-            println!("└───expected function identifier");
-            self.err = true;
+            self.error("E0203", "expected struct identifier", info);
         }
 
         let token = self.lexer.peek_next();
@@ -67,17 +217,17 @@ impl Scanner {
 
         let location = self.create_loc(token);
 
+        let generics = self.parse_generics();
+
         if !self.is(TokenType::OpenBrace) {
             let info = self.lexer.peek_next();
-            println!("{} {}:{}", "error:".red(), info.file, info.line);
-            println!("└───expected open brace `{{`, but got `{}`", info.value);
-            self.err = true;
+            self.error("E0202", format!("expected open brace `{{`, but got `{}`", info.value), info);
         }
 
         let mut child = Vec::new();
         let mut body = Vec::new();
         if self.is_next(TokenType::OpenBrace) {
-            while !self.is(TokenType::CloseBrace) {
+            while !self.is(TokenType::CloseBrace) && !self.is(TokenType::Eof) {
                 if self.is_next(TokenType::Public) {
                     if self.is(TokenType::Identifier) {
                         child.push(Box::new(self.parse_letdef_for_struct(true)));
@@ -85,40 +235,33 @@ impl Scanner {
                         body.push(Box::new(self.parse_funcdef(true)));
                     } else {
                         let info = self.lexer.peek_next();
-                        println!("{} {}:{}", "error:".red(), info.file, info.line);
-                        println!("└───unexpected `{}`", info.value);
-                        self.err = true;
+                        self.error("E0201", format!("unexpected `{}`", info.value), info);
                     }
                 } else if self.is(TokenType::Identifier) {
                     child.push(Box::new(self.parse_letdef_for_struct(false)));
-                } else if self.is(TokenType::Func) {
+                } else if self.is_next(TokenType::Func) {
                     body.push(Box::new(self.parse_funcdef(false)));
                 } else {
                     let info = self.lexer.peek_next();
-                    println!("{} {}:{}", "error:".red(), info.file, info.line);
-                    println!("└───unexpected `{}`", info.value);
-                    self.err = true;
+                    self.error("E0201", format!("unexpected `{}`", info.value), info);
                 }
             }
         } else {
             let info = self.lexer.peek();
-            println!("{} {}:{}", "error:".red(), info.file, info.line);
-            println!("└───expected open brace `{{`, but got `{}`", info.value);
-            self.err = true;
+            self.error("E0202", format!("expected open brace `{{`, but got `{}`", info.value), info);
         }
 
         if !self.is_next(TokenType::CloseBrace) {
             let info = self.lexer.peek();
-            println!("{} {}:{}", "error:".red(), info.file, info.line);
-            println!("└───expected open brace `}}`, but got `{}`", info.value);
-            self.err = true;
+            self.error("E0202", format!("expected close brace `}}`, but got `{}`", info.value), info);
         }
 
         Node::Struct {
-            name: name,
-            public: public,
-            child: child,
-            body: body,
+            name,
+            public,
+            generics,
+            child,
+            body,
             loc: location,
         }
     }
@@ -126,9 +269,7 @@ impl Scanner {
     fn parse_enum(&mut self, public: bool) -> Node {
         if !self.is(TokenType::Identifier) {
             let info = self.lexer.peek_next();
-            println!("{} {}:{}", "error:".red(), info.file, info.line);
-            println!("└───expected function identifier");
-            self.err = true;
+            self.error("E0203", "expected enum identifier", info);
         }
 
         let token = self.lexer.peek_next();
@@ -138,15 +279,12 @@ impl Scanner {
 
         if !self.is(TokenType::OpenBrace) {
             let info = self.lexer.peek_next();
-            println!("{} {}:{}", "error:".red(), info.file, info.line);
-            println!("└───expected open brace `{{`, but got `{}`", info.value);
-            self.err = true;
+            self.error("E0202", format!("expected open brace `{{`, but got `{}`", info.value), info);
         }
 
         let mut child = Vec::new();
-        let mut body = Vec::new();
         if self.is_next(TokenType::OpenBrace) {
-            while !self.is(TokenType::CloseBrace) {
+            while !self.is(TokenType::CloseBrace) && !self.is(TokenType::Eof) {
                 if self.is(TokenType::Identifier) {
                     let info = self.lexer.peek_next();
                     child.push(Box::new(Node::Var {
@@ -164,46 +302,35 @@ impl Scanner {
                         }
 
                         let info = self.lexer.peek();
-                        println!("{} {}:{}", "error:".red(), info.file, info.line);
-                        println!("└───expected comma `,`, but got `{}`", info.value);
-                        self.err = true;
+                        self.error("E0202", format!("expected comma `,`, but got `{}`", info.value), info);
 
                         if !self.is_expr() {
                             break;
                         }
                     } else if !self.is(TokenType::Identifier) {
                         let info = self.lexer.peek();
-                        println!("{} {}:{}", "error:".red(), info.file, info.line);
-                        println!("└───unexpected comma `,`");
-                        self.err = true;
+                        self.error("E0201", "unexpected comma `,`", info);
                         break;
                     }
                 } else {
                     let info = self.lexer.peek_next();
-                    println!("{} {}:{}", "error:".red(), info.file, info.line);
-                    println!("└───expected identifier, but got `{}`", info.value);
-                    self.err = true;
+                    self.error("E0203", format!("expected identifier, but got `{}`", info.value), info);
                 }
             }
         } else {
             let info = self.lexer.peek();
-            println!("{} {}:{}", "error:".red(), info.file, info.line);
-            println!("└───expected open brace `{{`, but got `{}`", info.value);
-            self.err = true;
+            self.error("E0202", format!("expected open brace `{{`, but got `{}`", info.value), info);
         }
 
         if !self.is_next(TokenType::CloseBrace) {
             let info = self.lexer.peek();
-            println!("{} {}:{}", "error:".red(), info.file, info.line);
-            println!("└───expected open brace `}}`, but got `{}`", info.value);
-            self.err = true;
+            self.error("E0202", format!("expected close brace `}}`, but got `{}`", info.value), info);
         }
 
-        Node::Struct {
-            name: name,
-            public: public,
-            child: child,
-            body: body,
+        Node::Enum {
+            name,
+            public,
+            child,
             loc: location,
         }
     }
@@ -212,26 +339,42 @@ impl Scanner {
         
         if !self.is(TokenType::Identifier) {
             let info = self.lexer.peek_next();
-            println!("{} {}:{}", "error:".red(), info.file, info.line);
-            println!("└───expected function identifier");
-            self.err = true;
+            self.error("E0203", "expected function identifier", info);
         }
 
         let token = self.lexer.peek_next();
-        let name = token.value.clone();
+        let mut name = token.value.clone();
 
-        let location = self.create_loc(token);
+        let mut location = self.create_loc(token);
+
+        let mut attach_to = None;
+        if self.is_next(TokenType::Dot) {
+            if !self.is(TokenType::Identifier) {
+                let info = self.lexer.peek();
+                self.error("E0203", format!("expected method identifier, but got `{}`", info.value), info);
+            } else {
+                let token = self.lexer.peek_next();
+
+                attach_to = Some(Node::Var {
+                    value: name,
+                    loc: location,
+                });
+
+                name = token.value.clone();
+                location = self.create_loc(token);
+            }
+        }
+
+        let generics = self.parse_generics();
 
         let mut args = Vec::new();
         if self.is_next(TokenType::OpenParent) {
             while !self.is(TokenType::CloseParent) && !self.is(TokenType::Eof) {
                 if self.is(TokenType::Comma) {
                     let info = self.lexer.peek_next();
-                    println!("{} {}:{}", "error:".red(), info.file, info.line);
-                    println!("└───unexpected comma `,`");
-                    self.err = true;
+                    self.error("E0201", "unexpected comma `,`", info);
                 } else if self.is(TokenType::Identifier) {
-                    args.push(Box::new(self.parse_letdef()));
+                    args.push(Box::new(self.parse_initializer()));
 
                     if self.is(TokenType::CloseParent) {
                         break;
@@ -243,25 +386,19 @@ impl Scanner {
                         }
 
                         let info = self.lexer.peek();
-                        println!("{} {}:{}", "error:".red(), info.file, info.line);
-                        println!("└───expected comma `,`, but got `{}`", info.value);
-                        self.err = true;
+                        self.error("E0202", format!("expected comma `,`, but got `{}`", info.value), info);
 
                         if !self.is_expr() {
                             break;
                         }
                     } else if !self.is(TokenType::Identifier) {
                         let info = self.lexer.peek();
-                        println!("{} {}:{}", "error:".red(), info.file, info.line);
-                        println!("└───unexpected comma `,`");
-                        self.err = true;
+                        self.error("E0201", "unexpected comma `,`", info);
                         break;
                     }
                 } else {
                     let info = self.lexer.peek();
-                    println!("{} {}:{}", "error:".red(), info.file, info.line);
-                    println!("└───unexpected `{}`", info.value);
-                    self.err = true;
+                    self.error("E0201", format!("unexpected `{}`", info.value), info);
 
                     match self.synchronize_until(&[
                         TokenType::Comma,
@@ -281,9 +418,7 @@ impl Scanner {
 
             if !self.is_next(TokenType::CloseParent) {
                 let info = self.lexer.peek();
-                println!("{} {}:{}", "error:".red(), info.file, info.line);
-                println!("└───expected close parenthise `)`, but got `{}`", info.value);
-                self.err = true;
+                self.error("E0202", format!("expected close parenthesis `)`, but got `{}`", info.value), info);
 
                 if let Some(found) = self.synchronize_until(&[
                     TokenType::CloseParent,
@@ -291,28 +426,23 @@ impl Scanner {
                     TokenType::OpenBrace,
                     TokenType::Func,
                     TokenType::Public,
-                ]) {
-                    if found == TokenType::CloseParent {
+                ])
+                    && found == TokenType::CloseParent {
                         self.is_next(TokenType::CloseParent);
                     }
-                }
             }
         } else {
             let info = self.lexer.peek();
-            println!("{} {}:{}", "error:".red(), info.file, info.line);
-            println!("└───expected open parenthise `(`, but got `{}`", info.value);
-            self.err = true;
+            self.error("E0202", format!("expected open parenthesis `(`, but got `{}`", info.value), info);
         }
 
         let mut return_type = Box::new(Node::Void);
         if self.is_next(TokenType::Arrow) {
-            if self.is(TokenType::Identifier) {
+            if self.is(TokenType::Identifier) || self.is(TokenType::Func) {
                 return_type = Box::new(self.parse_type());
             } else {
                 let info = self.lexer.peek();
-                println!("{} {}:{}", "error:".red(), info.file, info.line);
-                println!("└───expected type identifier");
-                self.err = true;
+                self.error("E0203", "expected type identifier", info);
             }
         }
 
@@ -321,29 +451,40 @@ impl Scanner {
             body = self.parse_block();
         } else {
             let info = self.lexer.peek();
-            println!("{} {}:{}", "error:".red(), info.file, info.line);
-            println!("└───expected open brace `{{`, but got `{}`", info.value);
-            self.err = true;
+            self.error("E0202", format!("expected open brace `{{`, but got `{}`", info.value), info);
 
             if let Some(found) = self.synchronize_until(&[
                 TokenType::OpenBrace,
                 TokenType::Func,
                 TokenType::Public,
                 TokenType::Eof,
-            ]) {
-                if found == TokenType::OpenBrace {
+            ])
+                && found == TokenType::OpenBrace {
                     self.is_next(TokenType::OpenBrace);
                     body = self.parse_block();
                 }
-            }
+        }
+
+        if let Some(attach_to) = attach_to {
+            return Node::Attach {
+                name,
+                attach_to: Box::new(attach_to),
+                public,
+                generics,
+                args,
+                rtype: return_type,
+                body,
+                loc: location,
+            };
         }
 
         Node::FuncDef {
-            name: name,
-            public: public,
-            args: args,
+            name,
+            public,
+            generics,
+            args,
             rtype: return_type,
-            body: body,
+            body,
             loc: location,
         }
     }
@@ -351,8 +492,14 @@ impl Scanner {
     fn parse_block(&mut self) -> Vec<Box<Node>> {
         let mut body = Vec::new();
 
+        let open = self.lexer.previous()
+            .filter(|token| token.kind == TokenType::OpenBrace)
+            .cloned();
+
         while !self.is(TokenType::CloseBrace) && !self.is(TokenType::Eof) {
-            if self.is_next(TokenType::Let) {
+            if self.is_next(TokenType::Error) {
+                continue;
+            } else if self.is_next(TokenType::Let) {
                 body.push(Box::new(self.parse_letdef()));
             } else if self.is(TokenType::Return) {
                 body.push(Box::new(self.parse_return()));
@@ -367,13 +514,19 @@ impl Scanner {
             } else if self.is(TokenType::Identifier) {
                 body.push(Box::new(self.parse_assignment()));
             }
-            else if self.is_next(TokenType::Break) { body.push(Box::new(Node::Break)); self.expect_semicolon(); } 
-            else if self.is_next(TokenType::Continue) { body.push(Box::new(Node::Continue)); self.expect_semicolon(); } 
+            else if self.is(TokenType::Break) {
+                let token = self.lexer.peek_next();
+                body.push(Box::new(Node::Break { loc: self.create_loc(token) }));
+                self.expect_semicolon();
+            }
+            else if self.is(TokenType::Continue) {
+                let token = self.lexer.peek_next();
+                body.push(Box::new(Node::Continue { loc: self.create_loc(token) }));
+                self.expect_semicolon();
+            }
             else {
                 let info = self.lexer.peek_next();
-                println!("{} {}:{}", "error:".red(), info.file, info.line);
-                println!("└───unexpected `{}`", info.value);
-                self.err = true;
+                self.error("E0201", format!("unexpected `{}`", info.value), info);
 
                 if let Some(TokenType::SemiColon) =
                     self.synchronize_until(&[TokenType::SemiColon, TokenType::CloseBrace])
@@ -385,9 +538,13 @@ impl Scanner {
 
         if !self.is_next(TokenType::CloseBrace) {
             let info = self.lexer.peek();
-            println!("{} {}:{}", "error:".red(), info.file, info.line);
-            println!("└───expected close brace `}}`, but got `{}`", info.value);
-            self.err = true;
+            let mut diagnostic = Diagnostic::error("E0202", format!("expected close brace `}}`, but got `{}`", info.value), self.create_loc(info));
+
+            if let Some(open) = open {
+                diagnostic = diagnostic.with_label(self.create_loc(open), "opening brace here");
+            }
+
+            self.diagnostics.push(diagnostic);
 
             if let Some(TokenType::CloseBrace) = self.synchronize_until(&[TokenType::CloseBrace]) {
                 self.is_next(TokenType::CloseBrace);
@@ -404,16 +561,14 @@ impl Scanner {
 
         if !self.is_next(TokenType::OpenBrace) {
             let info = self.lexer.peek();
-            println!("{} {}:{}", "error:".red(), info.file, info.line);
-            println!("└───expected open brace `{{`");
-            self.err = true;
+            self.error("E0202", "expected open brace `{`", info);
         }
 
         let body = self.parse_block();
 
         Node::WhileLoop {
             cond: Box::new(condition),
-            body: body,
+            body,
             loc: self.create_loc(info),
         }
     }
@@ -423,42 +578,34 @@ impl Scanner {
 
         if !self.is(TokenType::Identifier) {
             let info = self.lexer.peek();
-            println!("{} {}:{}", "error:".red(), info.file, info.line);
-            println!("└───expected initializer, but got `{}`", info.value);
-            self.err = true;
+            self.error("E0204", format!("expected initializer, but got `{}`", info.value), info);
         }
 
         let initializer = Box::new(self.parse_initializer());
 
         if !self.is_next(TokenType::In) {
             let info = self.lexer.peek();
-            println!("{} {}:{}", "error:".red(), info.file, info.line);
-            println!("└───expected syntax `in`, but got `{}`", info.value);
-            self.err = true;
+            self.error("E0202", format!("expected syntax `in`, but got `{}`", info.value), info);
         }
 
         if !self.is_expr() {
             let info = self.lexer.peek();
-            println!("{} {}:{}", "error:".red(), info.file, info.line);
-            println!("└───expected expr for iterator, but got `{}`", info.value);
-            self.err = true;
+            self.error("E0204", format!("expected expr for iterator, but got `{}`", info.value), info);
         }
 
         let iterator = Box::new(self.parse_expr());
 
         if !self.is_next(TokenType::OpenBrace) {
             let info = self.lexer.peek();
-            println!("{} {}:{}", "error:".red(), info.file, info.line);
-            println!("└───expected open brace `{{`, but got `{}`", info.value);
-            self.err = true;
+            self.error("E0202", format!("expected open brace `{{`, but got `{}`", info.value), info);
         }
 
         let body = self.parse_block();
 
         Node::ForLoop {
-            initializer: initializer,
-            iterator: iterator,
-            body: body,
+            initializer,
+            iterator,
+            body,
             loc: self.create_loc(info),
         }
     }
@@ -466,27 +613,22 @@ impl Scanner {
     fn parse_conditional(&mut self) -> Node {
         let info = self.lexer.peek();
 
-        let mut ifbody = Vec::new();
         let mut elsebody = Vec::new();
 
         let condition = self.parse_expr();
 
         if !self.is_next(TokenType::OpenBrace) {
             let info = self.lexer.peek();
-            println!("{} {}:{}", "error:".red(), info.file, info.line);
-            println!("└───expected open brace `{{`");
-            self.err = true;
+            self.error("E0202", "expected open brace `{`", info);
         }
 
-        ifbody = self.parse_block();
+        let ifbody = self.parse_block();
 
 
         if self.is_next(TokenType::Else) {
             if !self.is_next(TokenType::OpenBrace) {
                 let info = self.lexer.peek();
-                println!("{} {}:{}", "error:".red(), info.file, info.line);
-                println!("└───expected open brace `{{`");
-                self.err = true;
+                self.error("E0202", "expected open brace `{`", info);
             }
 
             elsebody = self.parse_block();
@@ -509,9 +651,7 @@ impl Scanner {
 
         if !self.is_next(TokenType::OpenBrace) {
             let info = self.lexer.peek();
-            println!("{} {}:{}", "error:".red(), info.file, info.line);
-            println!("└───expected open brace `{{`");
-            self.err = true;
+            self.error("E0202", "expected open brace `{`", info);
         }
 
         let mut default = Vec::new();
@@ -519,60 +659,99 @@ impl Scanner {
         while !self.is(TokenType::CloseBrace) && !self.is(TokenType::Eof) {
             if self.is_expr() {
                 let expr = self.parse_literal();
-                if self.is_next(TokenType::OpenBrace) {
-
-                    let body = self.parse_block();
-
+                if let Some(body) = self.parse_arm_body() {
                     child.push(Box::new((expr, body)));
-                } else {
-                    let info = self.lexer.peek();
-                    println!("{} {}:{}", "error:".red(), info.file, info.line);
-                    println!("└───expected open brace `{{`");
-                    self.err = true;
                 }
             } else if self.is_next(TokenType::Default) {
-                if self.is_next(TokenType::OpenBrace) {
-                    default = self.parse_block();
-                } else {
-                    let info = self.lexer.peek();
-                    println!("{} {}:{}", "error:".red(), info.file, info.line);
-                    println!("└───expected open brace `{{`");
-                    self.err = true;
+                if let Some(body) = self.parse_arm_body() {
+                    default = body;
                 }
             } else {
-                break;
+                let info = self.lexer.peek_next();
+                self.error("E0201", format!("unexpected `{}`", info.value), info);
+
+                // skip to the body of the arm, which still parses, or to the end of the match
+                if let Some(TokenType::OpenBrace) = self.synchronize_until(&[TokenType::OpenBrace, TokenType::CloseBrace]) {
+                    self.is_next(TokenType::OpenBrace);
+                    self.parse_block();
+                }
             }
         }
 
         if !self.is_next(TokenType::CloseBrace) {
             let info = self.lexer.peek();
-            println!("{} {}:{}", "error:".red(), info.file, info.line);
-            println!("└───expected close brace `}}`");
-            self.err = true;
+            self.error("E0202", "expected close brace `}`", info);
         }
 
         Node::MatchCase {
             value: Box::new(values),
-            default: default,
-            child: child,
+            default,
+            child,
             loc: self.create_loc(info),
         }
     }
 
+    // the block of a match arm; anything between the pattern and `{`, like `=>`, is reported
+    // once and skipped so the arm and the ones after it still parse
+    fn parse_arm_body(&mut self) -> Option<Vec<Box<Node>>> {
+        if !self.is_next(TokenType::OpenBrace) {
+            let info = self.lexer.peek();
+            self.error("E0202", format!("expected open brace `{{`, but got `{}`", info.value), info);
+
+            if self.synchronize_until(&[TokenType::OpenBrace, TokenType::CloseBrace]) != Some(TokenType::OpenBrace) {
+                return None;
+            }
+            self.is_next(TokenType::OpenBrace);
+        }
+        Some(self.parse_block())
+    }
+
+    // `<A, B>` after a struct or function name, the names become types inside its body
+    fn parse_generics(&mut self) -> Vec<Box<Node>> {
+        let mut generics = Vec::new();
+
+        if !self.is_next(TokenType::Less) {
+            return generics;
+        }
+
+        while !self.is(TokenType::Greater) && !self.is(TokenType::Eof) {
+            if !self.is(TokenType::Identifier) {
+                let info = self.lexer.peek();
+                self.error("E0203", format!("expected type parameter identifier, but got `{}`", info.value), info);
+                break;
+            }
+
+            let token = self.lexer.peek_next();
+            generics.push(Box::new(Node::Var {
+                value: token.value.clone(),
+                loc: self.create_loc(token),
+            }));
+
+            if !self.is_next(TokenType::Comma) {
+                break;
+            }
+        }
+
+        self.expect_greater();
+        generics
+    }
+
     fn parse_type(&mut self) -> Node {
+        if self.is(TokenType::Func) {
+            return self.parse_func_type();
+        }
 
         if !self.is(TokenType::Identifier) {
             let info = self.lexer.peek();
-            println!("{} {}:{}", "error:".red(), info.file, info.line);
-            println!("└───expected identifier");
-            self.err = true;
+            self.error("E0203", "expected identifier", info);
         }
         
-        let mut token = self.lexer.peek_next();
+        let token = self.lexer.peek_next();
+        let location = self.create_loc(token.clone());
         
         let mut node = Node::Var {
             value: token.value.clone(),
-            loc: self.create_loc(token),
+            loc: location.clone(),
         };
 
         if self.is_next(TokenType::DoubleColon) {
@@ -586,57 +765,76 @@ impl Scanner {
                 };
             } else {
                 let info = self.lexer.peek();
-                println!("{} {}:{}", "error:".red(), info.file, info.line);
-                println!("└───expected identifier");
-                self.err = true;
+                self.error("E0203", "expected identifier", info);
             }
         } else if self.is_next(TokenType::Less) {
-            if !self.is(TokenType::Identifier) {
-                let info = self.lexer.peek();
-                println!("{} {}:{}", "error:".red(), info.file, info.line);
-                println!("└───expected identifier");
-                self.err = true;
+            let mut params = Vec::new();
+
+            while !self.is(TokenType::Greater) && !self.is(TokenType::ShiftRight) && !self.is(TokenType::Eof) {
+                params.push(Box::new(self.parse_type()));
+
+                if !self.is_next(TokenType::Comma) {
+                    break;
+                }
             }
 
-            token = self.lexer.peek_next();
-            let left = Node::Var {
-                value: token.value.clone(),
-                loc: self.create_loc(token.clone())
+            self.expect_greater();
+
+            return Node::GenericType {
+                name: Box::new(node),
+                params,
+                loc: location,
             };
+        }
 
-            if self.is_next(TokenType::Comma) {
-                let right = self.parse_type();
+        node
+    }
 
-                if !self.is_next(TokenType::Greater) {
-                    let info = self.lexer.peek();
-                    println!("{} {}:{}", "error:".red(), info.file, info.line);
-                    println!("└───expected greater `>`");
-                    self.err = true;
-                }
+    fn parse_func_type(&mut self) -> Node {
+        let token = self.lexer.peek_next();
+        let location = self.create_loc(token);
 
-                return Node::DictType {
-                    dictname: Box::new(node),
-                    dictype: Box::new((left, right)),
-                    loc: self.create_loc(token)
-                };
-            } else {
-                
-                if !self.is_next(TokenType::Greater) {
-                    let info = self.lexer.peek();
-                    println!("{} {}:{}", "error:".red(), info.file, info.line);
-                    println!("└───expected greater `>`");
-                    self.err = true;
-                }
+        let mut params = Vec::new();
+        if self.is_next(TokenType::OpenParent) {
+            while !self.is(TokenType::CloseParent) && !self.is(TokenType::Eof) {
+                params.push(Box::new(self.parse_type()));
 
-                return Node::ListType {
-                    listname: Box::new(node),
-                    listtype: Box::new(left),
-                    loc: self.create_loc(token)
+                if !self.is_next(TokenType::Comma) {
+                    break;
                 }
             }
+
+            if !self.is_next(TokenType::CloseParent) {
+                let info = self.lexer.peek();
+                self.error("E0202", format!("expected close parenthesis `)`, but got `{}`", info.value), info);
+            }
+        } else {
+            let info = self.lexer.peek();
+            self.error("E0202", format!("expected open parenthesis `(`, but got `{}`", info.value), info);
         }
 
-        node
+        let mut rtype = Node::Void;
+        if self.is_next(TokenType::Arrow) {
+            rtype = self.parse_type();
+        }
+
+        Node::FuncType {
+            params,
+            rtype: Box::new(rtype),
+            loc: location,
+        }
+    }
+
+    // the `>` closing a type parameter list, `>>` closes two nested lists
+    fn expect_greater(&mut self) {
+        if self.is(TokenType::ShiftRight) {
+            self.lexer.split_token();
+        }
+
+        if !self.is_next(TokenType::Greater) {
+            let info = self.lexer.peek();
+            self.error("E0202", format!("expected greater `>`, but got `{}`", info.value), info);
+        }
     }
 
     fn parse_letdef_for_struct(&mut self, public: bool) -> Node {
@@ -654,16 +852,13 @@ impl Scanner {
             data_type = self.parse_type();
         }
 
-        let mut value = Node::Null {
-            value: "null".to_owned(),
-            loc: location.clone(),
-        };
+        let value = Node::Void;
 
         self.expect_semicolon();
 
         Node::LetDef {
-            name: name,
-            public: public,
+            name,
+            public,
             dtype: Box::new(data_type),
             value: Box::new(value),
             loc: location,
@@ -685,13 +880,10 @@ impl Scanner {
             data_type = self.parse_type();
         }
 
-        let mut value = Node::Null {
-            value: "null".to_owned(),
-            loc: location.clone(),
-        };
+        let value = Node::Void;
 
         Node::LetDef {
-            name: name,
+            name,
             public: false,
             dtype: Box::new(data_type),
             value: Box::new(value),
@@ -714,17 +906,12 @@ impl Scanner {
             data_type = self.parse_type();
         }
 
-        let mut value = Node::Null {
-            value: "null".to_owned(),
-            loc: location.clone(),
-        };
+        let mut value = Node::Void;
 
         if self.is_next(TokenType::Equal) {
             if self.is(TokenType::SemiColon) {
                 let info = self.lexer.peek();
-                println!("{} {}:{}", "error:".red(), info.file, info.line);
-                println!("└───expected value expr");
-                self.err = true;
+                self.error("E0204", "expected value expr", info);
             } else {
                 value = self.parse_expr();
             }
@@ -733,7 +920,7 @@ impl Scanner {
         self.expect_semicolon();
 
         Node::LetDef {
-            name: name,
+            name,
             public: false,
             dtype: Box::new(data_type),
             value: Box::new(value),
@@ -742,148 +929,75 @@ impl Scanner {
     }
 
     fn parse_expr(&mut self) -> Node {
-        let mut left = self.parse_logical();
-
-        if self.is(TokenType::Or) {
-            let token = self.lexer.peek_next();
- 
-            left = Node::BinaryOp {
-                lhs: Box::new(left),
-                rhs: Box::new(self.parse_expr()),
-                opr: token.value.clone(),
-                loc: self.create_loc(token),
-            };
-        }
-
-        return left;
-    }
- 
-    fn parse_logical(&mut self) -> Node {
-        let mut left = self.parse_equality();
-
-        if self.is(TokenType::And) {
-            let token = self.lexer.peek_next();
-            
-            left = Node::BinaryOp {
-                lhs: Box::new(left),
-                rhs: Box::new(self.parse_expr()),
-                opr: token.value.clone(),
-                loc: self.create_loc(token),
-            };
-        }
-
-        return left;
-    }
-
-    fn parse_equality(&mut self) -> Node {
-        let mut left = self.parse_overrounded();
-
-        if self.is(TokenType::DoubleEqual) || self.is(TokenType::NotEqual) {
-            let token = self.lexer.peek_next();
-            
-            left = Node::BinaryOp {
-                lhs: Box::new(left),
-                rhs: Box::new(self.parse_expr()),
-                opr: token.value.clone(),
-                loc: self.create_loc(token),
-            };
-        }
-
-        return left;
+        self.parse_binary(1)
     }
 
-    fn parse_overrounded(&mut self) -> Node {
-        let mut left = self.parse_high();
-
-        if self.is(TokenType::Less) || 
-            self.is(TokenType::Greater) || 
-            self.is(TokenType::LessEqual) || 
-            self.is(TokenType::GreaterEqual) {
-
-            let token = self.lexer.peek_next();
-
-            left = Node::BinaryOp {
-                lhs: Box::new(left),
-                rhs: Box::new(self.parse_expr()),
-                opr: token.value.clone(),
-                loc: self.create_loc(token),
-            };
-        }
-
-        return left;
+    fn binding_power(kind: &TokenType) -> Option<(u8, bool)> {
+        BINARY_OPERATORS
+            .iter()
+            .find(|(opr, _, _)| opr == kind)
+            .map(|(_, power, right_assoc)| (*power, *right_assoc))
     }
 
-    fn parse_high(&mut self) -> Node {
-        let mut left = self.parse_low();
-
-        if self.is(TokenType::Star) || 
-            self.is(TokenType::Slash) ||
-            self.is(TokenType::Percent) {
+    fn parse_binary(&mut self, min_power: u8) -> Node {
+        let mut left = self.parse_unary();
 
-            let token = self.lexer.peek_next();
+        loop {
+            let kind = self.lexer.peek_nth(0).kind.clone();
 
-            left = Node::BinaryOp {
-                lhs: Box::new(left),
-                rhs: Box::new(self.parse_expr()),
-                opr: token.value.clone(),
-                loc: self.create_loc(token),
+            let Some((power, right_assoc)) = Self::binding_power(&kind) else {
+                break;
             };
-        }
-
-        return left;
-    }
 
-    fn parse_low(&mut self) -> Node {
-        let mut left = self.parse_unary();
-
-        if self.is(TokenType::Plus) || 
-            self.is(TokenType::Minus) {
+            if power < min_power {
+                break;
+            }
 
             let token = self.lexer.peek_next();
+            let next_power = if right_assoc { power } else { power + 1 };
 
             left = Node::BinaryOp {
                 lhs: Box::new(left),
-                rhs: Box::new(self.parse_expr()),
+                rhs: Box::new(self.parse_binary(next_power)),
                 opr: token.value.clone(),
                 loc: self.create_loc(token),
             };
         }
 
-        return left;
+        left
     }
 
     fn parse_unary(&mut self) -> Node {
-        if self.is(TokenType::Plus) || 
-            self.is(TokenType::Minus) || 
-            self.is(TokenType::Not) {
+        if self.is(TokenType::Plus) ||
+            self.is(TokenType::Minus) ||
+            self.is(TokenType::Not) ||
+            self.is(TokenType::Tilde) {
 
             let token = self.lexer.peek_next();
 
             return Node::UnaryOp {
-                value: Box::new(self.parse_expr()),
+                value: Box::new(self.parse_unary()),
                 opr: token.value.clone(),
                 loc: self.create_loc(token),
             };
         }
 
-        return self.parse_parenthises();
+        self.parse_parenthises()
     }
 
     fn parse_parenthises(&mut self) -> Node {
-        if self.is(TokenType::OpenParent) {
+        if self.is_next(TokenType::OpenParent) {
             let node = self.parse_expr();
 
             if !self.is_next(TokenType::CloseParent) {
                 let info = self.lexer.peek();
-                    println!("{} {}:{}", "error:".red(), info.file, info.line);
-                println!("└───expected close parenthise `}}`");
-                self.err = true;
+                self.error("E0202", format!("expected close parenthesis `)`, but got `{}`", info.value), info);
             }
 
             return node;
         }
 
-        return self.parse_literal();
+        self.parse_literal()
     }
 
     fn parse_literal(&mut self) -> Node {
@@ -897,89 +1011,21 @@ impl Scanner {
             };
         } 
         else if self.is_next(TokenType::Number) {
-            
-            let value = token.value.clone();
-            let mut node: Node;
-
-            if let Ok(_) = value.parse::<i32>() {
-                node = Node::Int {
-                    value: token.value,
-                    lbit: false,
-                    loc: location,
-                };
-            } else if let Ok(_) = value.parse::<i64>() {
-                node = Node::Int {
-                    value: token.value,
-                    lbit: true,
-                    loc: location,
-                };
-            } else if let Ok(_) = value.parse::<f32>() {
-                node = Node::Float {
-                    value: token.value,
-                    lbit: false,
-                    loc: location,
-                };
-            } else if let Ok(_) = value.parse::<i64>() {
-                node = Node::Float {
-                    value: token.value,
-                    lbit: true,
-                    loc: location,
-                };
-            } else {                 
-                let info = self.lexer.peek();
-                println!("{} {}:{}", "error:".red(), info.file, info.line);
-                println!("└───cannot convert number");
-                std::process::exit(1);
-            }
+            let node = self.parse_number(token);
 
             if self.is_next(TokenType::Arrow) {
                 if self.is(TokenType::Number) {
                     let token = self.lexer.peek_next();
                     let location = self.create_loc(token.clone());
 
-                    let mut node_right: Node;
-
-                    if let Ok(_) = value.parse::<i32>() {
-                        node_right = Node::Int {
-                            value: token.value,
-                            lbit: false,
-                            loc: location.clone(),
-                        };
-                    } else if let Ok(_) = value.parse::<i64>() {
-                        node_right = Node::Int {
-                            value: token.value,
-                            lbit: true,
-                            loc: location.clone(),
-                        };
-                    } else if let Ok(_) = value.parse::<f32>() {
-                        node_right = Node::Float {
-                            value: token.value,
-                            lbit: false,
-                            loc: location.clone(),
-                        };
-                    } else if let Ok(_) = value.parse::<i64>() {
-                        node_right = Node::Float {
-                            value: token.value,
-                            lbit: true,
-                            loc: location.clone(),
-                        };
-                    } else {                 
-                        let info = self.lexer.peek();
-                        println!("{} {}:{}", "error:".red(), info.file, info.line);
-                        println!("└───cannot convert number");
-                        std::process::exit(1);
-                    }
-
                     return Node::Range {
                         min: Box::new(node),
-                        max: Box::new(node_right),
+                        max: Box::new(self.parse_number(token)),
                         loc: location,
                     }
                 } else {
                     let info = self.lexer.peek();
-                    println!("{} {}:{}", "error:".red(), info.file, info.line);
-                    println!("└───expected number, but got `{}`", info.value);
-                    self.err = true;
+                    self.error("E0202", format!("expected number, but got `{}`", info.value), info);
                 }
             }
 
@@ -994,7 +1040,7 @@ impl Scanner {
         else if self.is(TokenType::Identifier) {
             return self.parse_id();
         } 
-        else if self.is_next(TokenType::Null) {}
+        else if self.is_next(TokenType::Null) || self.is_next(TokenType::Error) {}
         else if self.is_next(TokenType::OpenBracket) {
 
             let mut element = Vec::new();
@@ -1003,9 +1049,7 @@ impl Scanner {
 
                 if self.is(TokenType::Comma) {
                     let info = self.lexer.peek_next();
-                    println!("{} {}:{}", "error:".red(), info.file, info.line);
-                    println!("└───unexpected comma `,`");
-                    self.err = true;
+                    self.error("E0201", "unexpected comma `,`", info);
                 } else if self.is_expr() {
                     element.push(Box::new(self.parse_expr()));
 
@@ -1018,18 +1062,14 @@ impl Scanner {
                         }
                         
                         let info = self.lexer.peek();
-                        println!("{} {}:{}", "error:".red(), info.file, info.line);
-                        println!("└───expected comma `,`");
-                        self.err = true;
+                        self.error("E0202", "expected comma `,`", info);
 
                         if !self.is_expr() {
                             break;
                         }
                     } else if !self.is_expr() {
                         let info = self.lexer.peek();
-                        println!("{} {}:{}", "error:".red(), info.file, info.line);
-                        println!("└───expected value expr");
-                        self.err = true;
+                        self.error("E0204", "expected value expr", info);
                         break;
                     }
                 } else if !self.is_expr() { break; }
@@ -1037,13 +1077,11 @@ impl Scanner {
 
             if !self.is_next(TokenType::CloseBracket) {
                 let info = self.lexer.peek();
-                println!("{} {}:{}", "error:".red(), info.file, info.line);
-                println!("└───expected close bracket `]`");
-                self.err = true;
+                self.error("E0202", "expected close bracket `]`", info);
             }
 
             return Node::List {
-                element: element,
+                element,
                 loc: location,
             };
         }
@@ -1054,12 +1092,10 @@ impl Scanner {
             while !self.is(TokenType::CloseBrace) && !self.is(TokenType::Eof) {
                 let left = self.parse_expr();
 
-                if !self.is(TokenType::Colon) {
+                if !self.is_next(TokenType::Colon) {
                     
                     let info = self.lexer.peek_next();
-                    println!("{} {}:{}", "error:".red(), info.file, info.line);
-                    println!("└───expected colon `:`, but got `{}`", info.value);
-                    self.err = true;
+                    self.error("E0202", format!("expected colon `:`, but got `{}`", info.value), info);
                     break;
                 }
 
@@ -1071,9 +1107,7 @@ impl Scanner {
                 if !self.is_next(TokenType::Comma) { 
 
                     let info = self.lexer.peek_next();
-                    println!("{} {}:{}", "error:".red(), info.file, info.line);
-                    println!("└───expected comma `,`");
-                    self.err = true;
+                    self.error("E0202", "expected comma `,`", info);
 
                     break; 
                 }
@@ -1081,21 +1115,17 @@ impl Scanner {
 
             if !self.is_next(TokenType::CloseBrace) {
                 let info = self.lexer.peek();
-                println!("{} {}:{}", "error:".red(), info.file, info.line);
-                println!("└───expected close brace `}}`");
-                self.err = true;
+                self.error("E0202", "expected close brace `}`", info);
             }
 
             return Node::Dict {
-                key_value: key_value,
+                key_value,
                 loc: location,
             };
         }
         else {
             let info = self.lexer.peek();
-            println!("{} {}:{}", "error:".red(), info.file, info.line);
-            println!("└───unknown value");
-            self.err = true;
+            self.error("E0204", "unknown value", info);
         }
 
         Node::Null {
@@ -1104,12 +1134,49 @@ impl Scanner {
         }
     }
 
+    fn parse_number(&mut self, token: Token) -> Node {
+        let location = self.create_loc(token.clone());
+        let value = token.value.clone();
+
+        // `int` and `float` are 64-bit like `long` and `double`, so a literal is never wider
+        if value.parse::<i64>().is_ok() {
+            Node::Int {
+                value,
+                lbit: false,
+                loc: location,
+            }
+        } else if value.contains(['.', 'e', 'E']) && value.parse::<f64>().is_ok() {
+            Node::Float {
+                value,
+                lbit: false,
+                loc: location,
+            }
+        } else if value.contains(['.', 'e', 'E']) {
+            // malformed float literals are already reported by the lexer
+            Node::Float {
+                value: "0.0".to_owned(),
+                lbit: true,
+                loc: location,
+            }
+        } else {
+            self.error("E0205", format!("integer literal `{}` is too large for a 64-bit integer", value), token);
+
+            Node::Int {
+                value: "0".to_owned(),
+                lbit: true,
+                loc: location,
+            }
+        }
+    }
+
     fn is_expr(&mut self) -> bool {
         if self.is(TokenType::Null) || self.is(TokenType::StringLiteral) ||
         self.is(TokenType::Number) || self.is(TokenType::True) || 
         self.is(TokenType::False) || self.is(TokenType::OpenBrace) ||
         self.is(TokenType::OpenBracket) || self.is(TokenType::OpenParent) ||
-        self.is(TokenType::Identifier) {
+        self.is(TokenType::Identifier) || self.is(TokenType::Minus) ||
+        self.is(TokenType::Plus) || self.is(TokenType::Not) ||
+        self.is(TokenType::Tilde) || self.is(TokenType::Error) {
             return true;
         }
 
@@ -1133,30 +1200,26 @@ impl Scanner {
 
         self.expect_semicolon();
 
-        return node;
+        node
     }
 
     fn parse_id(&mut self) -> Node {
-        let mut token = self.lexer.peek_next();
-
-        let mut location = self.create_loc(token.clone());
+        let token = self.lexer.peek_next();
 
         let mut node = Node::Var {
-            value: token.value,
-            loc: location,
+            value: token.value.clone(),
+            loc: self.create_loc(token),
         };
 
         loop {
             if self.is_next(TokenType::Dot) {
                 if !self.is(TokenType::Identifier) {
                     let info = self.lexer.peek();
-                    println!("{} {}:{}", "error:".red(), info.file, info.line);
-                    println!("└───expected identifier, but got `{}`", info.value);
-                    self.err = true;
+                    self.error("E0203", format!("expected identifier, but got `{}`", info.value), info);
                 } else {
-                    token = self.lexer.peek_next();
-                    
-                    location = self.create_loc(token.clone());
+                    let token = self.lexer.peek_next();
+                    let location = self.create_loc(token.clone());
+
                     node = Node::MemLockup {
                         targ: Box::new(Node::Var {
                             value: token.value,
@@ -1169,51 +1232,12 @@ impl Scanner {
             } else if self.is_next(TokenType::DoubleColon) {
                 if !self.is(TokenType::Identifier) {
                     let info = self.lexer.peek();
-                    println!("{} {}:{}", "error:".red(), info.file, info.line);
-                    println!("└───expected identifier, but got `{}`", info.value);
-                    self.err = true;
+                    self.error("E0203", format!("expected identifier, but got `{}`", info.value), info);
                 } else {
-                    token = self.lexer.peek();
-                    
-                    location = self.create_loc(token);
-                    node = Node::CodeLockup {
-                        targ: Box::new(self.parse_id()),
-                        obj: Box::new(node),
-                        loc: location,
-                    };
-                }
-            } else if self.is_next(TokenType::OpenParent) {
-                let args = self.parse_args();
-            } else {
-                break;
-            }
-        }
-        
-        node
-    }
-
-    fn parse_assignment(&mut self) -> Node {
-        let mut token = self.lexer.peek_next();
-
-        let mut location = self.create_loc(token.clone());
-
-        let mut node = Node::Var {
-            value: token.value,
-            loc: location,
-        };
+                    let token = self.lexer.peek_next();
+                    let location = self.create_loc(token.clone());
 
-        loop {
-            if self.is_next(TokenType::Dot) {
-                if !self.is(TokenType::Identifier) {
-                    let info = self.lexer.peek();
-                    println!("{} {}:{}", "error:".red(), info.file, info.line);
-                    println!("└───expected identifier, but got `{}`", info.value);
-                    self.err = true;
-                } else {
-                    token = self.lexer.peek_next();
-                    
-                    location = self.create_loc(token.clone());
-                    node = Node::MemLockup {
+                    node = Node::CodeLockup {
                         targ: Box::new(Node::Var {
                             value: token.value,
                             loc: location.clone(),
@@ -1222,48 +1246,49 @@ impl Scanner {
                         loc: location,
                     };
                 }
-            } else if self.is_next(TokenType::DoubleColon) {
-                if !self.is(TokenType::Identifier) {
-                    let info = self.lexer.peek();
-                    println!("{} {}:{}", "error:".red(), info.file, info.line);
-                    println!("└───expected identifier, but got `{}`", info.value);
-                    self.err = true;
-                } else {
-                    token = self.lexer.peek();
-                    
-                    location = self.create_loc(token);
-                    node = Node::CodeLockup {
-                        targ: Box::new(self.parse_id()),
-                        obj: Box::new(node),
-                        loc: location,
-                    };
-                }
-            } else if self.is_next(TokenType::OpenParent) {
-                let args = self.parse_args();
-            } else if self.is(TokenType::Equal) ||
-            self.is(TokenType::NotEqual) ||
-            self.is(TokenType::PlusEqual) ||
-            self.is(TokenType::MinusEqual) ||
-            self.is(TokenType::StarEqual) ||
-            self.is(TokenType::SlashEqual) ||
-            self.is(TokenType::PowerEqual) ||
-            self.is(TokenType::PercentEqual) ||
-            self.is(TokenType::DoubleEqual) {
-                let opr = self.lexer.peek_next();
-                let location = self.create_loc(opr.clone());
-
-                node = Node::AssignDef {
-                    targ: Box::new(node),
-                    value: Box::new(self.parse_expr()),
-                    opr: opr.value,
+            } else if self.is(TokenType::OpenParent) {
+                let token = self.lexer.peek_next();
+                let location = self.create_loc(token);
+
+                node = Node::FuncCall {
+                    func: Box::new(node),
+                    args: self.parse_args(),
                     loc: location,
                 };
-                break;
             } else {
                 break;
             }
         }
-        
+
+        node
+    }
+
+    fn parse_assignment(&mut self) -> Node {
+        let mut node = self.parse_id();
+
+        if self.is(TokenType::Equal) ||
+        self.is(TokenType::NotEqual) ||
+        self.is(TokenType::PlusEqual) ||
+        self.is(TokenType::MinusEqual) ||
+        self.is(TokenType::StarEqual) ||
+        self.is(TokenType::SlashEqual) ||
+        self.is(TokenType::PowerEqual) ||
+        self.is(TokenType::PercentEqual) ||
+        self.is(TokenType::DoubleEqual) {
+            let opr = self.lexer.peek_next();
+            let location = self.create_loc(opr.clone());
+
+            node = Node::AssignDef {
+                targ: Box::new(node),
+                value: Box::new(self.parse_expr()),
+                opr: opr.value,
+                loc: location,
+            };
+        } else if !matches!(node, Node::FuncCall { .. }) {
+            let info = self.lexer.peek();
+            self.error("E0202", format!("expected assignment or function call, but got `{}`", info.value), info);
+        }
+
         self.expect_semicolon();
 
         node
@@ -1279,28 +1304,20 @@ impl Scanner {
                 if self.is(TokenType::CloseParent) { break; }
                 if !self.is_next(TokenType::Comma) {
                     let info = self.lexer.peek();
-                    println!("{} {}:{}", "error:".red(), info.file, info.line);
-                    println!("└───expected comma `,`, but got `{}`", info.value);
-                    self.err = true;
+                    self.error("E0202", format!("expected comma `,`, but got `{}`", info.value), info);
                 }
             } else if self.is(TokenType::Comma) {
                 let info = self.lexer.peek_next();
-                println!("{} {}:{}", "error:".red(), info.file, info.line);
-                println!("└───unexpected comma `,`");
-                self.err = true;
+                self.error("E0201", "unexpected comma `,`", info);
             } else {
                 let info = self.lexer.peek_next();
-                println!("{} {}:{}", "error:".red(), info.file, info.line);
-                println!("└───expected expr argument, but got `{}`", info.value);
-                self.err = true;
+                self.error("E0204", format!("expected expr argument, but got `{}`", info.value), info);
             }
         }
 
         if !self.is_next(TokenType::CloseParent) {
             let info = self.lexer.peek_next();
-            println!("{} {}:{}", "error:".red(), info.file, info.line);
-            println!("└───expected close parent, but got `{}`", info.value);
-            self.err = true;
+            self.error("E0202", format!("expected close parenthesis `)`, but got `{}`", info.value), info);
         }
 
         args
@@ -1309,18 +1326,29 @@ impl Scanner {
     fn expect_semicolon(&mut self) {
         if !self.is_next(TokenType::SemiColon) {
             let info = self.lexer.peek();
-            println!("{} {}:{}", "error:".red(), info.file, info.line);
-            println!("└───expected semicolon `;`, but got `{}`", info.value);
-            self.err = true;
+            let message = format!("expected semicolon `;`, but got `{}`", info.value);
+
+            // point just past the end of the statement rather than at whatever follows it
+            let loc = match self.lexer.previous() {
+                Some(prev) => Location {
+                    file: prev.file.clone(),
+                    line: prev.line,
+                    column: prev.column + prev.value.chars().count(),
+                    start: prev.end,
+                    end: prev.end + 1,
+                },
+                None => self.create_loc(info),
+            };
+
+            self.diagnostics.push(Diagnostic::error("E0202", message, loc).with_help("add `;` at the end of the statement"));
         }
     }
 
     fn synchronize_until(&mut self, targets: &[TokenType]) -> Option<TokenType> {
         loop {
-            let token = self.lexer.peek();
-            let kind = token.kind.clone();
+            let kind = self.lexer.peek_nth(0).kind.clone();
 
-            if targets.iter().any(|expected| *expected == kind) {
+            if targets.contains(&kind) {
                 return Some(kind);
             }
 
@@ -1333,6 +1361,19 @@ impl Scanner {
     }
 
 
+    // skips to the next token that can start a top-level item
+    fn synchronize_item(&mut self) {
+        self.synchronize_until(&[
+            TokenType::Func,
+            TokenType::Struct,
+            TokenType::Enum,
+            TokenType::Import,
+            TokenType::Use,
+            TokenType::Alias,
+            TokenType::Public,
+        ]);
+    }
+
     fn is_next(&mut self, t: TokenType) -> bool {
         if self.is(t) { 
             self.lexer.peek_next();
@@ -1341,9 +1382,12 @@ impl Scanner {
     }
 
     fn is(&mut self, t: TokenType) -> bool {
-        if self.lexer.peek().kind == t {
-            true
-        } else { false }
+        self.lexer.peek_nth(0).kind == t
+    }
+
+    fn error(&mut self, code: &'static str, message: impl Into<String>, token: Token) {
+        let loc = self.create_loc(token);
+        self.diagnostics.push(Diagnostic::error(code, message, loc));
     }
 
     fn create_loc(&self, token: Token) -> Location {
@@ -1362,3 +1406,141 @@ impl Scanner {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the initializer of `let x = <expr>;` with every operation in parentheses
+    fn grouped(expr: &str) -> String {
+        fn group(node: &Node) -> String {
+            match node {
+                Node::BinaryOp { lhs, rhs, opr, .. } => format!("({} {} {})", group(lhs), opr, group(rhs)),
+                Node::UnaryOp { opr, value, .. } => format!("({}{})", opr, group(value)),
+                Node::FuncCall { func, args, .. } => {
+                    format!("{}({})", group(func), args.iter().map(|arg| group(arg)).collect::<Vec<_>>().join(", "))
+                }
+                Node::MemLockup { obj, targ, .. } => format!("({}.{})", group(obj), group(targ)),
+                Node::CodeLockup { obj, targ, .. } => format!("({}::{})", group(obj), group(targ)),
+                Node::Int { value, .. } | Node::Var { value, .. } | Node::Bool { value, .. } => value.clone(),
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+
+        let source = format!("func main() {{\n    let x = {};\n}}\n", expr);
+        let (nodes, diagnostics) = Scanner::new(Lexer::new(source, "main.mx".to_owned()), false).scan();
+        assert!(!diagnostics.has_errors(), "{:?}", diagnostics);
+        match nodes[0].as_ref() {
+            Node::FuncDef { body, .. } => match body[0].as_ref() {
+                Node::LetDef { value, .. } => group(value),
+                other => panic!("unexpected {:?}", other),
+            },
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    // the top-level items of `source`, which must parse cleanly
+    fn items(source: &str) -> Vec<Box<Node>> {
+        let (nodes, diagnostics) = Scanner::new(Lexer::new(source.to_owned(), "main.mx".to_owned()), false).scan();
+        assert!(!diagnostics.has_errors(), "{:?}", diagnostics);
+        nodes
+    }
+
+    fn errors(source: &str) -> Vec<(usize, usize, &'static str)> {
+        let (_, diagnostics) = Scanner::new(Lexer::new(source.to_owned(), "main.mx".to_owned()), false).scan();
+        diagnostics.iter().map(|d| (d.primary.line, d.primary.column, d.code)).collect()
+    }
+
+    #[test]
+    fn recovers_from_a_bad_match_arm() {
+        let source = "func main() {\n    match x {\n        1 {\n        }\n        10 => {\n            f(1);\n        }\n        default {\n        }\n    }\n    let y = 1;\n}\n\nfunc g() {\n}\n";
+        assert_eq!(errors(source), [(5, 12, "E0202")]);
+    }
+
+    #[test]
+    fn recovers_at_the_next_item() {
+        let source = "let x = 1 + 2;\noops here;\n\nfunc main() {\n}\n\npublic let y = 2;\nstruct S {\n}\n";
+        assert_eq!(errors(source), [(1, 1, "E0201"), (7, 8, "E0201")]);
+    }
+
+    #[test]
+    fn binds_by_precedence() {
+        assert_eq!(grouped("a || b && c"), "(a || (b && c))");
+        assert_eq!(grouped("a == b || c < d"), "((a == b) || (c < d))");
+        assert_eq!(grouped("a & 1 == 0"), "((a & 1) == 0)");
+        assert_eq!(grouped("a << 1 + 2"), "(a << (1 + 2))");
+        assert_eq!(grouped("1 + 2 * 3"), "(1 + (2 * 3))");
+        assert_eq!(grouped("2 * 3 ^ 2"), "(2 * (3 ^ 2))");
+        assert_eq!(grouped("-a ^ 2"), "((-a) ^ 2)");
+        assert_eq!(grouped("!a && b"), "((!a) && b)");
+    }
+
+    #[test]
+    fn associates_power_to_the_right_and_the_rest_to_the_left() {
+        assert_eq!(grouped("a - b - c"), "((a - b) - c)");
+        assert_eq!(grouped("a / b * c"), "((a / b) * c)");
+        assert_eq!(grouped("a || b || c"), "((a || b) || c)");
+        assert_eq!(grouped("2 ^ 3 ^ 2"), "(2 ^ (3 ^ 2))");
+    }
+
+    #[test]
+    fn chains_calls_and_lookups() {
+        assert_eq!(grouped("f(1)(2)"), "f(1)(2)");
+        assert_eq!(grouped("a.b(1).c()"), "((a.b)(1).c)()");
+        assert_eq!(grouped("std::max(1, g(2)) + 1"), "((std::max)(1, g(2)) + 1)");
+        assert_eq!(grouped("-f(x)"), "(-f(x))");
+    }
+
+    #[test]
+    fn parses_imports_uses_and_aliases() {
+        fn path(segments: &[Box<Node>]) -> Vec<&str> {
+            segments.iter().map(|segment| match segment.as_ref() {
+                Node::Var { value, .. } => value.as_str(),
+                other => panic!("unexpected {:?}", other),
+            }).collect()
+        }
+
+        let nodes = items("import std;
+import net::http;
+import a::b::{c, d};
+use geo::{Point, area};
+alias Names = List<str>;
+");
+        let shown: Vec<String> = nodes.iter().map(|node| match node.as_ref() {
+            Node::Import { package, module, .. } => format!("import {:?} {:?}", path(package), path(module)),
+            Node::Use { package, module, .. } => format!("use {:?} {:?}", path(package), path(module)),
+            Node::Alias { name, real, .. } => match real.as_ref() {
+                Node::GenericType { name: real, params, .. } => format!("alias {} {:?} {:?}", name, path(std::slice::from_ref(real)), path(params)),
+                other => panic!("unexpected {:?}", other),
+            },
+            other => panic!("unexpected {:?}", other),
+        }).collect();
+        assert_eq!(shown, [
+            r#"import [] ["std"]"#,
+            r#"import ["net"] ["http"]"#,
+            r#"import ["a", "b"] ["c", "d"]"#,
+            r#"use ["geo"] ["Point", "area"]"#,
+            r#"alias Names ["List"] ["str"]"#,
+        ]);
+    }
+
+    #[test]
+    fn parses_attached_methods() {
+        let nodes = items("struct Point {\n    x: int;\n}\n\npublic func Point.scaled(by: int) -> int {\n    return self.x * by;\n}\n");
+        let Node::Attach { name, attach_to, public, args, rtype, body, .. } = nodes[1].as_ref() else {
+            panic!("expected an attached method, found {:?}", nodes[1]);
+        };
+        assert_eq!(name, "scaled");
+        assert!(matches!(attach_to.as_ref(), Node::Var { value, .. } if value == "Point"));
+        assert!(*public);
+        assert_eq!(args.len(), 1);
+        assert!(matches!(rtype.as_ref(), Node::Var { value, .. } if value == "int"));
+        assert!(matches!(body[0].as_ref(), Node::Return { .. }));
+    }
+
+    #[test]
+    fn keeps_parsing_past_an_unknown_character() {
+        let source = "func main() {\n    let x = 1 \u{20ac} 2;\n    let y = ;\n}\n\nfunc g() {\n}\n";
+        assert_eq!(errors(source), [(2, 14, "E0202"), (2, 15, "E0101"), (2, 17, "E0201"), (3, 13, "E0204")]);
+    }
+}
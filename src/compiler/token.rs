@@ -62,6 +62,10 @@ pub enum TokenType {
 
     And,
     Or,
+    Pipe,
+    Tilde,
+    ShiftLeft,
+    ShiftRight,
 
     SemiColon,
     Colon,
@@ -0,0 +1,320 @@
+use super::node::{Node, Location};
+use super::resolver::SymbolId;
+
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Int,
+    Long,
+    Float,
+    Double,
+    Bool,
+    Str,
+    Void,
+    Null,
+    List(Box<Type>),
+    Dict(Box<Type>, Box<Type>),
+    Range(Box<Type>),
+    // a struct with its type arguments, empty unless the struct is generic
+    Struct(SymbolId, String, Vec<Type>),
+    Enum(SymbolId, String),
+    Function(Vec<Type>, Box<Type>),
+    // type parameter of the generic struct or function being checked
+    Param(SymbolId, String),
+    // not known statically (imported names, `std` calls) or already reported as an error
+    Unknown,
+    // placeholder the checker fills in from later usage, e.g. the item type of `[]`
+    Infer(usize),
+}
+
+impl Type {
+    pub fn is_unknown(&self) -> bool {
+        matches!(self, Type::Unknown | Type::Infer(_))
+    }
+
+    pub fn has_infer(&self) -> bool {
+        match self {
+            Type::Infer(_) => true,
+            Type::List(item) | Type::Range(item) => item.has_infer(),
+            Type::Dict(key, value) => key.has_infer() || value.has_infer(),
+            Type::Function(params, ret) => params.iter().any(|p| p.has_infer()) || ret.has_infer(),
+            Type::Struct(_, _, args) => args.iter().any(|a| a.has_infer()),
+            _ => false,
+        }
+    }
+
+    // replace type parameters with the types they are instantiated with
+    pub fn instantiate(&self, with: &HashMap<SymbolId, Type>) -> Type {
+        match self {
+            Type::Param(id, _) => with.get(id).cloned().unwrap_or_else(|| self.clone()),
+            Type::List(item) => Type::List(Box::new(item.instantiate(with))),
+            Type::Range(item) => Type::Range(Box::new(item.instantiate(with))),
+            Type::Dict(key, value) => Type::Dict(Box::new(key.instantiate(with)), Box::new(value.instantiate(with))),
+            Type::Function(params, ret) => Type::Function(
+                params.iter().map(|param| param.instantiate(with)).collect(),
+                Box::new(ret.instantiate(with)),
+            ),
+            Type::Struct(id, name, args) => Type::Struct(*id, name.clone(), args.iter().map(|arg| arg.instantiate(with)).collect()),
+            other => other.clone(),
+        }
+    }
+
+    pub fn is_integer(&self) -> bool {
+        matches!(self, Type::Int | Type::Long | Type::Unknown | Type::Infer(_))
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        matches!(self, Type::Int | Type::Long | Type::Float | Type::Double | Type::Unknown | Type::Infer(_))
+    }
+
+    pub fn is_bool(&self) -> bool {
+        matches!(self, Type::Bool | Type::Unknown | Type::Infer(_))
+    }
+
+    // numeric widening allowed without an explicit conversion
+    fn widens_to(&self, to: &Type) -> bool {
+        matches!(
+            (self, to),
+            (Type::Int, Type::Long) |
+            (Type::Int, Type::Float) |
+            (Type::Int, Type::Double) |
+            (Type::Long, Type::Double) |
+            (Type::Float, Type::Double)
+        )
+    }
+
+    pub fn is_assignable_to(&self, to: &Type) -> bool {
+        if self.is_unknown() || to.is_unknown() || self == to || self.widens_to(to) {
+            return true;
+        }
+
+        match (self, to) {
+            (Type::Null, Type::Str | Type::List(_) | Type::Dict(_, _) | Type::Struct(_, _, _)) => true,
+            (Type::List(a), Type::List(b)) => a.is_assignable_to(b) && b.is_assignable_to(a),
+            (Type::Dict(ak, av), Type::Dict(bk, bv)) => {
+                ak.is_assignable_to(bk) && bk.is_assignable_to(ak) &&
+                av.is_assignable_to(bv) && bv.is_assignable_to(av)
+            }
+            (Type::Range(a), Type::Range(b)) => a.is_assignable_to(b),
+            (Type::Struct(a, _, xs), Type::Struct(b, _, ys)) => {
+                a == b && xs.len() == ys.len() &&
+                xs.iter().zip(ys).all(|(x, y)| x.is_assignable_to(y) && y.is_assignable_to(x))
+            }
+            (Type::Function(ap, ar), Type::Function(bp, br)) => {
+                ap.len() == bp.len() && ar.is_assignable_to(br) &&
+                ap.iter().zip(bp).all(|(a, b)| b.is_assignable_to(a))
+            }
+            _ => false,
+        }
+    }
+
+    // the common type of two numeric operands, e.g. `int + double` is `double`
+    pub fn promote(&self, other: &Type) -> Type {
+        if self.is_unknown() || other.is_unknown() {
+            return Type::Unknown;
+        }
+
+        if self.widens_to(other) {
+            other.clone()
+        } else if other.widens_to(self) || self == other {
+            self.clone()
+        } else {
+            // long with float: the only pair without a lossless common type below double
+            Type::Double
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Long => write!(f, "long"),
+            Type::Float => write!(f, "float"),
+            Type::Double => write!(f, "double"),
+            Type::Bool => write!(f, "bool"),
+            Type::Str => write!(f, "str"),
+            Type::Void => write!(f, "void"),
+            Type::Null => write!(f, "null"),
+            Type::List(item) => write!(f, "List<{}>", item),
+            Type::Dict(key, value) => write!(f, "Dict<{}, {}>", key, value),
+            Type::Range(item) => write!(f, "Range<{}>", item),
+            Type::Struct(_, name, args) if !args.is_empty() => {
+                write!(f, "{}<", name)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ">")
+            }
+            Type::Struct(_, name, _) | Type::Enum(_, name) | Type::Param(_, name) => write!(f, "{}", name),
+            Type::Function(params, ret) => {
+                write!(f, "func(")?;
+                for (i, param) in params.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", param)?;
+                }
+                write!(f, ") -> {}", ret)
+            }
+            Type::Unknown => write!(f, "{{unknown}}"),
+            Type::Infer(_) => write!(f, "_"),
+        }
+    }
+}
+
+// what `let x: T;` holds before anything is assigned
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DefaultValue {
+    Int,
+    Float,
+    Bool,
+    Str,
+    List,
+    Dict,
+    Null,
+}
+
+// what `Owner::name` in an expression refers to
+#[derive(Debug, Clone, PartialEq)]
+pub enum Path {
+    // `Enum::Variant`, by the name the enum was declared with
+    Variant(String, String),
+    // `Struct::method`
+    Method(String, String),
+}
+
+// types the checker assigned to expressions and declarations, for later stages
+#[derive(Debug, Clone, Default)]
+pub struct TypeTable {
+    exprs: HashMap<(String, usize, usize, &'static str), Type>,
+    symbols: HashMap<SymbolId, Type>,
+    declarations: HashMap<(String, usize), SymbolId>,
+}
+
+impl TypeTable {
+    fn key(node: &Node, loc: &Location) -> (String, usize, usize, &'static str) {
+        // several nodes can share a token (`a.b` and `b`), the variant tells them apart
+        let kind = match node {
+            Node::MemLockup { .. } => "member",
+            Node::CodeLockup { .. } => "path",
+            Node::Range { .. } => "range",
+            Node::FuncCall { .. } => "call",
+            _ => "expr",
+        };
+        (loc.file.clone(), loc.start, loc.end, kind)
+    }
+
+    pub fn set_expr(&mut self, node: &Node, ty: Type) {
+        if let Some(loc) = node.loc() {
+            self.exprs.insert(Self::key(node, loc), ty);
+        }
+    }
+
+    pub fn type_of(&self, node: &Node) -> Option<&Type> {
+        node.loc().and_then(|loc| self.exprs.get(&Self::key(node, loc)))
+    }
+
+    pub fn set_symbol(&mut self, id: SymbolId, ty: Type) {
+        self.symbols.insert(id, ty);
+    }
+
+    pub fn symbol(&self, id: SymbolId) -> Option<&Type> {
+        self.symbols.get(&id)
+    }
+
+    pub fn set_declaration(&mut self, file: String, start: usize, id: SymbolId) {
+        self.declarations.insert((file, start), id);
+    }
+
+    // the type of what the node at `loc` declares: a variable, parameter, field or function
+    pub fn declared(&self, loc: &Location) -> Option<&Type> {
+        self.declarations.get(&(loc.file.clone(), loc.start)).and_then(|id| self.symbols.get(id))
+    }
+
+    // the default of the variable declared at `loc`, by its checked type so that every backend
+    // agrees, also when it was declared through an alias
+    pub fn default_value(&self, loc: &Location) -> DefaultValue {
+        match self.declared(loc) {
+            Some(Type::Int | Type::Long) => DefaultValue::Int,
+            Some(Type::Float | Type::Double) => DefaultValue::Float,
+            Some(Type::Bool) => DefaultValue::Bool,
+            Some(Type::Str) => DefaultValue::Str,
+            Some(Type::List(_)) => DefaultValue::List,
+            Some(Type::Dict(_, _)) => DefaultValue::Dict,
+            _ => DefaultValue::Null,
+        }
+    }
+
+    // what the `Owner::name` expression `node` refers to, a variant when the checker typed it as
+    // its enum and a method otherwise
+    pub fn path(&self, node: &Node) -> Option<Path> {
+        let Node::CodeLockup { targ, obj, .. } = node else {
+            return None;
+        };
+        let (Node::Var { value: owner, .. }, Node::Var { value: name, .. }) = (obj.as_ref(), targ.as_ref()) else {
+            return None;
+        };
+
+        match self.type_of(node) {
+            Some(Type::Enum(_, declared)) => Some(Path::Variant(declared.clone(), name.clone())),
+            _ => Some(Path::Method(owner.clone(), name.clone())),
+        }
+    }
+
+    pub fn map_types(&mut self, mut f: impl FnMut(&Type) -> Type) {
+        for ty in self.exprs.values_mut().chain(self.symbols.values_mut()) {
+            *ty = f(ty);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn let_loc(nodes: &[Box<Node>], name: &str) -> Location {
+        let Node::FuncDef { body, .. } = nodes.last().unwrap().as_ref() else {
+            panic!("expected `main` last");
+        };
+        body.iter().find_map(|node| match node.as_ref() {
+            Node::LetDef { name: declared, loc, .. } if declared == name => Some(loc.clone()),
+            _ => None,
+        }).unwrap()
+    }
+
+    #[test]
+    fn defaults_follow_the_checked_type_through_aliases() {
+        let (nodes, types) = crate::compiler::analyze(
+            "alias Num = int; alias Names = List<str>; func main() { let a: Num; let b: Names; let c: double; let d: bool; }",
+        );
+        assert_eq!(types.default_value(&let_loc(&nodes, "a")), DefaultValue::Int);
+        assert_eq!(types.default_value(&let_loc(&nodes, "b")), DefaultValue::List);
+        assert_eq!(types.default_value(&let_loc(&nodes, "c")), DefaultValue::Float);
+        assert_eq!(types.default_value(&let_loc(&nodes, "d")), DefaultValue::Bool);
+    }
+
+    #[test]
+    fn paths_are_variants_or_methods_by_their_checked_type() {
+        let (nodes, types) = crate::compiler::analyze(
+            "enum Color { Red } struct Point { x: int; } public func Point.zero() -> int { return 0; } \
+             func main() { let c = Color::Red; let f = Point::zero; }",
+        );
+        let Node::FuncDef { body, .. } = nodes.last().unwrap().as_ref() else {
+            panic!("expected `main` last");
+        };
+        let paths: Vec<Option<Path>> = body.iter().map(|node| match node.as_ref() {
+            Node::LetDef { value, .. } => types.path(value),
+            _ => None,
+        }).collect();
+        assert_eq!(paths, [
+            Some(Path::Variant("Color".to_owned(), "Red".to_owned())),
+            Some(Path::Method("Point".to_owned(), "zero".to_owned())),
+        ]);
+    }
+}
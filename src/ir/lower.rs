@@ -0,0 +1,836 @@
+use super::{BinaryOp, Block, BlockId, Constant, Function, Inst, Op, Program, Terminator, UnaryOp, Value};
+use crate::compiler::node::{Location, Node};
+use crate::compiler::types::{DefaultValue, Path, Type, TypeTable};
+
+use std::collections::{HashMap, HashSet};
+
+// a source variable or one the lowering introduces, which holds a different SSA value per block
+type Var = usize;
+
+struct Loop {
+    // where `continue` jumps
+    next: BlockId,
+    // where `break` jumps
+    exit: BlockId,
+}
+
+// a declared function whose body is lowered once every name is known
+struct Pending<'n> {
+    name: String,
+    owner: Option<String>,
+    args: &'n [Box<Node>],
+    body: &'n [Box<Node>],
+    loc: &'n Location,
+}
+
+// lowers the checked tree into SSA form one function at a time, placing phis while the blocks
+// are filled in as in Braun et al., "Simple and Efficient Construction of SSA Form"
+pub struct Lowerer<'a> {
+    types: &'a TypeTable,
+    functions: HashSet<String>,
+    // the instance type of each struct
+    structs: HashMap<String, Type>,
+    enums: HashMap<String, Vec<String>>,
+    methods: HashSet<(String, String)>,
+    // state of the function being lowered
+    blocks: Vec<Block>,
+    value_types: Vec<Type>,
+    current: BlockId,
+    loc: Option<Location>,
+    scopes: Vec<HashMap<String, Var>>,
+    vars: Vec<Type>,
+    defs: HashMap<(Var, BlockId), Value>,
+    // phis of blocks whose predecessors are not all known yet
+    incomplete: HashMap<BlockId, Vec<(Var, Value)>>,
+    sealed: HashSet<BlockId>,
+    preds: HashMap<BlockId, Vec<BlockId>>,
+    loops: Vec<Loop>,
+}
+
+impl<'a> Lowerer<'a> {
+    pub fn lower(nodes: &[Box<Node>], types: &'a TypeTable, file: &str) -> Program {
+        let mut lowerer = Self {
+            types,
+            functions: HashSet::new(),
+            structs: HashMap::new(),
+            enums: HashMap::new(),
+            methods: HashSet::new(),
+            blocks: Vec::new(),
+            value_types: Vec::new(),
+            current: BlockId(0),
+            loc: None,
+            scopes: Vec::new(),
+            vars: Vec::new(),
+            defs: HashMap::new(),
+            incomplete: HashMap::new(),
+            sealed: HashSet::new(),
+            preds: HashMap::new(),
+            loops: Vec::new(),
+        };
+
+        // everything is declared up front so calls can refer to later definitions
+        let mut bodies = Vec::new();
+        for node in nodes {
+            lowerer.declare(node, &mut bodies);
+        }
+
+        let functions = bodies.into_iter().map(|pending| lowerer.function(pending)).collect();
+        Program {
+            file: file.to_owned(),
+            functions,
+        }
+    }
+
+    fn declare<'n>(&mut self, node: &'n Node, bodies: &mut Vec<Pending<'n>>) {
+        match node {
+            Node::FuncDef { name, args, body, loc, .. } => {
+                self.functions.insert(name.clone());
+                bodies.push(Pending { name: name.clone(), owner: None, args, body, loc });
+            }
+            Node::Struct { name, body, loc, .. } => {
+                let instance = match self.types.declared(loc) {
+                    Some(Type::Function(_, instance)) => instance.as_ref().clone(),
+                    _ => Type::Unknown,
+                };
+                self.structs.insert(name.clone(), instance);
+
+                for method in body {
+                    if let Node::FuncDef { name: method, args, body, loc, .. } = method.as_ref() {
+                        self.methods.insert((name.clone(), method.clone()));
+                        bodies.push(Pending { name: format!("{}.{}", name, method), owner: Some(name.clone()), args, body, loc });
+                    }
+                }
+            }
+            Node::Attach { name, attach_to, args, body, loc, .. } => {
+                if let Node::Var { value: owner, .. } = attach_to.as_ref() {
+                    self.methods.insert((owner.clone(), name.clone()));
+                    bodies.push(Pending { name: format!("{}.{}", owner, name), owner: Some(owner.clone()), args, body, loc });
+                }
+            }
+            Node::Enum { name, child, .. } => {
+                let variants = child.iter().filter_map(|variant| match variant.as_ref() {
+                    Node::Var { value, .. } => Some(value.clone()),
+                    _ => None,
+                }).collect();
+                self.enums.insert(name.clone(), variants);
+            }
+            _ => {}
+        }
+    }
+
+    fn function(&mut self, Pending { name, owner, args, body, loc }: Pending) -> Function {
+        self.blocks.clear();
+        self.value_types.clear();
+        self.scopes = vec![HashMap::new()];
+        self.vars.clear();
+        self.defs.clear();
+        self.incomplete.clear();
+        self.sealed.clear();
+        self.preds.clear();
+        self.loops.clear();
+        self.loc = Some(loc.clone());
+
+        let entry = self.new_block();
+        self.seal(entry);
+        self.current = entry;
+
+        let mut params = Vec::new();
+        if let Some(owner) = owner {
+            let ty = self.structs.get(&owner).cloned().unwrap_or(Type::Unknown);
+            params.push(self.param("self", ty));
+        }
+        for arg in args {
+            if let Node::LetDef { name, loc, .. } = arg.as_ref() {
+                let ty = self.types.declared(loc).cloned().unwrap_or(Type::Unknown);
+                params.push(self.param(name, ty));
+            }
+        }
+
+        let result = match self.types.declared(loc) {
+            Some(Type::Function(_, result)) => result.as_ref().clone(),
+            _ => Type::Unknown,
+        };
+
+        self.block(body);
+
+        // falling off the end returns void
+        if !self.dead() {
+            let void = self.constant(Constant::Void, Type::Void);
+            self.terminate(Terminator::Return(void));
+        }
+
+        let mut function = Function {
+            name,
+            params,
+            result,
+            blocks: std::mem::take(&mut self.blocks),
+            values: self.value_types.len() as u32,
+        };
+        Self::finish(&mut function);
+        function
+    }
+
+    fn param(&mut self, name: &str, ty: Type) -> (Value, Type) {
+        let value = self.fresh(ty.clone());
+        let var = self.declare_var(name, ty.clone());
+        self.write(var, self.current, value);
+        (value, ty)
+    }
+
+    // drops the empty blocks left behind after jumps and the phis that turned out to be copies
+    fn finish(function: &mut Function) {
+        let preds = function.predecessors();
+        let entry = function.blocks.first().map(|block| block.id);
+        function.blocks.retain(|block| {
+            Some(block.id) == entry
+                || !preds[&block.id].is_empty()
+                || !block.insts.is_empty()
+                || !matches!(block.term, Terminator::Unreachable)
+        });
+
+        while let Some((block, index, same)) = Self::trivial_phi(function) {
+            let phi = function.blocks[block].insts.remove(index).result;
+            function.replace_uses(phi, same);
+        }
+
+        function.renumber();
+    }
+
+    // a phi whose operands are all one value apart from itself
+    fn trivial_phi(function: &Function) -> Option<(usize, usize, Value)> {
+        for (block_index, block) in function.blocks.iter().enumerate() {
+            for (index, inst) in block.insts.iter().enumerate() {
+                let Op::Phi(incoming) = &inst.op else {
+                    continue;
+                };
+
+                let mut others = incoming.iter().map(|(_, value)| *value).filter(|value| *value != inst.result);
+                if let Some(same) = others.next()
+                    && others.all(|value| value == same)
+                {
+                    return Some((block_index, index, same));
+                }
+            }
+        }
+        None
+    }
+
+    fn block(&mut self, body: &[Box<Node>]) {
+        self.scopes.push(HashMap::new());
+        for node in body {
+            self.stmt(node);
+        }
+        self.scopes.pop();
+    }
+
+    fn stmt(&mut self, node: &Node) {
+        if let Some(loc) = node.loc() {
+            self.loc = Some(loc.clone());
+        }
+
+        match node {
+            Node::LetDef { name, value, loc, .. } => {
+                let declared = self.types.declared(loc).cloned();
+                let value = match value.as_ref() {
+                    Node::Void => self.default_value(self.types.default_value(loc), declared.clone().unwrap_or(Type::Unknown)),
+                    value => self.expr(value),
+                };
+                let ty = declared.unwrap_or_else(|| self.value_type(value));
+                let var = self.declare_var(name, ty);
+                self.write(var, self.current, value);
+            }
+            Node::AssignDef { targ, value, opr, loc } => self.assign(targ, value, opr, loc),
+            Node::Return { value, loc } => {
+                let value = self.expr(value);
+                self.loc = Some(loc.clone());
+                self.terminate(Terminator::Return(value));
+            }
+            Node::Conditional { cond, body_if, body_else, loc } => {
+                let cond = self.expr(cond);
+                let then = self.new_block();
+                let otherwise = (!body_else.is_empty()).then(|| self.new_block());
+                let end = self.new_block();
+
+                self.loc = Some(loc.clone());
+                self.terminate(Terminator::Branch(cond, then, otherwise.unwrap_or(end)));
+
+                self.enter(then);
+                self.block(body_if);
+                self.terminate(Terminator::Jump(end));
+
+                if let Some(otherwise) = otherwise {
+                    self.enter(otherwise);
+                    self.block(body_else);
+                    self.terminate(Terminator::Jump(end));
+                }
+
+                self.enter(end);
+            }
+            Node::WhileLoop { cond, body, loc } => {
+                let header = self.new_block();
+                self.terminate(Terminator::Jump(header));
+                self.current = header;
+
+                let cond = self.expr(cond);
+                let body_block = self.new_block();
+                let exit = self.new_block();
+                self.loc = Some(loc.clone());
+                self.terminate(Terminator::Branch(cond, body_block, exit));
+
+                self.enter(body_block);
+                self.loops.push(Loop { next: header, exit });
+                self.block(body);
+                self.terminate(Terminator::Jump(header));
+                self.loops.pop();
+
+                self.seal(header);
+                self.enter(exit);
+            }
+            Node::ForLoop { initializer, iterator, body, loc } => {
+                let Node::LetDef { name, loc: name_loc, .. } = initializer.as_ref() else {
+                    return;
+                };
+                let item = self.types.declared(name_loc).cloned().unwrap_or(Type::Unknown);
+
+                self.scopes.push(HashMap::new());
+                match iterator.as_ref() {
+                    Node::Range { min, max, .. } if self.typed(min, Type::Unknown) == Type::Int && self.typed(max, Type::Unknown) == Type::Int => {
+                        self.range_loop(name, item, min, max, body, loc);
+                    }
+                    iterator => self.iter_loop(name, item, iterator, body, loc),
+                }
+                self.scopes.pop();
+            }
+            Node::MatchCase { value, child, default, .. } => {
+                let subject = self.expr(value);
+                let end = self.new_block();
+
+                for arm in child {
+                    let pattern = self.expr(&arm.0);
+                    let matched = self.emit(Op::Binary(BinaryOp::Eq, subject, pattern), Type::Bool);
+                    let then = self.new_block();
+                    let next = self.new_block();
+                    self.terminate(Terminator::Branch(matched, then, next));
+
+                    self.enter(then);
+                    self.block(&arm.1);
+                    self.terminate(Terminator::Jump(end));
+                    self.enter(next);
+                }
+
+                self.block(default);
+                self.terminate(Terminator::Jump(end));
+                self.enter(end);
+            }
+            Node::Break { .. } => {
+                if let Some(exit) = self.loops.last().map(|current| current.exit) {
+                    self.terminate(Terminator::Jump(exit));
+                }
+            }
+            Node::Continue { .. } => {
+                if let Some(next) = self.loops.last().map(|current| current.next) {
+                    self.terminate(Terminator::Jump(next));
+                }
+            }
+            _ => {
+                self.expr(node);
+            }
+        }
+    }
+
+    // `for i in a->b` counts in a hidden variable, so assigning to `i` does not change the iteration
+    fn range_loop(&mut self, name: &str, item: Type, min: &Node, max: &Node, body: &[Box<Node>], loc: &Location) {
+        let min = self.expr(min);
+        let max = self.expr(max);
+        self.loc = Some(loc.clone());
+        let counter = self.hidden_var(Type::Int);
+        self.write(counter, self.current, min);
+
+        let header = self.new_block();
+        self.terminate(Terminator::Jump(header));
+        self.current = header;
+
+        let position = self.read(counter, header);
+        let more = self.emit(Op::Binary(BinaryOp::Lt, position, max), Type::Bool);
+        let body_block = self.new_block();
+        let step = self.new_block();
+        let exit = self.new_block();
+        self.terminate(Terminator::Branch(more, body_block, exit));
+
+        self.enter(body_block);
+        let var = self.declare_var(name, item);
+        self.write(var, body_block, position);
+        self.loops.push(Loop { next: step, exit });
+        self.block(body);
+        self.terminate(Terminator::Jump(step));
+        self.loops.pop();
+
+        self.enter(step);
+        self.loc = Some(loc.clone());
+        let position = self.read(counter, step);
+        let one = self.constant(Constant::Int(1), Type::Int);
+        let next = self.emit(Op::Binary(BinaryOp::Add, position, one), Type::Int);
+        self.write(counter, step, next);
+        self.terminate(Terminator::Jump(header));
+
+        self.seal(header);
+        self.enter(exit);
+    }
+
+    // other iterables walk a snapshot, so pushing to a list inside the body does not loop forever
+    fn iter_loop(&mut self, name: &str, item: Type, iterator: &Node, body: &[Box<Node>], loc: &Location) {
+        let iterable = self.expr(iterator);
+        self.loc = Some(loc.clone());
+        let iter = self.emit(Op::IterInit(iterable), Type::List(Box::new(item.clone())));
+
+        let header = self.new_block();
+        self.terminate(Terminator::Jump(header));
+        self.current = header;
+
+        let more = self.emit(Op::IterHas(iter), Type::Bool);
+        let body_block = self.new_block();
+        let exit = self.new_block();
+        self.terminate(Terminator::Branch(more, body_block, exit));
+
+        self.enter(body_block);
+        let value = self.emit(Op::IterNext(iter), item.clone());
+        let var = self.declare_var(name, item);
+        self.write(var, body_block, value);
+        self.loops.push(Loop { next: header, exit });
+        self.block(body);
+        self.terminate(Terminator::Jump(header));
+        self.loops.pop();
+
+        self.seal(header);
+        self.enter(exit);
+    }
+
+    fn assign(&mut self, targ: &Node, value: &Node, opr: &str, loc: &Location) {
+        let opr = opr.trim_end_matches('=');
+
+        match targ {
+            Node::Var { value: name, loc: name_loc } => {
+                let Some(var) = self.local(name) else {
+                    self.loc = Some(name_loc.clone());
+                    self.fail(format!("cannot assign to `{}`", name));
+                    return;
+                };
+
+                let value = if opr.is_empty() {
+                    self.expr(value)
+                } else {
+                    let current = self.read(var, self.current);
+                    let value = self.expr(value);
+                    self.loc = Some(loc.clone());
+                    self.binary(opr, current, value, self.vars[var].clone())
+                };
+                self.write(var, self.current, value);
+            }
+            Node::MemLockup { targ: field, obj, .. } => {
+                let Node::Var { value: field, loc: field_loc } = field.as_ref() else {
+                    self.loc = Some(loc.clone());
+                    self.fail("invalid assignment target");
+                    return;
+                };
+
+                let object = self.expr(obj);
+                let value = if opr.is_empty() {
+                    self.expr(value)
+                } else {
+                    let ty = self.typed(targ, Type::Unknown);
+                    self.loc = Some(field_loc.clone());
+                    let current = self.emit(Op::GetField(object, field.clone()), ty.clone());
+                    let value = self.expr(value);
+                    self.loc = Some(loc.clone());
+                    self.binary(opr, current, value, ty)
+                };
+                self.loc = Some(field_loc.clone());
+                self.emit(Op::SetField(object, field.clone(), value), Type::Void);
+            }
+            _ => {
+                self.loc = Some(loc.clone());
+                self.fail("invalid assignment target");
+            }
+        }
+    }
+
+    // the value of `let x: T;` before anything is assigned, typed as the variable is declared
+    fn default_value(&mut self, default: DefaultValue, declared: Type) -> Value {
+        match default {
+            DefaultValue::Int => self.constant(Constant::Int(0), declared),
+            DefaultValue::Float => self.constant(Constant::Float(0.0), declared),
+            DefaultValue::Bool => self.constant(Constant::Bool(false), Type::Bool),
+            DefaultValue::Str => self.constant(Constant::Str(String::new()), Type::Str),
+            DefaultValue::List => self.emit(Op::List(Vec::new()), declared),
+            DefaultValue::Dict => self.emit(Op::Dict(Vec::new()), declared),
+            DefaultValue::Null => self.constant(Constant::Null, Type::Null),
+        }
+    }
+
+    fn expr(&mut self, node: &Node) -> Value {
+        if let Some(loc) = node.loc() {
+            self.loc = Some(loc.clone());
+        }
+
+        match node {
+            Node::Int { value, lbit, .. } => match value.parse() {
+                Ok(value) => {
+                    let ty = self.typed(node, if *lbit { Type::Long } else { Type::Int });
+                    self.constant(Constant::Int(value), ty)
+                }
+                Err(_) => self.fail(format!("invalid integer literal `{}`", value)),
+            },
+            Node::Float { value, lbit, .. } => match value.parse() {
+                Ok(value) => {
+                    let ty = self.typed(node, if *lbit { Type::Double } else { Type::Float });
+                    self.constant(Constant::Float(value), ty)
+                }
+                Err(_) => self.fail(format!("invalid float literal `{}`", value)),
+            },
+            Node::Bool { value, .. } => self.constant(Constant::Bool(value == "true"), Type::Bool),
+            Node::Str { value, .. } => self.constant(Constant::Str(value.clone()), Type::Str),
+            Node::Null { .. } => self.constant(Constant::Null, Type::Null),
+            Node::Void => self.constant(Constant::Void, Type::Void),
+            Node::Var { value, .. } => {
+                if let Some(var) = self.local(value) {
+                    self.read(var, self.current)
+                } else if self.functions.contains(value) {
+                    let ty = self.typed(node, Type::Unknown);
+                    self.emit(Op::Function(value.clone()), ty)
+                } else if self.structs.contains_key(value) {
+                    let ty = self.typed(node, Type::Unknown);
+                    self.emit(Op::Type(value.clone()), ty)
+                } else {
+                    self.fail(format!("`{}` is not available at runtime", value))
+                }
+            }
+            Node::List { element, .. } => {
+                let items = self.args(element);
+                let ty = self.typed(node, Type::Unknown);
+                self.emit(Op::List(items), ty)
+            }
+            Node::Dict { key_value, loc } => {
+                let mut pairs = Vec::new();
+                for pair in key_value {
+                    let key = self.expr(&pair.0);
+                    let value = self.expr(&pair.1);
+                    pairs.push((key, value));
+                }
+                self.loc = Some(loc.clone());
+                let ty = self.typed(node, Type::Unknown);
+                self.emit(Op::Dict(pairs), ty)
+            }
+            Node::Range { min, max, loc } => {
+                let min = self.expr(min);
+                let max = self.expr(max);
+                self.loc = Some(loc.clone());
+                let ty = self.typed(node, Type::Range(Box::new(Type::Int)));
+                self.emit(Op::Range(min, max), ty)
+            }
+            Node::MemLockup { targ, obj, .. } => {
+                let Node::Var { value: field, loc } = targ.as_ref() else {
+                    return self.constant(Constant::Void, Type::Void);
+                };
+
+                let object = self.expr(obj);
+                self.loc = Some(loc.clone());
+                let ty = self.typed(node, Type::Unknown);
+                self.emit(Op::GetField(object, field.clone()), ty)
+            }
+            Node::CodeLockup { .. } => self.path(node),
+            Node::FuncCall { func, args, loc } => self.call(node, func, args, loc),
+            Node::BinaryOp { lhs, rhs, opr, loc } => {
+                if opr == "&&" || opr == "||" {
+                    return self.logical(lhs, rhs, opr == "&&", loc);
+                }
+
+                let lhs = self.expr(lhs);
+                let rhs = self.expr(rhs);
+                self.loc = Some(loc.clone());
+                let fallback = match BinaryOp::from_symbol(opr) {
+                    Some(op) if op.is_comparison() => Type::Bool,
+                    _ => self.value_type(lhs),
+                };
+                let ty = self.typed(node, fallback);
+                self.binary(opr, lhs, rhs, ty)
+            }
+            Node::UnaryOp { opr, value, loc } => {
+                let value = self.expr(value);
+                self.loc = Some(loc.clone());
+                match UnaryOp::from_symbol(opr) {
+                    Some(op) => {
+                        let ty = self.typed(node, self.value_type(value));
+                        self.emit(Op::Unary(op, value), ty)
+                    }
+                    None => self.fail(format!("unknown unary operator `{}`", opr)),
+                }
+            }
+            _ => self.fail("expression cannot be evaluated"),
+        }
+    }
+
+    fn binary(&mut self, opr: &str, lhs: Value, rhs: Value, ty: Type) -> Value {
+        match BinaryOp::from_symbol(opr) {
+            Some(op) => self.emit(Op::Binary(op, lhs, rhs), ty),
+            None => self.fail(format!("unknown operator `{}`", opr)),
+        }
+    }
+
+    // `&&` and `||` only evaluate the right side when the left one does not decide the result
+    fn logical(&mut self, lhs: &Node, rhs: &Node, and: bool, loc: &Location) -> Value {
+        let result = self.hidden_var(Type::Bool);
+        let left = self.expr(lhs);
+        self.write(result, self.current, left);
+
+        let right_block = self.new_block();
+        let end = self.new_block();
+        self.loc = Some(loc.clone());
+        let (then, otherwise) = if and { (right_block, end) } else { (end, right_block) };
+        self.terminate(Terminator::Branch(left, then, otherwise));
+
+        self.enter(right_block);
+        let right = self.expr(rhs);
+        self.write(result, self.current, right);
+        self.terminate(Terminator::Jump(end));
+
+        self.enter(end);
+        self.read(result, end)
+    }
+
+    // `Enum::Variant` or `Struct::method`
+    fn path(&mut self, node: &Node) -> Value {
+        let ty = self.typed(node, Type::Unknown);
+        match self.types.path(node) {
+            Some(Path::Variant(owner, name)) if self.enums.get(&owner).is_some_and(|variants| variants.contains(&name)) => {
+                self.emit(Op::Variant(owner, name), ty)
+            }
+            Some(Path::Method(owner, name)) if self.methods.contains(&(owner.clone(), name.clone())) => {
+                self.emit(Op::Function(format!("{}.{}", owner, name)), ty)
+            }
+            _ => self.fail("path cannot be evaluated at runtime"),
+        }
+    }
+
+    fn call(&mut self, node: &Node, func: &Node, args: &[Box<Node>], loc: &Location) -> Value {
+        let ty = self.typed(node, Type::Unknown);
+
+        match func {
+            Node::CodeLockup { targ, obj, .. } => {
+                if let (Node::Var { value: name, .. }, Node::Var { value: owner, .. }) = (targ.as_ref(), obj.as_ref()) {
+                    if owner == "std" {
+                        let args = self.args(args);
+                        self.loc = Some(loc.clone());
+                        let ty = match name.as_str() {
+                            "print" | "println" => Type::Void,
+                            "len" => Type::Int,
+                            _ => ty,
+                        };
+                        return self.emit(Op::CallStd(name.clone(), args), ty);
+                    }
+
+                    if self.methods.contains(&(owner.clone(), name.clone())) {
+                        let args = self.args(args);
+                        self.loc = Some(loc.clone());
+                        return self.emit(Op::Call(format!("{}.{}", owner, name), args), ty);
+                    }
+                }
+            }
+            Node::MemLockup { targ, obj, .. } => {
+                if let Node::Var { value: name, loc: name_loc } = targ.as_ref() {
+                    let receiver = self.expr(obj);
+                    let mut args = self.args(args);
+                    self.loc = Some(name_loc.clone());
+
+                    // methods of a struct known statically are called directly
+                    if let Some(Type::Struct(_, owner, _)) = self.types.type_of(obj)
+                        && self.methods.contains(&(owner.clone(), name.clone()))
+                    {
+                        args.insert(0, receiver);
+                        return self.emit(Op::Call(format!("{}.{}", owner, name), args), ty);
+                    }
+                    return self.emit(Op::CallMethod(receiver, name.clone(), args), ty);
+                }
+            }
+            Node::Var { value: name, .. } if self.local(name).is_none() => {
+                if self.functions.contains(name) {
+                    let args = self.args(args);
+                    self.loc = Some(loc.clone());
+                    return self.emit(Op::Call(name.clone(), args), ty);
+                }
+                if self.structs.contains_key(name) {
+                    let args = self.args(args);
+                    self.loc = Some(loc.clone());
+                    return self.emit(Op::New(name.clone(), args), ty);
+                }
+            }
+            _ => {}
+        }
+
+        let callee = self.expr(func);
+        let args = self.args(args);
+        self.loc = Some(loc.clone());
+        self.emit(Op::CallValue(callee, args), ty)
+    }
+
+    fn args(&mut self, args: &[Box<Node>]) -> Vec<Value> {
+        args.iter().map(|arg| self.expr(arg)).collect()
+    }
+
+    // the type the checker gave `node`, if it knows one
+    fn typed(&self, node: &Node, fallback: Type) -> Type {
+        match self.types.type_of(node) {
+            Some(ty) if !ty.is_unknown() => ty.clone(),
+            _ => fallback,
+        }
+    }
+
+    fn value_type(&self, value: Value) -> Type {
+        self.value_types[value.0 as usize].clone()
+    }
+
+    fn local(&self, name: &str) -> Option<Var> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name)).copied()
+    }
+
+    fn declare_var(&mut self, name: &str, ty: Type) -> Var {
+        let var = self.hidden_var(ty);
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_owned(), var);
+        }
+        var
+    }
+
+    // a variable no name refers to, for loop counters and `&&` / `||` results
+    fn hidden_var(&mut self, ty: Type) -> Var {
+        self.vars.push(ty);
+        self.vars.len() - 1
+    }
+
+    fn write(&mut self, var: Var, block: BlockId, value: Value) {
+        self.defs.insert((var, block), value);
+    }
+
+    fn read(&mut self, var: Var, block: BlockId) -> Value {
+        if let Some(value) = self.defs.get(&(var, block)) {
+            return *value;
+        }
+
+        let value = if !self.sealed.contains(&block) {
+            // filled in once every predecessor is known
+            let phi = self.phi(var, block);
+            self.incomplete.entry(block).or_default().push((var, phi));
+            phi
+        } else {
+            let preds = self.preds.get(&block).cloned().unwrap_or_default();
+            match preds.as_slice() {
+                [] => {
+                    let ty = self.vars[var].clone();
+                    self.insert_at_start(block, Op::Undef, ty)
+                }
+                [pred] => self.read(var, *pred),
+                _ => {
+                    // written first so a loop reaching back here finds the phi
+                    let phi = self.phi(var, block);
+                    self.write(var, block, phi);
+                    self.add_operands(var, phi, block);
+                    phi
+                }
+            }
+        };
+
+        self.write(var, block, value);
+        value
+    }
+
+    fn phi(&mut self, var: Var, block: BlockId) -> Value {
+        let ty = self.vars[var].clone();
+        self.insert_at_start(block, Op::Phi(Vec::new()), ty)
+    }
+
+    fn add_operands(&mut self, var: Var, phi: Value, block: BlockId) {
+        let preds = self.preds.get(&block).cloned().unwrap_or_default();
+        for pred in preds {
+            let value = self.read(var, pred);
+            let inst = self.blocks[block.0 as usize].insts.iter_mut().find(|inst| inst.result == phi);
+            if let Some(Inst { op: Op::Phi(incoming), .. }) = inst {
+                incoming.push((pred, value));
+            }
+        }
+    }
+
+    // after the phis already at the start of `block`
+    fn insert_at_start(&mut self, block: BlockId, op: Op, ty: Type) -> Value {
+        let result = self.fresh(ty.clone());
+        let insts = &mut self.blocks[block.0 as usize].insts;
+        let at = insts.iter().take_while(|inst| matches!(inst.op, Op::Phi(_))).count();
+        insts.insert(at, Inst { result, ty, op, loc: None });
+        result
+    }
+
+    // every predecessor of `block` is known from here on
+    fn seal(&mut self, block: BlockId) {
+        for (var, phi) in self.incomplete.remove(&block).unwrap_or_default() {
+            self.add_operands(var, phi, block);
+        }
+        self.sealed.insert(block);
+    }
+
+    // continue in `block` once every jump to it was emitted
+    fn enter(&mut self, block: BlockId) {
+        self.seal(block);
+        self.current = block;
+    }
+
+    fn new_block(&mut self) -> BlockId {
+        let id = BlockId(self.blocks.len() as u32);
+        self.blocks.push(Block {
+            id,
+            insts: Vec::new(),
+            term: Terminator::Unreachable,
+        });
+        id
+    }
+
+    // an empty block nothing jumps to, where code after `return`, `break` and `continue` goes
+    fn dead(&self) -> bool {
+        self.current != BlockId(0)
+            && self.preds.get(&self.current).is_none_or(Vec::is_empty)
+            && self.blocks[self.current.0 as usize].insts.is_empty()
+    }
+
+    // ends the current block, anything emitted after it lands in a block nothing jumps to
+    fn terminate(&mut self, term: Terminator) {
+        if self.dead() {
+            return;
+        }
+
+        for target in term.successors() {
+            self.preds.entry(target).or_default().push(self.current);
+        }
+        self.blocks[self.current.0 as usize].term = term;
+
+        let next = self.new_block();
+        self.seal(next);
+        self.current = next;
+    }
+
+    fn constant(&mut self, constant: Constant, ty: Type) -> Value {
+        self.emit(Op::Const(constant), ty)
+    }
+
+    fn fail(&mut self, message: impl Into<String>) -> Value {
+        self.emit(Op::Fail(message.into()), Type::Unknown)
+    }
+
+    fn fresh(&mut self, ty: Type) -> Value {
+        self.value_types.push(ty);
+        Value(self.value_types.len() as u32 - 1)
+    }
+
+    fn emit(&mut self, op: Op, ty: Type) -> Value {
+        let result = self.fresh(ty.clone());
+        let loc = self.loc.clone();
+        self.blocks[self.current.0 as usize].insts.push(Inst { result, ty, op, loc });
+        result
+    }
+}
@@ -0,0 +1,451 @@
+pub mod lower;
+pub mod verify;
+
+use crate::compiler::node::Location;
+use crate::compiler::types::Type;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+// a virtual register, assigned by exactly one instruction or parameter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Value(pub u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockId(pub u32);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+    Null,
+    Void,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+    BitAnd,
+    BitOr,
+    Shl,
+    Shr,
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+    Pos,
+    Not,
+    BitNot,
+}
+
+#[derive(Debug, Clone)]
+pub enum Op {
+    Const(Constant),
+    // a value read on a path where the variable was never assigned, only in unreachable code
+    Undef,
+    // a function or method as a value
+    Function(String),
+    // a struct as a value, calling it builds an instance
+    Type(String),
+    // `Enum::Variant`
+    Variant(String, String),
+    Binary(BinaryOp, Value, Value),
+    Unary(UnaryOp, Value),
+    // direct call of a function, or of a method named `Owner.name` with the receiver first
+    Call(String, Vec<Value>),
+    CallValue(Value, Vec<Value>),
+    // method looked up on the receiver at runtime, e.g. `push` on a list
+    CallMethod(Value, String, Vec<Value>),
+    CallStd(String, Vec<Value>),
+    // an instance of the named struct, fields in declaration order
+    New(String, Vec<Value>),
+    List(Vec<Value>),
+    Dict(Vec<(Value, Value)>),
+    Range(Value, Value),
+    GetField(Value, String),
+    SetField(Value, String, Value),
+    // a snapshot of the items of an iterable, walked by `IterHas` and `IterNext`
+    IterInit(Value),
+    IterHas(Value),
+    IterNext(Value),
+    // the value flowing in from each predecessor, always at the start of a block
+    Phi(Vec<(BlockId, Value)>),
+    // raise a runtime error
+    Fail(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Inst {
+    pub result: Value,
+    pub ty: Type,
+    pub op: Op,
+    pub loc: Option<Location>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Terminator {
+    Jump(BlockId),
+    // condition, then the targets when it is true and false
+    Branch(Value, BlockId, BlockId),
+    Return(Value),
+    Unreachable,
+}
+
+#[derive(Debug, Clone)]
+pub struct Block {
+    pub id: BlockId,
+    pub insts: Vec<Inst>,
+    pub term: Terminator,
+}
+
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub name: String,
+    pub params: Vec<(Value, Type)>,
+    pub result: Type,
+    // the first block is the entry
+    pub blocks: Vec<Block>,
+    // one past the highest value in use
+    pub values: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Program {
+    pub file: String,
+    pub functions: Vec<Function>,
+}
+
+impl BinaryOp {
+    pub fn from_symbol(opr: &str) -> Option<BinaryOp> {
+        let op = match opr {
+            "+" => BinaryOp::Add,
+            "-" => BinaryOp::Sub,
+            "*" => BinaryOp::Mul,
+            "/" => BinaryOp::Div,
+            "%" => BinaryOp::Rem,
+            "^" => BinaryOp::Pow,
+            "&" => BinaryOp::BitAnd,
+            "|" => BinaryOp::BitOr,
+            "<<" => BinaryOp::Shl,
+            ">>" => BinaryOp::Shr,
+            "==" => BinaryOp::Eq,
+            "!=" => BinaryOp::Ne,
+            "<" => BinaryOp::Lt,
+            ">" => BinaryOp::Gt,
+            "<=" => BinaryOp::Le,
+            ">=" => BinaryOp::Ge,
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BinaryOp::Add => "add",
+            BinaryOp::Sub => "sub",
+            BinaryOp::Mul => "mul",
+            BinaryOp::Div => "div",
+            BinaryOp::Rem => "rem",
+            BinaryOp::Pow => "pow",
+            BinaryOp::BitAnd => "and",
+            BinaryOp::BitOr => "or",
+            BinaryOp::Shl => "shl",
+            BinaryOp::Shr => "shr",
+            BinaryOp::Eq => "eq",
+            BinaryOp::Ne => "ne",
+            BinaryOp::Lt => "lt",
+            BinaryOp::Gt => "gt",
+            BinaryOp::Le => "le",
+            BinaryOp::Ge => "ge",
+        }
+    }
+
+    pub fn is_comparison(&self) -> bool {
+        matches!(self, BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Gt | BinaryOp::Le | BinaryOp::Ge)
+    }
+}
+
+impl UnaryOp {
+    pub fn from_symbol(opr: &str) -> Option<UnaryOp> {
+        let op = match opr {
+            "-" => UnaryOp::Neg,
+            "+" => UnaryOp::Pos,
+            "!" => UnaryOp::Not,
+            "~" => UnaryOp::BitNot,
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            UnaryOp::Neg => "neg",
+            UnaryOp::Pos => "pos",
+            UnaryOp::Not => "not",
+            UnaryOp::BitNot => "bitnot",
+        }
+    }
+}
+
+impl Op {
+    pub fn operands(&self) -> Vec<Value> {
+        match self {
+            Op::Const(_) | Op::Undef | Op::Function(_) | Op::Type(_) | Op::Variant(_, _) | Op::Fail(_) => Vec::new(),
+            Op::Unary(_, value) | Op::GetField(value, _) | Op::IterInit(value) | Op::IterHas(value) | Op::IterNext(value) => vec![*value],
+            Op::Binary(_, lhs, rhs) | Op::Range(lhs, rhs) | Op::SetField(lhs, _, rhs) => vec![*lhs, *rhs],
+            Op::Call(_, args) | Op::CallStd(_, args) | Op::New(_, args) | Op::List(args) => args.clone(),
+            Op::CallValue(callee, args) | Op::CallMethod(callee, _, args) => std::iter::once(*callee).chain(args.iter().copied()).collect(),
+            Op::Dict(pairs) => pairs.iter().flat_map(|(key, value)| [*key, *value]).collect(),
+            Op::Phi(incoming) => incoming.iter().map(|(_, value)| *value).collect(),
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> Vec<&mut Value> {
+        match self {
+            Op::Const(_) | Op::Undef | Op::Function(_) | Op::Type(_) | Op::Variant(_, _) | Op::Fail(_) => Vec::new(),
+            Op::Unary(_, value) | Op::GetField(value, _) | Op::IterInit(value) | Op::IterHas(value) | Op::IterNext(value) => vec![value],
+            Op::Binary(_, lhs, rhs) | Op::Range(lhs, rhs) | Op::SetField(lhs, _, rhs) => vec![lhs, rhs],
+            Op::Call(_, args) | Op::CallStd(_, args) | Op::New(_, args) | Op::List(args) => args.iter_mut().collect(),
+            Op::CallValue(callee, args) | Op::CallMethod(callee, _, args) => std::iter::once(callee).chain(args.iter_mut()).collect(),
+            Op::Dict(pairs) => pairs.iter_mut().flat_map(|(key, value)| [key, value]).collect(),
+            Op::Phi(incoming) => incoming.iter_mut().map(|(_, value)| value).collect(),
+        }
+    }
+}
+
+impl Terminator {
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Terminator::Jump(target) => vec![*target],
+            Terminator::Branch(_, then, otherwise) => vec![*then, *otherwise],
+            Terminator::Return(_) | Terminator::Unreachable => Vec::new(),
+        }
+    }
+
+    pub fn operands(&self) -> Vec<Value> {
+        match self {
+            Terminator::Branch(value, _, _) | Terminator::Return(value) => vec![*value],
+            Terminator::Jump(_) | Terminator::Unreachable => Vec::new(),
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> Vec<&mut Value> {
+        match self {
+            Terminator::Branch(value, _, _) | Terminator::Return(value) => vec![value],
+            Terminator::Jump(_) | Terminator::Unreachable => Vec::new(),
+        }
+    }
+
+    fn targets_mut(&mut self) -> Vec<&mut BlockId> {
+        match self {
+            Terminator::Jump(target) => vec![target],
+            Terminator::Branch(_, then, otherwise) => vec![then, otherwise],
+            Terminator::Return(_) | Terminator::Unreachable => Vec::new(),
+        }
+    }
+}
+
+impl Function {
+    pub fn block(&self, id: BlockId) -> Option<&Block> {
+        self.blocks.iter().find(|block| block.id == id)
+    }
+
+    pub fn predecessors(&self) -> HashMap<BlockId, Vec<BlockId>> {
+        let mut preds: HashMap<BlockId, Vec<BlockId>> = self.blocks.iter().map(|block| (block.id, Vec::new())).collect();
+        for block in &self.blocks {
+            for target in block.term.successors() {
+                preds.entry(target).or_default().push(block.id);
+            }
+        }
+        preds
+    }
+
+    // blocks the entry can reach, in reverse postorder
+    pub fn reachable(&self) -> Vec<BlockId> {
+        let Some(entry) = self.blocks.first() else {
+            return Vec::new();
+        };
+
+        let mut order = Vec::new();
+        let mut seen = HashSet::from([entry.id]);
+        // each entry holds a block and how many of its successors were visited
+        let mut stack = vec![(entry.id, 0)];
+        while let Some((id, visited)) = stack.last_mut() {
+            let successors = self.block(*id).map(|block| block.term.successors()).unwrap_or_default();
+            if *visited == successors.len() {
+                order.push(*id);
+                stack.pop();
+                continue;
+            }
+
+            // the last successor is finished first, so the first one comes first in the order
+            let target = successors[successors.len() - 1 - *visited];
+            *visited += 1;
+            if seen.insert(target) {
+                stack.push((target, 0));
+            }
+        }
+
+        order.reverse();
+        order
+    }
+
+    // replace every use of `from` with `to`
+    pub fn replace_uses(&mut self, from: Value, to: Value) {
+        for block in &mut self.blocks {
+            let operands = block.insts.iter_mut().flat_map(|inst| inst.op.operands_mut()).chain(block.term.operands_mut());
+            for operand in operands {
+                if *operand == from {
+                    *operand = to;
+                }
+            }
+        }
+    }
+
+    // reachable blocks first in reverse postorder, numbered from 0 in that order
+    pub fn renumber(&mut self) {
+        let mut order = self.reachable();
+        for block in &self.blocks {
+            if !order.contains(&block.id) {
+                order.push(block.id);
+            }
+        }
+        let ids: HashMap<BlockId, BlockId> = order.iter().enumerate().map(|(i, id)| (*id, BlockId(i as u32))).collect();
+
+        let mut blocks = std::mem::take(&mut self.blocks);
+        blocks.sort_by_key(|block| ids[&block.id]);
+        for block in &mut blocks {
+            block.id = ids[&block.id];
+            for target in block.term.targets_mut() {
+                *target = ids[target];
+            }
+            for inst in &mut block.insts {
+                if let Op::Phi(incoming) = &mut inst.op {
+                    for (pred, _) in incoming {
+                        *pred = ids[pred];
+                    }
+                }
+            }
+        }
+        self.blocks = blocks;
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "%{}", self.0)
+    }
+}
+
+impl fmt::Display for BlockId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "bb{}", self.0)
+    }
+}
+
+impl fmt::Display for Constant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Constant::Int(value) => write!(f, "{}", value),
+            Constant::Float(value) => write!(f, "{:?}", value),
+            Constant::Bool(value) => write!(f, "{}", value),
+            Constant::Str(value) => write!(f, "{:?}", value),
+            Constant::Null => write!(f, "null"),
+            Constant::Void => write!(f, "void"),
+        }
+    }
+}
+
+fn list(values: &[Value]) -> String {
+    values.iter().map(Value::to_string).collect::<Vec<_>>().join(", ")
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Op::Const(constant) => write!(f, "const {}", constant),
+            Op::Undef => write!(f, "undef"),
+            Op::Function(name) => write!(f, "func {}", name),
+            Op::Type(name) => write!(f, "type {}", name),
+            Op::Variant(owner, name) => write!(f, "variant {}::{}", owner, name),
+            Op::Binary(op, lhs, rhs) => write!(f, "{} {}, {}", op.name(), lhs, rhs),
+            Op::Unary(op, value) => write!(f, "{} {}", op.name(), value),
+            Op::Call(name, args) => write!(f, "call {}({})", name, list(args)),
+            Op::CallValue(callee, args) => write!(f, "call {}({})", callee, list(args)),
+            Op::CallMethod(receiver, name, args) => write!(f, "call {}.{}({})", receiver, name, list(args)),
+            Op::CallStd(name, args) => write!(f, "call std::{}({})", name, list(args)),
+            Op::New(name, args) => write!(f, "new {}({})", name, list(args)),
+            Op::List(items) => write!(f, "list [{}]", list(items)),
+            Op::Dict(pairs) => {
+                let pairs: Vec<String> = pairs.iter().map(|(key, value)| format!("{}: {}", key, value)).collect();
+                write!(f, "dict {{{}}}", pairs.join(", "))
+            }
+            Op::Range(min, max) => write!(f, "range {}, {}", min, max),
+            Op::GetField(object, field) => write!(f, "get {}.{}", object, field),
+            Op::SetField(object, field, value) => write!(f, "set {}.{}, {}", object, field, value),
+            Op::IterInit(value) => write!(f, "iter.init {}", value),
+            Op::IterHas(value) => write!(f, "iter.has {}", value),
+            Op::IterNext(value) => write!(f, "iter.next {}", value),
+            Op::Phi(incoming) => {
+                let incoming: Vec<String> = incoming.iter().map(|(pred, value)| format!("[{}: {}]", pred, value)).collect();
+                write!(f, "phi {}", incoming.join(", "))
+            }
+            Op::Fail(message) => write!(f, "fail {:?}", message),
+        }
+    }
+}
+
+impl fmt::Display for Terminator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Terminator::Jump(target) => write!(f, "jmp {}", target),
+            Terminator::Branch(cond, then, otherwise) => write!(f, "br {}, {}, {}", cond, then, otherwise),
+            Terminator::Return(value) => write!(f, "ret {}", value),
+            Terminator::Unreachable => write!(f, "unreachable"),
+        }
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let params: Vec<String> = self.params.iter().map(|(value, ty)| format!("{}: {}", value, ty)).collect();
+        writeln!(f, "func {}({}) -> {} {{", self.name, params.join(", "), self.result)?;
+        for block in &self.blocks {
+            writeln!(f, "{}:", block.id)?;
+            for inst in &block.insts {
+                write!(f, "    {}: {} = {}", inst.result, inst.ty, inst.op)?;
+                match &inst.loc {
+                    Some(loc) => writeln!(f, "  ; {}:{}", loc.line, loc.column)?,
+                    None => writeln!(f)?,
+                }
+            }
+            writeln!(f, "    {}", block.term)?;
+        }
+        writeln!(f, "}}")
+    }
+}
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "; {}", self.file)?;
+        for function in &self.functions {
+            writeln!(f)?;
+            write!(f, "{}", function)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,272 @@
+use super::{BlockId, Function, Op, Program, Terminator, Value};
+use crate::compiler::types::Type;
+
+use std::collections::{HashMap, HashSet};
+
+// where a value is defined, parameters come before the first instruction of the entry
+#[derive(Clone, Copy)]
+struct Def {
+    block: BlockId,
+    index: Option<usize>,
+}
+
+// checks the invariants passes and backends rely on: well-formed blocks, one definition per
+// value that dominates every use, and phis that agree with the predecessors of their block
+pub fn verify(program: &Program) -> Result<(), String> {
+    for function in &program.functions {
+        verify_function(function).map_err(|e| format!("in `{}`: {}", function.name, e))?;
+    }
+    Ok(())
+}
+
+pub fn verify_function(function: &Function) -> Result<(), String> {
+    let Some(entry) = function.blocks.first().map(|block| block.id) else {
+        return Err("function has no blocks".to_owned());
+    };
+
+    let mut ids = HashSet::new();
+    for block in &function.blocks {
+        if !ids.insert(block.id) {
+            return Err(format!("block {} is defined more than once", block.id));
+        }
+    }
+    for block in &function.blocks {
+        for target in block.term.successors() {
+            if !ids.contains(&target) {
+                return Err(format!("{} jumps to the missing block {}", block.id, target));
+            }
+        }
+    }
+
+    let mut defs = HashMap::new();
+    let mut types = HashMap::new();
+    for (value, ty) in &function.params {
+        if defs.insert(*value, Def { block: entry, index: None }).is_some() {
+            return Err(format!("{} is defined more than once", value));
+        }
+        types.insert(*value, ty);
+    }
+    for block in &function.blocks {
+        for (index, inst) in block.insts.iter().enumerate() {
+            if inst.result.0 >= function.values {
+                return Err(format!("{} is out of range, the function has {} values", inst.result, function.values));
+            }
+            if defs.insert(inst.result, Def { block: block.id, index: Some(index) }).is_some() {
+                return Err(format!("{} is defined more than once", inst.result));
+            }
+            types.insert(inst.result, &inst.ty);
+        }
+    }
+
+    let preds = function.predecessors();
+    let reachable = function.reachable();
+    let idom = dominators(&reachable, &preds);
+
+    for block in &function.blocks {
+        let mut past_phis = false;
+        let mut block_preds = preds[&block.id].clone();
+        block_preds.sort();
+
+        for inst in &block.insts {
+            let Op::Phi(incoming) = &inst.op else {
+                past_phis = true;
+                continue;
+            };
+
+            if past_phis {
+                return Err(format!("phi {} in {} comes after other instructions", inst.result, block.id));
+            }
+            let mut from: Vec<BlockId> = incoming.iter().map(|(pred, _)| *pred).collect();
+            from.sort();
+            if from != block_preds {
+                return Err(format!("phi {} in {} does not have one operand per predecessor", inst.result, block.id));
+            }
+
+            // a phi operand is used at the end of the predecessor it flows in from
+            for (pred, value) in incoming {
+                if idom.contains_key(pred) && !available(*value, &defs, &idom, *pred, None) {
+                    return Err(format!("{} flowing into {} from {} does not dominate it", value, inst.result, pred));
+                }
+            }
+        }
+
+        // only reachable code has dominators to check against
+        if !idom.contains_key(&block.id) {
+            continue;
+        }
+
+        for (index, inst) in block.insts.iter().enumerate() {
+            if matches!(inst.op, Op::Phi(_)) {
+                continue;
+            }
+            for value in inst.op.operands() {
+                if !available(value, &defs, &idom, block.id, Some(index)) {
+                    return Err(format!("{} is used by {} before it is defined", value, inst.result));
+                }
+            }
+        }
+
+        for value in block.term.operands() {
+            if !available(value, &defs, &idom, block.id, None) {
+                return Err(format!("{} is used by the terminator of {} before it is defined", value, block.id));
+            }
+        }
+
+        if let Terminator::Branch(cond, _, _) = &block.term
+            && let Some(ty) = types.get(cond)
+            && **ty != Type::Bool
+            && !ty.is_unknown()
+        {
+            return Err(format!("branch condition {} in {} is `{}`, not `bool`", cond, block.id, ty));
+        }
+    }
+
+    Ok(())
+}
+
+// whether `value` is defined before the instruction at `index` of `block`, or before the end of
+// `block` when there is no index
+fn available(value: Value, defs: &HashMap<Value, Def>, idom: &HashMap<BlockId, BlockId>, block: BlockId, index: Option<usize>) -> bool {
+    let Some(def) = defs.get(&value) else {
+        return false;
+    };
+
+    if def.block == block {
+        return match (def.index, index) {
+            (None, _) | (_, None) => true,
+            (Some(def), Some(used)) => def < used,
+        };
+    }
+    dominates(def.block, block, idom)
+}
+
+fn dominates(a: BlockId, mut b: BlockId, idom: &HashMap<BlockId, BlockId>) -> bool {
+    loop {
+        if a == b {
+            return true;
+        }
+        match idom.get(&b) {
+            Some(parent) if *parent != b => b = *parent,
+            _ => return false,
+        }
+    }
+}
+
+// immediate dominators of the reachable blocks, the entry is its own
+// (Cooper, Harvey and Kennedy, "A Simple, Fast Dominance Algorithm")
+fn dominators(order: &[BlockId], preds: &HashMap<BlockId, Vec<BlockId>>) -> HashMap<BlockId, BlockId> {
+    let Some(entry) = order.first().copied() else {
+        return HashMap::new();
+    };
+
+    let position: HashMap<BlockId, usize> = order.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+    let mut idom = HashMap::from([(entry, entry)]);
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for block in &order[1..] {
+            let mut new = None;
+            for pred in &preds[block] {
+                if !idom.contains_key(pred) {
+                    continue;
+                }
+                new = Some(match new {
+                    None => *pred,
+                    Some(current) => intersect(*pred, current, &idom, &position),
+                });
+            }
+
+            if let Some(new) = new
+                && idom.get(block) != Some(&new)
+            {
+                idom.insert(*block, new);
+                changed = true;
+            }
+        }
+    }
+
+    idom
+}
+
+fn intersect(mut a: BlockId, mut b: BlockId, idom: &HashMap<BlockId, BlockId>, position: &HashMap<BlockId, usize>) -> BlockId {
+    while a != b {
+        while position[&a] > position[&b] {
+            a = idom[&a];
+        }
+        while position[&b] > position[&a] {
+            b = idom[&b];
+        }
+    }
+    a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler;
+    use crate::ir::lower::Lowerer;
+    use crate::ir::{BinaryOp, Block};
+
+    // `sum` lowered, with a loop so there are phis and a back edge
+    fn lowered() -> Function {
+        let source = "func sum(n: int) -> int {\n    let total = 0;\n    let i = 0;\n    while i < n {\n        total = total + i;\n        i = i + 1;\n    }\n    return total;\n}\n";
+        let (nodes, types) = compiler::analyze(source);
+        let program = Lowerer::lower(&nodes, &types, "main.mx");
+        program.functions.into_iter().find(|function| function.name == "sum").unwrap()
+    }
+
+    fn phi_block(function: &mut Function) -> &mut Block {
+        function.blocks.iter_mut().find(|block| block.insts.iter().any(|inst| matches!(inst.op, Op::Phi(_)))).unwrap()
+    }
+
+    #[test]
+    fn accepts_lowered_code() {
+        assert_eq!(verify_function(&lowered()), Ok(()));
+    }
+
+    #[test]
+    fn rejects_a_jump_to_a_missing_block() {
+        let mut function = lowered();
+        function.blocks[0].term = Terminator::Jump(BlockId(99));
+        assert!(verify_function(&function).unwrap_err().contains("missing block"));
+    }
+
+    #[test]
+    fn rejects_a_use_before_its_definition() {
+        let mut function = lowered();
+        let block = function.blocks.iter_mut().find(|block| block.insts.iter().any(|inst| matches!(inst.op, Op::Binary(..)))).unwrap();
+        let index = block.insts.iter().position(|inst| matches!(inst.op, Op::Binary(..))).unwrap();
+        let Op::Binary(_, lhs, _) = block.insts[index].op else { unreachable!() };
+        block.insts[index].op = Op::Binary(BinaryOp::Add, lhs, block.insts[index].result);
+        assert!(verify_function(&function).unwrap_err().contains("before it is defined"));
+    }
+
+    #[test]
+    fn rejects_a_phi_missing_a_predecessor() {
+        let mut function = lowered();
+        let block = phi_block(&mut function);
+        let Op::Phi(incoming) = &mut block.insts[0].op else { unreachable!() };
+        incoming.pop();
+        assert!(verify_function(&function).unwrap_err().contains("one operand per predecessor"));
+    }
+
+    #[test]
+    fn rejects_a_phi_after_other_instructions() {
+        let mut function = lowered();
+        let block = phi_block(&mut function);
+        let phi = block.insts.remove(0);
+        block.insts.push(phi);
+        assert!(verify_function(&function).is_err());
+    }
+
+    #[test]
+    fn rejects_a_branch_on_a_non_bool() {
+        let mut function = lowered();
+        let int = function.params[0].0;
+        let block = function.blocks.iter_mut().find(|block| matches!(block.term, Terminator::Branch(..))).unwrap();
+        let Terminator::Branch(_, then, otherwise) = block.term else { unreachable!() };
+        block.term = Terminator::Branch(int, then, otherwise);
+        assert!(verify_function(&function).unwrap_err().contains("not `bool`"));
+    }
+}
@@ -1,14 +1,45 @@
+#![allow(clippy::vec_box, reason = "every stage passes syntax trees around as `Vec<Box<Node>>`, the shape the parser builds them in")]
+
 use colored::*;
 use std::env;
 use std::fs;
 use std::fs::File;
+use std::collections::HashSet;
 use std::path::Path;
-#[allow(unused_imports)]
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Write};
 
 mod compiler;
 use compiler::lexer::Lexer;
 use compiler::scanner::Scanner;
+use compiler::diagnostic::{Diagnostic, Diagnostics, Emitter};
+use compiler::resolver::Resolver;
+use compiler::checker::Checker;
+use compiler::optimizer;
+use compiler::module::{self, SourceModule};
+use compiler::node::{Node, Location};
+use compiler::types::TypeTable;
+use compiler::incremental::{source_hash, BuildCache};
+
+mod runtime;
+use runtime::interpreter::{self, Interpreter};
+use runtime::value::Value;
+use runtime::bytecode::Module;
+use runtime::compile::BytecodeCompiler;
+use runtime::vm::Vm;
+
+mod backend;
+use backend::asm::AsmGenerator;
+use backend::c::{CGenerator, RUNTIME_HEADER};
+use backend::wasm::{WasmGenerator, HOST};
+
+mod ir;
+use ir::lower::Lowerer;
+
+mod project;
+use project::config::Backend;
+use project::layout::{self, Artifact, BuildDir};
+use project::manifest::{self as manifest, ManifestError, ProjectManifest};
+use project::registry;
 
 static DEBUG_VERBOSE: bool = false;
 
@@ -38,6 +69,12 @@ fn create_project(name: String) -> Option<String> {
         return Some(format!("{} {}", "error:".red(), "directory is exists"));
     }
 
+    // the directory name becomes the project name, and with it the name of the executable
+    let project = project_path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    if !manifest::valid_name(project) {
+        return Some(format!("{} invalid project name `{}`, names start with a letter and only contain letters, digits, `-` and `_`", "error:".red(), project));
+    }
+
     fs::create_dir_all(project_path).ok()?;
 
     let src_path = project_path.join("src");
@@ -51,15 +88,7 @@ func main() {
     "#;
 
     fs::create_dir_all(src_path).ok()?;
-    let mut file_config = File::create(mix_config).ok()?;
-    writeln!(file_config, "{{").ok()?;
-    writeln!(file_config, "\t{}", format!("\"name\": \"{}\",", name)).ok()?;
-    writeln!(file_config, "\t\"version\": \"1.0.0\",").ok()?;
-    writeln!(file_config, "\t\"author\": \"...\",").ok()?;
-    // writeln!(file_config, "\"target\": \"x86_64-gnu-linux\",").ok()?;
-    writeln!(file_config, "").ok()?;
-    writeln!(file_config, "\t\"packages\": []").ok()?;
-    writeln!(file_config, "}}").ok()?;
+    fs::write(mix_config, ProjectManifest::new(project).to_string()).ok()?;
 
     let mut file_main = File::create(main_path).ok()?;
     writeln!(file_main, "{}", main_content).ok()?;
@@ -67,89 +96,549 @@ func main() {
     None
 }
 
-fn compile_program(dir: &str) -> Option<String> {
+// everything a later stage needs from a project that passed all checks
+struct Program {
+    nodes: Vec<Box<Node>>,
+    types: TypeTable,
+    emitter: Emitter,
+    file: String,
+    manifest: ProjectManifest,
+}
+
+// lex, parse, resolve and type check the project in `dir`, printing every diagnostic; with a
+// `cache`, modules whose inputs did not change since the last build are only read for their
+// declarations
+fn analyze_program(dir: &str, manifest: ProjectManifest, cache: Option<&mut BuildCache>) -> Result<Program, String> {
     let path = Path::new(dir);
 
+    let main_path = path.join(&manifest.entry);
+    if !main_path.exists() || main_path.is_dir() { 
+        return Err(format!("{} `{}` file not found", "error:".red(), manifest.entry));
+    }
+
+    let packages = registry::vendored(path, &manifest).map_err(|e| format!("{} {}", "error:".red(), e))?;
+    let sources = project::sources::discover(path, &manifest.entry, &packages).map_err(|e| format!("{} {}", "error:".red(), e))?;
+
+    let mut emitter = Emitter::new();
+    let mut inputs = Vec::new();
+    for source in sources {
+        let content = fs::read_to_string(&source.path)
+            .map_err(|e| format!("{} cannot read `{}`: {}", "error:".red(), source.path.display(), e))?;
+        let full_path = source.path.canonicalize().map_err(|e| format!("{} {}", "error:".red(), e))?;
+        let file = full_path.display().to_string();
+        emitter.add_source(file.clone(), content.clone());
+        inputs.push((source.module, file, content));
+    }
+
+    let mut diagnostics = Diagnostics::new();
+    let mut trusted = HashSet::new();
+    let modules = match cache {
+        Some(cache) => {
+            let modules = cache.select(inputs, |file, content| parse_module(file, content, &mut diagnostics));
+            trusted.extend(cache.reused.keys().cloned());
+            modules
+        }
+        None => inputs.into_iter().map(|(name, file, content)| {
+            let nodes = parse_module(&file, content, &mut diagnostics);
+            SourceModule { name, file, nodes }
+        }).collect(),
+    };
+
+    // the entry comes first
+    let file = modules[0].file.clone();
+    let mut nodes = Vec::new();
+    let mut types = TypeTable::default();
+
+    // name resolution on a broken tree only produces follow-up noise
+    if !diagnostics.has_errors() {
+        let mut resolver = Resolver::new(DEBUG_VERBOSE);
+        let (symbols, resolve_diagnostics) = resolver.resolve(&modules);
+        diagnostics.extend(resolve_diagnostics);
+
+        if !diagnostics.has_errors() {
+            nodes = module::link(modules, &symbols);
+            let mut checker = Checker::new(&symbols, DEBUG_VERBOSE);
+            checker.trust(trusted);
+            let (checked, check_diagnostics) = checker.check(&nodes);
+            diagnostics.extend(check_diagnostics);
+            types = checked;
+        }
+    }
+
+    for diagnostic in diagnostics.iter() {
+        println!("{}", emitter.render(diagnostic));
+    }
+
+    if diagnostics.has_errors() {
+        return Err(format!("{} could not compile due to {} previous error(s)", "error:".red(), diagnostics.error_count()));
+    }
+
+    Ok(Program { nodes, types, emitter, file, manifest })
+}
+
+fn parse_module(file: &str, content: String, diagnostics: &mut Diagnostics) -> Vec<Box<Node>> {
+    let lexer = Lexer::new(content, file.to_owned());
+    let mut scanner = Scanner::new(lexer, DEBUG_VERBOSE);
+    let (nodes, scan_diagnostics) = scanner.scan();
+    diagnostics.extend(scan_diagnostics);
+    nodes
+}
+
+// the `mix.conf` of the project at `path`, printing what is wrong with it if anything
+fn load_manifest(path: &Path) -> Result<ProjectManifest, String> {
     if !path.exists() || !path.is_dir() {
-        return Some(format!("{} {}", "error:".red(), "project directory not found"));
+        return Err(format!("{} {}", "error:".red(), "project directory not found"));
     }
 
-    let main_path = path.join("src/main.mx");
     let config_path = path.join("mix.conf");
 
     if !config_path.exists() || config_path.is_dir() { 
-        return Some(format!("{} {}", "error:".red(), "`main.mx` file not found"));
+        return Err(format!("{} {}", "error:".red(), "`mix.conf` file not found"));
     }
 
-    if !main_path.exists() || main_path.is_dir() { 
-        return Some(format!("{} {}", "error:".red(), "`main.mx` file not found"));
+    let config = fs::read_to_string(&config_path).map_err(|e| format!("{} cannot read `mix.conf`: {}", "error:".red(), e))?;
+    ProjectManifest::parse(&config).map_err(|error| {
+        let file = config_path.canonicalize().unwrap_or(config_path.clone()).display().to_string();
+        let mut emitter = Emitter::new();
+        emitter.add_source(file.clone(), config.clone());
+        println!("{}", emitter.render(&manifest_diagnostic(&file, error)));
+        format!("{} invalid `mix.conf`", "error:".red())
+    })
+}
+
+// a problem in `mix.conf` as a diagnostic underlining the value it is about
+fn manifest_diagnostic(file: &str, error: ManifestError) -> Diagnostic {
+    let span = error.span;
+    let loc = Location { file: file.to_owned(), line: span.line, column: span.column, start: span.start, end: span.end };
+    let diagnostic = Diagnostic::error(error.code, error.message, loc);
+    match error.help {
+        Some(help) => diagnostic.with_help(help),
+        None => diagnostic,
     }
+}
 
-    let _config = fs::read_to_string(&config_path).unwrap();
-    let content = fs::read_to_string(&main_path).unwrap();
+// the `mix build` flags
+#[derive(Debug, Clone, Copy, Default)]
+struct BuildOptions<'a> {
+    backend: Option<&'a str>,
+    target: Option<&'a str>,
+    emit: Option<&'a str>,
+    disassemble: bool,
+    profile: Option<&'a str>,
+    // `-O` overrides the level of the profile
+    opt_level: Option<u32>,
+    verbose: bool,
+}
 
-    let full_path = main_path.canonicalize().ok()?;
-    let lexer = Lexer::new(content, full_path.display().to_string());
-    
-    let mut scanner = Scanner::new(lexer, DEBUG_VERBOSE);
-    let nodes = scanner.scan();
+// compile the project in `dir` with the backend `mix.conf`, `--target=` or `--backend=` picks,
+// or only write the intermediate form `--emit=` names
+fn compile_program(dir: &str, options: BuildOptions) -> Option<String> {
+    let BuildOptions { backend, target, emit, disassemble, profile, opt_level, verbose } = options;
+    let mut manifest = match load_manifest(Path::new(dir)) {
+        Ok(manifest) => manifest,
+        Err(e) => return Some(e),
+    };
 
-    println!("{:?}", nodes);
+    let profile = profile.unwrap_or("debug");
+    let Some(profile) = manifest.profile(profile).cloned() else {
+        let names: Vec<String> = manifest.profiles.iter().map(|profile| format!("`{}`", profile.name)).collect();
+        return Some(format!("{} unknown profile `{}`, expected one of {}", "error:".red(), profile, names.join(", ")));
+    };
+    let opt_level = opt_level.unwrap_or(profile.opt_level);
+    if let Some(cflags) = profile.cflags {
+        manifest.build.cflags = cflags;
+    }
+
+    let backend = match backend {
+        Some(name) => match Backend::from_name(name) {
+            Some(backend) => backend,
+            None => return Some(format!("{} unknown backend `{}`, expected `bytecode`, `c`, `asm` or `wasm`", "error:".red(), name)),
+        },
+        None => match target {
+            Some(name) => match Backend::from_target(name) {
+                Some(backend) => backend,
+                None => return Some(format!("{} unknown target `{}`, expected `x86_64-gnu-linux` or `wasm32`", "error:".red(), name)),
+            },
+            None => manifest.backend(),
+        },
+    };
+
+    let build = BuildDir::new(Path::new(dir), &profile.name, backend.target_dir());
+
+    // only bytecode links code compiled by earlier builds, and `-O2` drops unused functions
+    // across the whole program, which reused modules would hide calls from
+    let mut cache = None;
+    if backend == Backend::Bytecode && emit.is_none() {
+        if opt_level < 2 {
+            cache = Some(BuildCache::new(&build, opt_level, verbose));
+        } else if verbose {
+            println!("[CACHE] every module is compiled at -O2");
+        }
+    }
+
+    let mut program = match analyze_program(dir, manifest, cache.as_mut()) {
+        Ok(program) => program,
+        Err(e) => return Some(e),
+    };
+
+    if DEBUG_VERBOSE {
+        println!("{:?}", program.nodes);
+    }
+
+    let reports = optimizer::optimize(&mut program.nodes, opt_level);
+    if verbose {
+        for report in reports {
+            println!("[OPTIMIZE] {}: {} change(s)", report.pass, report.changes.len());
+            for change in report.changes {
+                println!("    {}:{}:{} {}", change.loc.file, change.loc.line, change.loc.column, change.message);
+            }
+        }
+    }
+
+    let result = match emit {
+        Some("ir") => emit_ir(&program, &build),
+        Some(kind) => Err(format!("{} unknown emit kind `{}`, expected `ir`", "error:".red(), kind)),
+        None => match backend {
+            Backend::Bytecode => build_bytecode(&program, &build, disassemble, cache),
+            Backend::C => build_native(&program, &build),
+            Backend::Asm => build_asm(&program, &build),
+            Backend::Wasm => build_wasm(&program, &build),
+        },
+    };
+
+    if let Err(e) = result {
+        return Some(e);
+    }
 
     println!("COMPILE DONE");
     
     None
 }
 
-#[allow(dead_code)]
-fn execute_program() {
+// the SSA form of every function at `ir/main.ir`, checked by the verifier first
+fn emit_ir(program: &Program, build: &BuildDir) -> Result<(), String> {
+    let ir = Lowerer::lower(&program.nodes, &program.types, &program.file);
+    ir::verify::verify(&ir).map_err(|e| format!("{} internal compiler error: invalid IR {}", "error:".red(), e))?;
+
+    build.write(Artifact::Ir, "main.ir", ir.to_string()).map_err(|e| format!("{} {}", "error:".red(), e))?;
+    Ok(())
+}
+
+// bytecode for the VM at `bin/main.mxb`, then the modules compiled this time in `cache`
+fn build_bytecode(program: &Program, build: &BuildDir, disassemble: bool, cache: Option<BuildCache>) -> Result<(), String> {
+    let mut module = match &cache {
+        Some(cache) => BytecodeCompiler::compile_reusing(&program.nodes, &program.types, &program.file, &cache.reused).map_err(|e| {
+            format!("{} internal compiler error: stale build cache, {}, run `mix clean` and build again", "error:".red(), e)
+        })?,
+        None => BytecodeCompiler::compile(&program.nodes, &program.types, &program.file),
+    };
+    module.sources = module.files().into_iter()
+        .filter_map(|file| Some((file.clone(), source_hash(program.emitter.source(file)?))))
+        .collect();
+
+    build.write(Artifact::Binary, "main.mxb", module.to_bytes()).map_err(|e| format!("{} {}", "error:".red(), e))?;
+    if let Some(cache) = cache {
+        cache.save(&module).map_err(|e| format!("{} {}", "error:".red(), e))?;
+    }
+
+    if disassemble {
+        print!("{}", module.disassemble());
+    }
+
+    Ok(())
+}
+
+// C source at `obj/main.c`, compiled to an executable in `bin` named after the project
+fn build_native(program: &Program, build: &BuildDir) -> Result<(), String> {
+    let source = CGenerator::new(&program.types, &program.file).generate(&program.nodes).map_err(|diagnostic| {
+        println!("{}", program.emitter.render(&diagnostic));
+        format!("{} could not compile due to 1 previous error(s)", "error:".red())
+    })?;
+
+    let error = |e| format!("{} {}", "error:".red(), e);
+    let source_path = build.write(Artifact::Object, "main.c", source).map_err(error)?;
+    build.write(Artifact::Object, "mix_runtime.h", RUNTIME_HEADER).map_err(error)?;
+
+    let config = &program.manifest.build;
+    let output_path = build.output(Artifact::Binary, &program.manifest.name).map_err(error)?;
+    let output = std::process::Command::new(&config.cc)
+        .args(&config.cflags)
+        .arg("-o")
+        .arg(&output_path)
+        .arg(&source_path)
+        .arg("-lm")
+        .arg("-pthread")
+        .output()
+        .map_err(|e| format!("{} cannot run C compiler `{}`: {}", "error:".red(), config.cc, e))?;
+
+    if !output.status.success() {
+        print!("{}", String::from_utf8_lossy(&output.stderr));
+        return Err(format!("{} C compiler `{}` failed on `{}`", "error:".red(), config.cc, source_path.display()));
+    }
+
+    Ok(())
+}
+
+// x86-64 assembly at `obj/main.s`, assembled and linked without the C library
+fn build_asm(program: &Program, build: &BuildDir) -> Result<(), String> {
+    let source = AsmGenerator::new(&program.types, &program.file).generate(&program.nodes).map_err(|diagnostics| {
+        for diagnostic in diagnostics.iter() {
+            println!("{}", program.emitter.render(diagnostic));
+        }
+        format!("{} could not compile due to {} previous error(s)", "error:".red(), diagnostics.error_count())
+    })?;
+
+    let error = |e| format!("{} {}", "error:".red(), e);
+    let source_path = build.write(Artifact::Object, "main.s", source).map_err(error)?;
+
+    let config = &program.manifest.build;
+    let object_path = build.output(Artifact::Object, "main.o").map_err(error)?;
+    let output_path = build.output(Artifact::Binary, &program.manifest.name).map_err(error)?;
+    let steps = [
+        (&config.assembler, "assembler", [object_path.as_path(), source_path.as_path()]),
+        (&config.linker, "linker", [output_path.as_path(), object_path.as_path()]),
+    ];
+
+    for (command, role, [output, input]) in steps {
+        let result = std::process::Command::new(command)
+            .arg("-o")
+            .arg(output)
+            .arg(input)
+            .output()
+            .map_err(|e| format!("{} cannot run {} `{}`: {}", "error:".red(), role, command, e))?;
+
+        if !result.status.success() {
+            print!("{}", String::from_utf8_lossy(&result.stderr));
+            return Err(format!("{} {} `{}` failed on `{}`", "error:".red(), role, command, input.display()));
+        }
+    }
+
+    Ok(())
+}
+
+// `obj/main.wat` and the `bin/main.wasm` assembled from it, with `bin/mix_host.mjs` to run it
+fn build_wasm(program: &Program, build: &BuildDir) -> Result<(), String> {
+    let source = WasmGenerator::new(&program.types, &program.file).generate(&program.nodes).map_err(|diagnostics| {
+        for diagnostic in diagnostics.iter() {
+            println!("{}", program.emitter.render(diagnostic));
+        }
+        format!("{} could not compile due to {} previous error(s)", "error:".red(), diagnostics.error_count())
+    })?;
+
+    let binary = backend::wat::assemble(&source)
+        .map_err(|e| format!("{} cannot assemble the generated module: {}", "error:".red(), e))?;
+
+    let files: [(Artifact, &str, &[u8]); 3] = [
+        (Artifact::Object, "main.wat", source.as_bytes()),
+        (Artifact::Binary, "main.wasm", &binary),
+        (Artifact::Binary, "mix_host.mjs", HOST.as_bytes()),
+    ];
+    for (kind, name, content) in files {
+        build.write(kind, name, content).map_err(|e| format!("{} {}", "error:".red(), e))?;
+    }
+
+    Ok(())
+}
+
+// `mix install [package]` in the project at `dir`, printing each package it installed or removed
+fn install_packages(dir: &str, package: Option<&str>, registry: Option<&str>) -> Result<(), String> {
+    let path = Path::new(dir);
+    let manifest = load_manifest(path)?;
+    let changes = registry::install(path, manifest, package, registry).map_err(|e| format!("{} {}", "error:".red(), e))?;
+    for change in changes {
+        println!("{}", change);
+    }
+    Ok(())
+}
+
+// run the project in `dir`, or a `.mxb` file built earlier, returning the exit code of `main`
+fn execute_program(target: &str, interpret: bool) -> Result<i32, String> {
+    if target.ends_with(".mxb") {
+        let bytes = fs::read(target).map_err(|e| format!("{} cannot read `{}`: {}", "error:".red(), target, e))?;
+        let module = Module::from_bytes(&bytes).map_err(|e| format!("{} `{}`: {}", "error:".red(), target, e))?;
+
+        // the sources are only needed to underline runtime errors, so a moved project still runs,
+        // and one edited since the build would underline the wrong text
+        let mut emitter = Emitter::new();
+        for file in module.files() {
+            if let Ok(content) = fs::read_to_string(file)
+                && module.sources.iter().any(|(source, hash)| source == file && *hash == source_hash(&content))
+            {
+                emitter.add_source(file.clone(), content);
+            }
+        }
+
+        return run_module(&module, &emitter);
+    }
+
+    let manifest = load_manifest(Path::new(target))?;
+    let program = analyze_program(target, manifest, None)?;
+
+    if !interpret {
+        let module = BytecodeCompiler::compile(&program.nodes, &program.types, &program.file);
+        return run_module(&module, &program.emitter);
+    }
+
+    let entry = entry_location(&program.file);
+
+    // a tree-walker recurses on the host stack, so give deep Mix recursion room to fail cleanly
+    let result = std::thread::scope(|scope| {
+        std::thread::Builder::new()
+            .stack_size(interpreter::STACK_SIZE)
+            .spawn_scoped(scope, || Interpreter::new(&program.nodes, &program.types, DEBUG_VERBOSE).run(&entry).map(exit_code))
+            .map_err(|e| format!("{} {}", "error:".red(), e))?
+            .join()
+            .map_err(|_| format!("{} interpreter crashed", "error:".red()))
+    })?;
+
+    result.map_err(|diagnostic| {
+        println!("{}", program.emitter.render(&diagnostic));
+        format!("{} program exited with a runtime error", "error:".red())
+    })
+}
+
+fn run_module(module: &Module, emitter: &Emitter) -> Result<i32, String> {
+    let entry = entry_location(&module.file);
+
+    Vm::new(module, DEBUG_VERBOSE).run(&entry).map(exit_code).map_err(|diagnostic| {
+        println!("{}", emitter.render(&diagnostic));
+        format!("{} program exited with a runtime error", "error:".red())
+    })
+}
 
+fn entry_location(file: &str) -> Location {
+    Location {
+        file: file.to_owned(),
+        line: 1,
+        column: 1,
+        start: 0,
+        end: 1,
+    }
+}
+
+fn exit_code(value: Value) -> i32 {
+    match value {
+        Value::Int(code) => code as i32,
+        _ => 0,
+    }
 }
 
 fn main() {
     let args: Vec<String> = env::args().collect();
 
+    if !io::stdout().is_terminal() {
+        colored::control::set_override(false);
+    }
+
     if args.len() <= 1 {
         print_help();
         return;
     }
 
+    // `--flag` options and `-O` levels may appear anywhere after the command, and those taking
+    // a value accept both `--name=value` and `--name value`
+    let mut flags: Vec<String> = Vec::new();
+    let mut positional: Vec<String> = Vec::new();
+    let mut rest = args.iter().enumerate();
+    while let Some((i, arg)) = rest.next() {
+        if i >= 2 && arg.starts_with("-O") {
+            flags.push(arg.clone());
+        } else if !arg.starts_with("--") {
+            positional.push(arg.clone());
+        } else if i < 2 {
+            continue;
+        } else if matches!(arg.as_str(), "--target" | "--backend" | "--emit" | "--profile" | "--registry") && let Some((_, value)) = rest.next() {
+            flags.push(format!("{}={}", arg, value));
+        } else {
+            flags.push(arg.clone());
+        }
+    }
+    let flags: Vec<&str> = flags.iter().map(String::as_str).collect();
+    let args = positional;
+
     if args.len() >= 2 {
         if args[1] == "help" {
             print_help();
         } else if args[1] == "run" {
-            println!("under development");
-            todo!();
+            let dir = if args.len() == 2 { "./" } else { &args[2] };
+
+            match execute_program(dir, flags.contains(&"--interpret")) {
+                Ok(0) => {}
+                Ok(code) => std::process::exit(code),
+                Err(e) => {
+                    println!("{}", e);
+                    std::process::exit(1);
+                }
+            }
         } else if args[1] == "create" && args.len() == 3 {
             let p = create_project(args[2].clone());
             
             if let Some(e) = p {
-                println!("{} {}", "error:".red(), "failed to create new project");
+                println!("{} failed to create new project", "error:".red());
                 println!("{}", e);
             }
 
         } else if args[1] == "build" && args.len() >= 2 {
             
-            if args.len() == 2 {
-                compile_program("./");
+            let opt_level = match flags.iter().find_map(|flag| flag.strip_prefix("-O")) {
+                None => None,
+                Some("") => Some(2),
+                Some(level) => match level.parse() {
+                    Ok(level @ 0..=2) => Some(level),
+                    _ => {
+                        println!("{} invalid optimization level `-O{}`, expected `-O0`, `-O1` or `-O2`", "error:".red(), level);
+                        std::process::exit(1);
+                    }
+                },
+            };
+            let options = BuildOptions {
+                backend: flags.iter().find_map(|flag| flag.strip_prefix("--backend=")),
+                target: flags.iter().find_map(|flag| flag.strip_prefix("--target=")),
+                emit: flags.iter().find_map(|flag| flag.strip_prefix("--emit=")),
+                disassemble: flags.contains(&"--disassemble"),
+                profile: flags.iter().find_map(|flag| flag.strip_prefix("--profile=")),
+                opt_level,
+                verbose: flags.contains(&"--verbose"),
+            };
+            let p = if args.len() == 2 {
+                compile_program("./", options)
             } else {
-                compile_program(&args[2]);
+                compile_program(&args[2], options)
+            };
+
+            if let Some(e) = p {
+                println!("{}", e);
+                std::process::exit(1);
             }
 
-        } else if args[1] == "install" && args.len() == 3 {
-            println!("under development");
-            todo!();
+        } else if args[1] == "install" && args.len() <= 3 {
+            let registry = flags.iter().find_map(|flag| flag.strip_prefix("--registry="));
+
+            match install_packages("./", args.get(2).map(String::as_str), registry) {
+                Ok(()) => println!("INSTALL DONE"),
+                Err(e) => {
+                    println!("{}", e);
+                    std::process::exit(1);
+                }
+            }
         } else if args[1] == "update" {
             println!("under development");
             todo!();
-        } else if args[1] == "clean" {
-            println!("under development");
-            todo!();
+        } else if args[1] == "clean" && args.len() <= 3 {
+            let dir = if args.len() == 2 { "./" } else { &args[2] };
+
+            match layout::clean(Path::new(dir)) {
+                Ok(removed) => println!("CLEAN DONE, removed {} file(s)", removed),
+                Err(e) => {
+                    println!("{} {}", "error:".red(), e);
+                    std::process::exit(1);
+                }
+            }
         } else {
-            println!("{} {}", "error:".red(), "command failed");
-            println!("  {}", "all command available in `help`");
-            println!("  {}", "or learn more in https://mix.org/docs/index.html");
-            println!("{} {}", "example:".cyan(), "mix help");
+            println!("{} command failed", "error:".red());
+            println!("  all command available in `help`");
+            println!("  or learn more in https://mix.org/docs/index.html");
+            println!("{} mix help", "example:".cyan());
         }
     }
 }
@@ -0,0 +1,76 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Backend {
+    // `main.mxb` for the VM
+    Bytecode,
+    // portable C handed to the system C compiler
+    C,
+    // x86-64 assembly handed to the system assembler and linker
+    Asm,
+    // a WebAssembly module with a JavaScript host to run it
+    Wasm,
+}
+
+impl Backend {
+    pub fn from_name(name: &str) -> Option<Backend> {
+        match name {
+            "bytecode" => Some(Backend::Bytecode),
+            "c" => Some(Backend::C),
+            "asm" => Some(Backend::Asm),
+            "wasm" => Some(Backend::Wasm),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Bytecode => "bytecode",
+            Backend::C => "c",
+            Backend::Asm => "asm",
+            Backend::Wasm => "wasm",
+        }
+    }
+
+    // the directory under `build/<profile>` its output goes to
+    pub fn target_dir(self) -> &'static str {
+        match self {
+            Backend::Bytecode => "bytecode",
+            Backend::C => "native",
+            Backend::Asm => "x86_64-gnu-linux",
+            Backend::Wasm => "wasm32",
+        }
+    }
+
+    // the backend a `target` triple builds with
+    pub fn from_target(target: &str) -> Option<Backend> {
+        match target {
+            "x86_64-gnu-linux" => Some(Backend::Asm),
+            "wasm32" => Some(Backend::Wasm),
+            _ => None,
+        }
+    }
+}
+
+// the `build` object of `mix.conf`, how `mix build` produces its output
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuildConfig {
+    // `None` leaves the choice to the target
+    pub backend: Option<Backend>,
+    // C compiler command and the flags passed before the sources
+    pub cc: String,
+    pub cflags: Vec<String>,
+    // assembler and linker commands for the `asm` backend
+    pub assembler: String,
+    pub linker: String,
+}
+
+impl Default for BuildConfig {
+    fn default() -> BuildConfig {
+        BuildConfig {
+            backend: None,
+            cc: "cc".to_owned(),
+            cflags: vec!["-O2".to_owned()],
+            assembler: "as".to_owned(),
+            linker: "ld".to_owned(),
+        }
+    }
+}
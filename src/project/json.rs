@@ -0,0 +1,435 @@
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    Str(String),
+    Array(Vec<Json>),
+    // kept as pairs so the order of the file survives a round trip
+    Object(Vec<(String, Json)>),
+}
+
+// where a value or an error sits in the source, `start` and `end` are byte offsets
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct JsonError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at line {}, column {}", self.message, self.span.line, self.span.column)
+    }
+}
+
+// the span of every value in a document, by its path: `""` for the root,
+// `build.cflags[1]` for the second item of the `cflags` array inside `build`
+pub type Spans = HashMap<String, Span>;
+
+impl Json {
+    pub fn parse_spanned(source: &str) -> Result<(Json, Spans), JsonError> {
+        let mut parser = Parser {
+            chars: source.chars().collect(),
+            pos: 0,
+            offset: 0,
+            line: 1,
+            column: 1,
+            path: String::new(),
+            spans: Spans::new(),
+        };
+
+        parser.skip_whitespace();
+        let value = parser.value()?;
+        parser.skip_whitespace();
+
+        if parser.peek().is_some() {
+            return Err(parser.error("unexpected content after the end of the document"));
+        }
+
+        Ok((value, parser.spans))
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(pairs) => pairs.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::Str(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Json::Null => "null",
+            Json::Bool(_) => "boolean",
+            Json::Number(_) => "number",
+            Json::Str(_) => "string",
+            Json::Array(_) => "array",
+            Json::Object(_) => "object",
+        }
+    }
+
+    fn write(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        let indent = "\t".repeat(depth + 1);
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{}", value),
+            Json::Number(value) if value.fract() == 0.0 && value.abs() < 1e15 => write!(f, "{}", *value as i64),
+            Json::Number(value) => write!(f, "{}", value),
+            Json::Str(value) => write_string(f, value),
+            Json::Array(items) if items.is_empty() => write!(f, "[]"),
+            Json::Object(pairs) if pairs.is_empty() => write!(f, "{{}}"),
+            // arrays of plain values stay on one line, like `"cflags": ["-O2"]`
+            Json::Array(items) if items.iter().all(|item| !matches!(item, Json::Array(_) | Json::Object(_))) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    item.write(f, depth)?;
+                }
+                write!(f, "]")
+            }
+            Json::Array(items) => {
+                writeln!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    write!(f, "{}", indent)?;
+                    item.write(f, depth + 1)?;
+                    writeln!(f, "{}", if i + 1 < items.len() { "," } else { "" })?;
+                }
+                write!(f, "{}]", &indent[1..])
+            }
+            Json::Object(pairs) => {
+                writeln!(f, "{{")?;
+                for (i, (key, value)) in pairs.iter().enumerate() {
+                    write!(f, "{}", indent)?;
+                    write_string(f, key)?;
+                    write!(f, ": ")?;
+                    value.write(f, depth + 1)?;
+                    writeln!(f, "{}", if i + 1 < pairs.len() { "," } else { "" })?;
+                }
+                write!(f, "{}}}", &indent[1..])
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for ch in value.chars() {
+        match ch {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            ch if ch.is_control() => write!(f, "\\u{:04x}", ch as u32)?,
+            ch => write!(f, "{}", ch)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+// pretty printed with tabs, the way `mix create` writes `mix.conf`
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    // byte offset of `pos`
+    offset: usize,
+    line: usize,
+    column: usize,
+    // path of the value being parsed
+    path: String,
+    spans: Spans,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.pos += 1;
+        self.offset += ch.len_utf8();
+
+        if ch == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(ch)
+    }
+
+    fn here(&self) -> Span {
+        let width = self.peek().map(char::len_utf8).unwrap_or(0);
+        Span {
+            line: self.line,
+            column: self.column,
+            start: self.offset,
+            end: self.offset + width,
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> JsonError {
+        JsonError {
+            message: message.into(),
+            span: self.here(),
+        }
+    }
+
+    // an error covering everything from `start` up to the current position
+    fn error_since(&self, start: Span, message: impl Into<String>) -> JsonError {
+        JsonError {
+            message: message.into(),
+            span: Span { end: self.offset.max(start.end), ..start },
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), JsonError> {
+        match self.peek() {
+            Some(ch) if ch == expected => {
+                self.bump();
+                Ok(())
+            }
+            Some(ch) => Err(self.error(format!("expected `{}`, found `{}`", expected, ch))),
+            None => Err(self.error(format!("expected `{}`, found end of file", expected))),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.bump();
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, JsonError> {
+        let start = self.here();
+        let value = self.bare_value()?;
+        self.spans.insert(self.path.clone(), Span { end: self.offset, ..start });
+        Ok(value)
+    }
+
+    // the path of the value at `key` or `[index]` of the current one
+    fn child(&self, key: &str) -> String {
+        if self.path.is_empty() || key.starts_with('[') {
+            format!("{}{}", self.path, key)
+        } else {
+            format!("{}.{}", self.path, key)
+        }
+    }
+
+    fn bare_value(&mut self) -> Result<Json, JsonError> {
+        match self.peek() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => self.string().map(Json::Str),
+            Some('-' | '0'..='9') => self.number(),
+            Some(ch) if ch.is_alphabetic() => self.keyword(),
+            Some(ch) => Err(self.error(format!("expected a value, found `{}`", ch))),
+            None => Err(self.error("expected a value, found end of file")),
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, JsonError> {
+        self.expect('{')?;
+        let mut pairs: Vec<(String, Json)> = Vec::new();
+
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.bump();
+            return Ok(Json::Object(pairs));
+        }
+
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.error("expected a string key"));
+            }
+
+            let start = self.here();
+            let key = self.string()?;
+            if pairs.iter().any(|(existing, _)| *existing == key) {
+                return Err(self.error_since(start, format!("duplicate key `{}`", key)));
+            }
+
+            self.skip_whitespace();
+            self.expect(':')?;
+            self.skip_whitespace();
+            let path = self.child(&key);
+            let parent = std::mem::replace(&mut self.path, path);
+            let value = self.value()?;
+            self.path = parent;
+            pairs.push((key, value));
+
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => {
+                    self.bump();
+                }
+                Some('}') => {
+                    self.bump();
+                    return Ok(Json::Object(pairs));
+                }
+                _ => return Err(self.error("expected `,` or `}` after an object entry")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, JsonError> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.bump();
+            return Ok(Json::Array(items));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let path = self.child(&format!("[{}]", items.len()));
+            let parent = std::mem::replace(&mut self.path, path);
+            let item = self.value()?;
+            self.path = parent;
+            items.push(item);
+
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => {
+                    self.bump();
+                }
+                Some(']') => {
+                    self.bump();
+                    return Ok(Json::Array(items));
+                }
+                _ => return Err(self.error("expected `,` or `]` after an array item")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.expect('"')?;
+        let mut value = String::new();
+
+        loop {
+            let escape = self.here();
+            match self.bump() {
+                Some('"') => return Ok(value),
+                Some('\\') => {
+                    let ch = match self.bump() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => {
+                            let digits: String = (0..4).filter_map(|_| self.bump()).collect();
+                            u32::from_str_radix(&digits, 16).ok().and_then(char::from_u32)
+                                .ok_or_else(|| self.error_since(escape, format!("invalid unicode escape `\\u{}`", digits)))?
+                        }
+                        _ => return Err(self.error_since(escape, "invalid escape sequence")),
+                    };
+                    value.push(ch);
+                }
+                Some('\n') | None => return Err(self.error("unterminated string")),
+                Some(ch) => value.push(ch),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, JsonError> {
+        let start = self.here();
+        let mut text = String::new();
+
+        while let Some(ch) = self.peek()
+            && (ch.is_ascii_digit() || matches!(ch, '-' | '+' | '.' | 'e' | 'E'))
+        {
+            text.push(ch);
+            self.bump();
+        }
+
+        text.parse().map(Json::Number).map_err(|_| self.error_since(start, format!("invalid number `{}`", text)))
+    }
+
+    fn keyword(&mut self) -> Result<Json, JsonError> {
+        let start = self.here();
+        let mut word = String::new();
+
+        while let Some(ch) = self.peek()
+            && ch.is_alphanumeric()
+        {
+            word.push(ch);
+            self.bump();
+        }
+
+        match word.as_str() {
+            "true" => Ok(Json::Bool(true)),
+            "false" => Ok(Json::Bool(false)),
+            "null" => Ok(Json::Null),
+            _ => Err(self.error_since(start, format!("unexpected `{}`", word))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_the_span_of_every_value() {
+        let source = "{\n\t\"name\": \"mix\",\n\t\"build\": { \"cflags\": [\"-O2\", \"-g\"] }\n}";
+        let (json, spans) = Json::parse_spanned(source).unwrap();
+        assert_eq!(json.get("name").and_then(Json::as_str), Some("mix"));
+
+        let span = spans["name"];
+        assert_eq!((span.line, span.column), (2, 10));
+        assert_eq!(&source[span.start..span.end], "\"mix\"");
+
+        let span = spans["build.cflags[1]"];
+        assert_eq!((span.line, span.column), (3, 31));
+        assert_eq!(&source[span.start..span.end], "\"-g\"");
+    }
+
+    #[test]
+    fn locates_errors() {
+        let error = Json::parse_spanned("{\n\t\"name\": \"mix\",\n}").unwrap_err();
+        assert_eq!((error.span.line, error.span.column), (3, 1));
+
+        let error = Json::parse_spanned("{} {}").unwrap_err();
+        assert_eq!(error.to_string(), "unexpected content after the end of the document at line 1, column 4");
+    }
+}
@@ -0,0 +1,177 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+pub const BUILD_DIR: &str = "build";
+
+// every file `mix` wrote under `build`, one path relative to it per line, so `mix clean`
+// leaves alone anything else that ended up there
+const RECORD: &str = ".mix-build";
+
+// what a file in a build is, which decides the directory it goes to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Artifact {
+    // `--emit=` dumps
+    Ir,
+    // generated sources and objects on the way to an executable
+    Object,
+    // what the build is for: executables, bytecode and WebAssembly modules
+    Binary,
+    // what `mix build` keeps of each module to skip it next time
+    Cache,
+}
+
+impl Artifact {
+    fn dir(self) -> &'static str {
+        match self {
+            Artifact::Ir => "ir",
+            Artifact::Object => "obj",
+            Artifact::Binary => "bin",
+            Artifact::Cache => "cache",
+        }
+    }
+}
+
+// the output directory of one profile and target: `build/<profile>/<target>/{ir,obj,bin,cache}`
+#[derive(Debug, Clone)]
+pub struct BuildDir {
+    project: PathBuf,
+    build: PathBuf,
+    base: PathBuf,
+}
+
+impl BuildDir {
+    pub fn new(project: &Path, profile: &str, target: &str) -> BuildDir {
+        let build = project.join(BUILD_DIR);
+        let base = build.join(profile).join(target);
+        BuildDir { project: project.to_owned(), build, base }
+    }
+
+    pub fn path(&self, kind: Artifact, name: &str) -> PathBuf {
+        self.base.join(kind.dir()).join(name)
+    }
+
+    // the path for a file an outside tool is about to write, recorded as the build's own
+    pub fn output(&self, kind: Artifact, name: &str) -> Result<PathBuf, String> {
+        let path = self.path(kind, name);
+
+        // one level at a time, so a symlink leading outside the project is refused before
+        // anything is created through it
+        let dirs: Vec<&Path> = path.ancestors().skip(1).take_while(|dir| dir.starts_with(&self.build)).collect();
+        for dir in dirs.into_iter().rev() {
+            if fs::symlink_metadata(dir).is_err() {
+                fs::create_dir(dir).map_err(|e| format!("cannot create `{}`: {}", dir.display(), e))?;
+            }
+            inside(&self.project, dir)?;
+        }
+        if fs::symlink_metadata(&path).is_ok() {
+            inside(&self.project, &path)?;
+        }
+
+        record(&self.build, &path)?;
+        Ok(path)
+    }
+
+    pub fn write(&self, kind: Artifact, name: &str, content: impl AsRef<[u8]>) -> Result<PathBuf, String> {
+        let path = self.output(kind, name)?;
+        fs::write(&path, content).map_err(|e| format!("cannot write `{}`: {}", path.display(), e))?;
+        Ok(path)
+    }
+}
+
+// `path` with every symlink followed, when that stays inside `project` without being it
+fn inside(project: &Path, path: &Path) -> Result<PathBuf, String> {
+    let root = project.canonicalize().map_err(|e| format!("cannot read `{}`: {}", project.display(), e))?;
+    let canonical = path.canonicalize().map_err(|e| format!("cannot read `{}`: {}", path.display(), e))?;
+    if !canonical.starts_with(&root) || canonical == root {
+        return Err(format!("`{}` leads outside the project, leaving it alone", path.display()));
+    }
+    Ok(canonical)
+}
+
+fn record(build: &Path, path: &Path) -> Result<(), String> {
+    let relative = path.strip_prefix(build).unwrap_or(path);
+    let line: Vec<String> = relative.iter().map(|part| part.to_string_lossy().into_owned()).collect();
+    let line = line.join("/");
+
+    let record_path = build.join(RECORD);
+    let mut recorded = fs::read_to_string(&record_path).unwrap_or_default();
+    if recorded.lines().any(|existing| existing == line) {
+        return Ok(());
+    }
+    recorded.push_str(&line);
+    recorded.push('\n');
+    fs::write(&record_path, recorded).map_err(|e| format!("cannot write `{}`: {}", record_path.display(), e))
+}
+
+// removes what `mix build` wrote under the `build` directory of `project`, then the
+// directories that left empty; returns how many files went away
+pub fn clean(project: &Path) -> Result<usize, String> {
+    if !project.join("mix.conf").is_file() {
+        return Err(format!("`{}` is not a project, `mix.conf` file not found", project.display()));
+    }
+
+    let build = project.join(BUILD_DIR);
+    if fs::symlink_metadata(&build).is_err() {
+        return Ok(0);
+    }
+
+    let canonical = inside(project, &build)?;
+
+    let record_path = build.join(RECORD);
+    let Ok(recorded) = fs::read_to_string(&record_path) else {
+        return Err(format!("`{}` was not made by `mix build`, leaving it alone", build.display()));
+    };
+
+    let mut removed = 0;
+    let mut dirs = Vec::new();
+    for line in recorded.lines().filter(|line| !line.is_empty()) {
+        let relative = Path::new(line);
+        // a record edited to point elsewhere is skipped rather than trusted
+        if !relative.components().all(|part| matches!(part, Component::Normal(_))) {
+            continue;
+        }
+
+        let path = build.join(relative);
+        let inside = path.parent()
+            .and_then(|parent| parent.canonicalize().ok())
+            .is_some_and(|parent| parent.starts_with(&canonical));
+        if !inside || !fs::symlink_metadata(&path).is_ok_and(|meta| !meta.is_dir()) {
+            continue;
+        }
+
+        fs::remove_file(&path).map_err(|e| format!("cannot remove `{}`: {}", path.display(), e))?;
+        removed += 1;
+        dirs.extend(relative.ancestors().skip(1).filter(|dir| !dir.as_os_str().is_empty()).map(|dir| build.join(dir)));
+    }
+
+    fs::remove_file(&record_path).map_err(|e| format!("cannot remove `{}`: {}", record_path.display(), e))?;
+
+    // deepest first, and only once nothing else is left in them
+    dirs.sort_by(|a, b| b.components().count().cmp(&a.components().count()).then_with(|| a.cmp(b)));
+    dirs.dedup();
+    for dir in dirs.iter().chain(std::iter::once(&build)) {
+        let _ = fs::remove_dir(dir);
+    }
+
+    Ok(removed)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_to_write_through_a_build_leading_outside() {
+        let root = std::env::temp_dir().join(format!("mix-layout-test-{}", std::process::id()));
+        let (project, outside) = (root.join("project"), root.join("outside"));
+        fs::create_dir_all(&project).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        std::os::unix::fs::symlink(&outside, project.join(BUILD_DIR)).unwrap();
+
+        let result = BuildDir::new(&project, "debug", "bytecode").write(Artifact::Binary, "main.mxb", "");
+        let written = fs::read_dir(&outside).unwrap().count();
+        fs::remove_dir_all(&root).ok();
+        assert!(result.unwrap_err().contains("leads outside the project"));
+        assert_eq!(written, 0);
+    }
+}
@@ -0,0 +1,86 @@
+use super::json::Json;
+use super::manifest::valid_name;
+use super::semver::Version;
+
+use std::fmt;
+
+pub const LOCK_FORMAT: u32 = 1;
+
+// a package `mix install` picked, with the hash of the sources it vendored
+#[derive(Debug, Clone, PartialEq)]
+pub struct LockedPackage {
+    pub name: String,
+    pub version: Version,
+    // `sha256:` and the hex digest of the package files, see `registry::checksum`
+    pub checksum: String,
+    // names of the packages it depends on, each locked in the same file
+    pub dependencies: Vec<String>,
+}
+
+// `mix.lock`: every package of the project, direct or not, at the exact version it builds with
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Lockfile {
+    pub packages: Vec<LockedPackage>,
+}
+
+impl Lockfile {
+    pub fn parse(source: &str) -> Result<Lockfile, String> {
+        let (json, _) = Json::parse_spanned(source).map_err(|e| e.to_string())?;
+
+        match json.get("format") {
+            Some(Json::Number(format)) if *format == LOCK_FORMAT as f64 => {}
+            Some(format) => return Err(format!("unsupported format `{}`", format)),
+            None => return Err("missing `format`".to_owned()),
+        }
+
+        let packages = json.get("packages").and_then(Json::as_array).ok_or("`packages` must be an array")?;
+        let mut lock = Lockfile::default();
+        for (i, package) in packages.iter().enumerate() {
+            let field = |key: &str| package.get(key).and_then(Json::as_str)
+                .ok_or_else(|| format!("`packages[{}].{}` must be a string", i, key));
+
+            // names become directories under `vendor`
+            let name = field("name")?.to_owned();
+            if !valid_name(&name) {
+                return Err(format!("`packages[{}].name`: invalid package name `{}`", i, name));
+            }
+            let version = Version::parse(field("version")?).map_err(|e| format!("`packages[{}].version`: {}", i, e))?;
+            let checksum = field("checksum")?.to_owned();
+            let dependencies = match package.get("dependencies") {
+                Some(Json::Array(items)) => items.iter().map(|item| item.as_str().map(str::to_owned))
+                    .collect::<Option<Vec<String>>>()
+                    .filter(|names| names.iter().all(|name| valid_name(name)))
+                    .ok_or_else(|| format!("`packages[{}].dependencies` must hold package names", i))?,
+                None => Vec::new(),
+                Some(_) => return Err(format!("`packages[{}].dependencies` must be an array", i)),
+            };
+            lock.packages.push(LockedPackage { name, version, checksum, dependencies });
+        }
+
+        Ok(lock)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&LockedPackage> {
+        self.packages.iter().find(|package| package.name == name)
+    }
+
+    pub fn to_json(&self) -> Json {
+        let packages = self.packages.iter().map(|package| Json::Object(vec![
+            ("name".to_owned(), Json::Str(package.name.clone())),
+            ("version".to_owned(), Json::Str(package.version.to_string())),
+            ("checksum".to_owned(), Json::Str(package.checksum.clone())),
+            ("dependencies".to_owned(), Json::Array(package.dependencies.iter().cloned().map(Json::Str).collect())),
+        ])).collect();
+
+        Json::Object(vec![
+            ("format".to_owned(), Json::Number(LOCK_FORMAT as f64)),
+            ("packages".to_owned(), Json::Array(packages)),
+        ])
+    }
+}
+
+impl fmt::Display for Lockfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.to_json())
+    }
+}
@@ -0,0 +1,457 @@
+use super::config::{Backend, BuildConfig};
+use super::json::{Json, JsonError, Span, Spans};
+use super::semver::{Version, VersionReq};
+
+use std::fmt;
+use std::path::{Component, Path};
+
+pub const DEFAULT_ENTRY: &str = "src/main.mx";
+
+// a dependency from `packages`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Package {
+    pub name: String,
+    pub version: VersionReq,
+}
+
+// a named set of build settings, picked with `mix build --profile=<name>`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    pub opt_level: u32,
+    // replaces `build.cflags` when set
+    pub cflags: Option<Vec<String>>,
+}
+
+impl Profile {
+    // `debug` is the one `mix build` uses without `--profile`
+    pub fn defaults() -> Vec<Profile> {
+        vec![
+            Profile { name: "debug".to_owned(), opt_level: 0, cflags: None },
+            Profile { name: "release".to_owned(), opt_level: 2, cflags: None },
+        ]
+    }
+}
+
+// a problem in `mix.conf`, pointing at the value it is about
+#[derive(Debug, Clone)]
+pub struct ManifestError {
+    pub code: &'static str,
+    pub message: String,
+    pub span: Span,
+    pub help: Option<String>,
+}
+
+impl ManifestError {
+    fn with_help(mut self, help: impl Into<String>) -> ManifestError {
+        self.help = Some(help.into());
+        self
+    }
+}
+
+impl From<JsonError> for ManifestError {
+    fn from(error: JsonError) -> ManifestError {
+        ManifestError { code: "E0701", message: error.message, span: error.span, help: None }
+    }
+}
+
+// everything `mix.conf` says about a project
+#[derive(Debug, Clone)]
+pub struct ProjectManifest {
+    pub name: String,
+    pub version: Version,
+    pub author: String,
+    pub packages: Vec<Package>,
+    pub target: Option<String>,
+    // the package registry directory, relative to the project
+    pub registry: Option<String>,
+    // the file holding `main`, relative to the project
+    pub entry: String,
+    pub build: BuildConfig,
+    pub profiles: Vec<Profile>,
+}
+
+impl ProjectManifest {
+    // the manifest of a project `mix create` just made
+    pub fn new(name: &str) -> ProjectManifest {
+        ProjectManifest {
+            name: name.to_owned(),
+            version: Version::new(1, 0, 0),
+            author: "...".to_owned(),
+            packages: Vec::new(),
+            target: None,
+            registry: None,
+            entry: DEFAULT_ENTRY.to_owned(),
+            build: BuildConfig::default(),
+            profiles: Profile::defaults(),
+        }
+    }
+
+    pub fn parse(source: &str) -> Result<ProjectManifest, ManifestError> {
+        let (json, spans) = Json::parse_spanned(source)?;
+        Reader { spans: &spans }.manifest(&json)
+    }
+
+    // an explicit `build.backend` wins over the one the target implies
+    pub fn backend(&self) -> Backend {
+        self.build.backend
+            .or_else(|| self.target.as_deref().and_then(Backend::from_target))
+            .unwrap_or(Backend::Bytecode)
+    }
+
+    pub fn profile(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+
+    // optional keys still at their default are left out
+    pub fn to_json(&self) -> Json {
+        let mut pairs = vec![
+            ("name", Json::Str(self.name.clone())),
+            ("version", Json::Str(self.version.to_string())),
+            ("author", Json::Str(self.author.clone())),
+            ("packages", packages_json(&self.packages)),
+        ];
+
+        if let Some(target) = &self.target {
+            pairs.push(("target", Json::Str(target.clone())));
+        }
+        if let Some(registry) = &self.registry {
+            pairs.push(("registry", Json::Str(registry.clone())));
+        }
+        if self.entry != DEFAULT_ENTRY {
+            pairs.push(("entry", Json::Str(self.entry.clone())));
+        }
+
+        let defaults = BuildConfig::default();
+        let mut build = Vec::new();
+        if let Some(backend) = self.build.backend {
+            build.push(("backend", Json::Str(backend.name().to_owned())));
+        }
+        if self.build.cc != defaults.cc {
+            build.push(("cc", Json::Str(self.build.cc.clone())));
+        }
+        if self.build.cflags != defaults.cflags {
+            build.push(("cflags", strings(&self.build.cflags)));
+        }
+        if self.build.assembler != defaults.assembler {
+            build.push(("as", Json::Str(self.build.assembler.clone())));
+        }
+        if self.build.linker != defaults.linker {
+            build.push(("ld", Json::Str(self.build.linker.clone())));
+        }
+        if !build.is_empty() {
+            pairs.push(("build", object(build)));
+        }
+
+        let defaults = Profile::defaults();
+        let profiles: Vec<(String, Json)> = self.profiles.iter().filter(|profile| !defaults.contains(profile)).map(|profile| {
+            let mut settings = vec![("opt-level", Json::Number(profile.opt_level as f64))];
+            if let Some(cflags) = &profile.cflags {
+                settings.push(("cflags", strings(cflags)));
+            }
+            (profile.name.clone(), object(settings))
+        }).collect();
+        if !profiles.is_empty() {
+            pairs.push(("profiles", Json::Object(profiles)));
+        }
+
+        object(pairs)
+    }
+}
+
+impl fmt::Display for ProjectManifest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.to_json())
+    }
+}
+
+// `source`, the text of a `mix.conf`, with its `packages` replaced by `packages` and everything
+// else left as written, so `mix install` keeps keys and formatting it does not know about
+pub fn with_packages(source: &str, packages: &[Package]) -> Result<String, ManifestError> {
+    let (json, spans) = Json::parse_spanned(source)?;
+    let Json::Object(pairs) = &json else {
+        return Err(ManifestError { code: "E0702", message: "`mix.conf` must hold an object".to_owned(), span: spans[""], help: None });
+    };
+
+    // the whitespace the line holding `offset` starts with
+    let indent = |offset: usize| {
+        let line = &source[source[..offset].rfind('\n').map_or(0, |i| i + 1)..];
+        line[..line.len() - line.trim_start_matches([' ', '\t']).len()].to_owned()
+    };
+    // nested lines follow the indentation of the line the value starts on, one level per tab
+    let render = |indent: &str| {
+        let unit = if indent.is_empty() { "\t" } else { indent };
+        let text = packages_json(packages).to_string();
+        let lines: Vec<String> = text.lines().enumerate().map(|(i, line)| {
+            if i == 0 {
+                return line.to_owned();
+            }
+            let nested = line.trim_start_matches('\t');
+            format!("{}{}{}", indent, unit.repeat(line.len() - nested.len()), nested)
+        }).collect();
+        lines.join("\n")
+    };
+
+    let (start, end, text) = match spans.get("packages") {
+        Some(span) => (span.start, span.end, render(&indent(span.start))),
+        None => match pairs.last().map(|(key, _)| spans[key.as_str()]) {
+            Some(last) => {
+                let indent = indent(last.start);
+                (last.end, last.end, format!(",\n{}\"packages\": {}", indent, render(&indent)))
+            }
+            None => {
+                let open = spans[""].start + 1;
+                (open, open, format!("\n\t\"packages\": {}\n", render("\t")))
+            }
+        },
+    };
+    Ok(format!("{}{}{}", &source[..start], text, &source[end..]))
+}
+
+fn packages_json(packages: &[Package]) -> Json {
+    Json::Array(packages.iter().map(|package| object(vec![
+        ("name", Json::Str(package.name.clone())),
+        ("version", Json::Str(package.version.to_string())),
+    ])).collect())
+}
+
+// project, package and profile names end up in file names
+pub fn valid_name(name: &str) -> bool {
+    name.starts_with(|ch: char| ch.is_ascii_alphabetic())
+        && name.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_')
+}
+
+fn object(pairs: Vec<(&str, Json)>) -> Json {
+    Json::Object(pairs.into_iter().map(|(key, value)| (key.to_owned(), value)).collect())
+}
+
+fn strings(items: &[String]) -> Json {
+    Json::Array(items.iter().cloned().map(Json::Str).collect())
+}
+
+// the path of `key` inside the value at `path`, as `Json::parse_spanned` names it
+fn child(path: &str, key: &str) -> String {
+    if path.is_empty() { key.to_owned() } else { format!("{}.{}", path, key) }
+}
+
+// checks a parsed `mix.conf`, with the spans of its values to point errors at
+struct Reader<'a> {
+    spans: &'a Spans,
+}
+
+impl Reader<'_> {
+    fn manifest(&self, json: &Json) -> Result<ProjectManifest, ManifestError> {
+        self.object(json, "")?;
+        let mut manifest = ProjectManifest::new("");
+
+        let name = self.string(self.required(json, "", "name")?, "name")?;
+        if !valid_name(name) {
+            return Err(self.error("name", format!("invalid project name `{}`", name))
+                .with_help("names start with a letter and only contain letters, digits, `-` and `_`"));
+        }
+        manifest.name = name.to_owned();
+
+        let version = self.string(self.required(json, "", "version")?, "version")?;
+        manifest.version = Version::parse(version).map_err(|e| ManifestError {
+            code: "E0703",
+            message: format!("invalid version `{}`: {}", version, e),
+            span: self.span("version"),
+            help: Some("versions follow Semantic Versioning, like `1.0.0` or `0.2.1-beta.1`".to_owned()),
+        })?;
+
+        manifest.author = match json.get("author") {
+            Some(author) => self.string(author, "author")?.to_owned(),
+            None => String::new(),
+        };
+
+        if let Some(packages) = json.get("packages") {
+            for (i, package) in self.array(packages, "packages")?.iter().enumerate() {
+                let path = format!("packages[{}]", i);
+                self.object(package, &path)?;
+
+                let name = self.string(self.required(package, &path, "name")?, &child(&path, "name"))?;
+                if !valid_name(name) {
+                    return Err(self.error(&child(&path, "name"), format!("invalid package name `{}`", name)));
+                }
+                if manifest.packages.iter().any(|existing| existing.name == name) {
+                    return Err(self.error(&child(&path, "name"), format!("package `{}` is listed more than once", name)));
+                }
+                let version_path = child(&path, "version");
+                let version = self.string(self.required(package, &path, "version")?, &version_path)?;
+                let version = VersionReq::parse(version).map_err(|e| ManifestError {
+                    code: "E0703",
+                    message: format!("invalid version requirement `{}`: {}", version, e),
+                    span: self.span(&version_path),
+                    help: Some("requirements look like `^1.2`, `~0.3.1`, `=1.0.0` or `>=1.0, <2.0`".to_owned()),
+                })?;
+                manifest.packages.push(Package { name: name.to_owned(), version });
+            }
+        }
+
+        if let Some(target) = json.get("target") {
+            let target = self.string(target, "target")?;
+            if Backend::from_target(target).is_none() {
+                return Err(self.error("target", format!("unknown target `{}`, expected `x86_64-gnu-linux` or `wasm32`", target)));
+            }
+            manifest.target = Some(target.to_owned());
+        }
+
+        if let Some(registry) = json.get("registry") {
+            manifest.registry = Some(self.string(registry, "registry")?.to_owned());
+        }
+
+        if let Some(entry) = json.get("entry") {
+            let entry = self.string(entry, "entry")?;
+            let inside = Path::new(entry).components().all(|part| matches!(part, Component::Normal(_) | Component::CurDir));
+            if !inside || !entry.ends_with(".mx") {
+                return Err(self.error("entry", format!("`entry` must be a `.mx` file inside the project, found `{}`", entry)));
+            }
+            manifest.entry = entry.to_owned();
+        }
+
+        if let Some(build) = json.get("build") {
+            self.build(build, &mut manifest.build)?;
+        }
+
+        if let Some(profiles) = json.get("profiles") {
+            for (name, settings) in self.object(profiles, "profiles")? {
+                let path = child("profiles", name);
+                if !valid_name(name) {
+                    return Err(self.error(&path, format!("invalid profile name `{}`", name)));
+                }
+                self.object(settings, &path)?;
+
+                // a profile named after a built-in one changes only the keys it sets
+                let mut profile = manifest.profile(name).cloned()
+                    .unwrap_or(Profile { name: name.clone(), opt_level: 0, cflags: None });
+
+                if let Some(level) = settings.get("opt-level") {
+                    let level_path = child(&path, "opt-level");
+                    profile.opt_level = match level {
+                        Json::Number(level) if [0.0, 1.0, 2.0].contains(level) => *level as u32,
+                        _ => return Err(self.error(&level_path, format!("`{}` must be 0, 1 or 2", level_path))),
+                    };
+                }
+                if let Some(cflags) = settings.get("cflags") {
+                    profile.cflags = Some(self.strings(cflags, &child(&path, "cflags"))?);
+                }
+
+                match manifest.profiles.iter_mut().find(|existing| existing.name == *name) {
+                    Some(existing) => *existing = profile,
+                    None => manifest.profiles.push(profile),
+                }
+            }
+        }
+
+        Ok(manifest)
+    }
+
+    fn build(&self, build: &Json, config: &mut BuildConfig) -> Result<(), ManifestError> {
+        self.object(build, "build")?;
+
+        if let Some(backend) = build.get("backend") {
+            let name = self.string(backend, "build.backend")?;
+            config.backend = Some(Backend::from_name(name).ok_or_else(|| {
+                self.error("build.backend", format!("unknown backend `{}`, expected `bytecode`, `c`, `asm` or `wasm`", name))
+            })?);
+        }
+        if let Some(cc) = build.get("cc") {
+            config.cc = self.string(cc, "build.cc")?.to_owned();
+        }
+        if let Some(cflags) = build.get("cflags") {
+            config.cflags = self.strings(cflags, "build.cflags")?;
+        }
+        if let Some(assembler) = build.get("as") {
+            config.assembler = self.string(assembler, "build.as")?.to_owned();
+        }
+        if let Some(linker) = build.get("ld") {
+            config.linker = self.string(linker, "build.ld")?.to_owned();
+        }
+
+        Ok(())
+    }
+
+    fn span(&self, path: &str) -> Span {
+        self.spans.get(path).or_else(|| self.spans.get("")).copied().unwrap_or_default()
+    }
+
+    fn error(&self, path: &str, message: impl Into<String>) -> ManifestError {
+        ManifestError { code: "E0702", message: message.into(), span: self.span(path), help: None }
+    }
+
+    // a missing key is reported at the opening brace of the object that lacks it
+    fn required<'j>(&self, json: &'j Json, path: &str, key: &str) -> Result<&'j Json, ManifestError> {
+        json.get(key).ok_or_else(|| {
+            let mut error = self.error(path, format!("missing `{}`", child(path, key)));
+            error.span.end = error.span.start + 1;
+            error
+        })
+    }
+
+    fn object<'j>(&self, json: &'j Json, path: &str) -> Result<&'j [(String, Json)], ManifestError> {
+        match json {
+            Json::Object(pairs) => Ok(pairs),
+            _ if path.is_empty() => Err(self.error(path, format!("`mix.conf` must hold an object, found {}", json.type_name()))),
+            _ => Err(self.error(path, format!("`{}` must be an object, found {}", path, json.type_name()))),
+        }
+    }
+
+    fn array<'j>(&self, json: &'j Json, path: &str) -> Result<&'j [Json], ManifestError> {
+        json.as_array().ok_or_else(|| self.error(path, format!("`{}` must be an array, found {}", path, json.type_name())))
+    }
+
+    fn string<'j>(&self, json: &'j Json, path: &str) -> Result<&'j str, ManifestError> {
+        json.as_str().ok_or_else(|| self.error(path, format!("`{}` must be a string, found {}", path, json.type_name())))
+    }
+
+    fn strings(&self, json: &Json, path: &str) -> Result<Vec<String>, ManifestError> {
+        self.array(json, path)?.iter().enumerate()
+            .map(|(i, item)| self.string(item, &format!("{}[{}]", path, i)).map(str::to_owned))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(source: &str) -> ManifestError {
+        ProjectManifest::parse(source).unwrap_err()
+    }
+
+    #[test]
+    fn reads_a_manifest() {
+        let manifest = ProjectManifest::parse(
+            "{\n\t\"name\": \"app\",\n\t\"version\": \"0.2.0\",\n\t\"packages\": [{ \"name\": \"json\", \"version\": \"^1.2\" }]\n}",
+        ).unwrap();
+        assert_eq!(manifest.name, "app");
+        assert_eq!(manifest.version, Version::new(0, 2, 0));
+        assert_eq!(manifest.packages, [Package { name: "json".to_owned(), version: VersionReq::parse("^1.2").unwrap() }]);
+    }
+
+    #[test]
+    fn points_errors_at_the_offending_value() {
+        let source = "{\n\t\"name\": \"app\",\n\t\"version\": \"1.0\"\n}";
+        let e = error(source);
+        assert_eq!((e.code, e.span.line, e.span.column), ("E0703", 3, 13));
+        assert_eq!(&source[e.span.start..e.span.end], "\"1.0\"");
+
+        let e = error("{\n\t\"name\": \"app\",\n\t\"version\": \"1.0.0\",\n\t\"packages\": [{ \"name\": \"json\" }]\n}");
+        assert_eq!((e.code, e.span.line), ("E0702", 4));
+
+        let e = error("{\n\t\"name\": 3,\n\t\"version\": \"1.0.0\"\n}");
+        assert_eq!((e.code, e.span.line, e.span.column), ("E0702", 2, 10));
+
+        let e = error("{\n\t\"name\": \"app\"\n");
+        assert_eq!((e.code, e.span.line), ("E0701", 3));
+    }
+
+    #[test]
+    fn edits_only_the_packages() {
+        let source = "{\n\t\"name\": \"app\",\n\t\"version\": \"1.0.0\",\n\t\"custom\": true,\n\t\"packages\": []\n}\n";
+        let packages = [Package { name: "json".to_owned(), version: VersionReq::parse("^1.2.0").unwrap() }];
+        let edited = with_packages(source, &packages).unwrap();
+        assert!(edited.starts_with("{\n\t\"name\": \"app\",\n\t\"version\": \"1.0.0\",\n\t\"custom\": true,\n"));
+        assert_eq!(ProjectManifest::parse(&edited).unwrap().packages, packages);
+    }
+}
@@ -0,0 +1,9 @@
+pub mod json;
+pub mod config;
+pub mod semver;
+pub mod manifest;
+pub mod hash;
+pub mod lock;
+pub mod registry;
+pub mod sources;
+pub mod layout;
@@ -0,0 +1,475 @@
+use super::hash::Sha256;
+use super::lock::{LockedPackage, Lockfile};
+use super::manifest::{self, Package, ProjectManifest};
+use super::semver::{Version, VersionReq};
+
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// where `mix install` copies packages to, one directory per package
+pub const VENDOR_DIR: &str = "vendor";
+
+// one published version of a package: `<registry>/<name>/<version>/` holding a `mix.conf`
+// and the `src` directory of the package
+#[derive(Debug, Clone)]
+pub struct Release {
+    pub name: String,
+    pub version: Version,
+    pub path: PathBuf,
+    pub dependencies: Vec<Package>,
+}
+
+// a package registry on the file system, so installing works without a network
+pub struct Registry {
+    root: PathBuf,
+    // every release of a package, newest first, read on first use
+    releases: HashMap<String, Vec<Release>>,
+}
+
+impl Registry {
+    pub fn open(root: &Path) -> Result<Registry, String> {
+        if !root.is_dir() {
+            return Err(format!("package registry `{}` not found", root.display()));
+        }
+        Ok(Registry { root: root.to_owned(), releases: HashMap::new() })
+    }
+
+    pub fn releases(&mut self, name: &str) -> Result<&[Release], String> {
+        if !self.releases.contains_key(name) {
+            let releases = self.read(name)?;
+            self.releases.insert(name.to_owned(), releases);
+        }
+        Ok(&self.releases[name])
+    }
+
+    // the newest release that is not a pre-release, the one `mix install <name>` adds
+    pub fn latest(&mut self, name: &str) -> Result<Option<&Release>, String> {
+        Ok(self.releases(name)?.iter().find(|release| release.version.pre.is_empty()))
+    }
+
+    fn read(&self, name: &str) -> Result<Vec<Release>, String> {
+        let dir = self.root.join(name);
+        if !dir.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut releases = Vec::new();
+        let entries = fs::read_dir(&dir).map_err(|e| format!("cannot read `{}`: {}", dir.display(), e))?;
+        for entry in entries {
+            let path = entry.map_err(|e| format!("cannot read `{}`: {}", dir.display(), e))?.path();
+            let Some(folder) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            if folder.starts_with('.') || !path.is_dir() {
+                continue;
+            }
+
+            let version = Version::parse(folder).map_err(|e| format!("`{}` is not a release: {}", path.display(), e))?;
+            let config_path = path.join("mix.conf");
+            let config = fs::read_to_string(&config_path).map_err(|e| format!("cannot read `{}`: {}", config_path.display(), e))?;
+            let manifest = ProjectManifest::parse(&config)
+                .map_err(|e| format!("invalid `{}`: {} at line {}, column {}", config_path.display(), e.message, e.span.line, e.span.column))?;
+            if manifest.name != name || manifest.version != version {
+                return Err(format!("`{}` declares `{} {}`, expected `{} {}`", config_path.display(), manifest.name, manifest.version, name, version));
+            }
+
+            releases.push(Release { name: name.to_owned(), version, path, dependencies: manifest.packages });
+        }
+
+        releases.sort_by(|a, b| b.version.cmp(&a.version));
+        Ok(releases)
+    }
+
+    // picks one release of every package `packages` needs, directly or not, so that every
+    // requirement on it holds; versions in `locked` are tried first, then the newest ones
+    pub fn resolve(&mut self, project: &str, packages: &[Package], locked: &Lockfile) -> Result<Vec<Release>, String> {
+        let pending: Vec<Demand> = packages.iter().map(|package| Demand {
+            name: package.name.clone(),
+            version: package.version.clone(),
+            by: project.to_owned(),
+        }).collect();
+
+        let mut solver = Solver { registry: self, locked, conflict: None };
+        match solver.solve(&Picked::new(), &pending)? {
+            Some(picked) => Ok(picked.into_values().map(|(release, _)| release).collect()),
+            None => Err(format!("cannot resolve the dependencies: {}", solver.conflict.unwrap_or_default())),
+        }
+    }
+}
+
+// a requirement on a package, `by` names who has it
+#[derive(Debug, Clone)]
+struct Demand {
+    name: String,
+    version: VersionReq,
+    by: String,
+}
+
+// the release picked for every package so far, with the demand that picked it
+type Picked = BTreeMap<String, (Release, Demand)>;
+
+struct Solver<'r> {
+    registry: &'r mut Registry,
+    locked: &'r Lockfile,
+    // the first dead end, reported when no choice works out
+    conflict: Option<String>,
+}
+
+impl Solver<'_> {
+    fn solve(&mut self, picked: &Picked, pending: &[Demand]) -> Result<Option<Picked>, String> {
+        let Some((demand, rest)) = pending.split_first() else {
+            return Ok(Some(picked.clone()));
+        };
+
+        if let Some((release, first)) = picked.get(&demand.name) {
+            if demand.version.matches(&release.version) {
+                return self.solve(picked, rest);
+            }
+            self.fail(format!(
+                "`{}` requires `{} {}`, but `{}` requires `{} {}` and got `{}`",
+                demand.by, demand.name, demand.version, first.by, first.name, first.version, release.version,
+            ));
+            return Ok(None);
+        }
+
+        let releases = self.registry.releases(&demand.name)?;
+        if releases.is_empty() {
+            self.fail(format!("no package `{}` in the registry, required by `{}`", demand.name, demand.by));
+            return Ok(None);
+        }
+
+        let mut candidates: Vec<Release> = releases.iter().filter(|release| demand.version.matches(&release.version)).cloned().collect();
+        if candidates.is_empty() {
+            let available: Vec<String> = releases.iter().map(|release| release.version.to_string()).collect();
+            self.fail(format!(
+                "no version of `{}` matches `{}`, required by `{}`, available: {}",
+                demand.name, demand.version, demand.by, available.join(", "),
+            ));
+            return Ok(None);
+        }
+        if let Some(locked) = self.locked.get(&demand.name)
+            && let Some(index) = candidates.iter().position(|release| release.version == locked.version)
+        {
+            let release = candidates.remove(index);
+            candidates.insert(0, release);
+        }
+
+        for release in candidates {
+            let by = format!("{} {}", release.name, release.version);
+            let mut next = rest.to_vec();
+            next.extend(release.dependencies.iter().map(|package| Demand {
+                name: package.name.clone(),
+                version: package.version.clone(),
+                by: by.clone(),
+            }));
+
+            let mut picked = picked.clone();
+            picked.insert(demand.name.clone(), (release, demand.clone()));
+            if let Some(solution) = self.solve(&picked, &next)? {
+                return Ok(Some(solution));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn fail(&mut self, message: String) {
+        self.conflict.get_or_insert(message);
+    }
+}
+
+// the files of the package at `dir` relative to it, in a stable order: its `mix.conf` and
+// everything under `src` that is not hidden
+pub fn package_files(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut files = vec![PathBuf::from("mix.conf")];
+    let src = dir.join("src");
+    if src.is_dir() {
+        collect(dir, &src, &mut files)?;
+    }
+    files[1..].sort();
+    Ok(files)
+}
+
+fn collect(dir: &Path, current: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries = fs::read_dir(current).map_err(|e| format!("cannot read `{}`: {}", current.display(), e))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("cannot read `{}`: {}", current.display(), e))?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+
+        let path = entry.path();
+        let kind = entry.file_type().map_err(|e| format!("cannot read `{}`: {}", path.display(), e))?;
+        if kind.is_dir() {
+            collect(dir, &path, files)?;
+        } else if kind.is_file() {
+            files.push(path.strip_prefix(dir).unwrap_or(&path).to_owned());
+        }
+    }
+    Ok(())
+}
+
+// the hash `mix.lock` records for the package at `dir`, over the path and contents of each of
+// its files, so a vendored copy hashes the same as the release it came from
+pub fn checksum(dir: &Path) -> Result<String, String> {
+    let mut hasher = Sha256::new();
+    for file in package_files(dir)? {
+        let path = dir.join(&file);
+        let content = fs::read(&path).map_err(|e| format!("cannot read `{}`: {}", path.display(), e))?;
+        let name: Vec<String> = file.iter().map(|part| part.to_string_lossy().into_owned()).collect();
+
+        hasher.update(name.join("/").as_bytes());
+        hasher.update(&[0]);
+        hasher.update(&(content.len() as u64).to_be_bytes());
+        hasher.update(&content);
+    }
+    Ok(format!("sha256:{}", hasher.finish()))
+}
+
+// replaces whatever is at `target` with a copy of the package files of `source`
+pub fn vendor(source: &Path, target: &Path) -> Result<(), String> {
+    if target.exists() {
+        fs::remove_dir_all(target).map_err(|e| format!("cannot remove `{}`: {}", target.display(), e))?;
+    }
+
+    for file in package_files(source)? {
+        let (from, to) = (source.join(&file), target.join(&file));
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("cannot create `{}`: {}", parent.display(), e))?;
+        }
+        fs::copy(&from, &to).map_err(|e| format!("cannot copy `{}`: {}", from.display(), e))?;
+    }
+    Ok(())
+}
+
+// `vendor/<name>` in the project at `root`, refused when it resolves to anywhere else, so a
+// symlink or an edited `mix.lock` cannot point an install at other files
+fn vendor_dir(root: &Path, name: &str) -> Result<PathBuf, String> {
+    let target = root.join(VENDOR_DIR).join(name);
+    if !manifest::valid_name(name) {
+        return Err(format!("invalid package name `{}`", name));
+    }
+
+    let vendor = root.join(VENDOR_DIR);
+    if fs::symlink_metadata(&vendor).is_err() {
+        return Ok(target);
+    }
+    let canonical = |path: &Path| path.canonicalize().map_err(|e| format!("cannot read `{}`: {}", path.display(), e));
+    let (project, vendor) = (canonical(root)?, canonical(&vendor)?);
+    let inside = vendor.starts_with(&project) && vendor != project
+        && (fs::symlink_metadata(&target).is_err() || canonical(&target)?.parent() == Some(vendor.as_path()));
+    if !inside {
+        return Err(format!("`{}` leads outside `{}`, leaving it alone", target.display(), VENDOR_DIR));
+    }
+    Ok(target)
+}
+
+// the vendored directory of every package in `mix.lock`, after checking the lock still
+// satisfies `mix.conf` and nobody edited the vendored sources
+pub fn vendored(root: &Path, manifest: &ProjectManifest) -> Result<Vec<(String, PathBuf)>, String> {
+    if manifest.packages.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let lock_path = root.join("mix.lock");
+    let lock = match fs::read_to_string(&lock_path) {
+        Ok(source) => Lockfile::parse(&source).map_err(|e| format!("invalid `mix.lock`: {}", e))?,
+        Err(_) => return Err("`mix.lock` not found, run `mix install` to install the packages of `mix.conf`".to_owned()),
+    };
+
+    for package in &manifest.packages {
+        if !lock.get(&package.name).is_some_and(|locked| package.version.matches(&locked.version)) {
+            return Err(format!("`mix.lock` has no version of `{} {}`, run `mix install` to update it", package.name, package.version));
+        }
+    }
+
+    let mut packages = Vec::new();
+    for locked in &lock.packages {
+        let dir = vendor_dir(root, &locked.name)?;
+        if !checksum(&dir).is_ok_and(|sum| sum == locked.checksum) {
+            return Err(format!(
+                "`{}` does not match `{} {}` in `mix.lock`, run `mix install` to restore it",
+                Path::new(VENDOR_DIR).join(&locked.name).display(), locked.name, locked.version,
+            ));
+        }
+        packages.push((locked.name.clone(), dir));
+    }
+    Ok(packages)
+}
+
+// `--registry=`, then `MIX_REGISTRY`, then `registry` in `mix.conf`, then `~/.mix/registry`
+pub fn registry_path(root: &Path, manifest: &ProjectManifest, flag: Option<&str>) -> Result<PathBuf, String> {
+    if let Some(flag) = flag {
+        return Ok(PathBuf::from(flag));
+    }
+    if let Some(registry) = env::var_os("MIX_REGISTRY") {
+        return Ok(PathBuf::from(registry));
+    }
+    if let Some(registry) = &manifest.registry {
+        return Ok(root.join(registry));
+    }
+    match env::var_os("HOME") {
+        Some(home) => Ok(Path::new(&home).join(".mix").join("registry")),
+        None => Err("no package registry, pass `--registry=<dir>` or set `MIX_REGISTRY`".to_owned()),
+    }
+}
+
+// `mix install [package]`: adds `package`, written `name` or `name@requirement`, to `mix.conf`,
+// picks a version of every package the project at `root` needs, copies them to `vendor` and
+// records them in `mix.lock`; returns a line for each package installed or removed
+pub fn install(root: &Path, mut manifest: ProjectManifest, package: Option<&str>, registry: Option<&str>) -> Result<Vec<String>, String> {
+    let mut registry = Registry::open(&registry_path(root, &manifest, registry)?)?;
+
+    let packages = manifest.packages.clone();
+    if let Some(package) = package {
+        let (name, requirement) = match package.split_once('@') {
+            Some((name, requirement)) => (name, Some(requirement)),
+            None => (package, None),
+        };
+        if !manifest::valid_name(name) {
+            return Err(format!("invalid package name `{}`", name));
+        }
+
+        let existing = manifest.packages.iter().position(|package| package.name == name);
+        let version = match (requirement, existing) {
+            (Some(text), _) => VersionReq::parse(text).map_err(|e| format!("invalid version requirement `{}`: {}", text, e))?,
+            (None, Some(index)) => manifest.packages[index].version.clone(),
+            (None, None) => match registry.latest(name)? {
+                Some(release) => VersionReq::caret(&release.version),
+                None => return Err(format!("no release of `{}` in the registry", name)),
+            },
+        };
+        match existing {
+            Some(index) => manifest.packages[index].version = version,
+            None => manifest.packages.push(Package { name: name.to_owned(), version }),
+        }
+    }
+
+    let lock_path = root.join("mix.lock");
+    let old = match fs::read_to_string(&lock_path) {
+        Ok(source) => Lockfile::parse(&source).map_err(|e| format!("invalid `mix.lock`: {}", e))?,
+        Err(_) => Lockfile::default(),
+    };
+    let releases = registry.resolve(&manifest.name, &manifest.packages, &old)?;
+
+    // a release that changed since it was locked could be anything, so nothing is copied then
+    let mut lock = Lockfile::default();
+    for release in &releases {
+        let checksum = checksum(&release.path)?;
+        if let Some(locked) = old.get(&release.name)
+            && locked.version == release.version
+            && locked.checksum != checksum
+        {
+            return Err(format!(
+                "`{} {}` in the registry does not match its checksum in `mix.lock`, remove it from `mix.lock` to accept the new contents",
+                release.name, release.version,
+            ));
+        }
+        lock.packages.push(LockedPackage {
+            name: release.name.clone(),
+            version: release.version.clone(),
+            checksum,
+            dependencies: release.dependencies.iter().map(|package| package.name.clone()).collect(),
+        });
+    }
+
+    let mut changes = Vec::new();
+    for (release, locked) in releases.iter().zip(&lock.packages) {
+        let target = vendor_dir(root, &release.name)?;
+        if !checksum(&target).is_ok_and(|sum| sum == locked.checksum) {
+            vendor(&release.path, &target)?;
+            changes.push(format!("installed `{} {}`", release.name, release.version));
+        }
+    }
+
+    // only the `vendor` directories of packages the old lock had are removed
+    for stale in old.packages.iter().filter(|package| lock.get(&package.name).is_none()) {
+        let target = vendor_dir(root, &stale.name)?;
+        if target.is_dir() {
+            fs::remove_dir_all(&target).map_err(|e| format!("cannot remove `{}`: {}", target.display(), e))?;
+            changes.push(format!("removed `{} {}`", stale.name, stale.version));
+        }
+    }
+
+    fs::write(&lock_path, lock.to_string()).map_err(|e| format!("cannot write `mix.lock`: {}", e))?;
+    if manifest.packages != packages {
+        let config_path = root.join("mix.conf");
+        let config = fs::read_to_string(&config_path).map_err(|e| format!("cannot read `mix.conf`: {}", e))?;
+        let config = manifest::with_packages(&config, &manifest.packages)
+            .map_err(|e| format!("invalid `mix.conf`: {} at line {}, column {}", e.message, e.span.line, e.span.column))?;
+        fs::write(&config_path, config).map_err(|e| format!("cannot write `mix.conf`: {}", e))?;
+    }
+
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // name, version and the (name, requirement) of each dependency
+    type Spec<'s> = (&'s str, &'s str, &'s [(&'s str, &'s str)]);
+
+    // a registry in a temporary directory holding `releases`
+    fn registry(name: &str, releases: &[Spec]) -> (PathBuf, Registry) {
+        let root = env::temp_dir().join(format!("mix-registry-test-{}-{}", std::process::id(), name));
+        fs::remove_dir_all(&root).ok();
+        for (name, version, dependencies) in releases {
+            let dir = root.join(name).join(version);
+            fs::create_dir_all(dir.join("src")).unwrap();
+            let mut manifest = ProjectManifest::new(name);
+            manifest.version = Version::parse(version).unwrap();
+            manifest.packages = packages(dependencies);
+            fs::write(dir.join("mix.conf"), manifest.to_json().to_string()).unwrap();
+        }
+        let registry = Registry::open(&root).unwrap();
+        (root, registry)
+    }
+
+    fn packages(dependencies: &[(&str, &str)]) -> Vec<Package> {
+        dependencies.iter().map(|(name, version)| Package { name: name.to_string(), version: VersionReq::parse(version).unwrap() }).collect()
+    }
+
+    fn picked(releases: Vec<Release>) -> Vec<String> {
+        let mut picked: Vec<String> = releases.iter().map(|release| format!("{} {}", release.name, release.version)).collect();
+        picked.sort();
+        picked
+    }
+
+    #[test]
+    fn backtracks_to_releases_that_agree() {
+        let (root, mut registry) = registry("backtrack", &[
+            ("a", "1.0.0", &[("b", "^1.0")]),
+            ("a", "1.1.0", &[("b", "^2.0")]),
+            ("b", "1.2.0", &[]),
+            ("b", "2.0.0", &[]),
+        ]);
+        let solution = registry.resolve("app", &packages(&[("a", "^1.0"), ("b", "^1.0")]), &Lockfile::default());
+        fs::remove_dir_all(&root).ok();
+        assert_eq!(picked(solution.unwrap()), ["a 1.0.0", "b 1.2.0"]);
+    }
+
+    #[test]
+    fn prefers_locked_versions() {
+        let (root, mut registry) = registry("locked", &[("a", "1.0.0", &[]), ("a", "1.3.0", &[])]);
+        let locked = Lockfile { packages: vec![LockedPackage {
+            name: "a".to_owned(),
+            version: Version::new(1, 0, 0),
+            checksum: String::new(),
+            dependencies: Vec::new(),
+        }] };
+        let solution = registry.resolve("app", &packages(&[("a", "^1.0")]), &locked);
+        fs::remove_dir_all(&root).ok();
+        assert_eq!(picked(solution.unwrap()), ["a 1.0.0"]);
+    }
+
+    #[test]
+    fn reports_the_first_conflict() {
+        let (root, mut registry) = registry("conflict", &[("a", "1.0.0", &[("b", "^2.0")]), ("b", "1.0.0", &[])]);
+        let error = registry.resolve("app", &packages(&[("a", "^1.0")]), &Lockfile::default()).unwrap_err();
+        fs::remove_dir_all(&root).ok();
+        assert_eq!(error, "cannot resolve the dependencies: no version of `b` matches `^2.0`, required by `a 1.0.0`, available: 1.0.0");
+    }
+}
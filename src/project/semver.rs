@@ -0,0 +1,384 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+// a Semantic Versioning 2.0.0 version, `MAJOR.MINOR.PATCH` with optional `-pre.release`
+// identifiers and `+build` metadata
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    pub pre: Vec<String>,
+    pub build: Vec<String>,
+}
+
+impl Version {
+    pub fn new(major: u64, minor: u64, patch: u64) -> Version {
+        Version { major, minor, patch, pre: Vec::new(), build: Vec::new() }
+    }
+
+    pub fn parse(text: &str) -> Result<Version, String> {
+        let (text, build) = match text.split_once('+') {
+            Some((text, build)) => (text, Self::identifiers(build, "build metadata", false)?),
+            None => (text, Vec::new()),
+        };
+        let (core, pre) = match text.split_once('-') {
+            Some((core, pre)) => (core, Self::identifiers(pre, "pre-release", true)?),
+            None => (text, Vec::new()),
+        };
+
+        let parts: Vec<&str> = core.split('.').collect();
+        let [major, minor, patch] = parts.as_slice() else {
+            return Err(format!("`{}` is not `MAJOR.MINOR.PATCH`", core));
+        };
+
+        Ok(Version {
+            major: Self::number(major)?,
+            minor: Self::number(minor)?,
+            patch: Self::number(patch)?,
+            pre,
+            build,
+        })
+    }
+
+    fn number(part: &str) -> Result<u64, String> {
+        if part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(format!("`{}` is not a number", part));
+        }
+        if part.len() > 1 && part.starts_with('0') {
+            return Err(format!("`{}` has a leading zero", part));
+        }
+        part.parse().map_err(|_| format!("`{}` is too large", part))
+    }
+
+    fn identifiers(text: &str, kind: &str, numeric: bool) -> Result<Vec<String>, String> {
+        text.split('.').map(|id| {
+            if id.is_empty() {
+                return Err(format!("empty {} identifier", kind));
+            }
+            if !id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-') {
+                return Err(format!("{} identifier `{}` may only contain letters, digits and `-`", kind, id));
+            }
+            // numeric pre-release identifiers are compared as numbers
+            if numeric && id.len() > 1 && id.starts_with('0') && id.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(format!("{} identifier `{}` has a leading zero", kind, id));
+            }
+            Ok(id.to_owned())
+        }).collect()
+    }
+}
+
+// precedence as the specification defines it, build metadata only breaks ties
+impl Ord for Version {
+    fn cmp(&self, other: &Version) -> Ordering {
+        (self.major, self.minor, self.patch).cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| compare_pre(&self.pre, &other.pre))
+            .then_with(|| self.build.cmp(&other.build))
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Version) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// a release comes after its pre-releases, and numeric identifiers sort below others
+fn compare_pre(a: &[String], b: &[String]) -> Ordering {
+    match (a.is_empty(), b.is_empty()) {
+        (true, true) => return Ordering::Equal,
+        (true, false) => return Ordering::Greater,
+        (false, true) => return Ordering::Less,
+        _ => {}
+    }
+
+    for (a, b) in a.iter().zip(b) {
+        let order = match (a.parse::<u64>(), b.parse::<u64>()) {
+            (Ok(a), Ok(b)) => a.cmp(&b),
+            (Ok(_), Err(_)) => Ordering::Less,
+            (Err(_), Ok(_)) => Ordering::Greater,
+            (Err(_), Err(_)) => a.cmp(b),
+        };
+        if order != Ordering::Equal {
+            return order;
+        }
+    }
+    a.len().cmp(&b.len())
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if !self.pre.is_empty() {
+            write!(f, "-{}", self.pre.join("."))?;
+        }
+        if !self.build.is_empty() {
+            write!(f, "+{}", self.build.join("."))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Exact,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+    Tilde,
+    Caret,
+}
+
+impl Op {
+    fn symbol(self) -> &'static str {
+        match self {
+            Op::Exact => "=",
+            Op::Greater => ">",
+            Op::GreaterEq => ">=",
+            Op::Less => "<",
+            Op::LessEq => "<=",
+            Op::Tilde => "~",
+            Op::Caret => "^",
+        }
+    }
+}
+
+// one part of a requirement, like `>=1.2`; a missing minor or patch matches any
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Comparator {
+    op: Op,
+    major: u64,
+    minor: Option<u64>,
+    patch: Option<u64>,
+    pre: Vec<String>,
+}
+
+impl Comparator {
+    fn parse(text: &str) -> Result<Comparator, String> {
+        let (op, rest) = [
+            (">=", Op::GreaterEq), ("<=", Op::LessEq), ("=", Op::Exact), (">", Op::Greater),
+            ("<", Op::Less), ("~", Op::Tilde), ("^", Op::Caret),
+        ].iter().find_map(|(symbol, op)| text.strip_prefix(symbol).map(|rest| (*op, rest)))
+            .unwrap_or((Op::Caret, text));
+        let rest = rest.trim_start();
+
+        if rest.contains('+') {
+            return Err(format!("`{}` cannot have build metadata", text));
+        }
+        let (core, pre) = match rest.split_once('-') {
+            Some((core, pre)) => (core, Version::identifiers(pre, "pre-release", true)?),
+            None => (rest, Vec::new()),
+        };
+
+        let parts: Vec<&str> = core.split('.').collect();
+        if parts.len() > 3 {
+            return Err(format!("`{}` has more than three version numbers", core));
+        }
+        let numbers = parts.iter().map(|part| Version::number(part)).collect::<Result<Vec<u64>, String>>()?;
+        if !pre.is_empty() && numbers.len() < 3 {
+            return Err(format!("`{}` needs a full `MAJOR.MINOR.PATCH` before its pre-release", rest));
+        }
+
+        Ok(Comparator { op, major: numbers[0], minor: numbers.get(1).copied(), patch: numbers.get(2).copied(), pre })
+    }
+
+    fn matches(&self, version: &Version) -> bool {
+        match self.op {
+            Op::Exact => self.exact(version),
+            Op::Greater => self.greater(version),
+            Op::GreaterEq => self.exact(version) || self.greater(version),
+            Op::Less => self.less(version),
+            Op::LessEq => self.exact(version) || self.less(version),
+            Op::Tilde => self.tilde(version),
+            Op::Caret => self.caret(version),
+        }
+    }
+
+    fn exact(&self, version: &Version) -> bool {
+        version.major == self.major
+            && self.minor.is_none_or(|minor| version.minor == minor)
+            && self.patch.is_none_or(|patch| version.patch == patch && version.pre == self.pre)
+    }
+
+    fn greater(&self, version: &Version) -> bool {
+        if version.major != self.major {
+            return version.major > self.major;
+        }
+        let Some(minor) = self.minor else { return false };
+        if version.minor != minor {
+            return version.minor > minor;
+        }
+        let Some(patch) = self.patch else { return false };
+        if version.patch != patch {
+            return version.patch > patch;
+        }
+        compare_pre(&version.pre, &self.pre) == Ordering::Greater
+    }
+
+    fn less(&self, version: &Version) -> bool {
+        if version.major != self.major {
+            return version.major < self.major;
+        }
+        let Some(minor) = self.minor else { return false };
+        if version.minor != minor {
+            return version.minor < minor;
+        }
+        let Some(patch) = self.patch else { return false };
+        if version.patch != patch {
+            return version.patch < patch;
+        }
+        compare_pre(&version.pre, &self.pre) == Ordering::Less
+    }
+
+    // `~1.2.3` allows patch updates, `~1` minor ones
+    fn tilde(&self, version: &Version) -> bool {
+        if version.major != self.major || self.minor.is_some_and(|minor| version.minor != minor) {
+            return false;
+        }
+        match self.patch {
+            Some(patch) if version.patch != patch => version.patch > patch,
+            Some(_) => compare_pre(&version.pre, &self.pre) != Ordering::Less,
+            None => true,
+        }
+    }
+
+    // `^1.2.3` allows anything up to the next breaking release: the first non-zero number stays
+    fn caret(&self, version: &Version) -> bool {
+        if version.major != self.major {
+            return false;
+        }
+        let Some(minor) = self.minor else { return true };
+        let Some(patch) = self.patch else {
+            return if self.major > 0 { version.minor >= minor } else { version.minor == minor };
+        };
+
+        if self.major > 0 {
+            if version.minor != minor {
+                return version.minor > minor;
+            }
+        } else if version.minor != minor || (minor == 0 && version.patch != patch) {
+            return false;
+        }
+        if version.patch != patch {
+            return version.patch > patch;
+        }
+        compare_pre(&version.pre, &self.pre) != Ordering::Less
+    }
+}
+
+impl fmt::Display for Comparator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.op.symbol(), self.major)?;
+        if let Some(minor) = self.minor {
+            write!(f, ".{}", minor)?;
+        }
+        if let Some(patch) = self.patch {
+            write!(f, ".{}", patch)?;
+        }
+        if !self.pre.is_empty() {
+            write!(f, "-{}", self.pre.join("."))?;
+        }
+        Ok(())
+    }
+}
+
+// a dependency's version constraint, like `^1.2`, `~0.3.1` or `>=1.0, <2.0`; a bare version is
+// a caret requirement and `*` matches every release
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionReq {
+    comparators: Vec<Comparator>,
+}
+
+impl VersionReq {
+    pub fn parse(text: &str) -> Result<VersionReq, String> {
+        let text = text.trim();
+        if text == "*" {
+            return Ok(VersionReq { comparators: Vec::new() });
+        }
+        if text.is_empty() {
+            return Err("empty version requirement".to_owned());
+        }
+
+        let comparators = text.split(',').map(|part| Comparator::parse(part.trim())).collect::<Result<_, _>>()?;
+        Ok(VersionReq { comparators })
+    }
+
+    // the requirement `mix install` writes for a package it adds at `version`
+    pub fn caret(version: &Version) -> VersionReq {
+        VersionReq { comparators: vec![Comparator {
+            op: Op::Caret,
+            major: version.major,
+            minor: Some(version.minor),
+            patch: Some(version.patch),
+            pre: version.pre.clone(),
+        }] }
+    }
+
+    // pre-releases only match a requirement that names a pre-release of the same version, so
+    // `^1.0` never picks `1.1.0-beta`
+    pub fn matches(&self, version: &Version) -> bool {
+        self.comparators.iter().all(|comparator| comparator.matches(version))
+            && (version.pre.is_empty() || self.comparators.iter().any(|comparator| {
+                !comparator.pre.is_empty()
+                    && (comparator.major, comparator.minor, comparator.patch) == (version.major, Some(version.minor), Some(version.patch))
+            }))
+    }
+}
+
+impl fmt::Display for VersionReq {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.comparators.is_empty() {
+            return write!(f, "*");
+        }
+        let parts: Vec<String> = self.comparators.iter().map(Comparator::to_string).collect();
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(req: &str, version: &str) -> bool {
+        VersionReq::parse(req).unwrap().matches(&Version::parse(version).unwrap())
+    }
+
+    #[test]
+    fn parses_versions() {
+        let version = Version::parse("1.2.3-beta.1+build.5").unwrap();
+        assert_eq!((version.major, version.minor, version.patch), (1, 2, 3));
+        assert_eq!(version.pre, ["beta", "1"]);
+        assert_eq!(version.to_string(), "1.2.3-beta.1+build.5");
+
+        assert!(Version::parse("1.2").is_err());
+        assert!(Version::parse("01.2.3").is_err());
+        assert!(Version::parse("1.2.3-").is_err());
+    }
+
+    #[test]
+    fn orders_pre_releases_before_their_release() {
+        let versions = ["1.0.0-alpha", "1.0.0-alpha.1", "1.0.0-beta", "1.0.0-beta.2", "1.0.0-beta.11", "1.0.0", "1.0.1"];
+        let parsed: Vec<Version> = versions.iter().map(|text| Version::parse(text).unwrap()).collect();
+        assert!(parsed.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn matches_requirements() {
+        assert!(matches("^1.2", "1.9.0"));
+        assert!(!matches("^1.2", "2.0.0"));
+        assert!(!matches("^0.3.1", "0.4.0"));
+        assert!(matches("~0.3.1", "0.3.9"));
+        assert!(!matches("~0.3.1", "0.4.0"));
+        assert!(matches(">=1.0, <2.0", "1.5.0"));
+        assert!(!matches(">=1.0, <2.0", "2.0.0"));
+        assert!(matches("*", "7.0.0"));
+    }
+
+    #[test]
+    fn matches_pre_releases_only_when_named() {
+        assert!(!matches("^1.0", "1.1.0-beta"));
+        assert!(matches("^1.1.0-beta", "1.1.0-beta.2"));
+        assert!(!matches("^1.1.0-beta", "1.2.0-beta"));
+    }
+}
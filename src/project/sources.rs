@@ -0,0 +1,95 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// a `.mx` file of a project, `module` is the path other files import it by
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub module: String,
+    pub path: PathBuf,
+}
+
+// the entry file, with an empty module path, followed by every other `.mx` file under `src`
+// in a stable order: `src/net/http.mx` is the module `net::http`. The files of each package
+// in `packages` come last, under its name: `src/lib.mx` is the package itself and
+// `src/parse.mx` is `json::parse` in the package `json`
+pub fn discover(root: &Path, entry: &str, packages: &[(String, PathBuf)]) -> Result<Vec<SourceFile>, String> {
+    let entry_path = root.join(entry);
+    let mut files = vec![SourceFile { module: String::new(), path: entry_path.clone() }];
+
+    let entry_path = entry_path.canonicalize().unwrap_or(entry_path);
+    modules(&root.join("src"), &[], |path| path.canonicalize().is_ok_and(|path| path == entry_path), &mut files)?;
+
+    for (name, dir) in packages {
+        // package names may contain `-`, module paths may not
+        let name = name.replace('-', "_");
+        let start = files.len();
+        modules(&dir.join("src"), std::slice::from_ref(&name), |_| false, &mut files)?;
+
+        for file in &mut files[start..] {
+            if file.module == format!("{}::lib", name) {
+                file.module = name.clone();
+            }
+        }
+    }
+
+    for (i, file) in files.iter().enumerate().skip(1) {
+        if let Some(other) = files[..i].iter().find(|other| other.module == file.module) {
+            return Err(format!("`{}` and `{}` are both the module `{}`", other.path.display(), file.path.display(), file.module));
+        }
+    }
+
+    Ok(files)
+}
+
+// the `.mx` files under `src` as modules below `prefix`, except those `skip` accepts
+fn modules(src: &Path, prefix: &[String], skip: impl Fn(&Path) -> bool, files: &mut Vec<SourceFile>) -> Result<(), String> {
+    let mut found = Vec::new();
+    if src.is_dir() {
+        collect(src, &mut found)?;
+    }
+    found.sort();
+
+    for path in found {
+        if skip(&path) {
+            continue;
+        }
+
+        let relative = path.strip_prefix(src).unwrap_or(&path).with_extension("");
+        let segments: Vec<&str> = relative.iter().filter_map(|part| part.to_str()).collect();
+        if let Some(invalid) = segments.iter().find(|segment| !is_identifier(segment)) {
+            return Err(format!("`{}` cannot be a module, `{}` is not an identifier", path.display(), invalid));
+        }
+
+        let module: Vec<&str> = prefix.iter().map(String::as_str).chain(segments).collect();
+        files.push(SourceFile { module: module.join("::"), path });
+    }
+
+    Ok(())
+}
+
+fn collect(dir: &Path, found: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("cannot read `{}`: {}", dir.display(), e))?;
+
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("cannot read `{}`: {}", dir.display(), e))?;
+        let path = entry.path();
+        // hidden files are editor and tool leftovers, and symlinked directories could loop
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+
+        let kind = entry.file_type().map_err(|e| format!("cannot read `{}`: {}", path.display(), e))?;
+        if kind.is_dir() {
+            collect(&path, found)?;
+        } else if path.extension().is_some_and(|extension| extension == "mx") && path.is_file() {
+            found.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.starts_with(|ch: char| ch.is_ascii_alphabetic() || ch == '_')
+        && name.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+}
@@ -0,0 +1,251 @@
+use super::value::Value;
+use crate::compiler::diagnostic::Diagnostic;
+use crate::compiler::node::Location;
+
+use std::io::{self, BufRead, Write};
+
+// a runtime error before it is tied to the source location that caused it
+#[derive(Debug, Clone)]
+pub struct Fault {
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl Fault {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self::with_code("E0504", message)
+    }
+
+    pub fn with_code(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn at(self, loc: &Location) -> Box<Diagnostic> {
+        Box::new(Diagnostic::error(self.code, self.message, loc.clone()))
+    }
+}
+
+// methods every list, dict and string value has
+pub fn method(receiver: Value, name: &str, mut args: Vec<Value>) -> Result<Value, Fault> {
+    let arg = |args: &mut Vec<Value>| if args.is_empty() { Value::Void } else { args.remove(0) };
+
+    match (&receiver, name) {
+        (Value::List(items), "len") => Ok(Value::Int(items.borrow().len() as i64)),
+        (Value::Dict(pairs), "len") => Ok(Value::Int(pairs.borrow().len() as i64)),
+        (Value::Str(value), "len") => Ok(Value::Int(value.chars().count() as i64)),
+        (Value::List(items), "push") => {
+            items.borrow_mut().push(arg(&mut args));
+            Ok(Value::Void)
+        }
+        (Value::List(items), "pop") => items.borrow_mut().pop()
+            .ok_or_else(|| Fault::with_code("E0502", "pop from an empty list")),
+        (Value::List(items), "get") => {
+            let index = index(&arg(&mut args), items.borrow().len())?;
+            Ok(items.borrow()[index].clone())
+        }
+        (Value::List(items), "set") => {
+            let index = index(&arg(&mut args), items.borrow().len())?;
+            items.borrow_mut()[index] = arg(&mut args);
+            Ok(Value::Void)
+        }
+        (Value::List(items), "contains") => {
+            let needle = arg(&mut args);
+            Ok(Value::Bool(items.borrow().contains(&needle)))
+        }
+        (Value::Dict(pairs), "get") => {
+            let key = arg(&mut args);
+            match pairs.borrow().iter().find(|(existing, _)| *existing == key) {
+                Some((_, value)) => Ok(value.clone()),
+                None => Err(Fault::with_code("E0503", format!("key `{}` not found in dict", key))),
+            }
+        }
+        (Value::Dict(pairs), "set") => {
+            let key = arg(&mut args);
+            let value = arg(&mut args);
+            let mut pairs = pairs.borrow_mut();
+            match pairs.iter_mut().find(|(existing, _)| *existing == key) {
+                Some(slot) => slot.1 = value,
+                None => pairs.push((key, value)),
+            }
+            Ok(Value::Void)
+        }
+        (Value::Dict(pairs), "contains") => {
+            let key = arg(&mut args);
+            Ok(Value::Bool(pairs.borrow().iter().any(|(existing, _)| *existing == key)))
+        }
+        (Value::Dict(pairs), "remove") => {
+            let key = arg(&mut args);
+            pairs.borrow_mut().retain(|(existing, _)| *existing != key);
+            Ok(Value::Void)
+        }
+        (Value::Dict(pairs), "keys") => Ok(Value::list(pairs.borrow().iter().map(|pair| pair.0.clone()).collect())),
+        (Value::Dict(pairs), "values") => Ok(Value::list(pairs.borrow().iter().map(|pair| pair.1.clone()).collect())),
+        (Value::Str(value), "contains") => match arg(&mut args) {
+            Value::Str(needle) => Ok(Value::Bool(value.contains(needle.as_str()))),
+            other => Err(Fault::new(format!("expected `str`, found `{}`", other.type_name()))),
+        },
+        (Value::Null, _) => Err(Fault::new(format!("cannot call `{}` on `null`", name))),
+        _ => Err(Fault::new(format!("no method `{}` on `{}`", name, receiver.type_name()))),
+    }
+}
+
+fn index(index: &Value, len: usize) -> Result<usize, Fault> {
+    match index {
+        Value::Int(i) if *i >= 0 && (*i as usize) < len => Ok(*i as usize),
+        Value::Int(i) => Err(Fault::with_code("E0502", format!("index {} out of bounds for a list of length {}", i, len))),
+        other => Err(Fault::new(format!("list index must be `int`, found `{}`", other.type_name()))),
+    }
+}
+
+pub fn std_function(name: &str, args: Vec<Value>) -> Result<Value, Fault> {
+    let text = args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>().join(" ");
+    let first = args.first().cloned().unwrap_or(Value::Void);
+
+    match name {
+        "print" => {
+            print!("{}", text);
+            Ok(Value::Void)
+        }
+        "println" => {
+            println!("{}", text);
+            Ok(Value::Void)
+        }
+        "input" => {
+            print!("{}", text);
+            io::stdout().flush().ok();
+
+            let mut line = String::new();
+            io::stdin().lock().read_line(&mut line).ok();
+            Ok(Value::Str(line.trim_end_matches(['\n', '\r']).to_owned()))
+        }
+        "str" => Ok(Value::Str(text)),
+        "int" | "long" => match first {
+            Value::Int(value) => Ok(Value::Int(value)),
+            Value::Float(value) => Ok(Value::Int(value as i64)),
+            Value::Bool(value) => Ok(Value::Int(value as i64)),
+            Value::Str(value) => value.trim().parse().map(Value::Int)
+                .map_err(|_| Fault::new(format!("cannot convert `{}` to `{}`", value, name))),
+            other => Err(Fault::new(format!("cannot convert `{}` to `{}`", other.type_name(), name))),
+        },
+        "float" | "double" => match first {
+            Value::Int(value) => Ok(Value::Float(value as f64)),
+            Value::Float(value) => Ok(Value::Float(value)),
+            Value::Str(value) => value.trim().parse().map(Value::Float)
+                .map_err(|_| Fault::new(format!("cannot convert `{}` to `{}`", value, name))),
+            other => Err(Fault::new(format!("cannot convert `{}` to `{}`", other.type_name(), name))),
+        },
+        "len" => method(first, "len", Vec::new()),
+        _ => Err(Fault::new(format!("no function `{}` in module `std`", name))),
+    }
+}
+
+pub fn binary(opr: &str, left: Value, right: Value) -> Result<Value, Fault> {
+    match (opr, &left, &right) {
+        ("==", _, _) => Ok(Value::Bool(left == right)),
+        ("!=", _, _) => Ok(Value::Bool(left != right)),
+        ("&&" | "||", Value::Bool(_), Value::Bool(b)) => Ok(Value::Bool(*b)),
+        ("+", Value::Str(a), Value::Str(b)) => Ok(Value::Str(format!("{}{}", a, b))),
+        ("<", Value::Str(a), Value::Str(b)) => Ok(Value::Bool(a < b)),
+        (">", Value::Str(a), Value::Str(b)) => Ok(Value::Bool(a > b)),
+        ("<=", Value::Str(a), Value::Str(b)) => Ok(Value::Bool(a <= b)),
+        (">=", Value::Str(a), Value::Str(b)) => Ok(Value::Bool(a >= b)),
+        (_, Value::Int(a), Value::Int(b)) => int_op(opr, *a, *b),
+        (_, Value::Int(_) | Value::Float(_), Value::Int(_) | Value::Float(_)) => {
+            let (a, b) = (left.as_float().unwrap_or(0.0), right.as_float().unwrap_or(0.0));
+            match opr {
+                "+" => Ok(Value::Float(a + b)),
+                "-" => Ok(Value::Float(a - b)),
+                "*" => Ok(Value::Float(a * b)),
+                "/" => Ok(Value::Float(a / b)),
+                "%" => Ok(Value::Float(a % b)),
+                "^" => Ok(Value::Float(a.powf(b))),
+                "<" => Ok(Value::Bool(a < b)),
+                ">" => Ok(Value::Bool(a > b)),
+                "<=" => Ok(Value::Bool(a <= b)),
+                ">=" => Ok(Value::Bool(a >= b)),
+                _ => Err(operand_error(opr, &left, &right)),
+            }
+        }
+        _ => Err(operand_error(opr, &left, &right)),
+    }
+}
+
+fn int_op(opr: &str, a: i64, b: i64) -> Result<Value, Fault> {
+    let value = match opr {
+        "+" => a.wrapping_add(b),
+        "-" => a.wrapping_sub(b),
+        "*" => a.wrapping_mul(b),
+        "/" | "%" if b == 0 => {
+            return Err(Fault::with_code("E0501", "attempt to divide by zero"));
+        }
+        "/" => a.wrapping_div(b),
+        "%" => a.wrapping_rem(b),
+        "^" if b < 0 => return Err(Fault::with_code("E0507", "attempt to raise an integer to a negative power")),
+        "^" => wrapping_pow(a, b),
+        "&" => a & b,
+        "|" => a | b,
+        "<<" => a.wrapping_shl(b as u32),
+        ">>" => a.wrapping_shr(b as u32),
+        "<" => return Ok(Value::Bool(a < b)),
+        ">" => return Ok(Value::Bool(a > b)),
+        "<=" => return Ok(Value::Bool(a <= b)),
+        ">=" => return Ok(Value::Bool(a >= b)),
+        _ => return Err(operand_error(opr, &Value::Int(a), &Value::Int(b))),
+    };
+
+    Ok(Value::Int(value))
+}
+
+// `a ^ b` by squaring with wrapping multiplication, over the whole exponent like the native
+// backends; `i64::wrapping_pow` only takes a `u32`
+fn wrapping_pow(mut base: i64, mut exponent: i64) -> i64 {
+    let mut result: i64 = 1;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = result.wrapping_mul(base);
+        }
+        base = base.wrapping_mul(base);
+        exponent >>= 1;
+    }
+    result
+}
+
+pub fn unary(opr: &str, value: Value) -> Result<Value, Fault> {
+    match (opr, value) {
+        ("-", Value::Int(value)) => Ok(Value::Int(value.wrapping_neg())),
+        ("-", Value::Float(value)) => Ok(Value::Float(-value)),
+        ("+", value @ (Value::Int(_) | Value::Float(_))) => Ok(value),
+        ("!", Value::Bool(value)) => Ok(Value::Bool(!value)),
+        ("~", Value::Int(value)) => Ok(Value::Int(!value)),
+        (opr, value) => Err(Fault::new(format!("cannot apply unary `{}` to `{}`", opr, value.type_name()))),
+    }
+}
+
+fn operand_error(opr: &str, left: &Value, right: &Value) -> Fault {
+    Fault::new(format!("cannot apply `{}` to `{}` and `{}`", opr, left.type_name(), right.type_name()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn int(opr: &str, a: i64, b: i64) -> Value {
+        binary(opr, Value::Int(a), Value::Int(b)).unwrap()
+    }
+
+    #[test]
+    fn integer_powers_wrap_over_the_whole_exponent() {
+        assert_eq!(int("^", 3, 5_000_000_001), Value::Int(7583655624082192387));
+        assert_eq!(int("^", -7, (1 << 62) + 3), Value::Int(-343));
+        assert_eq!(int("^", 2, 63), Value::Int(i64::MIN));
+        assert_eq!(int("^", 5, 0), Value::Int(1));
+    }
+
+    #[test]
+    fn negative_integer_powers_fail() {
+        assert_eq!(binary("^", Value::Int(2), Value::Int(-1)).unwrap_err().code, "E0507");
+    }
+}
@@ -0,0 +1,874 @@
+use crate::compiler::node::Location;
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+// file header, followed by the format version
+const MAGIC: &[u8; 4] = b"MXB\0";
+const VERSION: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    // push constant `n` of the module
+    Const(u32),
+    Null,
+    True,
+    False,
+    Void,
+    Pop,
+    Dup,
+    // locals of the current call, parameters come first
+    Load(u32),
+    Store(u32),
+    // push function `n` as a value
+    Function(u32),
+    // push struct `n`, calling it builds an instance
+    Type(u32),
+    // push variant `v` of enum `e`
+    Variant(u32, u32),
+    // field named by a string constant
+    GetField(u32),
+    SetField(u32),
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+    BitAnd,
+    BitOr,
+    Shl,
+    Shr,
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Neg,
+    Pos,
+    Not,
+    BitNot,
+    Jump(u32),
+    // both pop the condition
+    JumpIfFalse(u32),
+    JumpIfTrue(u32),
+    // argument count, the callee sits below the arguments
+    Call(u32),
+    // method name constant and argument count, the receiver sits below the arguments
+    CallMethod(u32, u32),
+    // `std` function name constant and argument count
+    CallStd(u32, u32),
+    Return,
+    List(u32),
+    Dict(u32),
+    Range,
+    // pop an iterable and keep a snapshot of its items in locals `n` and `n + 1`
+    IterInit(u32),
+    // push the next item of the iterator in local `n`, or jump when it is exhausted
+    IterNext(u32, u32),
+    // raise a runtime error with a string constant message
+    Fail(u32),
+}
+
+impl Op {
+    // the source operator of arithmetic, comparison and unary ops
+    pub fn symbol(&self) -> Option<&'static str> {
+        let symbol = match self {
+            Op::Add | Op::Pos => "+",
+            Op::Sub | Op::Neg => "-",
+            Op::Mul => "*",
+            Op::Div => "/",
+            Op::Rem => "%",
+            Op::Pow => "^",
+            Op::BitAnd => "&",
+            Op::BitOr => "|",
+            Op::Shl => "<<",
+            Op::Shr => ">>",
+            Op::Eq => "==",
+            Op::Ne => "!=",
+            Op::Lt => "<",
+            Op::Gt => ">",
+            Op::Le => "<=",
+            Op::Ge => ">=",
+            Op::Not => "!",
+            Op::BitNot => "~",
+            _ => return None,
+        };
+        Some(symbol)
+    }
+
+    pub fn binary(opr: &str) -> Option<Op> {
+        let op = match opr {
+            "+" => Op::Add,
+            "-" => Op::Sub,
+            "*" => Op::Mul,
+            "/" => Op::Div,
+            "%" => Op::Rem,
+            "^" => Op::Pow,
+            "&" => Op::BitAnd,
+            "|" => Op::BitOr,
+            "<<" => Op::Shl,
+            ">>" => Op::Shr,
+            "==" => Op::Eq,
+            "!=" => Op::Ne,
+            "<" => Op::Lt,
+            ">" => Op::Gt,
+            "<=" => Op::Le,
+            ">=" => Op::Ge,
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    pub fn unary(opr: &str) -> Option<Op> {
+        let op = match opr {
+            "-" => Op::Neg,
+            "+" => Op::Pos,
+            "!" => Op::Not,
+            "~" => Op::BitNot,
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Op::Const(_) => "CONST",
+            Op::Null => "NULL",
+            Op::True => "TRUE",
+            Op::False => "FALSE",
+            Op::Void => "VOID",
+            Op::Pop => "POP",
+            Op::Dup => "DUP",
+            Op::Load(_) => "LOAD",
+            Op::Store(_) => "STORE",
+            Op::Function(_) => "FUNCTION",
+            Op::Type(_) => "TYPE",
+            Op::Variant(_, _) => "VARIANT",
+            Op::GetField(_) => "GET_FIELD",
+            Op::SetField(_) => "SET_FIELD",
+            Op::Add => "ADD",
+            Op::Sub => "SUB",
+            Op::Mul => "MUL",
+            Op::Div => "DIV",
+            Op::Rem => "REM",
+            Op::Pow => "POW",
+            Op::BitAnd => "BIT_AND",
+            Op::BitOr => "BIT_OR",
+            Op::Shl => "SHL",
+            Op::Shr => "SHR",
+            Op::Eq => "EQ",
+            Op::Ne => "NE",
+            Op::Lt => "LT",
+            Op::Gt => "GT",
+            Op::Le => "LE",
+            Op::Ge => "GE",
+            Op::Neg => "NEG",
+            Op::Pos => "POS",
+            Op::Not => "NOT",
+            Op::BitNot => "BIT_NOT",
+            Op::Jump(_) => "JUMP",
+            Op::JumpIfFalse(_) => "JUMP_IF_FALSE",
+            Op::JumpIfTrue(_) => "JUMP_IF_TRUE",
+            Op::Call(_) => "CALL",
+            Op::CallMethod(_, _) => "CALL_METHOD",
+            Op::CallStd(_, _) => "CALL_STD",
+            Op::Return => "RETURN",
+            Op::List(_) => "LIST",
+            Op::Dict(_) => "DICT",
+            Op::Range => "RANGE",
+            Op::IterInit(_) => "ITER_INIT",
+            Op::IterNext(_, _) => "ITER_NEXT",
+            Op::Fail(_) => "FAIL",
+        }
+    }
+
+    // opcode byte and operands, in the order they are serialized
+    fn encode(&self) -> (u8, [u32; 2], usize) {
+        match *self {
+            Op::Const(a) => (0, [a, 0], 1),
+            Op::Null => (1, [0, 0], 0),
+            Op::True => (2, [0, 0], 0),
+            Op::False => (3, [0, 0], 0),
+            Op::Void => (4, [0, 0], 0),
+            Op::Pop => (5, [0, 0], 0),
+            Op::Dup => (6, [0, 0], 0),
+            Op::Load(a) => (7, [a, 0], 1),
+            Op::Store(a) => (8, [a, 0], 1),
+            Op::Function(a) => (9, [a, 0], 1),
+            Op::Type(a) => (10, [a, 0], 1),
+            Op::Variant(a, b) => (11, [a, b], 2),
+            Op::GetField(a) => (12, [a, 0], 1),
+            Op::SetField(a) => (13, [a, 0], 1),
+            Op::Add => (14, [0, 0], 0),
+            Op::Sub => (15, [0, 0], 0),
+            Op::Mul => (16, [0, 0], 0),
+            Op::Div => (17, [0, 0], 0),
+            Op::Rem => (18, [0, 0], 0),
+            Op::Pow => (19, [0, 0], 0),
+            Op::BitAnd => (20, [0, 0], 0),
+            Op::BitOr => (21, [0, 0], 0),
+            Op::Shl => (22, [0, 0], 0),
+            Op::Shr => (23, [0, 0], 0),
+            Op::Eq => (24, [0, 0], 0),
+            Op::Ne => (25, [0, 0], 0),
+            Op::Lt => (26, [0, 0], 0),
+            Op::Gt => (27, [0, 0], 0),
+            Op::Le => (28, [0, 0], 0),
+            Op::Ge => (29, [0, 0], 0),
+            Op::Neg => (30, [0, 0], 0),
+            Op::Pos => (31, [0, 0], 0),
+            Op::Not => (32, [0, 0], 0),
+            Op::BitNot => (33, [0, 0], 0),
+            Op::Jump(a) => (34, [a, 0], 1),
+            Op::JumpIfFalse(a) => (35, [a, 0], 1),
+            Op::JumpIfTrue(a) => (36, [a, 0], 1),
+            Op::Call(a) => (37, [a, 0], 1),
+            Op::CallMethod(a, b) => (38, [a, b], 2),
+            Op::CallStd(a, b) => (39, [a, b], 2),
+            Op::Return => (40, [0, 0], 0),
+            Op::List(a) => (41, [a, 0], 1),
+            Op::Dict(a) => (42, [a, 0], 1),
+            Op::Range => (43, [0, 0], 0),
+            Op::IterInit(a) => (44, [a, 0], 1),
+            Op::IterNext(a, b) => (45, [a, b], 2),
+            Op::Fail(a) => (46, [a, 0], 1),
+        }
+    }
+
+    fn decode(reader: &mut Reader) -> Result<Op, String> {
+        let opcode = reader.byte()?;
+        let mut arg = || reader.u32();
+
+        let op = match opcode {
+            0 => Op::Const(arg()?),
+            1 => Op::Null,
+            2 => Op::True,
+            3 => Op::False,
+            4 => Op::Void,
+            5 => Op::Pop,
+            6 => Op::Dup,
+            7 => Op::Load(arg()?),
+            8 => Op::Store(arg()?),
+            9 => Op::Function(arg()?),
+            10 => Op::Type(arg()?),
+            11 => Op::Variant(arg()?, arg()?),
+            12 => Op::GetField(arg()?),
+            13 => Op::SetField(arg()?),
+            14 => Op::Add,
+            15 => Op::Sub,
+            16 => Op::Mul,
+            17 => Op::Div,
+            18 => Op::Rem,
+            19 => Op::Pow,
+            20 => Op::BitAnd,
+            21 => Op::BitOr,
+            22 => Op::Shl,
+            23 => Op::Shr,
+            24 => Op::Eq,
+            25 => Op::Ne,
+            26 => Op::Lt,
+            27 => Op::Gt,
+            28 => Op::Le,
+            29 => Op::Ge,
+            30 => Op::Neg,
+            31 => Op::Pos,
+            32 => Op::Not,
+            33 => Op::BitNot,
+            34 => Op::Jump(arg()?),
+            35 => Op::JumpIfFalse(arg()?),
+            36 => Op::JumpIfTrue(arg()?),
+            37 => Op::Call(arg()?),
+            38 => Op::CallMethod(arg()?, arg()?),
+            39 => Op::CallStd(arg()?, arg()?),
+            40 => Op::Return,
+            41 => Op::List(arg()?),
+            42 => Op::Dict(arg()?),
+            43 => Op::Range,
+            44 => Op::IterInit(arg()?),
+            45 => Op::IterNext(arg()?, arg()?),
+            46 => Op::Fail(arg()?),
+            other => return Err(format!("unknown opcode {}", other)),
+        };
+
+        Ok(op)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant {
+    Int(i64),
+    Float(f64),
+    Str(String),
+}
+
+// source position of an instruction, for runtime errors
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Span {
+    pub line: u32,
+    pub column: u32,
+    pub start: u32,
+    pub end: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Function {
+    pub name: String,
+    pub arity: u32,
+    // methods keep `self` in local 0, ahead of the parameters
+    pub receiver: bool,
+    pub locals: u32,
+    pub code: Vec<Op>,
+    pub spans: Vec<Span>,
+    // source file the spans point into
+    pub file: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct StructDef {
+    pub name: String,
+    pub fields: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EnumDef {
+    pub name: String,
+    pub variants: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Method {
+    pub owner: String,
+    pub name: String,
+    pub function: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Module {
+    // the entry file of the program
+    pub file: String,
+    pub constants: Vec<Constant>,
+    pub functions: Vec<Function>,
+    pub structs: Vec<StructDef>,
+    pub enums: Vec<EnumDef>,
+    pub methods: Vec<Method>,
+    // index of `main`
+    pub entry: Option<u32>,
+    // hash of each source file as it was compiled, so runtime errors are only underlined in
+    // a file that did not change since
+    pub sources: Vec<(String, String)>,
+}
+
+impl Module {
+    // the entry file and every file a function was compiled from
+    pub fn files(&self) -> Vec<&String> {
+        let mut files: Vec<&String> = std::iter::once(&self.file).chain(self.functions.iter().map(|function| &function.file)).collect();
+        files.sort();
+        files.dedup();
+        files
+    }
+
+    pub fn location(&self, function: &Function, span: &Span) -> Location {
+        Location {
+            file: function.file.clone(),
+            line: span.line as usize,
+            column: span.column as usize,
+            start: span.start as usize,
+            end: span.end as usize,
+        }
+    }
+
+    // the functions compiled from `file` as a module of their own, for the build cache: what
+    // they refer to elsewhere comes along by name, functions as stubs without code, so a later
+    // build can link them into a module laid out differently
+    pub fn portable(&self, file: &str) -> Module {
+        let mut out = Module { file: file.to_owned(), ..Module::default() };
+        let mut functions = HashMap::new();
+        for (i, function) in self.functions.iter().enumerate().filter(|(_, function)| function.file == file) {
+            functions.insert(i as u32, out.functions.len() as u32);
+            out.functions.push(function.clone());
+        }
+
+        let (mut constants, mut structs, mut enums) = (HashMap::new(), HashMap::new(), HashMap::new());
+        for index in 0..out.functions.len() {
+            let mut code = std::mem::take(&mut out.functions[index].code);
+            for op in code.iter_mut() {
+                let mut constant = |index: &mut u32, out: &mut Module| {
+                    *index = *constants.entry(*index).or_insert_with(|| {
+                        out.constants.push(self.constants[*index as usize].clone());
+                        out.constants.len() as u32 - 1
+                    });
+                };
+
+                match op {
+                    Op::Const(index) | Op::GetField(index) | Op::SetField(index) | Op::Fail(index) |
+                    Op::CallMethod(index, _) | Op::CallStd(index, _) => constant(index, &mut out),
+                    Op::Function(index) => {
+                        *index = *functions.entry(*index).or_insert_with(|| {
+                            let function = &self.functions[*index as usize];
+                            out.functions.push(Function {
+                                name: function.name.clone(),
+                                arity: function.arity,
+                                receiver: function.receiver,
+                                file: function.file.clone(),
+                                ..Function::default()
+                            });
+                            out.functions.len() as u32 - 1
+                        });
+                    }
+                    Op::Type(index) => {
+                        *index = *structs.entry(*index).or_insert_with(|| {
+                            out.structs.push(self.structs[*index as usize].clone());
+                            out.structs.len() as u32 - 1
+                        });
+                    }
+                    Op::Variant(index, _) => {
+                        *index = *enums.entry(*index).or_insert_with(|| {
+                            out.enums.push(self.enums[*index as usize].clone());
+                            out.enums.len() as u32 - 1
+                        });
+                    }
+                    _ => {}
+                }
+            }
+            out.functions[index].code = code;
+        }
+
+        out
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Writer::default();
+
+        out.bytes.extend_from_slice(MAGIC);
+        out.u32(VERSION);
+        out.str(&self.file);
+
+        out.u32(self.sources.len() as u32);
+        for (file, hash) in &self.sources {
+            out.str(file);
+            out.str(hash);
+        }
+
+        out.u32(self.constants.len() as u32);
+        for constant in &self.constants {
+            match constant {
+                Constant::Int(value) => {
+                    out.bytes.push(0);
+                    out.bytes.extend_from_slice(&value.to_le_bytes());
+                }
+                Constant::Float(value) => {
+                    out.bytes.push(1);
+                    out.bytes.extend_from_slice(&value.to_le_bytes());
+                }
+                Constant::Str(value) => {
+                    out.bytes.push(2);
+                    out.str(value);
+                }
+            }
+        }
+
+        out.u32(self.structs.len() as u32);
+        for def in &self.structs {
+            out.str(&def.name);
+            out.strs(&def.fields);
+        }
+
+        out.u32(self.enums.len() as u32);
+        for def in &self.enums {
+            out.str(&def.name);
+            out.strs(&def.variants);
+        }
+
+        out.u32(self.methods.len() as u32);
+        for method in &self.methods {
+            out.str(&method.owner);
+            out.str(&method.name);
+            out.u32(method.function);
+        }
+
+        out.u32(self.functions.len() as u32);
+        for function in &self.functions {
+            out.str(&function.name);
+            out.u32(function.arity);
+            out.bytes.push(function.receiver as u8);
+            out.u32(function.locals);
+            out.str(&function.file);
+
+            out.u32(function.code.len() as u32);
+            for (op, span) in function.code.iter().zip(&function.spans) {
+                let (opcode, args, count) = op.encode();
+                out.bytes.push(opcode);
+                for arg in &args[..count] {
+                    out.u32(*arg);
+                }
+
+                out.u32(span.line);
+                out.u32(span.column);
+                out.u32(span.start);
+                out.u32(span.end);
+            }
+        }
+
+        out.u32(self.entry.map(|entry| entry + 1).unwrap_or(0));
+
+        out.bytes
+    }
+
+    // a module to run, every instruction checked so a damaged file cannot crash the VM
+    pub fn from_bytes(bytes: &[u8]) -> Result<Module, String> {
+        let module = Self::read(bytes)?;
+        module.verify(false)?;
+        Ok(module)
+    }
+
+    // like `from_bytes`, but for a module `portable` made, whose stubs have no code
+    pub fn from_portable_bytes(bytes: &[u8]) -> Result<Module, String> {
+        let module = Self::read(bytes)?;
+        module.verify(true)?;
+        Ok(module)
+    }
+
+    fn read(bytes: &[u8]) -> Result<Module, String> {
+        let mut reader = Reader::new(bytes);
+
+        if bytes.len() < MAGIC.len() || &bytes[..MAGIC.len()] != MAGIC {
+            return Err("not a Mix bytecode file".to_owned());
+        }
+        reader.pos = MAGIC.len();
+
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(format!("unsupported bytecode version {}, expected {}", version, VERSION));
+        }
+
+        let mut module = Module {
+            file: reader.str()?,
+            ..Module::default()
+        };
+
+        for _ in 0..reader.u32()? {
+            let file = reader.str()?;
+            module.sources.push((file, reader.str()?));
+        }
+
+        for _ in 0..reader.u32()? {
+            let constant = match reader.byte()? {
+                0 => Constant::Int(i64::from_le_bytes(reader.array()?)),
+                1 => Constant::Float(f64::from_le_bytes(reader.array()?)),
+                2 => Constant::Str(reader.str()?),
+                other => return Err(format!("unknown constant tag {}", other)),
+            };
+            module.constants.push(constant);
+        }
+
+        for _ in 0..reader.u32()? {
+            let name = reader.str()?;
+            module.structs.push(StructDef { name, fields: reader.strs()? });
+        }
+
+        for _ in 0..reader.u32()? {
+            let name = reader.str()?;
+            module.enums.push(EnumDef { name, variants: reader.strs()? });
+        }
+
+        for _ in 0..reader.u32()? {
+            let owner = reader.str()?;
+            let name = reader.str()?;
+            module.methods.push(Method { owner, name, function: reader.u32()? });
+        }
+
+        for _ in 0..reader.u32()? {
+            let mut function = Function {
+                name: reader.str()?,
+                arity: reader.u32()?,
+                receiver: reader.byte()? != 0,
+                locals: reader.u32()?,
+                file: reader.str()?,
+                ..Function::default()
+            };
+
+            for _ in 0..reader.u32()? {
+                function.code.push(Op::decode(&mut reader)?);
+                function.spans.push(Span {
+                    line: reader.u32()?,
+                    column: reader.u32()?,
+                    start: reader.u32()?,
+                    end: reader.u32()?,
+                });
+            }
+
+            module.functions.push(function);
+        }
+
+        module.entry = reader.u32()?.checked_sub(1);
+
+        Ok(module)
+    }
+
+    // checks that every index an instruction holds points into its table, every jump lands
+    // inside its function, locals stay below `locals`, and the operand stack has the same
+    // height whichever way an instruction is reached and never runs below empty
+    fn verify(&self, stubs: bool) -> Result<(), String> {
+        let check = |ok: bool, what: &str, index: u32, count: usize| {
+            if ok { Ok(()) } else { Err(format!("{} {} out of range, there are {}", what, index, count)) }
+        };
+
+        for method in &self.methods {
+            check((method.function as usize) < self.functions.len(), "function", method.function, self.functions.len())?;
+        }
+        if let Some(entry) = self.entry {
+            check((entry as usize) < self.functions.len(), "function", entry, self.functions.len())?;
+        }
+
+        for function in &self.functions {
+            if stubs && function.code.is_empty() {
+                continue;
+            }
+            self.verify_function(function, check).map_err(|e| format!("invalid function `{}`: {}", function.name, e))?;
+        }
+        Ok(())
+    }
+
+    fn verify_function(&self, function: &Function, check: impl Fn(bool, &str, u32, usize) -> Result<(), String>) -> Result<(), String> {
+        let code = &function.code;
+        if code.is_empty() || code.len() != function.spans.len() {
+            return Err("code and spans do not match".to_owned());
+        }
+        if !matches!(code[code.len() - 1], Op::Return | Op::Jump(_) | Op::Fail(_)) {
+            return Err("code runs past its end".to_owned());
+        }
+
+        // parameters and `self` come first, and every other slot is filled by a `Store` or an
+        // iterator taking two, so a function can never need more than that
+        let params = function.arity as u64 + function.receiver as u64;
+        if (function.locals as u64) < params || function.locals as u64 > params + 2 * code.len() as u64 {
+            return Err(format!("{} locals do not fit {} parameter(s) and {} instruction(s)", function.locals, params, code.len()));
+        }
+
+        let constant = |index: u32| check((index as usize) < self.constants.len(), "constant", index, self.constants.len());
+        let local = |slot: u32, width: u32| check(slot.checked_add(width).is_some_and(|end| end <= function.locals), "local", slot, function.locals as usize);
+        let target = |index: u32| check((index as usize) < code.len(), "jump target", index, code.len());
+
+        // operand stack height before each instruction, filled in as it is reached
+        let mut heights: Vec<Option<u32>> = vec![None; code.len()];
+        let mut pending = vec![(0usize, 0u32)];
+        while let Some((ip, height)) = pending.pop() {
+            match heights[ip] {
+                Some(known) if known == height => continue,
+                Some(known) => return Err(format!("instruction {} is reached with {} and {} values on the stack", ip, known, height)),
+                None => heights[ip] = Some(height),
+            }
+
+            let op = code[ip];
+            let (pops, pushes) = match op {
+                Op::Const(index) => (constant(index).map(|_| 0)?, 1),
+                Op::GetField(index) => (constant(index).map(|_| 1)?, 1),
+                Op::SetField(index) => (constant(index).map(|_| 2)?, 0),
+                Op::Fail(index) => (constant(index).map(|_| 0)?, 0),
+                Op::CallMethod(index, argc) => (constant(index).map(|_| argc as u64 + 1)?, 1),
+                Op::CallStd(index, argc) => (constant(index).map(|_| argc as u64)?, 1),
+                Op::Null | Op::True | Op::False | Op::Void => (0, 1),
+                Op::Pop => (1, 0),
+                Op::Dup => (1, 2),
+                Op::Load(slot) => (local(slot, 1).map(|_| 0)?, 1),
+                Op::Store(slot) => (local(slot, 1).map(|_| 1)?, 0),
+                Op::Function(id) => (check((id as usize) < self.functions.len(), "function", id, self.functions.len()).map(|_| 0)?, 1),
+                Op::Type(id) => (check((id as usize) < self.structs.len(), "struct", id, self.structs.len()).map(|_| 0)?, 1),
+                Op::Variant(id, variant) => {
+                    check((id as usize) < self.enums.len(), "enum", id, self.enums.len())?;
+                    let variants = self.enums[id as usize].variants.len();
+                    (check((variant as usize) < variants, "variant", variant, variants).map(|_| 0)?, 1)
+                }
+                Op::Neg | Op::Pos | Op::Not | Op::BitNot => (1, 1),
+                Op::Jump(index) | Op::JumpIfFalse(index) | Op::JumpIfTrue(index) => {
+                    target(index)?;
+                    (if matches!(op, Op::Jump(_)) { 0 } else { 1 }, 0)
+                }
+                Op::Call(argc) => (argc as u64 + 1, 1),
+                Op::Return => (1, 0),
+                Op::List(count) => (count as u64, 1),
+                Op::Dict(count) => (2 * count as u64, 1),
+                Op::Range => (2, 1),
+                Op::IterInit(slot) => (local(slot, 2).map(|_| 1)?, 0),
+                Op::IterNext(slot, index) => {
+                    local(slot, 2)?;
+                    target(index)?;
+                    (0, 1)
+                }
+                _ => (2, 1),
+            };
+
+            if pops > height as u64 {
+                return Err(format!("instruction {} takes {} values from a stack holding {}", ip, pops, height));
+            }
+            // the last instruction never falls through, so `ip + 1` stays inside the function
+            let rest = height - pops as u32;
+            match op {
+                Op::Return | Op::Fail(_) => {}
+                Op::Jump(index) => pending.push((index as usize, rest)),
+                Op::JumpIfFalse(index) | Op::JumpIfTrue(index) => pending.extend([(index as usize, rest), (ip + 1, rest)]),
+                Op::IterNext(_, index) => pending.extend([(index as usize, rest), (ip + 1, rest + 1)]),
+                _ => pending.push((ip + 1, rest + pushes)),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn disassemble(&self) -> String {
+        let mut out = String::new();
+
+        for (i, function) in self.functions.iter().enumerate() {
+            let _ = writeln!(out, "== {} #{} (arity {}, locals {}) ==", function.name, i, function.arity, function.locals);
+
+            for (offset, (op, span)) in function.code.iter().zip(&function.spans).enumerate() {
+                let _ = writeln!(out, "{:04} {:>4}:{:<3} {}", offset, span.line, span.column, self.describe(op));
+            }
+
+            out.push('\n');
+        }
+
+        out
+    }
+
+    fn describe(&self, op: &Op) -> String {
+        let constant = |index: u32| match self.constants.get(index as usize) {
+            Some(Constant::Int(value)) => format!("{}", value),
+            Some(Constant::Float(value)) => format!("{:?}", value),
+            Some(Constant::Str(value)) => format!("{:?}", value),
+            None => "?".to_owned(),
+        };
+
+        let detail = match *op {
+            Op::Const(index) | Op::GetField(index) | Op::SetField(index) | Op::Fail(index) => {
+                format!("{} ({})", index, constant(index))
+            }
+            Op::Function(index) => match self.functions.get(index as usize) {
+                Some(function) => format!("{} ({})", index, function.name),
+                None => index.to_string(),
+            },
+            Op::Type(index) => match self.structs.get(index as usize) {
+                Some(def) => format!("{} ({})", index, def.name),
+                None => index.to_string(),
+            },
+            Op::Variant(index, variant) => match self.enums.get(index as usize) {
+                Some(def) => format!("{} {} ({}::{})", index, variant, def.name, def.variants.get(variant as usize).map(String::as_str).unwrap_or("?")),
+                None => format!("{} {}", index, variant),
+            },
+            Op::CallMethod(name, argc) | Op::CallStd(name, argc) => format!("{} {} ({})", name, argc, constant(name)),
+            Op::IterNext(slot, target) => format!("{} -> {:04}", slot, target),
+            Op::Jump(target) | Op::JumpIfFalse(target) | Op::JumpIfTrue(target) => format!("-> {:04}", target),
+            Op::Load(index) | Op::Store(index) | Op::Call(index) | Op::List(index) | Op::Dict(index) | Op::IterInit(index) => {
+                index.to_string()
+            }
+            _ => String::new(),
+        };
+
+        format!("{:<14}{}", op.name(), detail).trim_end().to_owned()
+    }
+}
+
+// little-endian encoding shared with the build cache
+#[derive(Default)]
+pub(crate) struct Writer {
+    pub(crate) bytes: Vec<u8>,
+}
+
+impl Writer {
+    pub(crate) fn u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(crate) fn str(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.bytes.extend_from_slice(value.as_bytes());
+    }
+
+    pub(crate) fn strs(&mut self, values: &[String]) {
+        self.u32(values.len() as u32);
+        for value in values {
+            self.str(value);
+        }
+    }
+}
+
+pub(crate) struct Reader<'b> {
+    bytes: &'b [u8],
+    pos: usize,
+}
+
+impl<'b> Reader<'b> {
+    pub(crate) fn new(bytes: &'b [u8]) -> Reader<'b> {
+        Reader { bytes, pos: 0 }
+    }
+
+    pub(crate) fn done(&self) -> bool {
+        self.pos == self.bytes.len()
+    }
+
+    pub(crate) fn take(&mut self, len: usize) -> Result<&[u8], String> {
+        let end = self.pos.checked_add(len).filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| "unexpected end of bytecode file".to_owned())?;
+
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    pub(crate) fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    pub(crate) fn str(&mut self) -> Result<String, String> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| "invalid string in bytecode file".to_owned())
+    }
+
+    pub(crate) fn strs(&mut self) -> Result<Vec<String>, String> {
+        (0..self.u32()?).map(|_| self.str()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module(arity: u32, locals: u32) -> Module {
+        let code = vec![Op::Load(0), Op::Return];
+        Module {
+            functions: vec![Function {
+                name: "main".to_owned(),
+                arity,
+                locals,
+                spans: vec![Span::default(); code.len()],
+                code,
+                ..Function::default()
+            }],
+            entry: Some(0),
+            ..Module::default()
+        }
+    }
+
+    #[test]
+    fn loads_what_it_writes() {
+        let module = Module::from_bytes(&module(1, 1).to_bytes()).unwrap();
+        assert_eq!(module.functions[0].locals, 1);
+    }
+
+    #[test]
+    fn rejects_locals_the_code_cannot_use() {
+        assert!(Module::from_bytes(&module(1, u32::MAX).to_bytes()).unwrap_err().contains("locals"));
+        assert!(Module::from_bytes(&module(2, 1).to_bytes()).unwrap_err().contains("locals"));
+    }
+}
@@ -0,0 +1,700 @@
+use super::bytecode::{Constant, EnumDef, Function, Method, Module, Op, Span, StructDef};
+use crate::compiler::node::{Location, Node};
+use crate::compiler::types::{DefaultValue, Path, TypeTable};
+
+use std::collections::HashMap;
+
+// the code of a function a previous build compiled, see `Module::portable`
+pub type Cached = HashMap<String, Module>;
+
+struct Loop {
+    // where `continue` jumps
+    start: u32,
+    // `break` jumps, patched once the loop end is known
+    breaks: Vec<usize>,
+}
+
+// a declared function whose code is compiled once every name is known
+struct Pending<'n> {
+    id: u32,
+    receiver: bool,
+    args: &'n [Box<Node>],
+    body: &'n [Box<Node>],
+    loc: &'n Location,
+}
+
+// lowers the checked tree into a bytecode module, one function at a time
+pub struct BytecodeCompiler<'t> {
+    types: &'t TypeTable,
+    module: Module,
+    functions: HashMap<String, u32>,
+    structs: HashMap<String, u32>,
+    enums: HashMap<String, u32>,
+    methods: HashMap<(String, String), u32>,
+    strings: HashMap<String, u32>,
+    // state of the function being compiled
+    code: Vec<Op>,
+    spans: Vec<Span>,
+    span: Span,
+    scopes: Vec<HashMap<String, u32>>,
+    next: u32,
+    locals: u32,
+    loops: Vec<Loop>,
+}
+
+impl<'t> BytecodeCompiler<'t> {
+    pub fn compile(nodes: &[Box<Node>], types: &'t TypeTable, file: &str) -> Module {
+        Self::compile_reusing(nodes, types, file, &Cached::new()).expect("nothing cached to go stale")
+    }
+
+    // like `compile`, but functions from the files in `cached` take their code from there; their
+    // bodies may be left out of `nodes`. Fails when cached code refers to something now missing
+    pub fn compile_reusing(nodes: &[Box<Node>], types: &'t TypeTable, file: &str, cached: &Cached) -> Result<Module, String> {
+        let mut compiler = Self {
+            types,
+            module: Module {
+                file: file.to_owned(),
+                ..Module::default()
+            },
+            functions: HashMap::new(),
+            structs: HashMap::new(),
+            enums: HashMap::new(),
+            methods: HashMap::new(),
+            strings: HashMap::new(),
+            code: Vec::new(),
+            spans: Vec::new(),
+            span: Span::default(),
+            scopes: Vec::new(),
+            next: 0,
+            locals: 0,
+            loops: Vec::new(),
+        };
+
+        // everything is declared up front so calls can refer to later definitions
+        let mut bodies = Vec::new();
+        for node in nodes {
+            compiler.declare(node, &mut bodies);
+        }
+
+        for pending in bodies {
+            match cached.get(&pending.loc.file) {
+                Some(from) => compiler.adopt(from, pending.id)?,
+                None => compiler.function(pending),
+            }
+        }
+
+        compiler.module.entry = compiler.functions.get("main").copied();
+        Ok(compiler.module)
+    }
+
+    // copies the code of function `id` from the portable module `from`, pointing what it refers
+    // to at the tables of this module
+    fn adopt(&mut self, from: &Module, id: u32) -> Result<(), String> {
+        let name = self.module.functions[id as usize].name.clone();
+        let Some(source) = from.functions.iter().find(|function| function.name == name && !function.code.is_empty()) else {
+            return Err(format!("`{}` is missing from the cache", name));
+        };
+
+        let mut code = source.code.clone();
+        for op in code.iter_mut() {
+            match op {
+                Op::Const(index) | Op::GetField(index) | Op::SetField(index) | Op::Fail(index) |
+                Op::CallMethod(index, _) | Op::CallStd(index, _) => {
+                    *index = self.intern(from.constants[*index as usize].clone());
+                }
+                Op::Function(index) => {
+                    let target = &from.functions[*index as usize];
+                    let found = match target.name.rsplit_once('.').filter(|_| target.receiver) {
+                        Some((owner, method)) => self.methods.get(&(owner.to_owned(), method.to_owned())),
+                        None => self.functions.get(&target.name),
+                    };
+                    *index = *found.ok_or_else(|| format!("`{}` calls `{}`, which no longer exists", name, target.name))?;
+                }
+                Op::Type(index) => {
+                    let def = &from.structs[*index as usize];
+                    *index = self.structs.get(&def.name).copied()
+                        .filter(|found| self.module.structs[*found as usize].fields == def.fields)
+                        .ok_or_else(|| format!("`{}` builds `{}`, which changed", name, def.name))?;
+                }
+                Op::Variant(index, _) => {
+                    let def = &from.enums[*index as usize];
+                    *index = self.enums.get(&def.name).copied()
+                        .filter(|found| self.module.enums[*found as usize].variants == def.variants)
+                        .ok_or_else(|| format!("`{}` uses `{}`, which changed", name, def.name))?;
+                }
+                _ => {}
+            }
+        }
+
+        let function = &mut self.module.functions[id as usize];
+        function.code = code;
+        function.spans = source.spans.clone();
+        function.locals = source.locals;
+        function.file = source.file.clone();
+        Ok(())
+    }
+
+    fn declare<'n>(&mut self, node: &'n Node, bodies: &mut Vec<Pending<'n>>) {
+        match node {
+            Node::FuncDef { name, args, body, loc, .. } => {
+                let id = self.push_function(name, args.len(), false);
+                self.functions.insert(name.clone(), id);
+                bodies.push(Pending { id, receiver: false, args, body, loc });
+            }
+            Node::Struct { name, child, body, .. } => {
+                let fields = child.iter().filter_map(|field| match field.as_ref() {
+                    Node::LetDef { name, .. } => Some(name.clone()),
+                    _ => None,
+                }).collect();
+                self.structs.insert(name.clone(), self.module.structs.len() as u32);
+                self.module.structs.push(StructDef { name: name.clone(), fields });
+
+                for method in body {
+                    if let Node::FuncDef { name: method, args, body, loc, .. } = method.as_ref() {
+                        let id = self.push_method(name, method, args.len());
+                        bodies.push(Pending { id, receiver: true, args, body, loc });
+                    }
+                }
+            }
+            Node::Attach { name, attach_to, args, body, loc, .. } => {
+                if let Node::Var { value: owner, .. } = attach_to.as_ref() {
+                    let id = self.push_method(owner, name, args.len());
+                    bodies.push(Pending { id, receiver: true, args, body, loc });
+                }
+            }
+            Node::Enum { name, child, .. } => {
+                let variants = child.iter().filter_map(|variant| match variant.as_ref() {
+                    Node::Var { value, .. } => Some(value.clone()),
+                    _ => None,
+                }).collect();
+                self.enums.insert(name.clone(), self.module.enums.len() as u32);
+                self.module.enums.push(EnumDef { name: name.clone(), variants });
+            }
+            _ => {}
+        }
+    }
+
+    fn push_function(&mut self, name: &str, arity: usize, receiver: bool) -> u32 {
+        self.module.functions.push(Function {
+            name: name.to_owned(),
+            arity: arity as u32,
+            receiver,
+            ..Function::default()
+        });
+        self.module.functions.len() as u32 - 1
+    }
+
+    fn push_method(&mut self, owner: &str, name: &str, arity: usize) -> u32 {
+        let id = self.push_function(&format!("{}.{}", owner, name), arity, true);
+        self.methods.insert((owner.to_owned(), name.to_owned()), id);
+        self.module.methods.push(Method {
+            owner: owner.to_owned(),
+            name: name.to_owned(),
+            function: id,
+        });
+        id
+    }
+
+    fn function(&mut self, Pending { id, receiver, args, body, loc }: Pending) {
+        self.code.clear();
+        self.spans.clear();
+        self.scopes = vec![HashMap::new()];
+        self.next = 0;
+        self.locals = 0;
+
+        if receiver {
+            self.declare_local("self");
+        }
+        for arg in args {
+            if let Node::LetDef { name, .. } = arg.as_ref() {
+                self.declare_local(name);
+            }
+        }
+
+        self.block(body);
+
+        // falling off the end returns void
+        self.emit(Op::Void);
+        self.emit(Op::Return);
+
+        let function = &mut self.module.functions[id as usize];
+        function.code = std::mem::take(&mut self.code);
+        function.spans = std::mem::take(&mut self.spans);
+        function.locals = self.locals;
+        function.file = loc.file.clone();
+    }
+
+    fn block(&mut self, body: &[Box<Node>]) {
+        self.scopes.push(HashMap::new());
+        let next = self.next;
+
+        for node in body {
+            self.stmt(node);
+        }
+
+        // slots of this block are free again once it ends
+        self.next = next;
+        self.scopes.pop();
+    }
+
+    fn stmt(&mut self, node: &Node) {
+        if let Some(loc) = node.loc() {
+            self.span = Self::span(loc);
+        }
+
+        match node {
+            Node::LetDef { name, value, loc, .. } => {
+                match value.as_ref() {
+                    Node::Void => self.default_value(self.types.default_value(loc)),
+                    value => self.expr(value),
+                }
+                let slot = self.declare_local(name);
+                self.emit(Op::Store(slot));
+            }
+            Node::AssignDef { targ, value, opr, loc } => self.assign(targ, value, opr, loc),
+            Node::Return { value, loc } => {
+                self.expr(value);
+                self.span = Self::span(loc);
+                self.emit(Op::Return);
+            }
+            Node::Conditional { cond, body_if, body_else, .. } => {
+                self.expr(cond);
+                let to_else = self.emit(Op::JumpIfFalse(0));
+                self.block(body_if);
+
+                if body_else.is_empty() {
+                    self.patch(to_else);
+                } else {
+                    let to_end = self.emit(Op::Jump(0));
+                    self.patch(to_else);
+                    self.block(body_else);
+                    self.patch(to_end);
+                }
+            }
+            Node::WhileLoop { cond, body, .. } => {
+                let start = self.here();
+                self.expr(cond);
+                let exit = self.emit(Op::JumpIfFalse(0));
+
+                self.loops.push(Loop { start, breaks: Vec::new() });
+                self.block(body);
+                self.emit(Op::Jump(start));
+
+                self.patch(exit);
+                self.end_loop();
+            }
+            Node::ForLoop { initializer, iterator, body, loc } => {
+                let Node::LetDef { name, .. } = initializer.as_ref() else {
+                    return;
+                };
+
+                self.scopes.push(HashMap::new());
+                let next = self.next;
+
+                self.expr(iterator);
+                self.span = Self::span(loc);
+                // the snapshot and the position live in two hidden slots
+                let iter = self.hidden_local();
+                self.hidden_local();
+                self.emit(Op::IterInit(iter));
+
+                let start = self.here();
+                let exit = self.emit(Op::IterNext(iter, 0));
+                let slot = self.declare_local(name);
+                self.emit(Op::Store(slot));
+
+                self.loops.push(Loop { start, breaks: Vec::new() });
+                self.block(body);
+                self.emit(Op::Jump(start));
+
+                self.patch(exit);
+                self.end_loop();
+
+                self.next = next;
+                self.scopes.pop();
+            }
+            Node::MatchCase { value, child, default, .. } => {
+                self.scopes.push(HashMap::new());
+                let next = self.next;
+
+                self.expr(value);
+                let subject = self.hidden_local();
+                self.emit(Op::Store(subject));
+
+                let mut to_end = Vec::new();
+                for arm in child {
+                    self.emit(Op::Load(subject));
+                    self.expr(&arm.0);
+                    self.emit(Op::Eq);
+                    let to_next = self.emit(Op::JumpIfFalse(0));
+
+                    self.block(&arm.1);
+                    to_end.push(self.emit(Op::Jump(0)));
+                    self.patch(to_next);
+                }
+
+                self.block(default);
+                for jump in to_end {
+                    self.patch(jump);
+                }
+
+                self.next = next;
+                self.scopes.pop();
+            }
+            Node::Break { .. } => {
+                let jump = self.emit(Op::Jump(0));
+                if let Some(current) = self.loops.last_mut() {
+                    current.breaks.push(jump);
+                }
+            }
+            Node::Continue { .. } => {
+                if let Some(start) = self.loops.last().map(|current| current.start) {
+                    self.emit(Op::Jump(start));
+                }
+            }
+            _ => {
+                self.expr(node);
+                self.emit(Op::Pop);
+            }
+        }
+    }
+
+    fn end_loop(&mut self) {
+        if let Some(current) = self.loops.pop() {
+            for jump in current.breaks {
+                self.patch(jump);
+            }
+        }
+    }
+
+    fn assign(&mut self, targ: &Node, value: &Node, opr: &str, loc: &Location) {
+        let opr = opr.trim_end_matches('=');
+
+        match targ {
+            Node::Var { value: name, loc: name_loc } => {
+                let Some(slot) = self.local(name) else {
+                    self.span = Self::span(name_loc);
+                    self.fail(format!("cannot assign to `{}`", name));
+                    return;
+                };
+
+                if opr.is_empty() {
+                    self.expr(value);
+                } else {
+                    self.emit(Op::Load(slot));
+                    self.expr(value);
+                    self.span = Self::span(loc);
+                    self.binary(opr);
+                }
+                self.emit(Op::Store(slot));
+            }
+            Node::MemLockup { targ, obj, .. } => {
+                let Node::Var { value: field, loc: field_loc } = targ.as_ref() else {
+                    self.span = Self::span(loc);
+                    self.fail("invalid assignment target");
+                    return;
+                };
+                let field = self.string(field);
+
+                self.expr(obj);
+                if opr.is_empty() {
+                    self.expr(value);
+                } else {
+                    self.emit(Op::Dup);
+                    self.span = Self::span(field_loc);
+                    self.emit(Op::GetField(field));
+                    self.expr(value);
+                    self.span = Self::span(loc);
+                    self.binary(opr);
+                }
+                self.span = Self::span(field_loc);
+                self.emit(Op::SetField(field));
+            }
+            _ => {
+                self.span = Self::span(loc);
+                self.fail("invalid assignment target");
+            }
+        }
+    }
+
+    // the value of `let x: T;` before anything is assigned
+    fn default_value(&mut self, default: DefaultValue) {
+        match default {
+            DefaultValue::Int => self.constant(Constant::Int(0)),
+            DefaultValue::Float => self.constant(Constant::Float(0.0)),
+            DefaultValue::Bool => self.emit(Op::False),
+            DefaultValue::Str => {
+                let empty = self.string("");
+                self.emit(Op::Const(empty))
+            }
+            DefaultValue::List => self.emit(Op::List(0)),
+            DefaultValue::Dict => self.emit(Op::Dict(0)),
+            DefaultValue::Null => self.emit(Op::Null),
+        };
+    }
+
+    fn expr(&mut self, node: &Node) {
+        if let Some(loc) = node.loc() {
+            self.span = Self::span(loc);
+        }
+
+        match node {
+            Node::Int { value, .. } => match value.parse() {
+                Ok(value) => {
+                    self.constant(Constant::Int(value));
+                }
+                Err(_) => self.fail(format!("invalid integer literal `{}`", value)),
+            },
+            Node::Float { value, .. } => match value.parse() {
+                Ok(value) => {
+                    self.constant(Constant::Float(value));
+                }
+                Err(_) => self.fail(format!("invalid float literal `{}`", value)),
+            },
+            Node::Bool { value, .. } => {
+                self.emit(if value == "true" { Op::True } else { Op::False });
+            }
+            Node::Str { value, .. } => {
+                let value = self.string(value);
+                self.emit(Op::Const(value));
+            }
+            Node::Null { .. } => {
+                self.emit(Op::Null);
+            }
+            Node::Void => {
+                self.emit(Op::Void);
+            }
+            Node::Var { value, .. } => self.lookup(value),
+            Node::List { element, .. } => {
+                for item in element {
+                    self.expr(item);
+                }
+                self.emit(Op::List(element.len() as u32));
+            }
+            Node::Dict { key_value, loc } => {
+                for pair in key_value {
+                    self.expr(&pair.0);
+                    self.expr(&pair.1);
+                }
+                self.span = Self::span(loc);
+                self.emit(Op::Dict(key_value.len() as u32));
+            }
+            Node::Range { min, max, loc } => {
+                self.expr(min);
+                self.expr(max);
+                self.span = Self::span(loc);
+                self.emit(Op::Range);
+            }
+            Node::MemLockup { targ, obj, .. } => {
+                let Node::Var { value: field, loc } = targ.as_ref() else {
+                    self.emit(Op::Void);
+                    return;
+                };
+
+                self.expr(obj);
+                let field = self.string(field);
+                self.span = Self::span(loc);
+                self.emit(Op::GetField(field));
+            }
+            Node::CodeLockup { .. } => self.path(node),
+            Node::FuncCall { func, args, loc } => self.call(func, args, loc),
+            Node::BinaryOp { lhs, rhs, opr, loc } => {
+                self.expr(lhs);
+
+                // `&&` and `||` leave the left side on the stack when it decides the result
+                let jump = match opr.as_str() {
+                    "&&" => Op::JumpIfFalse(0),
+                    "||" => Op::JumpIfTrue(0),
+                    _ => {
+                        self.expr(rhs);
+                        self.span = Self::span(loc);
+                        self.binary(opr);
+                        return;
+                    }
+                };
+
+                self.span = Self::span(loc);
+                self.emit(Op::Dup);
+                let to_end = self.emit(jump);
+                self.emit(Op::Pop);
+                self.expr(rhs);
+                self.patch(to_end);
+            }
+            Node::UnaryOp { opr, value, loc } => {
+                self.expr(value);
+                self.span = Self::span(loc);
+                match Op::unary(opr) {
+                    Some(op) => {
+                        self.emit(op);
+                    }
+                    None => self.fail(format!("unknown unary operator `{}`", opr)),
+                }
+            }
+            _ => self.fail("expression cannot be evaluated"),
+        }
+    }
+
+    fn binary(&mut self, opr: &str) {
+        match Op::binary(opr) {
+            Some(op) => {
+                self.emit(op);
+            }
+            None => self.fail(format!("unknown operator `{}`", opr)),
+        }
+    }
+
+    fn lookup(&mut self, name: &str) {
+        if let Some(slot) = self.local(name) {
+            self.emit(Op::Load(slot));
+        } else if let Some(id) = self.functions.get(name).copied() {
+            self.emit(Op::Function(id));
+        } else if let Some(id) = self.structs.get(name).copied() {
+            self.emit(Op::Type(id));
+        } else {
+            self.fail(format!("`{}` is not available at runtime", name));
+        }
+    }
+
+    // `Enum::Variant` or `Struct::method`
+    fn path(&mut self, node: &Node) {
+        match self.types.path(node) {
+            Some(Path::Variant(owner, name)) => {
+                if let Some(id) = self.enums.get(&owner).copied()
+                    && let Some(variant) = self.module.enums[id as usize].variants.iter().position(|variant| *variant == name)
+                {
+                    self.emit(Op::Variant(id, variant as u32));
+                    return;
+                }
+            }
+            Some(Path::Method(owner, name)) => {
+                if let Some(id) = self.methods.get(&(owner, name)).copied() {
+                    self.emit(Op::Function(id));
+                    return;
+                }
+            }
+            None => {}
+        }
+
+        self.fail("path cannot be evaluated at runtime");
+    }
+
+    fn call(&mut self, func: &Node, args: &[Box<Node>], loc: &Location) {
+        if let Node::CodeLockup { targ, obj, .. } = func
+            && let (Node::Var { value: name, .. }, Node::Var { value: module, .. }) = (targ.as_ref(), obj.as_ref())
+            && module == "std"
+        {
+            self.args(args);
+            let name = self.string(name);
+            self.span = Self::span(loc);
+            self.emit(Op::CallStd(name, args.len() as u32));
+            return;
+        }
+
+        if let Node::MemLockup { targ, obj, .. } = func
+            && let Node::Var { value: name, loc: name_loc } = targ.as_ref()
+        {
+            self.expr(obj);
+            self.args(args);
+            let name = self.string(name);
+            self.span = Self::span(name_loc);
+            self.emit(Op::CallMethod(name, args.len() as u32));
+            return;
+        }
+
+        self.expr(func);
+        self.args(args);
+        self.span = Self::span(loc);
+        self.emit(Op::Call(args.len() as u32));
+    }
+
+    fn args(&mut self, args: &[Box<Node>]) {
+        for arg in args {
+            self.expr(arg);
+        }
+    }
+
+    fn local(&self, name: &str) -> Option<u32> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name)).copied()
+    }
+
+    fn declare_local(&mut self, name: &str) -> u32 {
+        let slot = self.hidden_local();
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_owned(), slot);
+        }
+        slot
+    }
+
+    // a slot no name refers to, for loop and match bookkeeping
+    fn hidden_local(&mut self) -> u32 {
+        let slot = self.next;
+        self.next += 1;
+        self.locals = self.locals.max(self.next);
+        slot
+    }
+
+    fn string(&mut self, value: &str) -> u32 {
+        if let Some(index) = self.strings.get(value) {
+            return *index;
+        }
+
+        let index = self.module.constants.len() as u32;
+        self.module.constants.push(Constant::Str(value.to_owned()));
+        self.strings.insert(value.to_owned(), index);
+        index
+    }
+
+    fn constant(&mut self, constant: Constant) -> usize {
+        let index = self.intern(constant);
+        self.emit(Op::Const(index))
+    }
+
+    fn intern(&mut self, constant: Constant) -> u32 {
+        if let Constant::Str(value) = &constant {
+            return self.string(value);
+        }
+        match self.module.constants.iter().position(|existing| *existing == constant) {
+            Some(index) => index as u32,
+            None => {
+                self.module.constants.push(constant);
+                self.module.constants.len() as u32 - 1
+            }
+        }
+    }
+
+    fn fail(&mut self, message: impl Into<String>) {
+        let message = self.string(&message.into());
+        self.emit(Op::Fail(message));
+    }
+
+    fn emit(&mut self, op: Op) -> usize {
+        self.code.push(op);
+        self.spans.push(self.span);
+        self.code.len() - 1
+    }
+
+    fn here(&self) -> u32 {
+        self.code.len() as u32
+    }
+
+    // points the jump at `at` to the next instruction
+    fn patch(&mut self, at: usize) {
+        let target = self.here();
+        self.code[at] = match self.code[at] {
+            Op::Jump(_) => Op::Jump(target),
+            Op::JumpIfFalse(_) => Op::JumpIfFalse(target),
+            Op::JumpIfTrue(_) => Op::JumpIfTrue(target),
+            Op::IterNext(slot, _) => Op::IterNext(slot, target),
+            op => op,
+        };
+    }
+
+    fn span(loc: &Location) -> Span {
+        Span {
+            line: loc.line as u32,
+            column: loc.column as u32,
+            start: loc.start as u32,
+            end: loc.end as u32,
+        }
+    }
+}
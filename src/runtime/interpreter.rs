@@ -0,0 +1,516 @@
+use super::builtins::{self, Fault};
+use super::value::{Instance, Value};
+use super::MAX_CALL_DEPTH;
+use crate::compiler::diagnostic::Diagnostic;
+use crate::compiler::node::{Location, Node};
+use crate::compiler::types::{DefaultValue, Path, TypeTable};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+// host stack to run on; how much one Mix call takes depends on how deeply its body nests, so
+// calls stop with `E0505` when the stack runs low, which may be before `MAX_CALL_DEPTH`
+pub const STACK_SIZE: usize = 512 << 20;
+
+// stack kept free below the deepest call, for the statements and expressions of its body
+const STACK_RESERVE: usize = 64 << 20;
+
+// boxed so the happy path does not carry a large error slot through every call
+type Result<T> = std::result::Result<T, Box<Diagnostic>>;
+
+// how a statement finished, so loops and calls know whether to keep going
+enum Flow {
+    Next,
+    Break,
+    Continue,
+    Return(Value),
+}
+
+struct Function<'a> {
+    name: String,
+    params: Vec<String>,
+    body: &'a [Box<Node>],
+}
+
+pub struct Interpreter<'a> {
+    types: &'a TypeTable,
+    functions: Vec<Function<'a>>,
+    globals: HashMap<String, usize>,
+    // (struct, method) to function index
+    methods: HashMap<(String, String), usize>,
+    // field names of every struct, in declaration order
+    structs: HashMap<String, Vec<String>>,
+    enums: HashMap<String, Vec<String>>,
+    // variable scopes of the function being executed, innermost last
+    scopes: Vec<HashMap<String, Value>>,
+    depth: usize,
+    // address of the host stack when `run` started
+    stack_base: usize,
+    debug: bool,
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(nodes: &'a [Box<Node>], types: &'a TypeTable, debug: bool) -> Self {
+        let mut interpreter = Self {
+            types,
+            functions: Vec::new(),
+            globals: HashMap::new(),
+            methods: HashMap::new(),
+            structs: HashMap::new(),
+            enums: HashMap::new(),
+            scopes: Vec::new(),
+            depth: 0,
+            stack_base: 0,
+            debug,
+        };
+
+        for node in nodes {
+            interpreter.declare(node);
+        }
+
+        interpreter
+    }
+
+    // runs `main` and returns its result
+    pub fn run(&mut self, entry: &Location) -> Result<Value> {
+        if self.debug {
+            println!("[INTERPRETER] Starting Execution");
+        }
+
+        let Some(main) = self.globals.get("main").copied() else {
+            return Err(Diagnostic::error("E0506", "`main` function not found", entry.clone())
+                .with_help("add `func main() { ... }` to the entry file").into());
+        };
+
+        self.stack_base = Self::stack_address();
+        let result = self.call(main, Vec::new(), None, entry);
+        io::stdout().flush().ok();
+        result
+    }
+
+    fn declare(&mut self, node: &'a Node) {
+        match node {
+            Node::FuncDef { name, args, body, .. } => {
+                let id = self.push_function(name, args, body);
+                self.globals.insert(name.clone(), id);
+            }
+            Node::Struct { name, child, body, .. } => {
+                let fields = child.iter().filter_map(|field| match field.as_ref() {
+                    Node::LetDef { name, .. } => Some(name.clone()),
+                    _ => None,
+                }).collect();
+                self.structs.insert(name.clone(), fields);
+
+                for method in body {
+                    if let Node::FuncDef { name: method, args, body, .. } = method.as_ref() {
+                        let id = self.push_function(method, args, body);
+                        self.methods.insert((name.clone(), method.clone()), id);
+                    }
+                }
+            }
+            Node::Attach { name, attach_to, args, body, .. } => {
+                if let Node::Var { value: owner, .. } = attach_to.as_ref() {
+                    let id = self.push_function(name, args, body);
+                    self.methods.insert((owner.clone(), name.clone()), id);
+                }
+            }
+            Node::Enum { name, child, .. } => {
+                let variants = child.iter().filter_map(|variant| match variant.as_ref() {
+                    Node::Var { value, .. } => Some(value.clone()),
+                    _ => None,
+                }).collect();
+                self.enums.insert(name.clone(), variants);
+            }
+            _ => {}
+        }
+    }
+
+    fn push_function(&mut self, name: &str, args: &'a [Box<Node>], body: &'a [Box<Node>]) -> usize {
+        let params = args.iter().filter_map(|arg| match arg.as_ref() {
+            Node::LetDef { name, .. } => Some(name.clone()),
+            _ => None,
+        }).collect();
+
+        self.functions.push(Function {
+            name: name.to_owned(),
+            params,
+            body,
+        });
+        self.functions.len() - 1
+    }
+
+    fn call(&mut self, id: usize, args: Vec<Value>, receiver: Option<Value>, loc: &Location) -> Result<Value> {
+        let function = &self.functions[id];
+
+        if function.params.len() != args.len() {
+            return Err(Self::error(
+                format!("`{}` takes {} argument(s) but {} were supplied", function.name, function.params.len(), args.len()),
+                loc,
+            ));
+        }
+
+        if self.depth >= MAX_CALL_DEPTH || self.stack_base.abs_diff(Self::stack_address()) > STACK_SIZE - STACK_RESERVE {
+            return Err(Diagnostic::error("E0505", format!("stack overflow while calling `{}`", function.name), loc.clone())
+                .with_note(format!("calls can nest at most {} deep", MAX_CALL_DEPTH)).into());
+        }
+
+        let mut frame: HashMap<String, Value> = function.params.iter().cloned().zip(args).collect();
+        if let Some(receiver) = receiver {
+            frame.insert("self".to_owned(), receiver);
+        }
+
+        let body = function.body;
+        let caller = std::mem::replace(&mut self.scopes, vec![frame]);
+        self.depth += 1;
+
+        let flow = self.exec_block(body);
+
+        self.depth -= 1;
+        self.scopes = caller;
+
+        match flow? {
+            Flow::Return(value) => Ok(value),
+            _ => Ok(Value::Void),
+        }
+    }
+
+    // where the stack of the calling frame currently ends
+    #[inline(never)]
+    fn stack_address() -> usize {
+        let marker = 0u8;
+        std::hint::black_box(&marker) as *const u8 as usize
+    }
+
+    fn exec_block(&mut self, body: &[Box<Node>]) -> Result<Flow> {
+        self.scopes.push(HashMap::new());
+
+        let mut flow = Ok(Flow::Next);
+        for node in body {
+            flow = self.exec_stmt(node);
+            if !matches!(flow, Ok(Flow::Next)) {
+                break;
+            }
+        }
+
+        self.scopes.pop();
+        flow
+    }
+
+    fn exec_stmt(&mut self, node: &Node) -> Result<Flow> {
+        match node {
+            Node::LetDef { name, value, loc, .. } => {
+                let value = match value.as_ref() {
+                    Node::Void => Self::default_value(self.types.default_value(loc)),
+                    value => self.eval(value)?,
+                };
+                self.define(name, value);
+            }
+            Node::AssignDef { targ, value, opr, loc } => {
+                let value = self.eval(value)?;
+                let value = match opr.as_str() {
+                    "=" => value,
+                    opr => {
+                        let current = self.eval(targ)?;
+                        builtins::binary(opr.trim_end_matches('='), current, value).map_err(|e| e.at(loc))?
+                    }
+                };
+                self.assign(targ, value, loc)?;
+            }
+            Node::Return { value, .. } => {
+                return Ok(Flow::Return(self.eval(value)?));
+            }
+            Node::Conditional { cond, body_if, body_else, .. } => {
+                let body = if self.condition(cond)? { body_if } else { body_else };
+                return self.exec_block(body);
+            }
+            Node::WhileLoop { cond, body, .. } => {
+                while self.condition(cond)? {
+                    match self.exec_block(body)? {
+                        Flow::Break => break,
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                        Flow::Next | Flow::Continue => {}
+                    }
+                }
+            }
+            Node::ForLoop { initializer, iterator, body, loc } => {
+                let Node::LetDef { name, .. } = initializer.as_ref() else {
+                    return Ok(Flow::Next);
+                };
+
+                for item in self.iterate(iterator, loc)? {
+                    self.scopes.push(HashMap::from([(name.clone(), item)]));
+                    let flow = self.exec_block(body);
+                    self.scopes.pop();
+
+                    match flow? {
+                        Flow::Break => break,
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                        Flow::Next | Flow::Continue => {}
+                    }
+                }
+            }
+            Node::MatchCase { value, child, default, .. } => {
+                let subject = self.eval(value)?;
+
+                for arm in child {
+                    if self.eval(&arm.0)? == subject {
+                        return self.exec_block(&arm.1);
+                    }
+                }
+
+                return self.exec_block(default);
+            }
+            Node::Break { .. } => return Ok(Flow::Break),
+            Node::Continue { .. } => return Ok(Flow::Continue),
+            _ => {
+                self.eval(node)?;
+            }
+        }
+
+        Ok(Flow::Next)
+    }
+
+    // the value of `let x: T;` before anything is assigned
+    fn default_value(default: DefaultValue) -> Value {
+        match default {
+            DefaultValue::Int => Value::Int(0),
+            DefaultValue::Float => Value::Float(0.0),
+            DefaultValue::Bool => Value::Bool(false),
+            DefaultValue::Str => Value::Str(String::new()),
+            DefaultValue::List => Value::list(Vec::new()),
+            DefaultValue::Dict => Value::dict(Vec::new()),
+            DefaultValue::Null => Value::Null,
+        }
+    }
+
+    fn condition(&mut self, cond: &Node) -> Result<bool> {
+        match self.eval(cond)? {
+            Value::Bool(value) => Ok(value),
+            other => Err(Self::error(format!("expected `bool` condition, found `{}`", other.type_name()), &Self::loc_of(cond))),
+        }
+    }
+
+    fn iterate(&mut self, iterator: &Node, loc: &Location) -> Result<Box<dyn Iterator<Item = Value>>> {
+        // ranges count as they go, like the VM; loops walk a snapshot of anything else, so pushing
+        // to the list inside the body does not loop forever
+        match self.eval(iterator)? {
+            Value::Range(min, max) => Ok(Box::new((min..max).map(Value::Int))),
+            Value::List(items) => Ok(Box::new(items.borrow().clone().into_iter())),
+            Value::Dict(pairs) => Ok(Box::new(pairs.borrow().iter().map(|pair| pair.0.clone()).collect::<Vec<_>>().into_iter())),
+            Value::Str(value) => Ok(Box::new(value.chars().map(|ch| Value::Str(ch.to_string())).collect::<Vec<_>>().into_iter())),
+            other => Err(Self::error(format!("`{}` is not iterable", other.type_name()), loc)),
+        }
+    }
+
+    fn define(&mut self, name: &str, value: Value) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_owned(), value);
+        }
+    }
+
+    fn assign(&mut self, targ: &Node, value: Value, loc: &Location) -> Result<()> {
+        match targ {
+            Node::Var { value: name, loc } => {
+                match self.scopes.iter_mut().rev().find_map(|scope| scope.get_mut(name)) {
+                    Some(slot) => {
+                        *slot = value;
+                        Ok(())
+                    }
+                    None => Err(Self::error(format!("cannot assign to `{}`", name), loc)),
+                }
+            }
+            Node::MemLockup { targ, obj, .. } => {
+                let Node::Var { value: field, loc } = targ.as_ref() else {
+                    return Err(Self::error("invalid assignment target", loc));
+                };
+
+                match self.eval(obj)? {
+                    Value::Struct(instance) => {
+                        let mut instance = instance.borrow_mut();
+                        match instance.fields.iter_mut().find(|(name, _)| name == field) {
+                            Some(slot) => {
+                                slot.1 = value;
+                                Ok(())
+                            }
+                            None => Err(Self::error(format!("no field `{}` on `{}`", field, instance.name), loc)),
+                        }
+                    }
+                    other => Err(Self::error(format!("cannot set field `{}` on `{}`", field, other.type_name()), loc)),
+                }
+            }
+            _ => Err(Self::error("invalid assignment target", loc)),
+        }
+    }
+
+    fn eval(&mut self, node: &Node) -> Result<Value> {
+        match node {
+            Node::Int { value, loc, .. } => value.parse().map(Value::Int)
+                .map_err(|_| Self::error(format!("invalid integer literal `{}`", value), loc)),
+            Node::Float { value, loc, .. } => value.parse().map(Value::Float)
+                .map_err(|_| Self::error(format!("invalid float literal `{}`", value), loc)),
+            Node::Bool { value, .. } => Ok(Value::Bool(value == "true")),
+            Node::Str { value, .. } => Ok(Value::Str(value.clone())),
+            Node::Null { .. } => Ok(Value::Null),
+            Node::Void => Ok(Value::Void),
+            Node::Var { value, loc } => self.lookup(value, loc),
+            Node::List { element, .. } => {
+                let items = element.iter().map(|item| self.eval(item)).collect::<Result<Vec<_>>>()?;
+                Ok(Value::list(items))
+            }
+            Node::Dict { key_value, .. } => {
+                let mut pairs: Vec<(Value, Value)> = Vec::new();
+                for pair in key_value {
+                    let key = self.eval(&pair.0)?;
+                    let value = self.eval(&pair.1)?;
+
+                    match pairs.iter_mut().find(|(existing, _)| *existing == key) {
+                        Some(slot) => slot.1 = value,
+                        None => pairs.push((key, value)),
+                    }
+                }
+                Ok(Value::dict(pairs))
+            }
+            Node::Range { min, max, loc } => match (self.eval(min)?, self.eval(max)?) {
+                (Value::Int(min), Value::Int(max)) => Ok(Value::Range(min, max)),
+                _ => Err(Self::error("range bounds must be integers", loc)),
+            },
+            Node::MemLockup { targ, obj, .. } => {
+                let Node::Var { value: field, loc } = targ.as_ref() else {
+                    return Ok(Value::Void);
+                };
+
+                match self.eval(obj)? {
+                    Value::Struct(instance) => {
+                        let instance = instance.borrow();
+                        match instance.fields.iter().find(|(name, _)| name == field) {
+                            Some((_, value)) => Ok(value.clone()),
+                            None => Err(Self::error(format!("no field `{}` on `{}`", field, instance.name), loc)),
+                        }
+                    }
+                    other => Err(Self::error(format!("cannot read field `{}` of `{}`", field, other.type_name()), loc)),
+                }
+            }
+            Node::CodeLockup { loc, .. } => self.path(node, loc),
+            Node::FuncCall { func, args, loc } => self.eval_call(func, args, loc),
+            Node::BinaryOp { lhs, rhs, opr, loc } => {
+                let left = self.eval(lhs)?;
+
+                // `&&` and `||` only evaluate the right side when it decides the result
+                match (opr.as_str(), &left) {
+                    ("&&", Value::Bool(false)) => return Ok(Value::Bool(false)),
+                    ("||", Value::Bool(true)) => return Ok(Value::Bool(true)),
+                    _ => {}
+                }
+
+                let right = self.eval(rhs)?;
+                builtins::binary(opr, left, right).map_err(|e| e.at(loc))
+            }
+            Node::UnaryOp { opr, value, loc } => {
+                let value = self.eval(value)?;
+                builtins::unary(opr, value).map_err(|e| e.at(loc))
+            }
+            other => Err(Self::error("expression cannot be evaluated", &Self::loc_of(other))),
+        }
+    }
+
+    fn lookup(&self, name: &str, loc: &Location) -> Result<Value> {
+        if let Some(value) = self.scopes.iter().rev().find_map(|scope| scope.get(name)) {
+            return Ok(value.clone());
+        }
+
+        if let Some(id) = self.globals.get(name) {
+            return Ok(Value::Function(*id));
+        }
+
+        if self.structs.contains_key(name) {
+            return Ok(Value::Type(name.to_owned()));
+        }
+
+        Err(Self::error(format!("`{}` is not available at runtime", name), loc))
+    }
+
+    // `Enum::Variant` or `Struct::method`
+    fn path(&self, node: &Node, loc: &Location) -> Result<Value> {
+        let value = match self.types.path(node) {
+            Some(Path::Variant(owner, name)) if self.enums.get(&owner).is_some_and(|variants| variants.contains(&name)) => {
+                Some(Value::Enum(owner, name))
+            }
+            Some(Path::Method(owner, name)) => self.methods.get(&(owner, name)).map(|id| Value::Function(*id)),
+            _ => None,
+        };
+
+        value.ok_or_else(|| Self::error("path cannot be evaluated at runtime", loc))
+    }
+
+    fn eval_call(&mut self, func: &Node, args: &[Box<Node>], loc: &Location) -> Result<Value> {
+        // `std::name(...)` calls into the interpreter itself
+        if let Node::CodeLockup { targ, obj, .. } = func
+            && let (Node::Var { value: name, .. }, Node::Var { value: module, .. }) = (targ.as_ref(), obj.as_ref())
+            && module == "std"
+        {
+            let args = self.eval_args(args)?;
+            return builtins::std_function(name, args).map_err(|e| e.at(loc));
+        }
+
+        if let Node::MemLockup { targ, obj, .. } = func
+            && let Node::Var { value: name, loc: name_loc } = targ.as_ref()
+        {
+            let receiver = self.eval(obj)?;
+            let args = self.eval_args(args)?;
+
+            return match &receiver {
+                Value::Struct(instance) => {
+                    let owner = instance.borrow().name.clone();
+                    let field = instance.borrow().fields.iter().find(|(field, _)| field == name).map(|field| field.1.clone());
+
+                    match (field, self.methods.get(&(owner.clone(), name.clone())).copied()) {
+                        (Some(callee), _) => self.call_value(callee, args, loc),
+                        (None, Some(id)) => self.call(id, args, Some(receiver), loc),
+                        (None, None) => Err(Self::error(format!("no method `{}` on `{}`", name, owner), name_loc)),
+                    }
+                }
+                _ => builtins::method(receiver, name, args).map_err(|e| e.at(name_loc)),
+            };
+        }
+
+        let callee = self.eval(func)?;
+        let args = self.eval_args(args)?;
+        self.call_value(callee, args, loc)
+    }
+
+    fn eval_args(&mut self, args: &[Box<Node>]) -> Result<Vec<Value>> {
+        args.iter().map(|arg| self.eval(arg)).collect()
+    }
+
+    fn call_value(&mut self, callee: Value, args: Vec<Value>, loc: &Location) -> Result<Value> {
+        match callee {
+            Value::Function(id) => self.call(id, args, None, loc),
+            Value::Type(name) => {
+                let fields = self.structs.get(&name).cloned().unwrap_or_default();
+                if fields.len() != args.len() {
+                    return Err(Self::error(format!("`{}` has {} field(s) but {} were supplied", name, fields.len(), args.len()), loc));
+                }
+
+                let fields = fields.into_iter().zip(args).collect();
+                Ok(Value::Struct(Rc::new(RefCell::new(Instance { name, fields }))))
+            }
+            other => Err(Self::error(format!("`{}` is not callable", other.type_name()), loc)),
+        }
+    }
+
+    fn error(message: impl Into<String>, loc: &Location) -> Box<Diagnostic> {
+        Fault::new(message).at(loc)
+    }
+
+    fn loc_of(node: &Node) -> Location {
+        node.loc().cloned().unwrap_or_else(|| Location {
+            file: String::new(),
+            line: 0,
+            column: 0,
+            start: 0,
+            end: 0,
+        })
+    }
+}
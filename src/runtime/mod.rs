@@ -0,0 +1,9 @@
+pub mod value;
+pub mod builtins;
+pub mod interpreter;
+pub mod bytecode;
+pub mod compile;
+pub mod vm;
+
+// how deep calls can nest, the same for `mix run` and every backend
+pub const MAX_CALL_DEPTH: usize = 100_000;
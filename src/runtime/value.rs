@@ -0,0 +1,149 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+// lists, dicts and struct instances are shared by reference, like in most scripting languages
+#[derive(Debug, Clone)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+    Null,
+    Void,
+    List(Rc<RefCell<Vec<Value>>>),
+    // kept as pairs so iteration follows insertion order
+    Dict(Rc<RefCell<Vec<(Value, Value)>>>),
+    Range(i64, i64),
+    Struct(Rc<RefCell<Instance>>),
+    Enum(String, String),
+    // index into the interpreter's function list
+    Function(usize),
+    // a struct name, calling it builds an instance
+    Type(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Instance {
+    pub name: String,
+    pub fields: Vec<(String, Value)>,
+}
+
+impl Value {
+    pub fn list(items: Vec<Value>) -> Value {
+        Value::List(Rc::new(RefCell::new(items)))
+    }
+
+    pub fn dict(pairs: Vec<(Value, Value)>) -> Value {
+        Value::Dict(Rc::new(RefCell::new(pairs)))
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::Bool(_) => "bool",
+            Value::Str(_) => "str",
+            Value::Null => "null",
+            Value::Void => "void",
+            Value::List(_) => "list",
+            Value::Dict(_) => "dict",
+            Value::Range(_, _) => "range",
+            Value::Struct(_) => "struct",
+            Value::Enum(_, _) => "enum",
+            Value::Function(_) => "function",
+            Value::Type(_) => "type",
+        }
+    }
+
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Value::Int(value) => Some(*value as f64),
+            Value::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    fn write(&self, f: &mut fmt::Formatter<'_>, nested: bool) -> fmt::Result {
+        match self {
+            Value::Int(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{:?}", value),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Str(value) if nested => write!(f, "{:?}", value),
+            Value::Str(value) => write!(f, "{}", value),
+            Value::Null => write!(f, "null"),
+            Value::Void => write!(f, "void"),
+            Value::List(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    item.write(f, true)?;
+                }
+                write!(f, "]")
+            }
+            Value::Dict(pairs) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in pairs.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    key.write(f, true)?;
+                    write!(f, ": ")?;
+                    value.write(f, true)?;
+                }
+                write!(f, "}}")
+            }
+            Value::Range(min, max) => write!(f, "{}->{}", min, max),
+            Value::Struct(instance) => {
+                let instance = instance.borrow();
+                write!(f, "{} {{", instance.name)?;
+                for (i, (name, value)) in instance.fields.iter().enumerate() {
+                    write!(f, "{} {}: ", if i > 0 { "," } else { "" }, name)?;
+                    value.write(f, true)?;
+                }
+                write!(f, " }}")
+            }
+            Value::Enum(name, variant) => write!(f, "{}::{}", name, variant),
+            Value::Function(_) => write!(f, "<function>"),
+            Value::Type(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Int(a), Value::Int(b)) => a == b,
+            (Value::Int(_) | Value::Float(_), Value::Int(_) | Value::Float(_)) => self.as_float() == other.as_float(),
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Str(a), Value::Str(b)) => a == b,
+            (Value::Null, Value::Null) | (Value::Void, Value::Void) => true,
+            (Value::List(a), Value::List(b)) => Rc::ptr_eq(a, b) || *a.borrow() == *b.borrow(),
+            (Value::Dict(a), Value::Dict(b)) => {
+                Rc::ptr_eq(a, b) || {
+                    let (a, b) = (a.borrow(), b.borrow());
+                    a.len() == b.len() && a.iter().all(|pair| b.contains(pair))
+                }
+            }
+            (Value::Range(a, b), Value::Range(c, d)) => a == c && b == d,
+            (Value::Struct(a), Value::Struct(b)) => {
+                Rc::ptr_eq(a, b) || {
+                    let (a, b) = (a.borrow(), b.borrow());
+                    a.name == b.name && a.fields == b.fields
+                }
+            }
+            (Value::Enum(a, x), Value::Enum(b, y)) => a == b && x == y,
+            (Value::Function(a), Value::Function(b)) => a == b,
+            (Value::Type(a), Value::Type(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, false)
+    }
+}
@@ -0,0 +1,380 @@
+use super::builtins::{self, Fault};
+use super::bytecode::{Constant, Module, Op};
+use super::value::{Instance, Value};
+use super::MAX_CALL_DEPTH;
+use crate::compiler::diagnostic::Diagnostic;
+use crate::compiler::node::Location;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+type Result<T> = std::result::Result<T, Box<Diagnostic>>;
+
+struct Frame {
+    function: usize,
+    ip: usize,
+    // first local of the call
+    base: usize,
+    // where the stack is cut back to on return, dropping the callee
+    bottom: usize,
+}
+
+pub struct Vm<'m> {
+    module: &'m Module,
+    stack: Vec<Value>,
+    frames: Vec<Frame>,
+    // (struct, method) to function index
+    methods: HashMap<(&'m str, &'m str), usize>,
+    // where errors outside of any function point, set by `run`
+    entry: Location,
+    debug: bool,
+}
+
+impl<'m> Vm<'m> {
+    pub fn new(module: &'m Module, debug: bool) -> Self {
+        let methods = module.methods.iter()
+            .map(|method| ((method.owner.as_str(), method.name.as_str()), method.function as usize))
+            .collect();
+
+        Self {
+            module,
+            stack: Vec::new(),
+            frames: Vec::new(),
+            methods,
+            entry: Location { file: module.file.clone(), line: 1, column: 1, start: 0, end: 1 },
+            debug,
+        }
+    }
+
+    // runs `main` and returns its result
+    pub fn run(&mut self, entry: &Location) -> Result<Value> {
+        if self.debug {
+            println!("[VM] Starting Execution");
+        }
+
+        self.entry = entry.clone();
+        let Some(main) = self.module.entry else {
+            return Err(Diagnostic::error("E0506", "`main` function not found", entry.clone())
+                .with_help("add `func main() { ... }` to the entry file").into());
+        };
+
+        self.stack.push(Value::Function(main as usize));
+        self.call(0, 0)?;
+
+        let result = self.execute();
+        io::stdout().flush().ok();
+        result
+    }
+
+    fn execute(&mut self) -> Result<Value> {
+        loop {
+            let frame = self.frames.last_mut().expect("a frame is active while executing");
+            let function = &self.module.functions[frame.function];
+            let op = function.code[frame.ip];
+            let base = frame.base;
+            frame.ip += 1;
+
+            match op {
+                Op::Const(index) => {
+                    let value = match &self.module.constants[index as usize] {
+                        Constant::Int(value) => Value::Int(*value),
+                        Constant::Float(value) => Value::Float(*value),
+                        Constant::Str(value) => Value::Str(value.clone()),
+                    };
+                    self.stack.push(value);
+                }
+                Op::Null => self.stack.push(Value::Null),
+                Op::True => self.stack.push(Value::Bool(true)),
+                Op::False => self.stack.push(Value::Bool(false)),
+                Op::Void => self.stack.push(Value::Void),
+                Op::Pop => {
+                    self.pop();
+                }
+                Op::Dup => {
+                    let top = self.peek(0).clone();
+                    self.stack.push(top);
+                }
+                Op::Load(slot) => {
+                    let value = self.stack[base + slot as usize].clone();
+                    self.stack.push(value);
+                }
+                Op::Store(slot) => {
+                    let value = self.pop();
+                    self.stack[base + slot as usize] = value;
+                }
+                Op::Function(id) => self.stack.push(Value::Function(id as usize)),
+                Op::Type(id) => self.stack.push(Value::Type(self.module.structs[id as usize].name.clone())),
+                Op::Variant(id, variant) => {
+                    let def = &self.module.enums[id as usize];
+                    self.stack.push(Value::Enum(def.name.clone(), def.variants[variant as usize].clone()));
+                }
+                Op::GetField(name) => {
+                    let field = self.string(name);
+                    let value = match self.pop() {
+                        Value::Struct(instance) => {
+                            let instance = instance.borrow();
+                            match instance.fields.iter().find(|(name, _)| name == field) {
+                                Some((_, value)) => value.clone(),
+                                None => return Err(self.fault(Fault::new(format!("no field `{}` on `{}`", field, instance.name)))),
+                            }
+                        }
+                        other => return Err(self.fault(Fault::new(format!("cannot read field `{}` of `{}`", field, other.type_name())))),
+                    };
+                    self.stack.push(value);
+                }
+                Op::SetField(name) => {
+                    let field = self.string(name);
+                    let value = self.pop();
+                    match self.pop() {
+                        Value::Struct(instance) => {
+                            let mut instance = instance.borrow_mut();
+                            match instance.fields.iter_mut().find(|(name, _)| name == field) {
+                                Some(slot) => slot.1 = value,
+                                None => return Err(self.fault(Fault::new(format!("no field `{}` on `{}`", field, instance.name)))),
+                            }
+                        }
+                        other => return Err(self.fault(Fault::new(format!("cannot set field `{}` on `{}`", field, other.type_name())))),
+                    }
+                }
+                Op::Neg | Op::Pos | Op::Not | Op::BitNot => {
+                    let value = self.pop();
+                    let result = builtins::unary(op.symbol().unwrap_or_default(), value).map_err(|e| self.fault(e))?;
+                    self.stack.push(result);
+                }
+                Op::Jump(target) => self.jump(target),
+                Op::JumpIfFalse(target) => {
+                    if !self.condition()? {
+                        self.jump(target);
+                    }
+                }
+                Op::JumpIfTrue(target) => {
+                    if self.condition()? {
+                        self.jump(target);
+                    }
+                }
+                Op::Call(argc) => {
+                    let bottom = self.stack.len() - argc as usize - 1;
+                    self.call(bottom, argc)?;
+                }
+                Op::CallMethod(name, argc) => self.call_method(name, argc)?,
+                Op::CallStd(name, argc) => {
+                    let args = self.stack.split_off(self.stack.len() - argc as usize);
+                    let result = builtins::std_function(self.string(name), args).map_err(|e| self.fault(e))?;
+                    self.stack.push(result);
+                }
+                Op::Return => {
+                    let result = self.pop();
+                    let Some(frame) = self.frames.pop() else {
+                        return Ok(result);
+                    };
+
+                    self.stack.truncate(frame.bottom);
+                    if self.frames.is_empty() {
+                        return Ok(result);
+                    }
+                    self.stack.push(result);
+                }
+                Op::List(count) => {
+                    let items = self.stack.split_off(self.stack.len() - count as usize);
+                    self.stack.push(Value::list(items));
+                }
+                Op::Dict(count) => {
+                    let items = self.stack.split_off(self.stack.len() - 2 * count as usize);
+                    let mut pairs: Vec<(Value, Value)> = Vec::new();
+                    let mut items = items.into_iter();
+
+                    while let (Some(key), Some(value)) = (items.next(), items.next()) {
+                        match pairs.iter_mut().find(|(existing, _)| *existing == key) {
+                            Some(slot) => slot.1 = value,
+                            None => pairs.push((key, value)),
+                        }
+                    }
+                    self.stack.push(Value::dict(pairs));
+                }
+                Op::Range => {
+                    let max = self.pop();
+                    let min = self.pop();
+                    match (min, max) {
+                        (Value::Int(min), Value::Int(max)) => self.stack.push(Value::Range(min, max)),
+                        _ => return Err(self.fault(Fault::new("range bounds must be integers"))),
+                    }
+                }
+                Op::IterInit(slot) => {
+                    // loops walk a snapshot, so pushing to the list inside the body does not loop
+                    // forever; a range is counted through instead, however long it is
+                    let items = match self.pop() {
+                        range @ Value::Range(..) => range,
+                        Value::List(items) => Value::list(items.borrow().clone()),
+                        Value::Dict(pairs) => Value::list(pairs.borrow().iter().map(|pair| pair.0.clone()).collect()),
+                        Value::Str(value) => Value::list(value.chars().map(|ch| Value::Str(ch.to_string())).collect()),
+                        other => return Err(self.fault(Fault::new(format!("`{}` is not iterable", other.type_name())))),
+                    };
+
+                    let slot = base + slot as usize;
+                    self.stack[slot] = items;
+                    self.stack[slot + 1] = Value::Int(0);
+                }
+                Op::IterNext(slot, target) => {
+                    let slot = base + slot as usize;
+                    let (item, position) = match (&self.stack[slot], &self.stack[slot + 1]) {
+                        (Value::List(items), Value::Int(position)) => (items.borrow().get(*position as usize).cloned(), *position),
+                        (Value::Range(min, max), Value::Int(position)) => {
+                            (min.checked_add(*position).filter(|value| value < max).map(Value::Int), *position)
+                        }
+                        _ => return Err(self.fault(Fault::new("loop iterator is corrupted"))),
+                    };
+
+                    match item {
+                        Some(item) => {
+                            self.stack[slot + 1] = Value::Int(position + 1);
+                            self.stack.push(item);
+                        }
+                        None => self.jump(target),
+                    }
+                }
+                Op::Fail(message) => return Err(self.fault(Fault::new(self.string(message)))),
+                op => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    let result = builtins::binary(op.symbol().unwrap_or_default(), left, right).map_err(|e| self.fault(e))?;
+                    self.stack.push(result);
+                }
+            }
+        }
+    }
+
+    // calls the value at `bottom` with the `argc` values above it
+    fn call(&mut self, bottom: usize, argc: u32) -> Result<()> {
+        match self.stack[bottom].clone() {
+            Value::Function(id) => {
+                let function = &self.module.functions[id];
+                if function.arity != argc {
+                    return Err(self.fault(Fault::new(
+                        format!("`{}` takes {} argument(s) but {} were supplied", function.name, function.arity, argc),
+                    )));
+                }
+
+                // a method called through its path has no receiver, its `self` slot is the callee's
+                let base = if function.receiver {
+                    self.stack[bottom] = Value::Null;
+                    bottom
+                } else {
+                    bottom + 1
+                };
+                self.enter(id, base, bottom)
+            }
+            Value::Type(name) => {
+                let fields = self.module.structs.iter().find(|def| def.name == name)
+                    .map(|def| def.fields.clone()).unwrap_or_default();
+                if fields.len() != argc as usize {
+                    return Err(self.fault(Fault::new(
+                        format!("`{}` has {} field(s) but {} were supplied", name, fields.len(), argc),
+                    )));
+                }
+
+                let args = self.stack.split_off(bottom + 1);
+                let fields = fields.into_iter().zip(args).collect();
+                self.stack[bottom] = Value::Struct(Rc::new(RefCell::new(Instance { name, fields })));
+                Ok(())
+            }
+            other => Err(self.fault(Fault::new(format!("`{}` is not callable", other.type_name())))),
+        }
+    }
+
+    fn call_method(&mut self, name: u32, argc: u32) -> Result<()> {
+        let bottom = self.stack.len() - argc as usize - 1;
+        let name = self.string(name);
+
+        let Value::Struct(instance) = self.stack[bottom].clone() else {
+            let args = self.stack.split_off(bottom + 1);
+            let receiver = self.pop();
+            let result = builtins::method(receiver, name, args).map_err(|e| self.fault(e))?;
+            self.stack.push(result);
+            return Ok(());
+        };
+
+        let instance = instance.borrow();
+        // a field holding a function shadows a method of the same name
+        if let Some((_, callee)) = instance.fields.iter().find(|(field, _)| field == name) {
+            self.stack[bottom] = callee.clone();
+            drop(instance);
+            return self.call(bottom, argc);
+        }
+
+        let Some(id) = self.methods.get(&(instance.name.as_str(), name)).copied() else {
+            return Err(self.fault(Fault::new(format!("no method `{}` on `{}`", name, instance.name))));
+        };
+        drop(instance);
+
+        let function = &self.module.functions[id];
+        if function.arity != argc {
+            return Err(self.fault(Fault::new(
+                format!("`{}` takes {} argument(s) but {} were supplied", function.name, function.arity, argc),
+            )));
+        }
+        self.enter(id, bottom, bottom)
+    }
+
+    fn enter(&mut self, id: usize, base: usize, bottom: usize) -> Result<()> {
+        let function = &self.module.functions[id];
+
+        if self.frames.len() >= MAX_CALL_DEPTH {
+            return Err(Diagnostic::error("E0505", format!("stack overflow while calling `{}`", function.name), self.location())
+                .with_note(format!("calls can nest at most {} deep", MAX_CALL_DEPTH)).into());
+        }
+
+        self.stack.resize(base + function.locals as usize, Value::Void);
+        self.frames.push(Frame {
+            function: id,
+            ip: 0,
+            base,
+            bottom,
+        });
+        Ok(())
+    }
+
+    fn condition(&mut self) -> Result<bool> {
+        match self.pop() {
+            Value::Bool(value) => Ok(value),
+            other => Err(self.fault(Fault::new(format!("expected `bool` condition, found `{}`", other.type_name())))),
+        }
+    }
+
+    fn jump(&mut self, target: u32) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.ip = target as usize;
+        }
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().unwrap_or(Value::Void)
+    }
+
+    fn peek(&self, depth: usize) -> &Value {
+        &self.stack[self.stack.len() - 1 - depth]
+    }
+
+    fn string(&self, index: u32) -> &'m str {
+        match &self.module.constants[index as usize] {
+            Constant::Str(value) => value,
+            _ => "",
+        }
+    }
+
+    // source location of the instruction that just ran
+    fn location(&self) -> Location {
+        match self.frames.last() {
+            Some(frame) => {
+                let function = &self.module.functions[frame.function];
+                self.module.location(function, &function.spans[frame.ip.saturating_sub(1)])
+            }
+            None => self.entry.clone(),
+        }
+    }
+
+    fn fault(&self, fault: Fault) -> Box<Diagnostic> {
+        fault.at(&self.location())
+    }
+}
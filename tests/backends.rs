@@ -0,0 +1,143 @@
+// runs the same programs under every way `mix` has of running one, which must all print the
+// same and exit with the same code
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+const MIX: &str = env!("CARGO_BIN_EXE_mix");
+
+#[derive(Debug, Clone, Copy)]
+enum Backend {
+    Vm,
+    Interpreter,
+    C,
+    Asm,
+}
+
+impl Backend {
+    // the output and exit code of the program in `project`
+    fn run(self, project: &Path) -> Output {
+        match self {
+            Backend::Vm => mix(project, &["run"]),
+            Backend::Interpreter => mix(project, &["run", "--interpret"]),
+            Backend::C => built(project, &["build", "--backend=c"], "native"),
+            Backend::Asm => built(project, &["build", "--target=x86_64-gnu-linux"], "x86_64-gnu-linux"),
+        }
+    }
+
+    // why the backend cannot run here, if it cannot
+    fn missing(self) -> Option<String> {
+        let tools: &[&str] = match self {
+            Backend::Vm | Backend::Interpreter => &[],
+            Backend::C => &["cc"],
+            Backend::Asm if !cfg!(all(target_arch = "x86_64", target_os = "linux")) => {
+                return Some("its programs only run on x86-64 Linux".to_owned());
+            }
+            Backend::Asm => &["as", "ld"],
+        };
+        tools.iter().find(|tool| Command::new(tool).arg("--version").output().is_err()).map(|tool| format!("`{}` not found", tool))
+    }
+}
+
+const ALL: &[Backend] = &[Backend::Vm, Backend::Interpreter, Backend::C, Backend::Asm];
+
+// the x86-64 backend has no structs, enums, lists or string operations yet
+const NOT_ASM: &[Backend] = &[Backend::Vm, Backend::Interpreter, Backend::C];
+
+fn mix(project: &Path, args: &[&str]) -> Output {
+    let (command, rest) = args.split_first().unwrap();
+    Command::new(MIX).arg(command).arg(project).args(rest).output().unwrap()
+}
+
+// builds the project with `args` and runs the binary under `build/debug/<target>`, or gives the
+// output of a build that failed
+fn built(project: &Path, args: &[&str], target: &str) -> Output {
+    let build = mix(project, args);
+    if !build.status.success() {
+        return build;
+    }
+    Command::new(project.join("build/debug").join(target).join("bin/program")).output().unwrap()
+}
+
+// the backends of `backends` that can run here, saying which are skipped
+fn runnable(backends: &[Backend]) -> impl Iterator<Item = Backend> + '_ {
+    backends.iter().copied().filter(|backend| match backend.missing() {
+        Some(reason) => {
+            eprintln!("skipping the {:?} backend: {}", backend, reason);
+            false
+        }
+        None => true,
+    })
+}
+
+// a project named `program` holding `source` as its entry, removed again on drop
+struct Project(PathBuf);
+
+impl Project {
+    fn new(test: &str, source: &str) -> Project {
+        let dir = std::env::temp_dir().join(format!("mix-backend-test-{}-{}", std::process::id(), test));
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("mix.conf"), "{\n\t\"name\": \"program\",\n\t\"version\": \"1.0.0\",\n\t\"author\": \"...\",\n\t\"packages\": []\n}\n").unwrap();
+        fs::write(dir.join("src/main.mx"), source).unwrap();
+        Project(dir)
+    }
+}
+
+impl Drop for Project {
+    fn drop(&mut self) {
+        fs::remove_dir_all(&self.0).ok();
+    }
+}
+
+// runs `source` on each of `backends`, expecting `stdout` and `code` from every one
+fn check(test: &str, source: &str, backends: &[Backend], stdout: &str, code: i32) {
+    let project = Project::new(test, source);
+    for backend in runnable(backends) {
+        let output = backend.run(&project.0);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(String::from_utf8_lossy(&output.stdout), stdout, "{:?}: {}", backend, stderr);
+        assert_eq!(output.status.code(), Some(code), "{:?}: {}", backend, stderr);
+    }
+}
+
+// runs `source` on each of `backends`, expecting every one to stop with diagnostic `code`;
+// `mix run` reports it on stdout, a built program on stderr
+fn check_error(test: &str, source: &str, backends: &[Backend], code: &str) {
+    let project = Project::new(test, source);
+    for backend in runnable(backends) {
+        let output = backend.run(&project.0);
+        let text = format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+        assert!(text.contains(&format!("error[{}]", code)), "{:?}: {}", backend, text);
+        assert_eq!(output.status.code(), Some(1), "{:?}: {}", backend, text);
+    }
+}
+
+#[test]
+fn computes_the_same_everywhere() {
+    let source = "import std;\n\nalias Num = int;\n\n\
+        func fib(n: int) -> int {\n    if n < 2 {\n        return n;\n    }\n    return fib(n - 1) + fib(n - 2);\n}\n\n\
+        func main() -> int {\n    let x: Num;\n    x += 1;\n    let base = 3;\n    let total = 0;\n    for i in 0->10 {\n        total += i;\n    }\n    \
+        std::println(x, total, fib(15));\n    \
+        std::println(base ^ 5000000001, -7 ^ 4611686018427387907, 2 ^ 63);\n    \
+        std::println(7 / 2, 7 % 3, -7 / 2, 1 < 2 && 2 > 3, \"mix\");\n    \
+        return 3;\n}\n";
+    let stdout = "1 45 610\n7583655624082192387 -343 -9223372036854775808\n3 1 -3 false mix\n";
+    check("compute", source, ALL, stdout, 3);
+}
+
+#[test]
+fn agrees_on_structs_enums_and_defaults() {
+    let source = "import std;\n\nenum Color { Red, Green }\n\nstruct Point {\n    x: int;\n}\n\n\
+        public func Point.seven() -> int {\n    return 7;\n}\n\nalias Text = str;\n\n\
+        func main() {\n    let c: Color = Color::Green;\n    match c {\n        Color::Red { std::println(\"red\"); }\n        Color::Green { std::println(\"green\"); }\n    }\n    \
+        let make = Point::seven;\n    std::println(make());\n    let t: Text;\n    std::println(t + \"!\");\n    \
+        let l: List<int>;\n    l.push(3);\n    std::println(l);\n}\n";
+    check("structs", source, NOT_ASM, "green\n7\n!\n[3]\n", 0);
+}
+
+#[test]
+fn stops_on_the_same_runtime_errors() {
+    check_error("divide", "func main() -> int {\n    let a = 0;\n    return 10 / a;\n}\n", ALL, "E0501");
+    check_error("power", "import std;\n\nfunc main() {\n    let e = -1;\n    std::println(2 ^ e);\n}\n", ALL, "E0507");
+}